@@ -1,13 +1,13 @@
 use harness::utils::{download_file, get_cached_file, HARNESS_BENCH_SCRATCH_DIR};
 use harness::{bench, Bencher};
 
-const NAME: &'static str = "bigimage-samdutton-simpl.jpg";
+const NAME: &str = "bigimage-samdutton-simpl.jpg";
 
 fn startup() {
     let file = get_cached_file(NAME)
         .or_else(|| {
             let url =
-                format!("https://raw.githubusercontent.com/samdutton/simpl/36d111d4ab66ab61c6d192e3c00cdf676b623431/bigimage/bigImage.jpg");
+                "https://raw.githubusercontent.com/samdutton/simpl/36d111d4ab66ab61c6d192e3c00cdf676b623431/bigimage/bigImage.jpg".to_string();
             println!("Downloading file: {url}");
             download_file(NAME, url).unwrap();
             get_cached_file(NAME)
@@ -1,14 +1,12 @@
 use harness::utils::{download_file, get_cached_file, HARNESS_BENCH_SCRATCH_DIR};
 use harness::{bench, Bencher};
 
-const NAME: &'static str = "file-examples-com-wav-fe7590659365eb907974df6.wav";
+const NAME: &str = "file-examples-com-wav-fe7590659365eb907974df6.wav";
 
 fn startup() {
     let file = get_cached_file(NAME)
         .or_else(|| {
-            let url = format!(
-                "https://file-examples.com/storage/fe7590659365eb907974df6/2017/11/file_example_WAV_10MG.wav"
-            );
+            let url = "https://file-examples.com/storage/fe7590659365eb907974df6/2017/11/file_example_WAV_10MG.wav".to_string();
             println!("Downloading file: {url}");
             download_file(NAME, url).unwrap();
             get_cached_file(NAME)
@@ -0,0 +1,174 @@
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use colored::Colorize;
+
+use crate::{configs::run_info::CrateInfo, utils::checksum};
+
+/// The manifest file in an archive, in the `sha256sum` format
+const MANIFEST: &str = "MANIFEST.sha256";
+
+fn logs_dir() -> anyhow::Result<PathBuf> {
+    Ok(CrateInfo::get_target_path()?.join("harness").join("logs"))
+}
+
+/// Recursively list all files in a directory, as sorted paths relative to `root`.
+fn list_files(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            list_files(root, &path, files)?;
+        } else {
+            files.push(path.strip_prefix(root)?.to_owned());
+        }
+    }
+    files.sort();
+    Ok(())
+}
+
+/// Bundle a run into a single compressed and checksummed archive
+#[derive(Parser)]
+pub struct ArchiveArgs {
+    /// The run id to archive
+    pub run_id: String,
+    /// Path to the output archive. Default to `<RUNID>.tar.zst`
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+impl ArchiveArgs {
+    pub fn run(&self) -> anyhow::Result<()> {
+        let log_dir = logs_dir()?.join(&self.run_id);
+        if !log_dir.join("config.toml").exists() {
+            anyhow::bail!("Run `{}` not found: {}", self.run_id, log_dir.display());
+        }
+        // Resolve the `latest` symlink to the real run id
+        let log_dir = log_dir.canonicalize()?;
+        let runid = log_dir.file_name().unwrap().to_string_lossy().into_owned();
+        let output = self
+            .output
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(format!("{}.tar.zst", runid)));
+        // Checksum all files
+        let mut files = vec![];
+        list_files(&log_dir, &log_dir, &mut files)?;
+        files.retain(|f| f.as_os_str() != MANIFEST);
+        let mut manifest = String::new();
+        for f in &files {
+            let hash = checksum::sha256_file(&log_dir.join(f))?;
+            manifest += &format!("{}  {}\n", hash, f.display());
+        }
+        // Bundle the files and the manifest into `<RUNID>/` in the archive
+        let encoder = zstd::Encoder::new(std::fs::File::create(&output)?, 19)?.auto_finish();
+        let mut builder = tar::Builder::new(encoder);
+        for f in &files {
+            builder.append_path_with_name(log_dir.join(f), Path::new(&runid).join(f))?;
+        }
+        let mut header = tar::Header::new_gnu();
+        header.set_size(manifest.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(chrono::Local::now().timestamp() as u64);
+        header.set_cksum();
+        builder.append_data(
+            &mut header,
+            Path::new(&runid).join(MANIFEST),
+            manifest.as_bytes(),
+        )?;
+        builder.into_inner()?;
+        // Checksum of the archive itself
+        let hash = checksum::sha256_file(&output)?;
+        let archive_name = output.file_name().unwrap().to_string_lossy();
+        let checksum_file = PathBuf::from(format!("{}.sha256", output.display()));
+        std::fs::write(&checksum_file, format!("{}  {}\n", hash, archive_name))?;
+        println!(
+            "{} Archived {} files of run `{}` to {}",
+            "✔".green(),
+            files.len(),
+            runid,
+            output.display()
+        );
+        println!("  sha256: {}", hash.bright_black());
+        Ok(())
+    }
+}
+
+/// Restore an archived run into the logs directory
+#[derive(Parser)]
+pub struct UnarchiveArgs {
+    /// Path to the archive created by `cargo harness archive`
+    pub archive: PathBuf,
+}
+
+impl UnarchiveArgs {
+    /// Check the archive against the `<archive>.sha256` file, if it exists.
+    fn verify_archive(&self) -> anyhow::Result<()> {
+        let checksum_file = PathBuf::from(format!("{}.sha256", self.archive.display()));
+        if !checksum_file.exists() {
+            return Ok(());
+        }
+        let expected = std::fs::read_to_string(&checksum_file)?;
+        let expected = expected.split_whitespace().next().unwrap_or_default();
+        if checksum::sha256_file(&self.archive)? != expected {
+            anyhow::bail!("Checksum mismatch: {}", self.archive.display());
+        }
+        Ok(())
+    }
+
+    /// Check all files of an unpacked run against its manifest.
+    fn verify_manifest(run_dir: &Path) -> anyhow::Result<()> {
+        let manifest_file = run_dir.join(MANIFEST);
+        if !manifest_file.exists() {
+            anyhow::bail!("Malformed archive: {} not found", MANIFEST);
+        }
+        for line in std::fs::read_to_string(manifest_file)?.lines() {
+            let Some((hash, file)) = line.split_once("  ") else {
+                anyhow::bail!("Malformed archive: invalid manifest line `{}`", line);
+            };
+            let path = run_dir.join(file);
+            if !path.exists() {
+                anyhow::bail!("Malformed archive: missing file `{}`", file);
+            }
+            if checksum::sha256_file(&path)? != hash {
+                anyhow::bail!("Checksum mismatch: {}", file);
+            }
+        }
+        Ok(())
+    }
+
+    fn unpack(&self, tmp_dir: &Path, logs_dir: &Path) -> anyhow::Result<String> {
+        let decoder = zstd::Decoder::new(std::fs::File::open(&self.archive)?)?;
+        tar::Archive::new(decoder).unpack(tmp_dir)?;
+        let entries = std::fs::read_dir(tmp_dir)?.flatten().collect::<Vec<_>>();
+        if entries.len() != 1 || !entries[0].path().is_dir() {
+            anyhow::bail!("Malformed archive: expected a single run directory");
+        }
+        let run_dir = entries[0].path();
+        let runid = entries[0].file_name().to_string_lossy().into_owned();
+        Self::verify_manifest(&run_dir)?;
+        let dest = logs_dir.join(&runid);
+        if dest.exists() {
+            anyhow::bail!("Run `{}` already exists: {}", runid, dest.display());
+        }
+        std::fs::remove_file(run_dir.join(MANIFEST))?;
+        std::fs::rename(&run_dir, &dest)?;
+        Ok(runid)
+    }
+
+    pub fn run(&self) -> anyhow::Result<()> {
+        self.verify_archive()?;
+        let logs_dir = logs_dir()?;
+        std::fs::create_dir_all(&logs_dir)?;
+        let tmp_dir = logs_dir.join(format!(".unarchive-{}", std::process::id()));
+        std::fs::create_dir_all(&tmp_dir)?;
+        let result = self.unpack(&tmp_dir, &logs_dir);
+        std::fs::remove_dir_all(&tmp_dir)?;
+        let runid = result?;
+        println!(
+            "{} Restored run `{}` to {}",
+            "✔".green(),
+            runid,
+            logs_dir.join(&runid).display()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,204 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use clap::{Parser, Subcommand};
+use colored::Colorize;
+
+use crate::{
+    configs::{harness::parse_duration, run_info::CrateInfo},
+    print_md,
+};
+
+use super::clean::{dir_size, format_size};
+
+/// The directories in the cache whose entries are listed and cleared one by one
+const GROUP_DIRS: [&str; 1] = ["datasets"];
+
+/// An entry in the cache directory
+struct CacheEntry {
+    /// The path relative to the cache directory, e.g. `input.bin` or `datasets/enwik8`
+    name: String,
+    path: PathBuf,
+    size: u64,
+    /// The time since the last modification
+    age: Duration,
+}
+
+impl CacheEntry {
+    fn new(name: String, path: PathBuf, now: SystemTime) -> Self {
+        let metadata = path.metadata().ok();
+        let size = match &metadata {
+            Some(m) if m.is_dir() => dir_size(&path),
+            Some(m) => m.len(),
+            None => 0,
+        };
+        let age = metadata
+            .and_then(|m| m.modified().ok())
+            .and_then(|t| now.duration_since(t).ok())
+            .unwrap_or_default();
+        Self {
+            name,
+            path,
+            size,
+            age,
+        }
+    }
+}
+
+fn cache_dir() -> anyhow::Result<PathBuf> {
+    Ok(CrateInfo::get_target_path()?.join("harness").join("cache"))
+}
+
+/// Format an age in its largest unit, e.g. `3d` or `5h`
+fn format_age(age: Duration) -> String {
+    match age.as_secs() {
+        s if s >= 86400 => format!("{}d", s / 86400),
+        s if s >= 3600 => format!("{}h", s / 3600),
+        s if s >= 60 => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
+
+/// Inspect and manage the cache directory `target/harness/cache`, i.e. the downloaded or generated benchmark inputs and datasets.
+///
+/// The cached files are kept across runs until they are cleared here, with `cargo harness clean --cache`, or with `cargo clean`.
+#[derive(Parser)]
+pub struct CacheArgs {
+    #[command(subcommand)]
+    command: CacheCommand,
+}
+
+#[derive(Subcommand)]
+enum CacheCommand {
+    /// List the entries of the cache directory, with their sizes and ages
+    List,
+    /// Delete all the entries of the cache directory, or only the given ones
+    Clear(CacheClearArgs),
+    /// Print the path of the cache directory
+    Path,
+}
+
+#[derive(Parser)]
+struct CacheClearArgs {
+    /// The entries to delete, as listed by `cache list`, e.g. `datasets/enwik8`. Default to all entries
+    entries: Vec<String>,
+    /// Only delete the entries that were last modified more than this long ago, e.g. `30d` or `12h`
+    #[arg(long, value_name = "DURATION")]
+    older_than: Option<String>,
+    /// Only list what would be deleted, without deleting anything
+    #[arg(long, default_value = "false")]
+    dry_run: bool,
+}
+
+impl CacheArgs {
+    /// All the entries in the cache directory, sorted by name.
+    /// The entries of the group directories (e.g. `datasets`) are listed individually.
+    fn entries(dir: &Path) -> anyhow::Result<Vec<CacheEntry>> {
+        let mut entries = vec![];
+        if !dir.exists() {
+            return Ok(entries);
+        }
+        let now = SystemTime::now();
+        for entry in std::fs::read_dir(dir)?.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let path = entry.path();
+            if GROUP_DIRS.contains(&name.as_str()) && path.is_dir() {
+                for e in std::fs::read_dir(&path)?.flatten() {
+                    let name = format!("{}/{}", name, e.file_name().to_string_lossy());
+                    entries.push(CacheEntry::new(name, e.path(), now));
+                }
+            } else {
+                entries.push(CacheEntry::new(name, path, now));
+            }
+        }
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+
+    fn list(&self) -> anyhow::Result<()> {
+        let dir = cache_dir()?;
+        let entries = Self::entries(&dir)?;
+        let total = entries.iter().map(|e| e.size).sum::<u64>();
+        print_md!("# Cache\n\n");
+        print_md!("* path: `{}`\n", dir.display());
+        print_md!("* entries: `{}`\n", entries.len());
+        print_md!("* total size: `{}`\n\n", format_size(total));
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let mut table = "|entry|size|age|\n".to_owned();
+        table += "|:-|-:|-:|\n";
+        for e in &entries {
+            table += &format!(
+                "|{}|{}|{}|\n",
+                e.name,
+                format_size(e.size),
+                format_age(e.age)
+            );
+        }
+        print_md!("{}", table);
+        Ok(())
+    }
+
+    fn clear(&self, args: &CacheClearArgs) -> anyhow::Result<()> {
+        let older_than = args.older_than.as_deref().map(parse_duration).transpose()?;
+        let entries = Self::entries(&cache_dir()?)?;
+        if let Some(name) = args
+            .entries
+            .iter()
+            .find(|n| !entries.iter().any(|e| e.name == **n))
+        {
+            anyhow::bail!("Cache entry `{}` does not exist.", name);
+        }
+        let action = if args.dry_run {
+            "Would delete"
+        } else {
+            "Deleted"
+        };
+        let mut deleted_entries = 0;
+        let mut deleted_bytes = 0;
+        for e in &entries {
+            if !args.entries.is_empty() && !args.entries.contains(&e.name) {
+                continue;
+            }
+            if older_than.is_some_and(|d| e.age < d) {
+                continue;
+            }
+            if !args.dry_run {
+                if e.path.is_dir() {
+                    std::fs::remove_dir_all(&e.path)?;
+                } else {
+                    std::fs::remove_file(&e.path)?;
+                }
+            }
+            deleted_entries += 1;
+            deleted_bytes += e.size;
+            println!("{} {}", action, e.path.display());
+        }
+        println!(
+            "{}",
+            format!(
+                "{} {} of {} entries, {} in total.",
+                action,
+                deleted_entries,
+                entries.len(),
+                format_size(deleted_bytes)
+            )
+            .green()
+        );
+        Ok(())
+    }
+
+    pub fn run(&self) -> anyhow::Result<()> {
+        match &self.command {
+            CacheCommand::List => self.list(),
+            CacheCommand::Clear(args) => self.clear(args),
+            CacheCommand::Path => {
+                println!("{}", cache_dir()?.display());
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use clap::Parser;
+use colored::Colorize;
+
+use crate::{
+    commands::report::data::{Bootstrap, Comparison, ReportData, StatTest},
+    configs::run_info::{CrateInfo, RunInfo},
+    print_md,
+};
+
+/// Check for performance regressions, and fail if any benchmark regressed significantly.
+///
+/// Intended as a merge gate in CI.
+#[derive(Parser)]
+pub struct CheckRegressionArgs {
+    /// The run id to check. Default to the latest run.
+    pub run_id: Option<String>,
+    /// The baseline to check against.
+    /// Either a build of the checked run, to check all other builds against it,
+    /// or the id of a previous run, to check each build against the same build in that run.
+    #[arg(long, value_name = "BUILD|RUNID")]
+    pub baseline: String,
+    /// The maximum allowed slowdown, e.g. `3%`
+    #[arg(long, default_value = "5%", value_parser = parse_threshold)]
+    pub threshold: f64,
+    /// The metric to check. Higher values are regressions
+    #[arg(long, default_value = "time")]
+    pub metric: String,
+    /// The significance level of the statistical tests
+    #[arg(long, default_value = "0.05")]
+    pub alpha: f64,
+    /// The statistical test to compare against the baseline
+    #[arg(long, value_enum, default_value_t = StatTest::Welch)]
+    pub stat_test: StatTest,
+}
+
+/// Parse a threshold like `3%` or `3` into a fraction
+pub(crate) fn parse_threshold(s: &str) -> Result<f64, String> {
+    let v = s
+        .trim()
+        .trim_end_matches('%')
+        .parse::<f64>()
+        .map_err(|_| format!("invalid threshold: {}", s))?;
+    if v < 0.0 {
+        return Err("threshold must not be negative".to_owned());
+    }
+    Ok(v / 100.0)
+}
+
+impl CheckRegressionArgs {
+    fn load(log_dir: &std::path::Path) -> anyhow::Result<(RunInfo, ReportData)> {
+        if !log_dir.exists() {
+            anyhow::bail!("Log dir not found: {}", log_dir.display());
+        }
+        let run = RunInfo::load(&log_dir.join("config.toml"))?;
+        let data = ReportData::load(log_dir, &run)?;
+        if data.benches.is_empty() {
+            anyhow::bail!("No results found in run `{}`", run.runid);
+        }
+        Ok((run, data))
+    }
+
+    pub fn run(&self) -> anyhow::Result<()> {
+        if !(self.alpha > 0.0 && self.alpha < 1.0) {
+            anyhow::bail!("The significance level must be between 0 and 1");
+        }
+        let logs_dir = CrateInfo::get_target_path()?.join("harness").join("logs");
+        let (run, data) = Self::load(&logs_dir.join(self.run_id.as_deref().unwrap_or("latest")))?;
+        let bootstrap = Bootstrap::default();
+        // (bench, build, comparison against the baseline)
+        let comparisons: Vec<(String, String, Comparison)> = if data.builds.contains(&self.baseline)
+        {
+            let summary =
+                data.per_metric_summary(&self.metric, &self.baseline, self.stat_test, bootstrap);
+            summary
+                .benches
+                .into_iter()
+                .flat_map(|(bench, builds)| {
+                    builds
+                        .into_iter()
+                        .filter(|(build, _)| *build != self.baseline)
+                        .map(move |(build, c)| (bench.clone(), build, c))
+                })
+                .collect()
+        } else if logs_dir.join(&self.baseline).join("config.toml").exists() {
+            let (_, base) = Self::load(&logs_dir.join(&self.baseline))?;
+            data.per_metric_run_comparison(&base, &self.metric, self.stat_test, bootstrap)
+                .pairs
+                .into_iter()
+                .map(|((bench, build), (_, c))| (bench, build, c))
+                .collect()
+        } else {
+            anyhow::bail!(
+                "`{}` is neither a build of run `{}` nor a previous run",
+                self.baseline,
+                run.runid
+            );
+        };
+        let checked = comparisons
+            .iter()
+            .filter(|(_, _, c)| c.ratio.is_some())
+            .count();
+        if checked == 0 {
+            anyhow::bail!(
+                "No `{}` results to compare against `{}`",
+                self.metric,
+                self.baseline
+            );
+        }
+        let significant = |c: &Comparison| c.test.is_some_and(|t| t.p < self.alpha);
+        let regressions = comparisons
+            .iter()
+            .filter(|(_, _, c)| c.ratio.is_some_and(|r| r > 1.0 + self.threshold) && significant(c))
+            .collect::<Vec<_>>();
+
+        print_md!("# Regression Check\n\n");
+        print_md!("* run: `{}`\n", run.runid);
+        print_md!("* baseline: `{}`\n", self.baseline);
+        print_md!(
+            "* regression: `{}` is more than {}% higher than the baseline, with p < {} ({})\n\n",
+            self.metric,
+            self.threshold * 100.0,
+            self.alpha,
+            self.stat_test.name()
+        );
+        if !regressions.is_empty() {
+            let mut table = "|bench|build|baseline|value|ratio|p|\n".to_owned();
+            table += "|:-|:-|-:|-:|-:|-:|\n";
+            for (bench, build, c) in &regressions {
+                let ratio = c.ratio.unwrap();
+                let p = c.test.unwrap().p;
+                table += &format!(
+                    "|{}|{}|{:.3}|{:.3}|{:.3}|{}|\n",
+                    bench,
+                    build,
+                    c.summary.mean / ratio,
+                    c.summary.mean,
+                    ratio,
+                    if p < 0.001 {
+                        "<0.001".to_owned()
+                    } else {
+                        format!("{:.3}", p)
+                    }
+                );
+            }
+            print_md!("{}\n", table);
+        }
+        let untested = comparisons
+            .iter()
+            .filter(|(_, _, c)| c.ratio.is_some() && c.test.is_none())
+            .count();
+        if untested > 0 {
+            eprintln!(
+                "{}",
+                format!(
+                    "WARNING: {} of {} comparisons have too few invocations for a statistical test, and are never reported as regressions.",
+                    untested, checked
+                )
+                .yellow()
+            );
+        }
+        if !regressions.is_empty() {
+            anyhow::bail!(
+                "{} of {} benchmark comparisons regressed",
+                regressions.len(),
+                checked
+            );
+        }
+        println!(
+            "{}",
+            format!("✔ No regressions in {} benchmark comparisons", checked).green()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use clap::Parser;
+use colored::Colorize;
+use serde::Deserialize;
+
+use crate::{
+    configs::{harness::parse_duration, run_info::CrateInfo},
+    utils::worktree,
+};
+
+/// The start time of a run, read from its `config.toml` without parsing the whole run info
+#[derive(Deserialize)]
+struct RunStart {
+    #[serde(rename = "start-time-utc")]
+    start_timestamp_utc: i64,
+}
+
+/// A run in the logs directory
+struct LoggedRun {
+    dir: PathBuf,
+    /// Seconds since the unix epoch
+    start_time: u64,
+}
+
+/// The total size of the files in a directory
+pub(crate) fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|e| match e.metadata() {
+            Ok(m) if m.is_dir() => dir_size(&e.path()),
+            Ok(m) => m.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+pub(crate) fn format_size(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / 1024.0 / 1024.0)
+}
+
+/// Delete old run logs, the scratch directory and the downloaded configs, and optionally the cache directory and the build artifacts.
+///
+/// The run logs are only deleted with `--all`, `--keep-last` or `--older-than`.
+/// Do not run this during a benchmark run, as the logs of the ongoing run may be deleted.
+#[derive(Parser)]
+pub struct CleanArgs {
+    /// Delete the logs of all runs
+    #[arg(long, default_value = "false", conflicts_with_all = ["keep_last", "older_than"])]
+    pub all: bool,
+    /// Keep the logs of the last N runs
+    #[arg(long, value_name = "N")]
+    pub keep_last: Option<usize>,
+    /// Only delete the logs of the runs that started more than this long ago, e.g. `30d` or `12h`
+    #[arg(long, value_name = "DURATION")]
+    pub older_than: Option<String>,
+    /// Also delete the cache directory, i.e. the downloaded or generated benchmark inputs
+    #[arg(long, default_value = "false")]
+    pub cache: bool,
+    /// Also delete the build artifacts, i.e. the target directories and worktrees of the builds, and the build cache
+    #[arg(long, default_value = "false")]
+    pub builds: bool,
+    /// Only list what would be deleted, without deleting anything
+    #[arg(long, default_value = "false")]
+    pub dry_run: bool,
+}
+
+impl CleanArgs {
+    /// All runs in the logs directory, from the latest to the oldest
+    fn runs(logs_dir: &Path) -> anyhow::Result<Vec<LoggedRun>> {
+        let mut runs = vec![];
+        if !logs_dir.exists() {
+            return Ok(runs);
+        }
+        for entry in std::fs::read_dir(logs_dir)?.flatten() {
+            let dir = entry.path();
+            if dir.is_symlink() || !dir.is_dir() {
+                continue;
+            }
+            // Fall back to the modification time for the runs without a readable config
+            let start_time = std::fs::read_to_string(dir.join("config.toml"))
+                .ok()
+                .and_then(|s| toml::from_str::<RunStart>(&s).ok())
+                .map(|r| r.start_timestamp_utc.max(0) as u64)
+                .or_else(|| {
+                    let mtime = entry.metadata().ok()?.modified().ok()?;
+                    Some(mtime.duration_since(UNIX_EPOCH).ok()?.as_secs())
+                })
+                .unwrap_or_default();
+            runs.push(LoggedRun { dir, start_time });
+        }
+        runs.sort_by_key(|r| std::cmp::Reverse(r.start_time));
+        Ok(runs)
+    }
+
+    fn remove_dir(&self, dir: &Path) -> anyhow::Result<u64> {
+        let size = dir_size(dir);
+        if !self.dry_run {
+            std::fs::remove_dir_all(dir)?;
+        }
+        Ok(size)
+    }
+
+    pub fn run(&self) -> anyhow::Result<()> {
+        let older_than = self.older_than.as_deref().map(parse_duration).transpose()?;
+        let harness_dir = CrateInfo::get_target_path()?.join("harness");
+        let logs_dir = harness_dir.join("logs");
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let runs = Self::runs(&logs_dir)?;
+        let clean_logs = self.all || self.keep_last.is_some() || self.older_than.is_some();
+        let action = if self.dry_run {
+            "Would delete"
+        } else {
+            "Deleted"
+        };
+        let mut deleted_runs = 0;
+        let mut deleted_bytes = 0;
+        for (i, run) in runs.iter().enumerate() {
+            if !clean_logs {
+                break;
+            }
+            if self.keep_last.is_some_and(|n| i < n) {
+                continue;
+            }
+            let age = now.saturating_sub(Duration::from_secs(run.start_time));
+            if older_than.is_some_and(|d| age < d) {
+                continue;
+            }
+            deleted_bytes += self.remove_dir(&run.dir)?;
+            deleted_runs += 1;
+            println!("{} {}", action, run.dir.display());
+        }
+        // Drop the `latest` symlink if its run is deleted
+        let latest = logs_dir.join("latest");
+        if !self.dry_run && latest.is_symlink() && !latest.exists() {
+            std::fs::remove_file(&latest)?;
+        }
+        for (dir, enabled) in [
+            (harness_dir.join("scratch"), true),
+            (harness_dir.join("remote"), true),
+            (harness_dir.join("cache"), self.cache),
+            (harness_dir.join("targets"), self.builds),
+            (harness_dir.join("builds"), self.builds),
+            (harness_dir.join("worktrees"), self.builds),
+        ] {
+            if enabled && dir.exists() {
+                deleted_bytes += self.remove_dir(&dir)?;
+                println!("{} {}", action, dir.display());
+            }
+        }
+        // Drop the records of the deleted worktrees from the repository
+        if self.builds && !self.dry_run {
+            let _ = worktree::prune();
+        }
+        if !clean_logs && !runs.is_empty() {
+            println!(
+                "Kept the logs of all {} runs. Use `--all`, `--keep-last N` or `--older-than DURATION` to delete them.",
+                runs.len()
+            );
+        }
+        println!(
+            "{}",
+            format!(
+                "{} {} of {} runs, {} in total.",
+                action,
+                deleted_runs,
+                runs.len(),
+                format_size(deleted_bytes)
+            )
+            .green()
+        );
+        Ok(())
+    }
+}
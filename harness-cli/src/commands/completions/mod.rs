@@ -0,0 +1,54 @@
+use std::path::{Path, PathBuf};
+
+use clap::{Command, CommandFactory, Parser};
+use clap_complete::Shell;
+
+/// The name of the CLI in the completions and man pages, i.e. the `harness` binary
+const BIN_NAME: &str = "harness";
+
+/// Generate the shell completions or the man pages of the CLI.
+///
+/// The completions are for the `harness` binary, which takes the same arguments as `cargo harness`.
+/// e.g. `harness completions bash > ~/.local/share/bash-completion/completions/harness`
+#[derive(Parser)]
+pub struct CompletionsArgs {
+    /// The shell to print the completions for
+    #[arg(value_enum, required_unless_present = "man")]
+    pub shell: Option<Shell>,
+    /// Write the man pages of the CLI and each subcommand to this directory instead, e.g. `harness.1` and `harness-run.1`
+    #[arg(long, value_name = "DIR", conflicts_with = "shell")]
+    pub man: Option<PathBuf>,
+}
+
+impl CompletionsArgs {
+    fn command() -> Command {
+        let mut cmd = crate::Cli::command().name(BIN_NAME).bin_name(BIN_NAME);
+        cmd.build();
+        cmd
+    }
+
+    /// Write the man page of a command and, recursively, of its subcommands.
+    /// The pages are named after the display names of the commands, e.g. `harness-run`.
+    fn write_man_pages(cmd: &Command, dir: &Path) -> anyhow::Result<()> {
+        let name = cmd.get_display_name().unwrap_or(cmd.get_name());
+        let page = dir.join(format!("{}.1", name));
+        let mut file = std::fs::File::create(&page)?;
+        clap_mangen::Man::new(cmd.clone()).render(&mut file)?;
+        println!("Man page written to {}", page.display());
+        for sub in cmd.get_subcommands().filter(|s| s.get_name() != "help") {
+            Self::write_man_pages(sub, dir)?;
+        }
+        Ok(())
+    }
+
+    pub fn run(&self) -> anyhow::Result<()> {
+        let mut cmd = Self::command();
+        if let Some(dir) = &self.man {
+            std::fs::create_dir_all(dir)?;
+            return Self::write_man_pages(&cmd, dir);
+        }
+        let shell = self.shell.unwrap();
+        clap_complete::generate(shell, &mut cmd, BIN_NAME, &mut std::io::stdout());
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use clap::Parser;
+use colored::Colorize;
+
+use crate::configs::{
+    harness::{HarnessConfig, Profile},
+    run_info::{CrateInfo, RunInfo},
+};
+
+/// Compare the configs of two profiles or runs
+#[derive(Parser)]
+pub struct DiffConfigArgs {
+    /// A profile name, a run id, or a path to a run's config.toml
+    pub a: String,
+    /// A profile name, a run id, or a path to a run's config.toml
+    pub b: String,
+}
+
+/// A resolved profile, either from the current Cargo.toml or from a previous run
+struct ConfigSource {
+    profile: Profile,
+    commit: Option<String>,
+}
+
+impl DiffConfigArgs {
+    fn load(&self, name: &str) -> anyhow::Result<ConfigSource> {
+        // A path to a config.toml
+        if name.ends_with(".toml") {
+            let run = RunInfo::load(&PathBuf::from(name))?;
+            return Ok(ConfigSource {
+                profile: run.profile.profile,
+                commit: Some(run.commit),
+            });
+        }
+        // A profile in the current Cargo.toml
+        let config = HarnessConfig::load_from_cargo_toml()?;
+        if let Some(profile) = config.profiles.get(name) {
+            return Ok(ConfigSource {
+                profile: profile.clone(),
+                commit: None,
+            });
+        }
+        // A run id
+        let config_path = CrateInfo::get_target_path()?
+            .join("harness")
+            .join("logs")
+            .join(name)
+            .join("config.toml");
+        if !config_path.exists() {
+            anyhow::bail!("Could not find a profile or a run named `{}`", name);
+        }
+        let run = RunInfo::load(&config_path)?;
+        Ok(ConfigSource {
+            profile: run.profile.profile,
+            commit: Some(run.commit),
+        })
+    }
+
+    /// Flatten a toml value into a list of dotted keys and their values
+    fn flatten(prefix: &str, value: &toml::Value, out: &mut BTreeMap<String, String>) {
+        match value {
+            toml::Value::Table(table) => {
+                for (k, v) in table {
+                    let key = if prefix.is_empty() {
+                        k.clone()
+                    } else {
+                        format!("{}.{}", prefix, k)
+                    };
+                    Self::flatten(&key, v, out);
+                }
+            }
+            v => {
+                out.insert(prefix.to_owned(), v.to_string());
+            }
+        }
+    }
+
+    fn flatten_config(config: &ConfigSource) -> anyhow::Result<BTreeMap<String, String>> {
+        let mut out = BTreeMap::new();
+        Self::flatten("", &toml::Value::try_from(&config.profile)?, &mut out);
+        if let Some(commit) = &config.commit {
+            out.insert("commit".to_owned(), commit.clone());
+        }
+        Ok(out)
+    }
+
+    pub fn run(&self) -> anyhow::Result<()> {
+        let a = Self::flatten_config(&self.load(&self.a)?)?;
+        let b = Self::flatten_config(&self.load(&self.b)?)?;
+        println!(
+            "{} {} {}\n",
+            self.a.bold().italic(),
+            "➔".bold(),
+            self.b.bold().italic()
+        );
+        let mut keys = a.keys().chain(b.keys()).collect::<Vec<_>>();
+        keys.sort();
+        keys.dedup();
+        let mut changes = 0;
+        let mut last_section = None;
+        for key in keys {
+            let (old, new) = (a.get(key), b.get(key));
+            if old == new {
+                continue;
+            }
+            changes += 1;
+            // Group the changes by the top-level key (builds, probes, env, ...)
+            let section = key.split('.').next().unwrap_or_default();
+            if last_section != Some(section) {
+                println!("{}", format!("[{}]", section).bold().blue());
+                last_section = Some(section);
+            }
+            match (old, new) {
+                (Some(old), Some(new)) => println!(
+                    "  {} {}: {} {} {}",
+                    "~".yellow(),
+                    key,
+                    old.italic(),
+                    "➔".bold(),
+                    new.italic()
+                ),
+                (Some(old), None) => println!("  {} {}: {}", "-".red(), key.red(), old.red()),
+                (None, Some(new)) => {
+                    println!("  {} {}: {}", "+".green(), key.green(), new.green())
+                }
+                (None, None) => unreachable!(),
+            }
+        }
+        if changes == 0 {
+            println!("{}", "No differences.".green());
+        }
+        Ok(())
+    }
+}
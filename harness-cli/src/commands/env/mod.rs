@@ -0,0 +1,288 @@
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use clap::{Parser, Subcommand};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+/// The systemd units that are kept off the isolated CPUs. The user slice is not restricted, as harness itself runs there.
+const RESTRICTED_UNITS: &[&str] = &["system.slice", "init.scope"];
+
+/// The settings changed by `env setup`, with their original values
+#[derive(Serialize, Deserialize, Default)]
+struct SavedEnv {
+    /// The original content of each modified sysfs or procfs file
+    files: BTreeMap<String, String>,
+    /// The systemd units that are restricted to the non-isolated CPUs
+    #[serde(
+        default,
+        rename = "restricted-units",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    restricted_units: Vec<String>,
+}
+
+/// The root-owned directory of the saved settings. It is a tmpfs, as the settings are also reset on reboot.
+const SAVED_ENV_DIR: &str = "/run/harness";
+
+fn saved_env_file() -> PathBuf {
+    Path::new(SAVED_ENV_DIR).join("env.toml")
+}
+
+/// Check that a file or directory is owned by root and only writable by root, so its content can be trusted
+#[cfg(unix)]
+fn check_root_owned(path: &Path) -> anyhow::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+    let meta = std::fs::symlink_metadata(path)?;
+    if meta.file_type().is_symlink() || meta.uid() != 0 || meta.mode() & 0o022 != 0 {
+        anyhow::bail!(
+            "{} must be owned by root and only writable by root.",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_root_owned(_path: &Path) -> anyhow::Result<()> {
+    unreachable!()
+}
+
+#[cfg(unix)]
+fn is_root() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+#[cfg(not(unix))]
+fn is_root() -> bool {
+    false
+}
+
+/// Run a command as root, with `sudo` if the current user is not root
+fn run_as_root(program: &str, args: &[&str], stdin: Option<&str>) -> anyhow::Result<()> {
+    let mut cmd = if is_root() {
+        Command::new(program)
+    } else {
+        let mut cmd = Command::new("sudo");
+        cmd.arg(program);
+        cmd
+    };
+    cmd.args(args).stdout(Stdio::null());
+    if stdin.is_some() {
+        cmd.stdin(Stdio::piped());
+    }
+    let mut child = cmd.spawn()?;
+    if let Some(input) = stdin {
+        child.stdin.take().unwrap().write_all(input.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        anyhow::bail!("`{} {}` failed: {}", program, args.join(" "), status);
+    }
+    Ok(())
+}
+
+fn write_as_root(path: &str, value: &str) -> anyhow::Result<()> {
+    if is_root() {
+        std::fs::write(path, value)
+            .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path, e))?;
+        return Ok(());
+    }
+    run_as_root("tee", &[path], Some(value))
+}
+
+/// The files to change for benchmarking, and their new values
+fn target_settings() -> Vec<(PathBuf, &'static str)> {
+    let mut settings = vec![];
+    let cpu_dir = Path::new("/sys/devices/system/cpu/");
+    let mut governors = std::fs::read_dir(cpu_dir)
+        .map(|d| {
+            d.flatten()
+                .map(|e| e.path().join("cpufreq/scaling_governor"))
+                .filter(|p| p.exists())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    governors.sort();
+    settings.extend(governors.into_iter().map(|p| (p, "performance")));
+    let no_turbo = cpu_dir.join("intel_pstate/no_turbo");
+    let boost = cpu_dir.join("cpufreq/boost");
+    if no_turbo.exists() {
+        settings.push((no_turbo, "1"));
+    } else if boost.exists() {
+        settings.push((boost, "0"));
+    }
+    settings.push(("/proc/sys/kernel/perf_event_paranoid".into(), "-1"));
+    settings
+}
+
+/// Prepare the machine for benchmarking, and restore it afterwards (*Linux only*).
+///
+/// This requires root privileges. The changes are applied with `sudo` if the current user is not root.
+#[derive(Parser)]
+pub struct EnvArgs {
+    #[command(subcommand)]
+    command: EnvCommand,
+}
+
+#[derive(Subcommand)]
+enum EnvCommand {
+    /// Set the scaling governors to `performance`, disable turbo boost, set `perf_event_paranoid` to -1, and optionally
+    /// keep the system services off some CPUs. The previous settings are saved for `restore`.
+    Setup(EnvSetupArgs),
+    /// Restore the settings saved by `setup`
+    Restore,
+}
+
+#[derive(Parser)]
+struct EnvSetupArgs {
+    /// Keep the system services off these CPUs (e.g. `2,3`), and pin the benchmarks to them with `cargo harness run --cpus`
+    #[arg(long, value_delimiter = ',', value_name = "CPUS")]
+    isolate_cpus: Option<Vec<usize>>,
+    /// Only print the settings that would be changed
+    #[arg(long, default_value = "false")]
+    dry_run: bool,
+}
+
+impl EnvArgs {
+    /// The online CPUs other than the isolated ones, as a comma-separated list
+    #[cfg(target_os = "linux")]
+    fn non_isolated_cpus(isolated: &[usize]) -> anyhow::Result<String> {
+        let online = std::fs::read_to_string("/sys/devices/system/cpu/online")?;
+        let online = crate::utils::sys::parse_cpu_list(&online);
+        if let Some(cpu) = isolated.iter().find(|c| !online.contains(c)) {
+            anyhow::bail!("CPU {} is not online.", cpu);
+        }
+        let others = online
+            .iter()
+            .filter(|c| !isolated.contains(c))
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>();
+        if others.is_empty() {
+            anyhow::bail!("Cannot isolate all the CPUs.");
+        }
+        Ok(others.join(","))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn non_isolated_cpus(_isolated: &[usize]) -> anyhow::Result<String> {
+        unreachable!()
+    }
+
+    fn setup(&self, args: &EnvSetupArgs) -> anyhow::Result<()> {
+        let saved_file = saved_env_file();
+        if saved_file.exists() {
+            anyhow::bail!(
+                "The environment is already set up. Run `cargo harness env restore` first. The saved settings are in {}.",
+                saved_file.display()
+            );
+        }
+        let mut saved = SavedEnv::default();
+        let mut changes = vec![];
+        for (path, value) in target_settings() {
+            let path = path.display().to_string();
+            let old = std::fs::read_to_string(&path)?.trim().to_owned();
+            if old != value {
+                saved.files.insert(path.clone(), old.clone());
+                changes.push((path, old, value));
+            }
+        }
+        let allowed_cpus = match &args.isolate_cpus {
+            Some(cpus) => Some(Self::non_isolated_cpus(cpus)?),
+            None => None,
+        };
+        if allowed_cpus.is_some() {
+            saved.restricted_units = RESTRICTED_UNITS.iter().map(|u| u.to_string()).collect();
+        }
+        if args.dry_run {
+            for (path, old, value) in &changes {
+                println!("Would set {}: {} ➔ {}", path, old, value);
+            }
+            if let Some(cpus) = &allowed_cpus {
+                for unit in RESTRICTED_UNITS {
+                    println!("Would restrict {} to CPUs {}", unit, cpus);
+                }
+            }
+            return Ok(());
+        }
+        // Save the settings first, so a partially applied setup can still be restored
+        run_as_root("mkdir", &["-p", "-m", "755", SAVED_ENV_DIR], None)?;
+        check_root_owned(Path::new(SAVED_ENV_DIR))?;
+        write_as_root(&saved_file.to_string_lossy(), &toml::to_string(&saved)?)?;
+        for (path, old, value) in &changes {
+            write_as_root(path, value)?;
+            println!("{} {}: {} ➔ {}", "✔".green(), path, old, value);
+        }
+        if let Some(cpus) = &allowed_cpus {
+            for unit in RESTRICTED_UNITS {
+                let property = format!("AllowedCPUs={}", cpus);
+                run_as_root(
+                    "systemctl",
+                    &["set-property", "--runtime", unit, &property],
+                    None,
+                )?;
+                println!("{} {}: restricted to CPUs {}", "✔".green(), unit, cpus);
+            }
+        }
+        println!(
+            "{}",
+            "Environment is set up. Run `cargo harness env restore` to restore the previous settings."
+                .green()
+        );
+        Ok(())
+    }
+
+    fn restore(&self) -> anyhow::Result<()> {
+        let saved_file = saved_env_file();
+        if !saved_file.exists() {
+            anyhow::bail!("No saved settings found. Run `cargo harness env setup` first.");
+        }
+        // The settings are restored as root, so only trust a root-owned file, and only the files and units that `setup` changes
+        check_root_owned(Path::new(SAVED_ENV_DIR))?;
+        check_root_owned(&saved_file)?;
+        let saved: SavedEnv = toml::from_str(&std::fs::read_to_string(&saved_file)?)?;
+        let targets = target_settings()
+            .into_iter()
+            .map(|(path, _)| path.display().to_string())
+            .collect::<Vec<_>>();
+        if let Some(path) = saved.files.keys().find(|p| !targets.contains(p)) {
+            anyhow::bail!("Refusing to restore {}: not changed by `setup`.", path);
+        }
+        if let Some(unit) = saved
+            .restricted_units
+            .iter()
+            .find(|u| !RESTRICTED_UNITS.contains(&u.as_str()))
+        {
+            anyhow::bail!("Refusing to restore {}: not changed by `setup`.", unit);
+        }
+        for (path, value) in &saved.files {
+            write_as_root(path, value)?;
+            println!("{} {}: {}", "✔".green(), path, value);
+        }
+        for unit in &saved.restricted_units {
+            run_as_root(
+                "systemctl",
+                &["set-property", "--runtime", unit, "AllowedCPUs="],
+                None,
+            )?;
+            println!("{} {}: all CPUs allowed", "✔".green(), unit);
+        }
+        run_as_root("rm", &["-f", &saved_file.to_string_lossy()], None)?;
+        println!("{}", "Environment is restored.".green());
+        Ok(())
+    }
+
+    pub fn run(&self) -> anyhow::Result<()> {
+        if !cfg!(target_os = "linux") {
+            anyhow::bail!("`cargo harness env` is only supported on Linux.");
+        }
+        match &self.command {
+            EnvCommand::Setup(args) => self.setup(args),
+            EnvCommand::Restore => self.restore(),
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use serde_json::{json, Map, Value};
+
+use crate::commands::report::data::{Bootstrap, ReportData, Summary};
+
+/// bencher.dev measures latency in nanoseconds, and harness in milliseconds
+const NS_PER_MS: f64 = 1e6;
+
+/// The bencher.dev measure of a metric. `time` is mapped to the built-in `latency` measure, and the other metrics to a measure of the same name.
+fn measure(metric: &str) -> (String, f64) {
+    if metric == "time" {
+        return ("latency".to_owned(), NS_PER_MS);
+    }
+    let slug = metric
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect::<String>();
+    (slug, 1.0)
+}
+
+/// Convert the results to the Bencher Metric Format (BMF): `{ <benchmark>: { <measure>: { value, lower_value, upper_value } } }`.
+///
+/// The value is the mean of each benchmark, bounded by its bootstrap confidence interval.
+/// Benchmarks are named `<bench>/<build>` if more than one build is exported.
+pub fn export(data: &ReportData, builds: &[&String]) -> Value {
+    let bootstrap = Bootstrap::default();
+    let mut benchmarks = Map::new();
+    for bench in &data.benches {
+        for build in builds {
+            let mut measures = Map::new();
+            for metric in &data.metrics {
+                let values = data.values(bench, build, metric);
+                let Some(summary) = Summary::new(values) else {
+                    continue;
+                };
+                let (slug, scale) = measure(metric);
+                let mut m = json!({ "value": summary.mean * scale });
+                if let Some(e) =
+                    bootstrap.estimate(values, |v| v.iter().sum::<f64>() / v.len() as f64)
+                {
+                    m["lower_value"] = json!(e.ci.0 * scale);
+                    m["upper_value"] = json!(e.ci.1 * scale);
+                }
+                measures.insert(slug, m);
+            }
+            if measures.is_empty() {
+                continue;
+            }
+            let name = if builds.len() > 1 {
+                format!("{}/{}", bench, build)
+            } else {
+                bench.clone()
+            };
+            benchmarks.insert(name, Value::Object(measures));
+        }
+    }
+    Value::Object(benchmarks)
+}
@@ -0,0 +1,74 @@
+use serde_json::{json, Value};
+
+use crate::{
+    commands::report::data::{quantile, ReportData, Summary},
+    configs::run_info::RunInfo,
+};
+
+/// CodSpeed measures wall time in nanoseconds, and harness in milliseconds
+const NS_PER_MS: f64 = 1e6;
+
+/// Convert the wall times of a build to CodSpeed's walltime results format, as written by its benchmark integrations to `$CODSPEED_PROFILE_FOLDER/results/<pid>.json`.
+///
+/// Each invocation is one round, of the measured iterations. The other iterations of each invocation are the warmup iterations.
+pub fn export(run: &RunInfo, data: &ReportData, build: &str) -> Value {
+    let iter_per_round = run.profile.measured_iterations.max(1);
+    let warmup_iters = run.profile.warmup_iterations;
+    let mut benchmarks = vec![];
+    for bench in &data.benches {
+        let times = data
+            .values(bench, build, "time")
+            .iter()
+            .map(|t| t * NS_PER_MS)
+            .collect::<Vec<_>>();
+        let Some(summary) = Summary::new(&times) else {
+            continue;
+        };
+        let mut sorted = times.clone();
+        sorted.sort_by(f64::total_cmp);
+        let (q1, q3) = (quantile(&sorted, 0.25), quantile(&sorted, 0.75));
+        let iqr = q3 - q1;
+        let iqr_outliers = times
+            .iter()
+            .filter(|t| **t < q1 - 1.5 * iqr || **t > q3 + 1.5 * iqr)
+            .count();
+        let stdev_outliers = times
+            .iter()
+            .filter(|t| (**t - summary.mean).abs() > summary.stddev)
+            .count();
+        benchmarks.push(json!({
+            "name": bench,
+            "uri": format!("{}::{}", run.project, bench),
+            "config": {
+                "warmup_time_ns": null,
+                "min_round_time_ns": null,
+                "max_time_ns": null,
+                "max_rounds": run.profile.invocations,
+            },
+            "stats": {
+                "min_ns": sorted[0],
+                "max_ns": sorted[sorted.len() - 1],
+                "mean_ns": summary.mean,
+                "stdev_ns": summary.stddev,
+                "q1_ns": q1,
+                "median_ns": quantile(&sorted, 0.5),
+                "q3_ns": q3,
+                "rounds": times.len(),
+                "total_time": times.iter().sum::<f64>() * iter_per_round as f64 / 1e9,
+                "iqr_outlier_rounds": iqr_outliers,
+                "stdev_outlier_rounds": stdev_outliers,
+                "iter_per_round": iter_per_round,
+                "warmup_iters": warmup_iters,
+            },
+        }));
+    }
+    json!({
+        "creator": {
+            "name": "harness",
+            "version": env!("CARGO_PKG_VERSION"),
+            "pid": std::process::id(),
+        },
+        "instrument": { "type": "walltime" },
+        "benchmarks": benchmarks,
+    })
+}
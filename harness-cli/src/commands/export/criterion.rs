@@ -0,0 +1,112 @@
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::commands::report::data::{quantile, Bootstrap, Estimate, ReportData};
+
+/// Criterion measures wall time in nanoseconds, and harness in milliseconds
+const NS_PER_MS: f64 = 1e6;
+
+fn median(values: &[f64]) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    quantile(&sorted, 0.5)
+}
+
+/// The median absolute deviation, scaled to be a consistent estimator of the standard deviation, as in criterion
+fn median_abs_dev(values: &[f64]) -> f64 {
+    let m = median(values);
+    median(&values.iter().map(|x| (x - m).abs()).collect::<Vec<_>>()) * 1.4826
+}
+
+fn std_dev(values: &[f64]) -> f64 {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    (values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt()
+}
+
+fn estimate_json(e: Estimate, confidence: f64) -> Value {
+    json!({
+        "confidence_interval": {
+            "confidence_level": confidence,
+            "lower_bound": e.ci.0,
+            "upper_bound": e.ci.1,
+        },
+        "point_estimate": e.point,
+        "standard_error": e.standard_error,
+    })
+}
+
+/// Export the wall times of each (bench, build) pair to `<out>/<bench>/<build>`, i.e. each build as a criterion baseline.
+///
+/// Each invocation is one criterion sample of one iteration. Pairs with less than two invocations are skipped, as criterion requires confidence intervals.
+/// Returns the number of exported pairs.
+pub fn export(data: &ReportData, builds: &[&String], out: &Path) -> anyhow::Result<usize> {
+    let bootstrap = Bootstrap::default();
+    let mut exported = 0;
+    for bench in &data.benches {
+        for build in builds {
+            let times = data
+                .values(bench, build, "time")
+                .iter()
+                .map(|t| t * NS_PER_MS)
+                .collect::<Vec<_>>();
+            let estimate = |statistic: fn(&[f64]) -> f64| {
+                bootstrap
+                    .estimate(&times, statistic)
+                    .map(|e| estimate_json(e, bootstrap.confidence))
+            };
+            let (Some(mean), Some(median), Some(mad), Some(std_dev)) = (
+                estimate(|v| v.iter().sum::<f64>() / v.len() as f64),
+                estimate(median),
+                estimate(median_abs_dev),
+                estimate(std_dev),
+            ) else {
+                continue;
+            };
+            let dir = out.join(bench).join(build);
+            std::fs::create_dir_all(&dir)?;
+            let benchmark = json!({
+                "group_id": bench,
+                "function_id": null,
+                "value_str": null,
+                "throughput": null,
+                "full_id": bench,
+                "directory_name": bench,
+                "title": bench,
+            });
+            let estimates = json!({
+                "mean": mean,
+                "median": median,
+                "median_abs_dev": mad,
+                "slope": null,
+                "std_dev": std_dev,
+            });
+            let sample = json!({
+                "sampling_mode": "Flat",
+                "iters": vec![1.0; times.len()],
+                "times": times,
+            });
+            let mut sorted = times.clone();
+            sorted.sort_by(f64::total_cmp);
+            let (q1, q3) = (quantile(&sorted, 0.25), quantile(&sorted, 0.75));
+            let iqr = q3 - q1;
+            let tukey = json!([
+                q1 - 3.0 * iqr,
+                q1 - 1.5 * iqr,
+                q3 + 1.5 * iqr,
+                q3 + 3.0 * iqr
+            ]);
+            for (name, value) in [
+                ("benchmark.json", benchmark),
+                ("estimates.json", estimates),
+                ("sample.json", sample),
+                ("tukey.json", tukey),
+            ] {
+                std::fs::write(dir.join(name), serde_json::to_string(&value)?)?;
+            }
+            exported += 1;
+        }
+    }
+    Ok(exported)
+}
@@ -0,0 +1,64 @@
+use crate::configs::{harness::PushConfig, run_info::RunInfo};
+
+use super::BenchSummary;
+
+/// Escape a measurement name, tag key, tag value, or field key of the line protocol
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace('=', "\\=")
+        .replace(' ', "\\ ")
+}
+
+/// Format the summaries in the InfluxDB line protocol.
+///
+/// Each (bench, build) pair is a point of the `harness` measurement, tagged with the project, profile, run, commit, bench, and build,
+/// with the fields `<metric>` (the mean), `<metric>_ci_lower`, `<metric>_ci_upper`, and `<metric>_invocations`, at the start time of the run.
+pub fn payload(run: &RunInfo, summaries: &[BenchSummary]) -> String {
+    let mut points = std::collections::BTreeMap::<(&str, &str, &str), Vec<String>>::new();
+    for s in summaries {
+        let metric = escape(&s.metric);
+        let fields = points.entry((&s.bench, &s.build, &s.commit)).or_default();
+        fields.push(format!("{}={}", metric, s.summary.mean));
+        fields.push(format!("{}_invocations={}i", metric, s.summary.n));
+        if let Some((lo, hi)) = s.ci {
+            fields.push(format!("{}_ci_lower={}", metric, lo));
+            fields.push(format!("{}_ci_upper={}", metric, hi));
+        }
+    }
+    let mut lines = String::new();
+    for ((bench, build, commit), fields) in points {
+        lines += &format!(
+            "harness,project={},profile={},runid={},commit={},bench={},build={} {} {}\n",
+            escape(&run.project),
+            escape(&run.profile.name),
+            escape(&run.runid),
+            escape(commit),
+            escape(bench),
+            escape(build),
+            fields.join(","),
+            run.start_timestamp_utc as i128 * 1_000_000_000
+        );
+    }
+    lines
+}
+
+/// Write the points to the InfluxDB write endpoint
+pub fn push(config: &PushConfig, url: &str, payload: String) -> anyhow::Result<()> {
+    let mut request = reqwest::blocking::Client::new()
+        .post(url)
+        .header("Content-Type", "text/plain; charset=utf-8")
+        .body(payload);
+    if let Ok(token) = std::env::var(&config.token_env) {
+        request = request.header("Authorization", format!("Token {}", token));
+    }
+    let response = request.send()?;
+    if !response.status().is_success() {
+        anyhow::bail!(
+            "Failed to write to InfluxDB: {} ({})",
+            response.status(),
+            response.text().unwrap_or_default().trim()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,234 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::{
+    commands::report::data::{Bootstrap, ReportData, Summary},
+    configs::{
+        harness::{HarnessConfig, PushConfig},
+        run_info::{CrateInfo, RunInfo},
+    },
+};
+
+mod bencher;
+mod codspeed;
+mod criterion;
+mod influxdb;
+mod prometheus;
+
+/// The format of the exported results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// The `target/criterion` directory layout of criterion.rs, with each build as a baseline. Only wall times are exported.
+    Criterion,
+    /// The Bencher Metric Format (BMF) JSON of bencher.dev, for `bencher run --adapter json --file <OUTPUT>`
+    BencherJson,
+    /// The walltime results JSON of CodSpeed. Only the wall times of one build are exported.
+    Codspeed,
+    /// Push the summary metrics to the Prometheus pushgateway at `push.prometheus` of the profile
+    Prometheus,
+    /// Push the summary metrics to the InfluxDB endpoint at `push.influxdb` of the profile
+    Influxdb,
+}
+
+/// The summary of a metric of a (bench, build) pair, for the monitoring endpoints
+struct BenchSummary {
+    bench: String,
+    build: String,
+    /// The commit of the build
+    commit: String,
+    metric: String,
+    summary: Summary,
+    /// The bootstrap confidence interval of the mean
+    ci: Option<(f64, f64)>,
+}
+
+fn bench_summaries(run: &RunInfo, data: &ReportData, builds: &[&String]) -> Vec<BenchSummary> {
+    let bootstrap = Bootstrap::default();
+    let mut summaries = vec![];
+    for bench in &data.benches {
+        for build in builds {
+            for metric in &data.metrics {
+                let values = data.values(bench, build, metric);
+                let Some(summary) = Summary::new(values) else {
+                    continue;
+                };
+                let ci = bootstrap
+                    .estimate(values, |v| v.iter().sum::<f64>() / v.len() as f64)
+                    .map(|e| e.ci);
+                let commit = run
+                    .profile
+                    .builds
+                    .get(*build)
+                    .and_then(|b| {
+                        b.commit.clone().or_else(|| {
+                            let version = b.version.as_ref()?;
+                            Some(format!("{}@{}", run.crate_info.name, version))
+                        })
+                    })
+                    .unwrap_or_else(|| run.commit.clone());
+                summaries.push(BenchSummary {
+                    bench: bench.clone(),
+                    build: build.to_string(),
+                    commit,
+                    metric: metric.clone(),
+                    summary,
+                    ci,
+                });
+            }
+        }
+    }
+    summaries
+}
+
+/// Export the results of a run for other benchmarking tools
+#[derive(Parser)]
+pub struct ExportArgs {
+    /// The run id to export. Default to the latest run.
+    pub run_id: Option<String>,
+    /// The export format
+    #[arg(long, value_enum)]
+    pub format: ExportFormat,
+    /// The output path. Default to `target/criterion` for `criterion`,
+    /// `$CODSPEED_PROFILE_FOLDER/results/<pid>.json` for `codspeed` if the variable is set,
+    /// and `<format>.json` in the log dir of the run otherwise.
+    /// For `prometheus` and `influxdb`, the metrics are written to this file instead of being pushed.
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+    /// Only export this build. Required by `codspeed` if the run has more than one build.
+    #[arg(long)]
+    pub build: Option<String>,
+}
+
+impl ExportArgs {
+    /// The push config of the run's profile. The current config in Cargo.toml takes precedence over the config recorded in the run,
+    /// so that the endpoints can be added or changed after the run.
+    fn push_config(run: &RunInfo) -> Option<PushConfig> {
+        HarnessConfig::load_from_cargo_toml()
+            .ok()
+            .and_then(|c| c.profiles.get(&run.profile.name)?.push.clone())
+            .or_else(|| run.profile.push.clone())
+    }
+
+    fn push(&self, run: &RunInfo, data: &ReportData, builds: &[&String]) -> anyhow::Result<()> {
+        let summaries = bench_summaries(run, data, builds);
+        let payload = match self.format {
+            ExportFormat::Prometheus => prometheus::payload(run, &summaries),
+            _ => influxdb::payload(run, &summaries),
+        };
+        if let Some(out) = &self.output {
+            std::fs::write(out, payload)?;
+            println!("Exported run `{}` to {}", run.runid, out.display());
+            return Ok(());
+        }
+        let config = Self::push_config(run);
+        let endpoint = |url: Option<&String>, key: &str| match url {
+            Some(url) => Ok(url.clone()),
+            None => Err(anyhow::anyhow!(
+                "No `push.{}` endpoint in profile `{}`",
+                key,
+                run.profile.name
+            )),
+        };
+        match self.format {
+            ExportFormat::Prometheus => {
+                let url = endpoint(
+                    config.as_ref().and_then(|c| c.prometheus.as_ref()),
+                    "prometheus",
+                )?;
+                prometheus::push(config.as_ref().unwrap(), &url, run, payload)?;
+                println!("Pushed run `{}` to {}", run.runid, url);
+            }
+            _ => {
+                let url = endpoint(
+                    config.as_ref().and_then(|c| c.influxdb.as_ref()),
+                    "influxdb",
+                )?;
+                influxdb::push(config.as_ref().unwrap(), &url, payload)?;
+                println!("Pushed run `{}` to {}", run.runid, url);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn run(&self) -> anyhow::Result<()> {
+        let target_dir = CrateInfo::get_target_path()?;
+        let log_dir = target_dir
+            .join("harness")
+            .join("logs")
+            .join(self.run_id.as_deref().unwrap_or("latest"));
+        if !log_dir.exists() {
+            anyhow::bail!("Log dir not found: {}", log_dir.display());
+        }
+        let run = RunInfo::load(&log_dir.join("config.toml"))?;
+        let data = ReportData::load(&log_dir, &run)?;
+        if data.benches.is_empty() {
+            anyhow::bail!("No results found in run `{}`", run.runid);
+        }
+        let builds = match &self.build {
+            Some(b) if !data.builds.contains(b) => anyhow::bail!("Build `{}` not found", b),
+            Some(b) => vec![b],
+            None => data.builds.iter().collect(),
+        };
+        let json_output = |name: &str| {
+            self.output
+                .clone()
+                .unwrap_or_else(|| log_dir.join(format!("{}.json", name)))
+        };
+        match self.format {
+            ExportFormat::Criterion => {
+                let out = self
+                    .output
+                    .clone()
+                    .unwrap_or_else(|| target_dir.join("criterion"));
+                let exported = criterion::export(&data, &builds, &out)?;
+                if exported == 0 {
+                    anyhow::bail!(
+                        "No benchmarks with at least two successful invocations of `time` in run `{}`",
+                        run.runid
+                    );
+                }
+                println!(
+                    "Exported {} benchmarks of run `{}` to {}",
+                    exported,
+                    run.runid,
+                    out.display()
+                );
+            }
+            ExportFormat::BencherJson => {
+                let out = json_output("bencher");
+                let json = bencher::export(&data, &builds);
+                std::fs::write(&out, serde_json::to_string_pretty(&json)?)?;
+                println!("Exported run `{}` to {}", run.runid, out.display());
+            }
+            ExportFormat::Codspeed => {
+                let [build] = builds[..] else {
+                    anyhow::bail!(
+                        "Run `{}` has more than one build. Please specify one with `--build`",
+                        run.runid
+                    );
+                };
+                let out = match std::env::var_os("CODSPEED_PROFILE_FOLDER") {
+                    Some(dir) if self.output.is_none() => {
+                        let dir = PathBuf::from(dir).join("results");
+                        std::fs::create_dir_all(&dir)?;
+                        dir.join(format!("{}.json", std::process::id()))
+                    }
+                    _ => json_output("codspeed"),
+                };
+                let json = codspeed::export(&run, &data, build);
+                std::fs::write(&out, serde_json::to_string_pretty(&json)?)?;
+                println!(
+                    "Exported build `{}` of run `{}` to {}",
+                    build,
+                    run.runid,
+                    out.display()
+                );
+            }
+            ExportFormat::Prometheus | ExportFormat::Influxdb => {
+                self.push(&run, &data, &builds)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,84 @@
+use crate::configs::{harness::PushConfig, run_info::RunInfo};
+
+use super::BenchSummary;
+
+/// A valid Prometheus metric name, with invalid characters replaced by `_`
+fn metric_name(metric: &str) -> String {
+    let name = metric
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    format!("harness_{}", name)
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Format the summaries in the Prometheus text exposition format.
+///
+/// Each metric is exported as `harness_<metric>` (the mean), `harness_<metric>_ci_lower`, `harness_<metric>_ci_upper`, and `harness_<metric>_invocations`,
+/// labelled with the run, commit, bench, and build.
+pub fn payload(run: &RunInfo, summaries: &[BenchSummary]) -> String {
+    let mut metrics = std::collections::BTreeMap::<String, Vec<String>>::new();
+    for s in summaries {
+        let labels = format!(
+            "{{project=\"{}\",runid=\"{}\",commit=\"{}\",bench=\"{}\",build=\"{}\"}}",
+            escape_label(&run.project),
+            escape_label(&run.runid),
+            escape_label(&s.commit),
+            escape_label(&s.bench),
+            escape_label(&s.build),
+        );
+        let name = metric_name(&s.metric);
+        let mut samples = vec![
+            (name.clone(), s.summary.mean),
+            (format!("{}_invocations", name), s.summary.n as f64),
+        ];
+        if let Some((lo, hi)) = s.ci {
+            samples.push((format!("{}_ci_lower", name), lo));
+            samples.push((format!("{}_ci_upper", name), hi));
+        }
+        for (name, value) in samples {
+            metrics
+                .entry(name.clone())
+                .or_default()
+                .push(format!("{}{} {}", name, labels, value));
+        }
+    }
+    let mut text = String::new();
+    for (name, lines) in metrics {
+        text += &format!("# TYPE {} gauge\n", name);
+        for line in lines {
+            text += &line;
+            text += "\n";
+        }
+    }
+    text
+}
+
+/// Push the metrics to the pushgateway, replacing the previous metrics of the same job and profile
+pub fn push(config: &PushConfig, url: &str, run: &RunInfo, payload: String) -> anyhow::Result<()> {
+    let url = format!(
+        "{}/metrics/job/{}/profile/{}",
+        url.trim_end_matches('/'),
+        config.job,
+        run.profile.name
+    );
+    let response = reqwest::blocking::Client::new()
+        .put(&url)
+        .header("Content-Type", "text/plain; version=0.0.4")
+        .body(payload)
+        .send()?;
+    if !response.status().is_success() {
+        anyhow::bail!(
+            "Failed to push to the Prometheus pushgateway: {} ({})",
+            response.status(),
+            response.text().unwrap_or_default().trim()
+        );
+    }
+    Ok(())
+}
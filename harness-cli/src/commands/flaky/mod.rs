@@ -0,0 +1,99 @@
+use std::collections::{BTreeMap, HashMap};
+
+use clap::Parser;
+use colored::Colorize;
+
+use crate::{
+    commands::history::HistoryIndex, configs::run_info::CrateInfo, print_md,
+    utils::events::FailureKind,
+};
+
+/// Show per-benchmark failure statistics across all previous runs
+#[derive(Parser)]
+pub struct FlakyArgs {
+    /// Only show benchmarks that failed at least once
+    #[arg(long, default_value = "false")]
+    pub failed_only: bool,
+}
+
+/// Accumulated failure statistics of a benchmark
+#[derive(Default)]
+struct BenchStats {
+    runs: usize,
+    invocations: usize,
+    failures: usize,
+    /// Failed attempts that were retried
+    retries: usize,
+    kinds: HashMap<FailureKind, usize>,
+}
+
+impl FlakyArgs {
+    pub fn run(&self) -> anyhow::Result<()> {
+        let logs_dir = CrateInfo::get_target_path()?.join("harness").join("logs");
+        // The failures of each run are accumulated in the history index, so only new or changed runs are read
+        let index_path = HistoryIndex::path()?;
+        let mut index = HistoryIndex::load(&index_path);
+        index.update(&logs_dir)?;
+        index.save(&index_path)?;
+        let mut stats = BTreeMap::<String, BenchStats>::new();
+        let mut runs = 0;
+        for (log_dir, run) in &index.runs {
+            if !log_dir.starts_with(&logs_dir) {
+                continue;
+            }
+            let Some(failures) = &run.failures else {
+                eprintln!(
+                    "{}",
+                    format!(
+                        "WARNING: Skipped {}: its events log is corrupt",
+                        log_dir.display()
+                    )
+                    .yellow()
+                );
+                continue;
+            };
+            runs += 1;
+            for (bench, f) in failures {
+                let s = stats.entry(bench.clone()).or_default();
+                s.runs += 1;
+                s.invocations += f.invocations;
+                s.failures += f.failures;
+                s.retries += f.retries;
+                for (kind, n) in &f.kinds {
+                    *s.kinds.entry(*kind).or_default() += n;
+                }
+            }
+        }
+        if runs == 0 {
+            anyhow::bail!("No previous runs found in {}", logs_dir.display());
+        }
+        print_md!("# Benchmark Failures\n\n");
+        print_md!("* runs: `{}`\n\n", runs);
+        let mut table =
+            "|bench|runs|invocations|failures|failure rate|retries|reasons|\n".to_owned();
+        table += "|:-|-:|-:|-:|-:|-:|:-|\n";
+        for (bench, s) in &stats {
+            if self.failed_only && s.failures == 0 && s.retries == 0 {
+                continue;
+            }
+            let mut kinds = s.kinds.iter().collect::<Vec<_>>();
+            kinds.sort();
+            let reasons = kinds
+                .iter()
+                .map(|(k, n)| format!("{} × {}", k.name(), n))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let rate = if s.invocations == 0 {
+                0.0
+            } else {
+                s.failures as f64 / s.invocations as f64 * 100.0
+            };
+            table += &format!(
+                "|{}|{}|{}|{}|{:.1}%|{}|{}|\n",
+                bench, s.runs, s.invocations, s.failures, rate, s.retries, reasons
+            );
+        }
+        print_md!("{}", table);
+        Ok(())
+    }
+}
@@ -0,0 +1,361 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use chrono::{DateTime, Local};
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    configs::run_info::{CrateInfo, RunInfo},
+    print_md,
+    utils::{
+        events::{self, Event, FailureKind},
+        results,
+    },
+};
+
+/// Show the trend of each benchmark across all previous runs
+#[derive(Parser)]
+pub struct HistoryArgs {
+    /// Only show this benchmark
+    #[arg(long)]
+    pub bench: Option<String>,
+    /// Only show this build
+    #[arg(long)]
+    pub build: Option<String>,
+    /// Only show runs of this profile
+    #[arg(long)]
+    pub profile: Option<String>,
+    /// The metric to show
+    #[arg(long, default_value = "time")]
+    pub metric: String,
+    /// Also index the runs under this directory. e.g. a user-level directory that collects logs from multiple crates or machines.
+    #[arg(long = "logs-dir", value_name = "DIR")]
+    pub logs_dirs: Vec<PathBuf>,
+    /// Export the trend table to a csv file
+    #[arg(long, value_name = "CSV")]
+    pub export: Option<PathBuf>,
+    /// Rebuild the index from scratch
+    #[arg(long, default_value = "false")]
+    pub reindex: bool,
+}
+
+/// The mean of a metric over the successful invocations of a (bench, build) pair in a run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedValue {
+    bench: String,
+    build: String,
+    metric: String,
+    mean: f64,
+    invocations: usize,
+}
+
+/// The failure statistics of a benchmark in a run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct IndexedFailures {
+    pub invocations: usize,
+    pub failures: usize,
+    /// Failed attempts that were retried
+    pub retries: usize,
+    pub kinds: HashMap<FailureKind, usize>,
+}
+
+/// The summary of a run in the history index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct IndexedRun {
+    runid: String,
+    profile: String,
+    commit: String,
+    #[serde(rename = "start-time-utc")]
+    start_timestamp_utc: i64,
+    /// The modification time of results.csv when the run was indexed. The run is re-indexed if results.csv changes.
+    #[serde(rename = "results-mtime")]
+    results_mtime: u64,
+    /// The modification time of events.jsonl when the run was indexed, or 0 if the run has no events log
+    #[serde(rename = "events-mtime")]
+    events_mtime: u64,
+    values: Vec<IndexedValue>,
+    /// bench -> the failure statistics of the benchmark. `None` if the events log of the run is corrupt.
+    pub failures: Option<BTreeMap<String, IndexedFailures>>,
+}
+
+/// The version of the history index. Indexes of other versions are rebuilt.
+const INDEX_VERSION: usize = 2;
+
+/// The history index at `target/harness/history.json`, keyed by the log directory of each run.
+///
+/// Summarizing a run requires parsing its results.csv file. The index caches the summaries so that querying the history stays fast as runs accumulate.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct HistoryIndex {
+    #[serde(default)]
+    version: usize,
+    pub runs: BTreeMap<PathBuf, IndexedRun>,
+}
+
+impl HistoryIndex {
+    pub fn path() -> anyhow::Result<PathBuf> {
+        Ok(CrateInfo::get_target_path()?
+            .join("harness")
+            .join("history.json"))
+    }
+
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|s| serde_json::from_str::<Self>(&s).ok())
+            .filter(|index| index.version == INDEX_VERSION)
+            .unwrap_or_default()
+    }
+
+    pub fn save(&mut self, path: &Path) -> anyhow::Result<()> {
+        self.version = INDEX_VERSION;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// The modification time of a file in seconds, or `None` if it does not exist
+    fn mtime(path: &Path) -> Option<u64> {
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+        Some(
+            modified
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        )
+    }
+
+    /// The failure statistics of each benchmark of a run, from its events log
+    fn index_failures(
+        log_dir: &Path,
+        run: &RunInfo,
+    ) -> anyhow::Result<BTreeMap<String, IndexedFailures>> {
+        let mut failures = BTreeMap::<String, IndexedFailures>::new();
+        let builds = run.profile.builds.len();
+        for bench in &run.crate_info.benches {
+            failures.entry(bench.clone()).or_default().invocations +=
+                run.profile.get_invocations(bench) * builds;
+        }
+        for event in events::load(log_dir)? {
+            match event {
+                Event::InvocationFailed { bench, kind, .. } => {
+                    let f = failures.entry(bench).or_default();
+                    f.failures += 1;
+                    *f.kinds.entry(kind).or_default() += 1;
+                }
+                Event::InvocationRetried { bench, kind, .. } => {
+                    let f = failures.entry(bench).or_default();
+                    f.retries += 1;
+                    *f.kinds.entry(kind).or_default() += 1;
+                }
+            }
+        }
+        Ok(failures)
+    }
+
+    fn index_run(
+        log_dir: &Path,
+        results_mtime: u64,
+        events_mtime: u64,
+    ) -> anyhow::Result<IndexedRun> {
+        let run = RunInfo::load(&log_dir.join("config.toml"))?;
+        let failures = Self::index_failures(log_dir, &run).ok();
+        let records = results::load_timing_records(
+            &log_dir.join("results.csv"),
+            run.profile.measured_iterations,
+        )?;
+        let mut values = BTreeMap::<(String, String, String), Vec<f64>>::new();
+        for r in records {
+            for (metric, v) in r.values {
+                values
+                    .entry((r.bench.clone(), r.build.clone(), metric))
+                    .or_default()
+                    .push(v);
+            }
+        }
+        Ok(IndexedRun {
+            runid: run.runid,
+            profile: run.profile.name.clone(),
+            commit: run.commit,
+            start_timestamp_utc: run.start_timestamp_utc,
+            results_mtime,
+            events_mtime,
+            failures,
+            values: values
+                .into_iter()
+                .map(|((bench, build, metric), v)| IndexedValue {
+                    bench,
+                    build,
+                    metric,
+                    mean: v.iter().sum::<f64>() / v.len() as f64,
+                    invocations: v.len(),
+                })
+                .collect(),
+        })
+    }
+
+    /// Index the new or changed runs under a logs directory, and drop the runs that no longer exist
+    pub fn update(&mut self, logs_dir: &Path) -> anyhow::Result<()> {
+        self.runs
+            .retain(|dir, _| !dir.starts_with(logs_dir) || dir.join("results.csv").exists());
+        if !logs_dir.exists() {
+            return Ok(());
+        }
+        for entry in std::fs::read_dir(logs_dir)?.flatten() {
+            let log_dir = entry.path();
+            if log_dir.is_symlink() || !log_dir.join("config.toml").exists() {
+                continue;
+            }
+            let Some(mtime) = Self::mtime(&log_dir.join("results.csv")) else {
+                continue;
+            };
+            let events_mtime = Self::mtime(&events::events_file(&log_dir)).unwrap_or(0);
+            if self
+                .runs
+                .get(&log_dir)
+                .is_some_and(|r| r.results_mtime == mtime && r.events_mtime == events_mtime)
+            {
+                continue;
+            }
+            // Skip the runs that cannot be parsed, e.g. from an incompatible harness version
+            if let Ok(run) = Self::index_run(&log_dir, mtime, events_mtime) {
+                self.runs.insert(log_dir, run);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl HistoryArgs {
+    fn format_time(timestamp_utc: i64) -> String {
+        DateTime::from_timestamp(timestamp_utc, 0)
+            .map(|t| t.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default()
+    }
+
+    fn export(&self, path: &Path, runs: &[&IndexedRun]) -> anyhow::Result<()> {
+        let mut csv = "date,runid,profile,commit,bench,build,metric,mean,invocations\n".to_owned();
+        for run in runs {
+            for v in self.values(run) {
+                csv += &format!(
+                    "{},{},{},{},{},{},{},{},{}\n",
+                    Self::format_time(run.start_timestamp_utc),
+                    run.runid,
+                    run.profile,
+                    run.commit,
+                    v.bench,
+                    v.build,
+                    v.metric,
+                    v.mean,
+                    v.invocations
+                );
+            }
+        }
+        std::fs::write(path, csv)?;
+        Ok(())
+    }
+
+    /// The values of a run that match the filters
+    fn values<'a>(&'a self, run: &'a IndexedRun) -> impl Iterator<Item = &'a IndexedValue> + 'a {
+        run.values.iter().filter(move |v| {
+            v.metric == self.metric
+                && self.bench.as_ref().is_none_or(|b| *b == v.bench)
+                && self.build.as_ref().is_none_or(|b| *b == v.build)
+        })
+    }
+
+    pub fn run(&self) -> anyhow::Result<()> {
+        let harness_dir = CrateInfo::get_target_path()?.join("harness");
+        let index_path = HistoryIndex::path()?;
+        let mut index = if self.reindex {
+            HistoryIndex::default()
+        } else {
+            HistoryIndex::load(&index_path)
+        };
+        let mut logs_dirs = vec![harness_dir.join("logs")];
+        for dir in &self.logs_dirs {
+            if !dir.exists() {
+                anyhow::bail!("Logs dir not found: {}", dir.display());
+            }
+            logs_dirs.push(dir.canonicalize()?);
+        }
+        for dir in &logs_dirs {
+            index.update(dir)?;
+        }
+        index.save(&index_path)?;
+        // Runs with matching results, oldest first
+        let mut runs = index
+            .runs
+            .iter()
+            .filter(|(dir, _)| logs_dirs.iter().any(|d| dir.starts_with(d)))
+            .map(|(_, r)| r)
+            .filter(|r| self.profile.as_ref().is_none_or(|p| *p == r.profile))
+            .filter(|r| self.values(r).next().is_some())
+            .collect::<Vec<_>>();
+        runs.sort_by_key(|r| r.start_timestamp_utc);
+        if runs.is_empty() {
+            anyhow::bail!("No `{}` results found in previous runs", self.metric);
+        }
+        if let Some(path) = &self.export {
+            self.export(path, &runs)?;
+            println!("Trend table exported to {}", path.display());
+            return Ok(());
+        }
+        let benches = runs
+            .iter()
+            .flat_map(|r| self.values(r).map(|v| v.bench.as_str()))
+            .collect::<BTreeSet<_>>();
+        print_md!("# Benchmark History\n\n");
+        print_md!("* runs: `{}`\n", runs.len());
+        print_md!(
+            "* metric: `{}` (mean of the successful invocations)\n\n",
+            self.metric
+        );
+        for bench in benches {
+            let builds = runs
+                .iter()
+                .flat_map(|r| self.values(r))
+                .filter(|v| v.bench == bench)
+                .map(|v| v.build.as_str())
+                .collect::<BTreeSet<_>>();
+            print_md!("## {}\n\n", bench);
+            let mut table = "|date|run|commit|".to_owned();
+            for build in &builds {
+                table += &format!("{}|", build);
+            }
+            table += "\n|:-|:-|:-|";
+            table += &"-:|".repeat(builds.len());
+            table += "\n";
+            for run in &runs {
+                let values = self
+                    .values(run)
+                    .filter(|v| v.bench == bench)
+                    .map(|v| (v.build.as_str(), v.mean))
+                    .collect::<BTreeMap<_, _>>();
+                if values.is_empty() {
+                    continue;
+                }
+                table += &format!(
+                    "|{}|{}|{}|",
+                    Self::format_time(run.start_timestamp_utc),
+                    run.runid,
+                    &run.commit[..run.commit.len().min(8)]
+                );
+                for build in &builds {
+                    match values.get(build) {
+                        Some(v) => table += &format!("{:.3}|", v),
+                        None => table += "-|",
+                    }
+                }
+                table += "\n";
+            }
+            print_md!("{}\n", table);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,282 @@
+use std::{collections::BTreeSet, path::Path};
+
+use clap::Parser;
+use colored::Colorize;
+
+use crate::{
+    configs::run_info::{CrateInfo, RunInfo},
+    print_md,
+    utils::results::{self, TimingRecord},
+};
+
+/// Analyze several runs of the same configuration as replicates
+#[derive(Parser)]
+pub struct MetaArgs {
+    /// The run ids to analyze. At least two runs are required.
+    #[arg(required = true, num_args = 2..)]
+    pub run_ids: Vec<String>,
+    /// The metric to analyze
+    #[arg(long, default_value = "time")]
+    pub metric: String,
+    /// The baseline build to compare other builds against. Default to the first build in alphabetical order.
+    #[arg(long)]
+    pub baseline: Option<String>,
+}
+
+/// A run and its timing records
+struct Replicate {
+    runid: String,
+    records: Vec<TimingRecord>,
+}
+
+/// The variance components of a metric, from a one-way random-effects ANOVA with runs as groups.
+struct VarianceComponents {
+    runs: usize,
+    invocations: usize,
+    mean: f64,
+    /// Variance between invocations of the same run
+    within: f64,
+    /// Variance between runs, after removing the within-run noise
+    between: f64,
+}
+
+impl VarianceComponents {
+    fn new(groups: &[Vec<f64>]) -> Option<Self> {
+        let groups = groups.iter().filter(|g| !g.is_empty()).collect::<Vec<_>>();
+        let k = groups.len();
+        let n = groups.iter().map(|g| g.len()).sum::<usize>();
+        if k < 2 || n <= k {
+            return None;
+        }
+        let mean_of = |g: &[f64]| g.iter().sum::<f64>() / g.len() as f64;
+        let grand_mean = groups.iter().flat_map(|g| g.iter()).sum::<f64>() / n as f64;
+        let ss_within = groups
+            .iter()
+            .map(|g| {
+                let m = mean_of(g);
+                g.iter().map(|x| (x - m).powi(2)).sum::<f64>()
+            })
+            .sum::<f64>();
+        let ss_between = groups
+            .iter()
+            .map(|g| g.len() as f64 * (mean_of(g) - grand_mean).powi(2))
+            .sum::<f64>();
+        let ms_within = ss_within / (n - k) as f64;
+        let ms_between = ss_between / (k - 1) as f64;
+        // The effective group size for unbalanced groups
+        let n0 = (n as f64
+            - groups
+                .iter()
+                .map(|g| (g.len() * g.len()) as f64)
+                .sum::<f64>()
+                / n as f64)
+            / (k - 1) as f64;
+        Some(Self {
+            runs: k,
+            invocations: n,
+            mean: grand_mean,
+            within: ms_within,
+            between: ((ms_between - ms_within) / n0).max(0.0),
+        })
+    }
+
+    /// The fraction of the total variance that is caused by run-to-run differences
+    fn between_share(&self) -> f64 {
+        let total = self.within + self.between;
+        if total == 0.0 {
+            0.0
+        } else {
+            self.between / total
+        }
+    }
+}
+
+impl MetaArgs {
+    fn load_replicate(&self, logs_dir: &Path, runid: &str) -> anyhow::Result<(RunInfo, Replicate)> {
+        let log_dir = logs_dir.join(runid);
+        if !log_dir.exists() {
+            anyhow::bail!("Log dir not found: {}", log_dir.display());
+        }
+        let run = RunInfo::load(&log_dir.join("config.toml"))?;
+        let records = results::load_timing_records(
+            &log_dir.join("results.csv"),
+            run.profile.measured_iterations,
+        )?;
+        Ok((
+            run,
+            Replicate {
+                runid: runid.to_owned(),
+                records,
+            },
+        ))
+    }
+
+    /// The metric values of each invocation of `bench` and `build` in a run
+    fn values(&self, replicate: &Replicate, bench: &str, build: &str) -> Vec<f64> {
+        replicate
+            .records
+            .iter()
+            .filter(|r| r.bench == bench && r.build == build)
+            .filter_map(|r| r.values.get(&self.metric).copied())
+            .collect()
+    }
+
+    /// The variance of the metric between the timing iterations of the same invocation, averaged over all invocations
+    fn within_invocation_variance(
+        &self,
+        replicates: &[Replicate],
+        bench: &str,
+        build: &str,
+    ) -> Option<f64> {
+        let variances = replicates
+            .iter()
+            .flat_map(|r| r.records.iter())
+            .filter(|r| r.bench == bench && r.build == build)
+            .filter_map(|r| r.variances.get(&self.metric).copied())
+            .collect::<Vec<_>>();
+        Self::mean(&variances)
+    }
+
+    fn mean(values: &[f64]) -> Option<f64> {
+        if values.is_empty() {
+            None
+        } else {
+            Some(values.iter().sum::<f64>() / values.len() as f64)
+        }
+    }
+
+    pub fn run(&self) -> anyhow::Result<()> {
+        let logs_dir = CrateInfo::get_target_path()?.join("harness").join("logs");
+        let mut replicates = vec![];
+        let mut profiles = vec![];
+        for runid in &self.run_ids {
+            let (run, replicate) = self.load_replicate(&logs_dir, runid)?;
+            profiles.push((runid.clone(), toml::to_string(&run.profile.profile)?));
+            replicates.push(replicate);
+        }
+        // Replicates should share the same configuration
+        for (runid, profile) in &profiles[1..] {
+            if *profile != profiles[0].1 {
+                eprintln!(
+                    "{}",
+                    format!(
+                        "WARNING: The profile of run `{}` is different from run `{}`. Use `cargo harness diff-config` to see the differences.",
+                        runid, profiles[0].0
+                    )
+                    .yellow()
+                );
+            }
+        }
+        let benches = replicates
+            .iter()
+            .flat_map(|r| r.records.iter().map(|r| r.bench.clone()))
+            .collect::<BTreeSet<_>>();
+        let builds = replicates
+            .iter()
+            .flat_map(|r| r.records.iter().map(|r| r.build.clone()))
+            .collect::<BTreeSet<_>>();
+        if benches.is_empty() {
+            anyhow::bail!("No results found in the given runs");
+        }
+        let baseline = match &self.baseline {
+            Some(b) if !builds.contains(b) => anyhow::bail!("Build `{}` not found", b),
+            Some(b) => b.clone(),
+            None => builds.first().unwrap().clone(),
+        };
+
+        print_md!("# Multi-Run Analysis\n\n");
+        print_md!(
+            "* runs: {}\n",
+            replicates
+                .iter()
+                .map(|r| format!("`{}`", r.runid))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        print_md!("* metric: `{}`\n", self.metric);
+        print_md!("* baseline: `{}`\n\n", baseline);
+
+        // Variance components of each bench and build
+        print_md!("## Variance Components\n\n");
+        let mut table =
+            "|bench|build|runs|invocations|mean|within-invocation stddev|within-run stddev|between-run stddev|between-run share|\n"
+                .to_owned();
+        table += "|:-|:-|-:|-:|-:|-:|-:|-:|-:|\n";
+        for bench in &benches {
+            for build in &builds {
+                let groups = replicates
+                    .iter()
+                    .map(|r| self.values(r, bench, build))
+                    .collect::<Vec<_>>();
+                let Some(vc) = VarianceComponents::new(&groups) else {
+                    continue;
+                };
+                let within_invocation = self
+                    .within_invocation_variance(&replicates, bench, build)
+                    .map(|v| format!("{:.3}", v.sqrt()))
+                    .unwrap_or_else(|| "-".to_owned());
+                table += &format!(
+                    "|{}|{}|{}|{}|{:.3}|{}|{:.3}|{:.3}|{:.1}%|\n",
+                    bench,
+                    build,
+                    vc.runs,
+                    vc.invocations,
+                    vc.mean,
+                    within_invocation,
+                    vc.within.sqrt(),
+                    vc.between.sqrt(),
+                    vc.between_share() * 100.0
+                );
+            }
+        }
+        print_md!("{}\n", table);
+
+        // Build differences across runs
+        print_md!("## Build Differences\n\n");
+        print_md!(
+            "Each run's ratio is the mean `{}` of the build divided by the mean of `{}`. A difference is stable if all runs agree on its direction.\n\n",
+            self.metric, baseline
+        );
+        let mut table = "|bench|build|".to_owned();
+        for r in &replicates {
+            table += &format!("{}|", r.runid);
+        }
+        table += "min ratio|max ratio|stable|\n|:-|:-|";
+        table += &"-:|".repeat(replicates.len() + 2);
+        table += ":-:|\n";
+        for bench in &benches {
+            for build in builds.iter().filter(|b| **b != baseline) {
+                let ratios = replicates
+                    .iter()
+                    .map(|r| {
+                        let base = Self::mean(&self.values(r, bench, &baseline))?;
+                        let this = Self::mean(&self.values(r, bench, build))?;
+                        Some(this / base)
+                    })
+                    .collect::<Vec<_>>();
+                let known = ratios.iter().flatten().copied().collect::<Vec<_>>();
+                if known.is_empty() {
+                    continue;
+                }
+                let min = known.iter().copied().fold(f64::INFINITY, f64::min);
+                let max = known.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                let stable = known.len() == ratios.len() && (min > 1.0 || max < 1.0);
+                table += &format!("|{}|{}|", bench, build);
+                for r in &ratios {
+                    match r {
+                        Some(r) => table += &format!("{:.3}|", r),
+                        None => table += "-|",
+                    }
+                }
+                table += &format!(
+                    "{:.3}|{:.3}|{}|\n",
+                    min,
+                    max,
+                    if stable { "✔" } else { "✘" }
+                );
+            }
+        }
+        print_md!("{}", table);
+        Ok(())
+    }
+}
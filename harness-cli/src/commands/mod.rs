@@ -1,3 +1,17 @@
+pub mod archive;
+pub mod cache;
+pub mod check_regression;
+pub mod clean;
+pub mod completions;
+pub mod diff_config;
+pub mod env;
+pub mod export;
+pub mod flaky;
+pub mod history;
+pub mod meta;
+pub mod plot;
+pub mod report;
 pub mod run;
 pub mod upload;
 pub mod viz;
+pub mod warmup;
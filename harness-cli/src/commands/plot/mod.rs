@@ -0,0 +1,129 @@
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use clap::Parser;
+
+use crate::{
+    commands::report::data::{Bootstrap, ReportData, StatTest},
+    configs::run_info::{CrateInfo, RunInfo},
+};
+
+/// The output format of the charts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum PlotFormat {
+    #[default]
+    Svg,
+    /// Converted from SVG with `rsvg-convert`
+    Png,
+}
+
+/// Plot a metric as a bar chart per benchmark and build, with confidence intervals as error bars.
+///
+/// The chart is written to the `plots` directory of the run's log directory.
+/// If the run has a `threads` sweep, the scaling curves of the metric are also plotted, as `<metric>.scaling.svg`.
+#[derive(Parser)]
+pub struct PlotArgs {
+    /// The metric to plot
+    #[arg(default_value = "time")]
+    pub metric: String,
+    /// The run id to plot. Default to the latest run.
+    pub run_id: Option<String>,
+    /// Normalize the values to this build, and also plot the geomean of the normalized values
+    #[arg(long)]
+    pub baseline: Option<String>,
+    /// The output format
+    #[arg(long, value_enum, default_value_t = PlotFormat::Svg)]
+    pub format: PlotFormat,
+}
+
+impl PlotArgs {
+    fn rsvg_convert_exists() -> bool {
+        Command::new("rsvg-convert")
+            .arg("--version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success())
+    }
+
+    /// Write an SVG chart, and convert it to PNG if requested. Returns the path of the written chart.
+    fn write_chart(&self, svg: &Path, content: &str) -> anyhow::Result<PathBuf> {
+        std::fs::write(svg, content)?;
+        if self.format != PlotFormat::Png {
+            return Ok(svg.to_owned());
+        }
+        let png = svg.with_extension("png");
+        let status = Command::new("rsvg-convert")
+            .arg("--format=png")
+            .arg("--zoom=2")
+            .arg("--output")
+            .arg(&png)
+            .arg(svg)
+            .status()?;
+        if !status.success() {
+            anyhow::bail!("Failed to convert {} to PNG", svg.display());
+        }
+        std::fs::remove_file(svg)?;
+        Ok(png)
+    }
+
+    pub fn run(&self) -> anyhow::Result<()> {
+        if self.format == PlotFormat::Png && !Self::rsvg_convert_exists() {
+            anyhow::bail!("rsvg-convert is not installed. Please install librsvg (e.g. `apt install librsvg2-bin`), or use `--format svg`");
+        }
+        let logs_dir = CrateInfo::get_target_path()?.join("harness").join("logs");
+        let log_dir = logs_dir.join(self.run_id.as_deref().unwrap_or("latest"));
+        if !log_dir.exists() {
+            anyhow::bail!("Log dir not found: {}", log_dir.display());
+        }
+        let run = RunInfo::load(&log_dir.join("config.toml"))?;
+        let data = ReportData::load(&log_dir, &run)?;
+        if !data.metrics.contains(&self.metric) {
+            anyhow::bail!("No `{}` results found in run `{}`", self.metric, run.runid);
+        }
+        if let Some(b) = &self.baseline {
+            if !data.builds.contains(b) {
+                anyhow::bail!("Build `{}` not found", b);
+            }
+        }
+        let baseline = self
+            .baseline
+            .clone()
+            .unwrap_or_else(|| data.builds.first().unwrap().clone());
+        // The baseline build goes first
+        let builds = std::iter::once(&baseline)
+            .chain(data.builds.iter().filter(|b| **b != baseline))
+            .collect::<Vec<_>>();
+        let summary = data.per_metric_summary(
+            &self.metric,
+            &baseline,
+            StatTest::default(),
+            Bootstrap::default(),
+        );
+        let mut chart = if self.baseline.is_some() {
+            summary.normalized_chart(&self.metric, &baseline, &builds)
+        } else {
+            summary.chart(&self.metric, &builds)
+        };
+        chart.title = format!("{}: {}", run.runid, chart.title);
+        let plots_dir = log_dir.join("plots");
+        std::fs::create_dir_all(&plots_dir)?;
+        let name = match &self.baseline {
+            Some(b) => format!("{}.norm-{}", self.metric, b),
+            None => self.metric.clone(),
+        };
+        let out = self.write_chart(&plots_dir.join(format!("{}.svg", name)), &chart.to_svg())?;
+        println!("Chart written to {}", out.display());
+        // The scaling curves of the `threads` sweep
+        if let Some(scaling) = data.scaling(&run, &self.metric) {
+            let mut chart = scaling.chart(&self.metric);
+            chart.title = format!("{}: {}", run.runid, chart.title);
+            let svg = plots_dir.join(format!("{}.scaling.svg", self.metric));
+            let out = self.write_chart(&svg, &chart.to_svg())?;
+            println!("Scaling chart written to {}", out.display());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,1069 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+use crate::{
+    configs::run_info::RunInfo,
+    utils::{
+        chart::{Bar, BarChart, LineChart},
+        results,
+    },
+};
+
+/// Summary statistics of a metric over the invocations of a (bench, build) pair
+#[derive(Debug, Clone)]
+pub struct Summary {
+    pub n: usize,
+    pub mean: f64,
+    pub stddev: f64,
+}
+
+impl Summary {
+    pub fn new(values: &[f64]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        let n = values.len();
+        let mean = values.iter().sum::<f64>() / n as f64;
+        let var = if n > 1 {
+            values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1) as f64
+        } else {
+            0.0
+        };
+        Some(Self {
+            n,
+            mean,
+            stddev: var.sqrt(),
+        })
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Geometric mean of positive values
+fn geomean(values: impl ExactSizeIterator<Item = f64>) -> f64 {
+    let n = values.len() as f64;
+    (values.map(|x| x.ln()).sum::<f64>() / n).exp()
+}
+
+/// The value at quantile `q` of sorted values, with linear interpolation
+pub fn quantile(sorted: &[f64], q: f64) -> f64 {
+    let pos = (sorted.len() - 1) as f64 * q;
+    let (lo, hi) = (pos.floor() as usize, pos.ceil() as usize);
+    sorted[lo] + (sorted[hi] - sorted[lo]) * (pos - lo as f64)
+}
+
+/// The Tukey fences `[Q1 - 1.5 IQR, Q3 + 1.5 IQR]`. Values outside the fences are outliers.
+/// `None` if there are less than four values.
+fn tukey_fences(values: &[f64]) -> Option<(f64, f64)> {
+    if values.len() < 4 {
+        return None;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let (q1, q3) = (quantile(&sorted, 0.25), quantile(&sorted, 0.75));
+    let iqr = q3 - q1;
+    Some((q1 - 1.5 * iqr, q3 + 1.5 * iqr))
+}
+
+/// The settings of the bootstrap confidence intervals
+#[derive(Debug, Clone, Copy)]
+pub struct Bootstrap {
+    /// The confidence level, e.g. `0.95`
+    pub confidence: f64,
+    /// The number of resamples
+    pub resamples: usize,
+}
+
+impl Default for Bootstrap {
+    fn default() -> Self {
+        Self {
+            confidence: 0.95,
+            resamples: 10000,
+        }
+    }
+}
+
+/// A bootstrap estimate of a statistic
+#[derive(Debug, Clone, Copy)]
+pub struct Estimate {
+    /// The statistic of the original sample
+    pub point: f64,
+    /// The percentile confidence interval
+    pub ci: (f64, f64),
+    /// The standard deviation of the statistic over the resamples
+    pub standard_error: f64,
+}
+
+impl Bootstrap {
+    /// The sorted estimates over the resamples of one or more samples.
+    ///
+    /// Each sample is resampled with replacement, independently. The resamples are seeded, so that reports of the same results are reproducible.
+    /// Empty if any sample has less than two values.
+    fn resample(&self, samples: &[&[f64]], estimate: impl Fn(&[Vec<f64>]) -> f64) -> Vec<f64> {
+        if samples.is_empty() || samples.iter().any(|s| s.len() < 2) {
+            return vec![];
+        }
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut resampled = samples
+            .iter()
+            .map(|s| vec![0.0; s.len()])
+            .collect::<Vec<_>>();
+        let mut estimates = vec![];
+        for _ in 0..self.resamples {
+            for (r, s) in resampled.iter_mut().zip(samples) {
+                for x in r.iter_mut() {
+                    *x = *s.choose(&mut rng).unwrap();
+                }
+            }
+            let e = estimate(&resampled);
+            if e.is_finite() {
+                estimates.push(e);
+            }
+        }
+        estimates.sort_by(f64::total_cmp);
+        estimates
+    }
+
+    /// The percentile confidence interval of an estimate over the resamples of one or more samples.
+    /// Returns `None` if any sample has less than two values.
+    fn interval(
+        &self,
+        samples: &[&[f64]],
+        estimate: impl Fn(&[Vec<f64>]) -> f64,
+    ) -> Option<(f64, f64)> {
+        self.percentile_interval(&self.resample(samples, estimate))
+    }
+
+    fn percentile_interval(&self, estimates: &[f64]) -> Option<(f64, f64)> {
+        if estimates.is_empty() {
+            return None;
+        }
+        let tail = (1.0 - self.confidence) / 2.0;
+        let percentile = |q: f64| estimates[((estimates.len() - 1) as f64 * q).round() as usize];
+        Some((percentile(tail), percentile(1.0 - tail)))
+    }
+
+    /// Estimate a statistic of a sample, with its confidence interval and standard error.
+    /// Returns `None` if the sample has less than two values.
+    pub fn estimate(&self, sample: &[f64], statistic: impl Fn(&[f64]) -> f64) -> Option<Estimate> {
+        let estimates = self.resample(&[sample], |s| statistic(&s[0]));
+        let ci = self.percentile_interval(&estimates)?;
+        let mean = mean(&estimates);
+        let var = estimates.iter().map(|x| (x - mean).powi(2)).sum::<f64>()
+            / (estimates.len() - 1).max(1) as f64;
+        Some(Estimate {
+            point: statistic(sample),
+            ci,
+            standard_error: var.sqrt(),
+        })
+    }
+}
+
+/// The statistical test used to compare a build against the baseline
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum StatTest {
+    /// Welch's unequal-variance t-test
+    #[default]
+    Welch,
+    /// Mann-Whitney U test. Rank-based, and does not assume normally distributed values
+    #[value(name = "mannwhitney")]
+    MannWhitney,
+    /// Permutation test on the difference of the means. Does not assume any distribution
+    Permutation,
+}
+
+impl StatTest {
+    /// The number of random permutations of the permutation test
+    const PERMUTATIONS: usize = 10000;
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StatTest::Welch => "Welch's t-test",
+            StatTest::MannWhitney => "Mann-Whitney U test",
+            StatTest::Permutation => "permutation test",
+        }
+    }
+
+    /// Test whether two samples are from different distributions.
+    ///
+    /// Returns `None` if either sample has less than two values, or if the test is undefined for the samples.
+    pub fn test(&self, a: &[f64], b: &[f64]) -> Option<TestResult> {
+        if a.len() < 2 || b.len() < 2 {
+            return None;
+        }
+        match self {
+            StatTest::Welch => welch_t_test(a, b),
+            StatTest::MannWhitney => mann_whitney_u_test(a, b),
+            StatTest::Permutation => permutation_test(a, b, Self::PERMUTATIONS),
+        }
+    }
+}
+
+/// The result of a statistical test
+#[derive(Debug, Clone, Copy)]
+pub struct TestResult {
+    /// The two-sided p-value
+    pub p: f64,
+}
+
+/// Welch's unequal-variance t-test between two samples. `None` if both samples have no variance.
+fn welch_t_test(a: &[f64], b: &[f64]) -> Option<TestResult> {
+    let (a, b) = (Summary::new(a)?, Summary::new(b)?);
+    let va = a.stddev.powi(2) / a.n as f64;
+    let vb = b.stddev.powi(2) / b.n as f64;
+    if va + vb == 0.0 {
+        return None;
+    }
+    let t = (a.mean - b.mean) / (va + vb).sqrt();
+    // Degrees of freedom from the Welch–Satterthwaite equation
+    let df = (va + vb).powi(2) / (va.powi(2) / (a.n - 1) as f64 + vb.powi(2) / (b.n - 1) as f64);
+    let p = regularized_incomplete_beta(df / (df + t * t), df / 2.0, 0.5);
+    Some(TestResult { p })
+}
+
+/// Mann-Whitney U test between two samples, with the normal approximation of the U statistic.
+/// Ties get the mean of their ranks. `None` if all values are equal.
+fn mann_whitney_u_test(a: &[f64], b: &[f64]) -> Option<TestResult> {
+    let (n1, n2) = (a.len() as f64, b.len() as f64);
+    let mut all = a
+        .iter()
+        .map(|x| (*x, true))
+        .chain(b.iter().map(|x| (*x, false)))
+        .collect::<Vec<_>>();
+    all.sort_by(|x, y| x.0.total_cmp(&y.0));
+    // Sum of the ranks of `a`, and the tie correction term of the variance
+    let mut rank_sum = 0.0;
+    let mut ties = 0.0;
+    let mut i = 0;
+    while i < all.len() {
+        let j = i + all[i..].iter().take_while(|x| x.0 == all[i].0).count();
+        let rank = (i + 1 + j) as f64 / 2.0;
+        rank_sum += rank * all[i..j].iter().filter(|x| x.1).count() as f64;
+        let t = (j - i) as f64;
+        ties += t * t * t - t;
+        i = j;
+    }
+    let n = n1 + n2;
+    let u = rank_sum - n1 * (n1 + 1.0) / 2.0;
+    let mean = n1 * n2 / 2.0;
+    let var = n1 * n2 / 12.0 * ((n + 1.0) - ties / (n * (n - 1.0)));
+    if var <= 0.0 {
+        return None;
+    }
+    // With continuity correction
+    let z = ((u - mean).abs() - 0.5).max(0.0) / var.sqrt();
+    Some(TestResult {
+        p: erfc(z / std::f64::consts::SQRT_2).min(1.0),
+    })
+}
+
+/// Permutation test on the difference of the means of two samples, with random permutations.
+/// The permutations are seeded, so that reports of the same results are reproducible.
+fn permutation_test(a: &[f64], b: &[f64], permutations: usize) -> Option<TestResult> {
+    let mean = |v: &[f64]| v.iter().sum::<f64>() / v.len() as f64;
+    let observed = (mean(a) - mean(b)).abs();
+    let mut all = a.iter().chain(b).copied().collect::<Vec<_>>();
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut extreme = 0;
+    for _ in 0..permutations {
+        all.shuffle(&mut rng);
+        let (x, y) = all.split_at(a.len());
+        // Allow for floating-point error when the permuted groups are the same as the original ones
+        if (mean(x) - mean(y)).abs() >= observed * (1.0 - 1e-9) {
+            extreme += 1;
+        }
+    }
+    Some(TestResult {
+        p: (extreme + 1) as f64 / (permutations + 1) as f64,
+    })
+}
+
+/// The complementary error function, with a fractional error below 1.2e-7 (Chebyshev approximation)
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let r = t
+        * (-z * z - 1.26551223
+            + t * (1.00002368
+                + t * (0.37409196
+                    + t * (0.09678418
+                        + t * (-0.18628806
+                            + t * (0.27886807
+                                + t * (-1.13520398
+                                    + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277)))))))))
+            .exp();
+    if x >= 0.0 {
+        r
+    } else {
+        2.0 - r
+    }
+}
+
+/// Natural log of the gamma function (Lanczos approximation)
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFS: [f64; 6] = [
+        76.18009172947146,
+        -86.50532032941677,
+        24.01409824083091,
+        -1.231739572450155,
+        0.1208650973866179e-2,
+        -0.5395239384953e-5,
+    ];
+    let tmp = x + 5.5 - (x + 0.5) * (x + 5.5).ln();
+    let mut ser = 1.000000000190015;
+    for (i, c) in COEFFS.iter().enumerate() {
+        ser += c / (x + 1.0 + i as f64);
+    }
+    -tmp + (2.5066282746310005 * ser / x).ln()
+}
+
+/// The regularized incomplete beta function `I_x(a, b)`
+fn regularized_incomplete_beta(x: f64, a: f64, b: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    // The continued fraction converges quickly only for x < (a + 1) / (a + b + 2)
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_continued_fraction(x, a, b) / a
+    } else {
+        1.0 - front * beta_continued_fraction(1.0 - x, b, a) / b
+    }
+}
+
+/// Evaluate the continued fraction of the incomplete beta function with the modified Lentz's method
+fn beta_continued_fraction(x: f64, a: f64, b: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    if d.abs() < TINY {
+        d = TINY;
+    }
+    d = 1.0 / d;
+    let mut h = d;
+    for m in 1..=300 {
+        let m = m as f64;
+        for aa in [
+            m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m)),
+            -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0)),
+        ] {
+            d = 1.0 + aa * d;
+            if d.abs() < TINY {
+                d = TINY;
+            }
+            c = 1.0 + aa / c;
+            if c.abs() < TINY {
+                c = TINY;
+            }
+            d = 1.0 / d;
+            h *= d * c;
+        }
+        if (d * c - 1.0).abs() < 1e-12 {
+            break;
+        }
+    }
+    h
+}
+
+/// The comparison of a build against the baseline build, for one benchmark
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    pub summary: Summary,
+    /// The bootstrap confidence interval of the mean. `None` if there are less than two invocations.
+    pub ci: Option<(f64, f64)>,
+    /// The number of outlier invocations. They are excluded from the summary if the outliers are dropped.
+    pub outliers: usize,
+    /// Mean of the build divided by the mean of the baseline
+    pub ratio: Option<f64>,
+    /// The statistical test against the baseline. `None` for the baseline itself, or if there are not enough invocations.
+    pub test: Option<TestResult>,
+}
+
+/// The comparison of all builds of a metric, for each benchmark
+#[derive(Debug, Clone)]
+pub struct MetricSummary {
+    /// The build that the other builds are compared against
+    pub baseline: String,
+    /// bench -> build -> comparison against the baseline
+    pub benches: BTreeMap<String, BTreeMap<String, Comparison>>,
+    /// build -> geometric mean of the ratios over all benchmarks
+    pub geomeans: BTreeMap<String, Geomean>,
+}
+
+impl MetricSummary {
+    /// The bar chart of the means of each build, normalized to the baseline, with the confidence intervals as error bars
+    pub fn normalized_chart(&self, metric: &str, baseline: &str, builds: &[&String]) -> BarChart {
+        let mut groups = vec![];
+        for (bench, comparisons) in &self.benches {
+            let Some(base) = comparisons.get(baseline).filter(|c| c.summary.mean != 0.0) else {
+                continue;
+            };
+            let bars = builds
+                .iter()
+                .map(|build| {
+                    let c = comparisons.get(*build)?;
+                    Some(Bar {
+                        value: c.summary.mean / base.summary.mean,
+                        error: c
+                            .ci
+                            .map(|(lo, hi)| (lo / base.summary.mean, hi / base.summary.mean)),
+                    })
+                })
+                .collect();
+            groups.push((bench.clone(), bars));
+        }
+        let geomeans = builds
+            .iter()
+            .map(|build| {
+                if *build == baseline {
+                    return Some(Bar {
+                        value: 1.0,
+                        error: None,
+                    });
+                }
+                let g = self.geomeans.get(*build)?;
+                Some(Bar {
+                    value: g.value,
+                    error: g.ci,
+                })
+            })
+            .collect();
+        groups.push(("geomean".to_owned(), geomeans));
+        BarChart {
+            title: format!("{} (normalized to {})", metric, baseline),
+            y_label: format!("{} / {}", metric, baseline),
+            series: builds.iter().map(|b| b.to_string()).collect(),
+            groups,
+            reference: Some(1.0),
+        }
+    }
+
+    /// The bar chart of the means of each build, with the confidence intervals as error bars
+    pub fn chart(&self, metric: &str, builds: &[&String]) -> BarChart {
+        let groups = self
+            .benches
+            .iter()
+            .map(|(bench, comparisons)| {
+                let bars = builds
+                    .iter()
+                    .map(|build| {
+                        let c = comparisons.get(*build)?;
+                        Some(Bar {
+                            value: c.summary.mean,
+                            error: c.ci,
+                        })
+                    })
+                    .collect();
+                (bench.clone(), bars)
+            })
+            .collect();
+        BarChart {
+            title: metric.to_owned(),
+            y_label: metric.to_owned(),
+            series: builds.iter().map(|b| b.to_string()).collect(),
+            groups,
+            reference: None,
+        }
+    }
+}
+
+/// The comparison of each (bench, build) pair of a metric between two runs
+#[derive(Debug, Clone)]
+pub struct RunComparison {
+    /// (bench, build) -> (the pair in the base run, the pair compared against the base run)
+    pub pairs: BTreeMap<(String, String), (Comparison, Comparison)>,
+    /// build -> geometric mean of the ratios over all benchmarks
+    pub geomeans: BTreeMap<String, Geomean>,
+}
+
+/// The geometric mean of the ratios of a build to the baseline, over all benchmarks
+#[derive(Debug, Clone)]
+pub struct Geomean {
+    pub value: f64,
+    /// The bootstrap confidence interval, from resampling the invocations of each benchmark.
+    /// `None` if any benchmark has less than two invocations.
+    pub ci: Option<(f64, f64)>,
+}
+
+impl Geomean {
+    /// The geomean of the ratios of the means of each (values, baseline values) pair
+    fn new(pairs: &[(&[f64], &[f64])], bootstrap: Bootstrap) -> Self {
+        let value = geomean(pairs.iter().map(|(x, b)| mean(x) / mean(b)));
+        let samples = pairs.iter().flat_map(|(x, b)| [*x, *b]).collect::<Vec<_>>();
+        let ci = bootstrap.interval(&samples, |s| {
+            geomean(s.chunks(2).map(|p| mean(&p[0]) / mean(&p[1])))
+        });
+        Self { value, ci }
+    }
+}
+
+/// The means of a metric over the thread counts of the `threads` sweep of a run
+#[derive(Debug, Clone)]
+pub struct Scaling {
+    /// The thread counts, in ascending order
+    pub threads: Vec<usize>,
+    /// (bench, build without the `.threads=<N>` suffix) -> the mean of each thread count. `None` for a missing variant.
+    pub curves: BTreeMap<(String, String), Vec<Option<f64>>>,
+}
+
+impl Scaling {
+    /// The mean of each thread count divided by the mean of the smallest thread count. `None` for a missing variant.
+    pub fn ratios(means: &[Option<f64>]) -> Vec<Option<f64>> {
+        let base = means.first().copied().flatten().filter(|m| *m != 0.0);
+        means.iter().map(|m| Some((*m)? / base?)).collect()
+    }
+
+    /// The line chart of each (bench, build), normalized to the smallest thread count
+    pub fn chart(&self, metric: &str) -> LineChart {
+        LineChart {
+            title: format!(
+                "{} scaling (normalized to {} threads)",
+                metric, self.threads[0]
+            ),
+            x_label: "threads".to_owned(),
+            y_label: format!("{} / {} at {} threads", metric, metric, self.threads[0]),
+            x: self.threads.iter().map(|t| t.to_string()).collect(),
+            series: self
+                .curves
+                .iter()
+                .map(|((bench, build), means)| {
+                    (format!("{} ({})", bench, build), Self::ratios(means))
+                })
+                .collect(),
+            reference: Some(1.0),
+        }
+    }
+}
+
+/// The per-invocation results of a run
+pub struct ReportData {
+    pub benches: BTreeSet<String>,
+    pub builds: BTreeSet<String>,
+    pub metrics: BTreeSet<String>,
+    /// (bench, build, metric) -> the values of all successful invocations
+    values: BTreeMap<(String, String, String), Vec<f64>>,
+    /// (bench, build, metric) -> the invocation of each value
+    invocations: BTreeMap<(String, String, String), Vec<usize>>,
+    /// (bench, build, metric) -> the outlier invocations and their values
+    pub outliers: BTreeMap<(String, String, String), Vec<(usize, f64)>>,
+}
+
+impl ReportData {
+    pub fn load(log_dir: &Path, run: &RunInfo) -> anyhow::Result<Self> {
+        let records = results::load_timing_records(
+            &log_dir.join("results.csv"),
+            run.profile.measured_iterations,
+        )?;
+        let mut data = Self {
+            benches: BTreeSet::new(),
+            builds: BTreeSet::new(),
+            metrics: BTreeSet::new(),
+            values: BTreeMap::new(),
+            invocations: BTreeMap::new(),
+            outliers: BTreeMap::new(),
+        };
+        for r in records {
+            data.benches.insert(r.bench.clone());
+            data.builds.insert(r.build.clone());
+            for (metric, v) in r.values {
+                data.metrics.insert(metric.clone());
+                let key = (r.bench.clone(), r.build.clone(), metric);
+                data.invocations
+                    .entry(key.clone())
+                    .or_default()
+                    .push(r.invocation);
+                data.values.entry(key).or_default().push(v);
+            }
+        }
+        Ok(data)
+    }
+
+    /// Find the outlier invocations of each (bench, build, metric) with the Tukey fences.
+    /// If `drop` is true, the outliers are also removed from the values.
+    pub fn detect_outliers(&mut self, drop: bool) {
+        for (key, values) in &mut self.values {
+            let Some((lo, hi)) = tukey_fences(values) else {
+                continue;
+            };
+            let invocations = self.invocations.get_mut(key).unwrap();
+            let outliers = invocations
+                .iter()
+                .zip(values.iter())
+                .filter(|(_, v)| **v < lo || **v > hi)
+                .map(|(i, v)| (*i, *v))
+                .collect::<Vec<_>>();
+            if outliers.is_empty() {
+                continue;
+            }
+            if drop {
+                let keep = values
+                    .iter()
+                    .map(|v| *v >= lo && *v <= hi)
+                    .collect::<Vec<_>>();
+                let mut keep_iter = keep.iter();
+                values.retain(|_| *keep_iter.next().unwrap());
+                let mut keep_iter = keep.iter();
+                invocations.retain(|_| *keep_iter.next().unwrap());
+            }
+            self.outliers.insert(key.clone(), outliers);
+        }
+    }
+
+    /// Add the results of a build of another run to this run, as build `name`.
+    /// Only the benchmarks and metrics of this run are added. Returns the number of benchmarks added.
+    pub fn import_build(&mut self, other: &ReportData, build: &str, name: &str) -> usize {
+        let mut benches = BTreeSet::new();
+        for ((bench, b, metric), values) in &other.values {
+            if b != build || !self.benches.contains(bench) || !self.metrics.contains(metric) {
+                continue;
+            }
+            let from = (bench.clone(), b.clone(), metric.clone());
+            let key = (bench.clone(), name.to_owned(), metric.clone());
+            if let Some(invocations) = other.invocations.get(&from) {
+                self.invocations.insert(key.clone(), invocations.clone());
+            }
+            if let Some(outliers) = other.outliers.get(&from) {
+                self.outliers.insert(key.clone(), outliers.clone());
+            }
+            self.values.insert(key, values.clone());
+            benches.insert(bench);
+        }
+        if !benches.is_empty() {
+            self.builds.insert(name.to_owned());
+        }
+        benches.len()
+    }
+
+    /// Only keep the metrics that `f` returns true for
+    pub fn retain_metrics(&mut self, f: impl Fn(&str) -> bool) {
+        self.metrics.retain(|m| f(m));
+        self.values.retain(|(_, _, m), _| f(m));
+        self.invocations.retain(|(_, _, m), _| f(m));
+        self.outliers.retain(|(_, _, m), _| f(m));
+    }
+
+    pub fn values(&self, bench: &str, build: &str, metric: &str) -> &[f64] {
+        self.values
+            .get(&(bench.to_owned(), build.to_owned(), metric.to_owned()))
+            .map_or(&[], |v| v.as_slice())
+    }
+
+    /// Summarize the values of a (bench, build, metric), and compare them against the values of a baseline, if given.
+    /// `None` if there are no values.
+    fn compare(
+        &self,
+        bench: &str,
+        build: &str,
+        metric: &str,
+        base_values: Option<&[f64]>,
+        stat_test: StatTest,
+        bootstrap: Bootstrap,
+    ) -> Option<Comparison> {
+        let values = self.values(bench, build, metric);
+        let summary = Summary::new(values)?;
+        let ratio = base_values
+            .and_then(Summary::new)
+            .filter(|b| b.mean != 0.0)
+            .map(|b| summary.mean / b.mean);
+        Some(Comparison {
+            ci: bootstrap.interval(&[values], |s| mean(&s[0])),
+            outliers: self
+                .outliers
+                .get(&(bench.to_owned(), build.to_owned(), metric.to_owned()))
+                .map_or(0, |o| o.len()),
+            ratio,
+            test: base_values.and_then(|b| stat_test.test(values, b)),
+            summary,
+        })
+    }
+
+    /// The means of a metric over the thread counts of the `threads` sweep. `None` if the run has no sweep.
+    pub fn scaling(&self, run: &RunInfo, metric: &str) -> Option<Scaling> {
+        // build -> (the build without the `.threads=<N>` suffix, thread count)
+        let variants = run
+            .profile
+            .builds
+            .iter()
+            .filter(|(name, _)| self.builds.contains(*name))
+            .filter_map(|(name, b)| {
+                let n = b.threads?;
+                let base = name.strip_suffix(&format!(".threads={}", n))?;
+                Some((name, (base.to_owned(), n)))
+            })
+            .collect::<BTreeMap<_, _>>();
+        let threads = variants
+            .values()
+            .map(|(_, n)| *n)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        if threads.is_empty() {
+            return None;
+        }
+        let mut curves = BTreeMap::<_, Vec<Option<f64>>>::new();
+        for bench in &self.benches {
+            for (build, (base, n)) in &variants {
+                let values = self.values(bench, build, metric);
+                if values.is_empty() {
+                    continue;
+                }
+                let i = threads.binary_search(n).unwrap();
+                curves
+                    .entry((bench.clone(), base.clone()))
+                    .or_insert_with(|| vec![None; threads.len()])[i] = Some(mean(values));
+            }
+        }
+        if curves.is_empty() {
+            return None;
+        }
+        Some(Scaling { threads, curves })
+    }
+
+    /// Compare every build of a metric against the baseline build
+    pub fn per_metric_summary(
+        &self,
+        metric: &str,
+        baseline: &str,
+        stat_test: StatTest,
+        bootstrap: Bootstrap,
+    ) -> MetricSummary {
+        let mut benches = BTreeMap::new();
+        // build -> (build values, baseline values) of each benchmark with a ratio
+        let mut ratios = BTreeMap::<String, Vec<(&[f64], &[f64])>>::new();
+        for bench in &self.benches {
+            let base_values = self.values(bench, baseline, metric);
+            let mut builds = BTreeMap::new();
+            for build in &self.builds {
+                let base = (build != baseline).then_some(base_values);
+                let Some(c) = self.compare(bench, build, metric, base, stat_test, bootstrap) else {
+                    continue;
+                };
+                if c.ratio.is_some_and(|r| r > 0.0) {
+                    ratios
+                        .entry(build.clone())
+                        .or_default()
+                        .push((self.values(bench, build, metric), base_values));
+                }
+                builds.insert(build.clone(), c);
+            }
+            if !builds.is_empty() {
+                benches.insert(bench.clone(), builds);
+            }
+        }
+        let geomeans = ratios
+            .into_iter()
+            .map(|(build, pairs)| (build, Geomean::new(&pairs, bootstrap)))
+            .collect();
+        MetricSummary {
+            baseline: baseline.to_owned(),
+            benches,
+            geomeans,
+        }
+    }
+
+    /// Compare every (bench, build) pair of a metric against the same pair in the results of another run
+    pub fn per_metric_run_comparison(
+        &self,
+        base: &ReportData,
+        metric: &str,
+        stat_test: StatTest,
+        bootstrap: Bootstrap,
+    ) -> RunComparison {
+        let mut pairs = BTreeMap::new();
+        // build -> (values, base run values) of each benchmark with a ratio
+        let mut ratios = BTreeMap::<String, Vec<(&[f64], &[f64])>>::new();
+        for bench in self.benches.intersection(&base.benches) {
+            for build in self.builds.intersection(&base.builds) {
+                let base_values = base.values(bench, build, metric);
+                let Some(b) = base.compare(bench, build, metric, None, stat_test, bootstrap) else {
+                    continue;
+                };
+                let Some(c) = self.compare(
+                    bench,
+                    build,
+                    metric,
+                    Some(base_values),
+                    stat_test,
+                    bootstrap,
+                ) else {
+                    continue;
+                };
+                if c.ratio.is_some_and(|r| r > 0.0) {
+                    ratios
+                        .entry(build.clone())
+                        .or_default()
+                        .push((self.values(bench, build, metric), base_values));
+                }
+                pairs.insert((bench.clone(), build.clone()), (b, c));
+            }
+        }
+        let geomeans = ratios
+            .into_iter()
+            .map(|(build, pairs)| (build, Geomean::new(&pairs, bootstrap)))
+            .collect();
+        RunComparison { pairs, geomeans }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The `sleep` dataset of R: the extra hours of sleep of two groups of 10 patients
+    const SLEEP_1: [f64; 10] = [0.7, -1.6, -0.2, -1.2, -0.1, 3.4, 3.7, 0.8, 0.0, 2.0];
+    const SLEEP_2: [f64; 10] = [1.9, 0.8, 1.1, 0.1, -0.1, 4.4, 5.5, 1.6, 4.6, 3.4];
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn ln_gamma_matches_reference_values() {
+        let cases = [
+            (0.5, std::f64::consts::PI.sqrt().ln()),
+            (1.0, 0.0),
+            (2.0, 0.0),
+            (3.7, 1.428072326665),
+            (10.0, 362880f64.ln()),
+            (100.5, 361.435540467778),
+        ];
+        for (x, expected) in cases {
+            assert_close(ln_gamma(x), expected, 1e-9 * expected.abs().max(1.0));
+        }
+    }
+
+    #[test]
+    fn incomplete_beta_matches_reference_values() {
+        // (x, a, b, I_x(a, b)), from `pbeta(x, a, b)` in R, or closed forms
+        let cases = [
+            (0.3, 2.5, 0.5, 0.018927124072),
+            (0.9, 10.0, 0.5, 0.151640909635),
+            (0.2, 0.5, 0.5, 0.295167235301),
+            // The binomial sum `pbinom(2, 6, 0.4, lower = FALSE)`
+            (0.4, 3.0, 4.0, 0.45568),
+            (0.99, 50.0, 0.5, 0.317304397874),
+            // Symmetric around 1/2
+            (0.5, 7.0, 7.0, 0.5),
+            (0.0, 2.0, 3.0, 0.0),
+            (1.0, 2.0, 3.0, 1.0),
+        ];
+        for (x, a, b, expected) in cases {
+            assert_close(regularized_incomplete_beta(x, a, b), expected, 1e-9);
+        }
+        // `I_x(a, 1) = x^a`, on both sides of the switch to the symmetric continued fraction
+        for x in [0.1, 0.5, 0.9] {
+            assert_close(regularized_incomplete_beta(x, 3.0, 1.0), x.powi(3), 1e-9);
+        }
+    }
+
+    #[test]
+    fn welch_t_test_matches_reference_values() {
+        // `t.test(extra ~ group, data = sleep)` in R: t = -1.8608, df = 17.776, p-value = 0.07939
+        let p = welch_t_test(&SLEEP_1, &SLEEP_2).unwrap().p;
+        assert_close(p, 0.079394140187, 1e-8);
+        // `t.test(c(1, 2, 3, 4, 5), c(2, 4, 6, 8, 10, 12))` in R: t = -2.3764, df = 6.9723, p-value = 0.04928
+        let p = welch_t_test(
+            &[1.0, 2.0, 3.0, 4.0, 5.0],
+            &[2.0, 4.0, 6.0, 8.0, 10.0, 12.0],
+        )
+        .unwrap()
+        .p;
+        assert_close(p, 0.049284338207, 1e-8);
+        // With one degree of freedom, t follows the Cauchy distribution: p = 1 - 2 atan(|t|) / pi
+        let p = welch_t_test(&[0.0, 2.0], &[0.0, 0.0]).unwrap().p;
+        assert_close(p, 1.0 - 2.0 * 1f64.atan() / std::f64::consts::PI, 1e-9);
+        // The test is symmetric
+        let q = welch_t_test(&SLEEP_2, &SLEEP_1).unwrap().p;
+        assert_close(q, 0.079394140187, 1e-8);
+        // Undefined without any variance
+        assert!(welch_t_test(&[1.0, 1.0], &[2.0, 2.0]).is_none());
+        assert!(StatTest::Welch.test(&[1.0], &[1.0, 2.0]).is_none());
+    }
+
+    #[test]
+    fn mann_whitney_u_test_matches_reference_values() {
+        let cases: [(&[f64], &[f64], f64); 3] = [
+            // `wilcox.test(extra ~ group, data = sleep, exact = FALSE)` in R: W = 25.5, p-value = 0.06933. With ties
+            (&SLEEP_1, &SLEEP_2, 0.069327575434),
+            // `mannwhitneyu(x, y, method="asymptotic")` in scipy: U = 17, p-value = 0.11135. Without ties
+            (
+                &[19.0, 22.0, 16.0, 29.0, 24.0],
+                &[20.0, 11.0, 17.0, 12.0],
+                0.111346886533,
+            ),
+            // `|U - n1 n2 / 2| = 0`. The continuity correction does not make the statistic negative
+            (&[1.0, 2.0], &[1.5, 1.75], 1.0),
+        ];
+        for (a, b, expected) in cases {
+            // The error of `erfc` is below 1.2e-7
+            assert_close(mann_whitney_u_test(a, b).unwrap().p, expected, 1e-6);
+            assert_close(mann_whitney_u_test(b, a).unwrap().p, expected, 1e-6);
+        }
+        // All values are tied
+        assert!(mann_whitney_u_test(&[1.0, 1.0], &[1.0, 1.0, 1.0]).is_none());
+    }
+
+    /// The exact p-value of the permutation test, over all the splits of the values into groups of the same sizes
+    fn exact_permutation_p(a: &[f64], b: &[f64]) -> f64 {
+        let all = a.iter().chain(b).copied().collect::<Vec<_>>();
+        let observed = (mean(a) - mean(b)).abs();
+        let (mut extreme, mut total) = (0, 0);
+        for mask in 0u32..(1 << all.len()) {
+            if mask.count_ones() as usize != a.len() {
+                continue;
+            }
+            let (x, y): (Vec<_>, Vec<_>) = (0..all.len()).partition(|i| mask & (1 << i) != 0);
+            let x = x.iter().map(|i| all[*i]).collect::<Vec<_>>();
+            let y = y.iter().map(|i| all[*i]).collect::<Vec<_>>();
+            total += 1;
+            if (mean(&x) - mean(&y)).abs() >= observed * (1.0 - 1e-9) {
+                extreme += 1;
+            }
+        }
+        extreme as f64 / total as f64
+    }
+
+    #[test]
+    fn permutation_test_matches_exact_p_values() {
+        let cases: [(&[f64], &[f64]); 4] = [
+            // Completely separated: the exact p-value is 2 / C(10, 5)
+            (&[1.0, 2.0, 3.0, 4.0, 5.0], &[11.0, 12.0, 13.0, 14.0, 15.0]),
+            (&[1.0, 3.0, 5.0, 7.0], &[2.0, 4.0, 6.0, 8.0, 10.0]),
+            (&[1.0, 1.0, 2.0, 2.0], &[1.0, 2.0, 2.0, 3.0, 3.0, 3.0]),
+            (&SLEEP_1, &SLEEP_2),
+        ];
+        let permutations = StatTest::PERMUTATIONS;
+        for (a, b) in cases {
+            let exact = exact_permutation_p(a, b);
+            let p = permutation_test(a, b, permutations).unwrap().p;
+            // Within four standard errors of the Monte Carlo estimate
+            let error = 4.0 * (exact * (1.0 - exact) / permutations as f64).sqrt();
+            assert_close(p, exact, error + 1.0 / permutations as f64);
+            // The permutations are seeded
+            assert_eq!(p, permutation_test(a, b, permutations).unwrap().p);
+        }
+        assert_close(
+            exact_permutation_p(&[1.0, 2.0, 3.0, 4.0, 5.0], &[11.0, 12.0, 13.0, 14.0, 15.0]),
+            2.0 / 252.0,
+            1e-12,
+        );
+        // Identical groups are never significant
+        let p = permutation_test(&[1.0, 2.0, 3.0], &[1.0, 2.0, 3.0], 1000)
+            .unwrap()
+            .p;
+        assert_eq!(p, 1.0);
+    }
+
+    #[test]
+    fn bootstrap_intervals_are_reproducible_and_cover_the_estimate() {
+        let bootstrap = Bootstrap {
+            confidence: 0.95,
+            resamples: 2000,
+        };
+        let estimate = bootstrap.estimate(&SLEEP_2, mean).unwrap();
+        assert_eq!(estimate.point, mean(&SLEEP_2));
+        assert!(estimate.ci.0 < estimate.point && estimate.point < estimate.ci.1);
+        assert!(estimate.standard_error > 0.0);
+        // The resamples are seeded
+        let again = bootstrap.estimate(&SLEEP_2, mean).unwrap();
+        assert_eq!(
+            (again.ci, again.standard_error),
+            (estimate.ci, estimate.standard_error)
+        );
+        // The standard error of the mean is close to `s / sqrt(n)`
+        let summary = Summary::new(&SLEEP_2).unwrap();
+        let expected = summary.stddev * ((summary.n - 1) as f64 / summary.n as f64).sqrt()
+            / (summary.n as f64).sqrt();
+        assert!((estimate.standard_error / expected - 1.0).abs() < 0.1);
+        // A higher confidence level gives a wider interval
+        let wide = Bootstrap {
+            confidence: 0.99,
+            ..bootstrap
+        }
+        .estimate(&SLEEP_2, mean)
+        .unwrap();
+        assert!(wide.ci.0 <= estimate.ci.0 && estimate.ci.1 <= wide.ci.1);
+        // No interval without any variation to resample
+        assert_eq!(
+            bootstrap.interval(&[&[3.0, 3.0, 3.0]], |s| mean(&s[0])),
+            Some((3.0, 3.0))
+        );
+        assert!(bootstrap.estimate(&[1.0], mean).is_none());
+        assert!(bootstrap
+            .interval(&[&SLEEP_1, &[1.0]], |s| mean(&s[0]))
+            .is_none());
+    }
+
+    #[test]
+    fn geomean_intervals_are_reproducible_and_cover_the_geomean() {
+        let bootstrap = Bootstrap {
+            confidence: 0.95,
+            resamples: 2000,
+        };
+        let (a, base_a) = ([1.9, 2.0, 2.1, 2.0], [0.9, 1.0, 1.1, 1.0]);
+        let (b, base_b) = ([7.5, 8.0, 8.5, 8.0], [1.0, 1.0, 1.0, 1.0]);
+        let pairs: [(&[f64], &[f64]); 2] = [(&a, &base_a), (&b, &base_b)];
+        let geomean = Geomean::new(&pairs, bootstrap);
+        // The geomean of the ratios 2 and 8
+        assert!((geomean.value - 4.0).abs() < 1e-12);
+        let ci = geomean.ci.unwrap();
+        assert!(ci.0 < geomean.value && geomean.value < ci.1);
+        assert_eq!(Geomean::new(&pairs, bootstrap).ci, Some(ci));
+        // Benchmarks with a single invocation have no interval
+        let single: [(&[f64], &[f64]); 1] = [(&[2.0], &[1.0])];
+        let geomean = Geomean::new(&single, bootstrap);
+        assert_eq!((geomean.value, geomean.ci), (2.0, None));
+    }
+
+    #[test]
+    fn tukey_fences_match_the_quartiles() {
+        // Q1 = 3, Q3 = 7, so IQR = 4
+        let values = [100.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0];
+        assert_eq!(tukey_fences(&values), Some((-3.0, 13.0)));
+        assert_eq!(tukey_fences(&[1.0, 2.0, 100.0]), None);
+    }
+
+    #[test]
+    fn detect_outliers_drops_the_outlier_invocations() {
+        let key = |bench: &str| (bench.to_owned(), "a".to_owned(), "time".to_owned());
+        let mut data = ReportData {
+            benches: ["foo", "bar"].map(|b| b.to_owned()).into(),
+            builds: ["a".to_owned()].into(),
+            metrics: ["time".to_owned()].into(),
+            values: [
+                (
+                    key("foo"),
+                    vec![1.0, 2.0, 3.0, 4.0, 100.0, 5.0, 6.0, 7.0, 8.0],
+                ),
+                (key("bar"), vec![1.0, 2.0, 100.0]),
+            ]
+            .into(),
+            invocations: [(key("foo"), (0..9).collect()), (key("bar"), vec![0, 1, 2])].into(),
+            outliers: BTreeMap::new(),
+        };
+        data.detect_outliers(true);
+        assert_eq!(data.outliers, [(key("foo"), vec![(4, 100.0)])].into());
+        assert_eq!(
+            data.values[&key("foo")],
+            [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0]
+        );
+        assert_eq!(data.invocations[&key("foo")], [0, 1, 2, 3, 5, 6, 7, 8]);
+        // Too few values to find outliers
+        assert_eq!(data.values[&key("bar")], [1.0, 2.0, 100.0]);
+    }
+}
@@ -0,0 +1,220 @@
+use chrono::{DateTime, Local};
+
+use crate::{configs::run_info::RunInfo, utils::chart::escape};
+
+use super::{
+    data::{MetricSummary, ReportData},
+    ReportArgs,
+};
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em auto; max-width: 1200px; padding: 0 1em; color: #222; }
+h1 { border-bottom: 2px solid #4c72b0; }
+table { border-collapse: collapse; margin: 1em 0; }
+th, td { border: 1px solid #ddd; padding: 4px 10px; text-align: right; }
+th:first-child, td:first-child { text-align: left; }
+th { background: #f0f3f8; }
+tr.geomean td { font-style: italic; background: #fafafa; }
+.chart { overflow-x: auto; }
+.notes { color: #555; }
+.up { color: #c44e52; }
+.down { color: #55a868; }
+";
+
+fn format_time(timestamp_utc: i64) -> String {
+    DateTime::from_timestamp(timestamp_utc, 0)
+        .map(|t| t.with_timezone(&Local).to_rfc2822())
+        .unwrap_or_default()
+}
+
+/// Render the markdown highlights of a table cell: the bold best build, and the `▲` and `▼` markers
+fn format_cell(cell: &str) -> String {
+    let (cell, bold) = match cell.strip_prefix("**").and_then(|c| c.strip_suffix("**")) {
+        Some(c) => (c, true),
+        None => (cell, false),
+    };
+    let html = escape(cell)
+        .replace('▲', "<span class=\"up\">▲</span>")
+        .replace('▼', "<span class=\"down\">▼</span>");
+    if bold {
+        format!("<b>{}</b>", html)
+    } else {
+        html
+    }
+}
+
+fn table(header: &[String], rows: &[Vec<String>], classes: &[&str]) -> String {
+    let mut html = "<table>\n<tr>".to_owned();
+    for h in header {
+        html += &format!("<th>{}</th>", escape(h));
+    }
+    html += "</tr>\n";
+    for (i, row) in rows.iter().enumerate() {
+        match classes.get(i).filter(|c| !c.is_empty()) {
+            Some(c) => html += &format!("<tr class=\"{}\">", c),
+            None => html += "<tr>",
+        }
+        for cell in row {
+            html += &format!("<td>{}</td>", format_cell(cell));
+        }
+        html += "</tr>\n";
+    }
+    html += "</table>\n";
+    html
+}
+
+impl ReportArgs {
+    /// Render the report as a self-contained HTML page, with inline styles and SVG charts
+    pub(super) fn render_html(
+        &self,
+        run: &RunInfo,
+        data: &ReportData,
+        baseline: &String,
+        summaries: &[(String, MetricSummary)],
+    ) -> String {
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n",
+            escape(&format!("Benchmark Results: {}", run.runid)),
+            STYLE
+        );
+        html += &format!("<h1>Benchmark Results: {}</h1>\n", escape(&run.runid));
+        // Run metadata
+        html += "<h2>Run</h2>\n";
+        let mut builds = run.profile.builds.iter().collect::<Vec<_>>();
+        builds.sort_by_key(|(name, _)| *name);
+        let metadata = [
+            ("project", run.project.clone()),
+            ("profile", run.profile.name.clone()),
+            ("commit", run.commit.clone()),
+            ("start time", format_time(run.start_timestamp_utc)),
+            (
+                "finish time",
+                run.finish_timestamp_utc
+                    .map(format_time)
+                    .unwrap_or_else(|| "-".to_owned()),
+            ),
+            ("invocations", run.profile.invocations.to_string()),
+            (
+                "warm-up iterations",
+                run.profile.warmup_iterations.to_string(),
+            ),
+            (
+                "measured iterations",
+                run.profile.measured_iterations.to_string(),
+            ),
+            (
+                "builds",
+                builds
+                    .iter()
+                    .map(|(name, b)| match (&b.commit, &b.version) {
+                        (Some(c), _) => format!("{} ({})", name, c),
+                        (None, Some(v)) => format!("{} ({}@{})", name, run.crate_info.name, v),
+                        (None, None) => name.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            ("host", run.system.host.clone()),
+            (
+                "os",
+                format!(
+                    "{} {} ({})",
+                    run.system.os, run.system.kernel, run.system.arch
+                ),
+            ),
+            ("cpu", run.system.cpu_model.clone()),
+            (
+                "memory",
+                format!("{:.1} GB", run.system.memory_size as f64 / 1e9),
+            ),
+            ("rustc", run.system.rustc.clone()),
+        ];
+        html += &table(
+            &["".to_owned(), "".to_owned()],
+            &metadata
+                .into_iter()
+                .map(|(k, v)| vec![k.to_owned(), v])
+                .collect::<Vec<_>>(),
+            &[],
+        );
+        html += "<ul class=\"notes\">\n";
+        html += &format!(
+            "<li>Values: mean [{}% bootstrap confidence interval] over the successful invocations{}.</li>\n",
+            self.confidence * 100.0,
+            match run.profile.warmup_invocations {
+                0 => String::new(),
+                n => format!(", excluding the first {} warm-up invocations", n),
+            }
+        );
+        html += &format!(
+            "<li>Ratios: mean of the build divided by the mean of <code>{}</code>. <code>*</code> marks a significant difference ({}, p &lt; {}).</li>\n",
+            escape(baseline),
+            self.stat_test.name(),
+            self.alpha
+        );
+        html += &format!(
+            "<li>Highlights: <span class=\"up\">▲</span> and <span class=\"down\">▼</span> mark significant differences larger than {}%. The best (lowest) mean of each benchmark is in bold.</li>\n",
+            self.threshold * 100.0
+        );
+        html += &format!(
+            "<li>Outliers: <code>⚠N</code> marks N invocations outside the Tukey fences (1.5 × IQR){}.</li>\n",
+            if self.drop_outliers {
+                ". They are excluded from the summary"
+            } else {
+                ""
+            }
+        );
+        html += "</ul>\n";
+        // Summary tables and charts of each metric
+        for (metric, summary) in summaries {
+            html += &format!("<h2>{}</h2>\n", escape(metric));
+            if summary.baseline != *baseline {
+                html += &format!(
+                    "<p>Normalized to <code>{}</code>.</p>\n",
+                    escape(&summary.baseline)
+                );
+            }
+            let others = Self::others(data, summary);
+            let all_builds = std::iter::once(&summary.baseline)
+                .chain(others.iter().copied())
+                .collect::<Vec<_>>();
+            let chart = summary.normalized_chart(metric, &summary.baseline, &all_builds);
+            html += &format!("<div class=\"chart\">\n{}</div>\n", chart.to_svg());
+            let (header, mut rows, geomean) = self.summary_table(&others, summary);
+            let mut classes = vec![""; rows.len()];
+            rows.push(geomean);
+            classes.push("geomean");
+            html += &table(&header, &rows, &classes);
+            if let Some(scaling) = data.scaling(run, metric) {
+                html += &format!("<h3>{} scaling</h3>\n", escape(metric));
+                html += &format!(
+                    "<div class=\"chart\">\n{}</div>\n",
+                    scaling.chart(metric).to_svg()
+                );
+            }
+        }
+        // Outliers
+        if !data.outliers.is_empty() {
+            html += "<h2>Outliers</h2>\n";
+            let header = ["metric", "bench", "build", "invocation", "value"].map(String::from);
+            let rows = data
+                .outliers
+                .iter()
+                .flat_map(|((bench, build, metric), outliers)| {
+                    outliers.iter().map(move |(invocation, value)| {
+                        vec![
+                            metric.clone(),
+                            bench.clone(),
+                            build.clone(),
+                            invocation.to_string(),
+                            format!("{:.3}", value),
+                        ]
+                    })
+                })
+                .collect::<Vec<_>>();
+            html += &table(&header, &rows, &[]);
+        }
+        html += "</body>\n</html>\n";
+        html
+    }
+}
@@ -0,0 +1,113 @@
+use serde_json::{json, Map, Value};
+
+use crate::configs::run_info::RunInfo;
+
+use super::{
+    data::{Comparison, MetricSummary, ReportData, Scaling},
+    ReportArgs,
+};
+
+fn ci(ci: Option<(f64, f64)>) -> Value {
+    match ci {
+        Some((lo, hi)) => json!([lo, hi]),
+        None => Value::Null,
+    }
+}
+
+impl ReportArgs {
+    fn comparison_json(&self, c: &Comparison) -> Value {
+        json!({
+            "n": c.summary.n,
+            "mean": c.summary.mean,
+            "stddev": c.summary.stddev,
+            "ci": ci(c.ci),
+            "outliers": c.outliers,
+            "ratio": c.ratio,
+            "p": c.test.map(|t| t.p),
+            "significant": c.test.is_some_and(|t| t.p < self.alpha),
+        })
+    }
+
+    fn scaling_json(scaling: &Scaling) -> Value {
+        let curves = scaling
+            .curves
+            .iter()
+            .map(|((bench, build), means)| {
+                json!({
+                    "bench": bench,
+                    "build": build,
+                    "means": means,
+                    "ratios": Scaling::ratios(means),
+                })
+            })
+            .collect::<Vec<_>>();
+        json!({
+            "threads": scaling.threads,
+            "curves": curves,
+        })
+    }
+
+    /// Render the summary statistics of the report as JSON, for scripts to consume
+    pub(super) fn render_json(
+        &self,
+        run: &RunInfo,
+        data: &ReportData,
+        baseline: &String,
+        summaries: &[(String, MetricSummary)],
+    ) -> String {
+        let mut metrics = Map::new();
+        for (metric, summary) in summaries {
+            let benches = summary
+                .benches
+                .iter()
+                .map(|(bench, builds)| {
+                    let builds = builds
+                        .iter()
+                        .map(|(build, c)| (build.clone(), self.comparison_json(c)))
+                        .collect::<Map<_, _>>();
+                    (bench.clone(), Value::Object(builds))
+                })
+                .collect::<Map<_, _>>();
+            let geomeans = summary
+                .geomeans
+                .iter()
+                .map(|(build, g)| (build.clone(), json!({ "value": g.value, "ci": ci(g.ci) })))
+                .collect::<Map<_, _>>();
+            let mut m = json!({
+                "baseline": summary.baseline,
+                "benches": benches,
+                "geomeans": geomeans,
+            });
+            if let Some(scaling) = data.scaling(run, metric) {
+                m["scaling"] = Self::scaling_json(&scaling);
+            }
+            metrics.insert(metric.clone(), m);
+        }
+        let outliers = data
+            .outliers
+            .iter()
+            .flat_map(|((bench, build, metric), outliers)| {
+                outliers.iter().map(move |(invocation, value)| {
+                    json!({
+                        "metric": metric,
+                        "bench": bench,
+                        "build": build,
+                        "invocation": invocation,
+                        "value": value,
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+        let report = json!({
+            "run": run.runid,
+            "baseline": baseline,
+            "stat-test": self.stat_test.name(),
+            "alpha": self.alpha,
+            "confidence": self.confidence,
+            "outliers-dropped": self.drop_outliers,
+            "metrics": metrics,
+            "outliers": outliers,
+        });
+        serde_json::to_string_pretty(&report).unwrap()
+    }
+}
@@ -0,0 +1,817 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use clap::Parser;
+use colored::Colorize;
+
+use crate::{
+    commands::check_regression::parse_threshold,
+    configs::run_info::{CrateInfo, RunInfo},
+    utils::md::print_md,
+};
+
+pub mod data;
+mod html;
+mod json;
+mod pr_comment;
+
+use data::{Bootstrap, Comparison, MetricSummary, ReportData, Scaling, StatTest};
+use pr_comment::Diff;
+
+/// The format of the report
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ReportFormat {
+    /// The full markdown report
+    #[default]
+    #[value(alias = "md")]
+    Markdown,
+    /// A compact markdown summary of the differences to the baseline, with collapsible per-benchmark details. Sized to fit in a GitHub comment.
+    PrComment,
+    /// The full report as a self-contained HTML page, with bar charts of each metric
+    Html,
+    /// The summary statistics of each metric, benchmark, and build as JSON
+    Json,
+}
+
+impl ReportFormat {
+    /// The file extension of the report
+    fn extension(&self) -> &'static str {
+        match self {
+            ReportFormat::Markdown | ReportFormat::PrComment => "md",
+            ReportFormat::Html => "html",
+            ReportFormat::Json => "json",
+        }
+    }
+}
+
+/// Report the results of a run: the mean and confidence interval of each metric per benchmark and build,
+/// and whether each difference to the baseline build is statistically significant.
+/// With `--compare`, report the differences of each benchmark and build between two runs instead.
+#[derive(Parser)]
+pub struct ReportArgs {
+    /// The run id to report. Default to the latest run.
+    pub run_id: Option<String>,
+    /// The baseline build to compare other builds against. Default to the first build in alphabetical order.
+    #[arg(long)]
+    pub baseline: Option<String>,
+    /// The significance level of the statistical tests
+    #[arg(long, default_value = "0.05")]
+    pub alpha: f64,
+    /// Mark the significant differences to the baseline that are larger than this threshold with `▲` (higher) or `▼` (lower), e.g. `3%`
+    #[arg(long, default_value = "5%", value_parser = parse_threshold)]
+    pub threshold: f64,
+    /// The statistical test to compare each build against the baseline.
+    /// Use `mannwhitney` or `permutation` for non-normally distributed results.
+    #[arg(long, value_enum, default_value_t = StatTest::Welch)]
+    pub stat_test: StatTest,
+    /// The confidence level of the bootstrap confidence intervals
+    #[arg(long, default_value = "0.95")]
+    pub confidence: f64,
+    /// The number of resamples of the bootstrap confidence intervals
+    #[arg(long, default_value = "10000")]
+    pub resamples: usize,
+    /// Exclude the outlier invocations (outside the Tukey fences) from the summary
+    #[arg(long, default_value = "false")]
+    pub drop_outliers: bool,
+    /// Compare each benchmark and build against the same benchmark and build in another run.
+    /// Ratios are the means of this other run divided by the means of the reported run.
+    #[arg(long, value_name = "RUNID")]
+    pub compare: Option<String>,
+    /// Also write the report, with bar charts of each metric, to a self-contained HTML file
+    #[arg(long, value_name = "FILE")]
+    pub html: Option<PathBuf>,
+    /// Also append the markdown report to the GitHub Actions job summary, i.e. the file at `$GITHUB_STEP_SUMMARY`
+    #[arg(long, default_value = "false")]
+    pub github_summary: bool,
+    /// Use a build of an earlier run as the baseline (e.g. `<RUNID>:HEAD` of the last release), instead of a build of the reported run.
+    /// The benchmarks are joined by name.
+    #[arg(long, value_name = "RUNID:BUILD")]
+    pub baseline_run: Option<String>,
+    /// The format of the report
+    #[arg(long, value_enum, default_value_t = ReportFormat::Markdown)]
+    pub format: ReportFormat,
+    /// Write the report to this file instead of printing it.
+    /// Without a file, the report is written to `report.<md|html|json>` in the log directory of the run, next to `results.csv`
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<Option<PathBuf>>,
+    /// Normalize the metrics to this build instead of the baseline, e.g. `HEAD~1`.
+    /// `METRIC=BUILD` only normalizes one metric, e.g. `--norm-to time=HEAD~1,memory=jemalloc`. Overrides the `norm-to` builds of the profile.
+    #[arg(long, value_delimiter = ',', value_name = "[METRIC=]BUILD")]
+    pub norm_to: Vec<String>,
+    /// Only report these metrics, e.g. `time,PERF_COUNT_HW_CPU_CYCLES`. A trailing `*` matches any suffix.
+    /// Overrides the `report-metrics` of the profile. Default to all metrics
+    #[arg(long, value_delimiter = ',')]
+    pub metrics: Vec<String>,
+    /// Do not report these metrics. A trailing `*` matches any suffix
+    #[arg(long, value_delimiter = ',')]
+    pub exclude_metrics: Vec<String>,
+}
+
+/// Whether a metric matches a pattern of `--metrics`. A trailing `*` matches any suffix.
+fn matches_metric(metric: &str, pattern: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => metric.starts_with(prefix),
+        None => metric == pattern,
+    }
+}
+
+impl ReportArgs {
+    /// Format a ratio to the baseline, with a marker for a significant difference beyond the threshold, and a significance marker
+    fn format_ratio(&self, c: &Comparison) -> String {
+        let Some(ratio) = c.ratio else {
+            return "-".to_owned();
+        };
+        let mut s = format!("{:.3}×", ratio);
+        if !c.test.is_some_and(|t| t.p < self.alpha) {
+            return s;
+        }
+        if ratio > 1.0 + self.threshold {
+            s += " ▲";
+        } else if ratio < 1.0 - self.threshold {
+            s += " ▼";
+        }
+        s + " *"
+    }
+
+    fn format_ci(value: f64, ci: Option<(f64, f64)>) -> String {
+        match ci {
+            Some((lo, hi)) => format!("{:.3} [{:.3}, {:.3}]", value, lo, hi),
+            None => format!("{:.3}", value),
+        }
+    }
+
+    fn format_p(c: &Comparison) -> String {
+        match c.test {
+            Some(t) if t.p < 0.001 => "<0.001".to_owned(),
+            Some(t) => format!("{:.3}", t.p),
+            None => "-".to_owned(),
+        }
+    }
+
+    fn load(&self, logs_dir: &Path, run_id: &str) -> anyhow::Result<(RunInfo, ReportData)> {
+        let log_dir = logs_dir.join(run_id);
+        if !log_dir.exists() {
+            anyhow::bail!("Log dir not found: {}", log_dir.display());
+        }
+        let run = RunInfo::load(&log_dir.join("config.toml"))?;
+        let mut data = ReportData::load(&log_dir, &run)?;
+        data.detect_outliers(self.drop_outliers);
+        if data.benches.is_empty() {
+            anyhow::bail!("No results found in run `{}`", run.runid);
+        }
+        self.select_metrics(&run, &mut data)?;
+        Ok((run, data))
+    }
+
+    /// Only keep the metrics selected by `--metrics` or the `report-metrics` of the profile, and not excluded by `--exclude-metrics`
+    fn select_metrics(&self, run: &RunInfo, data: &mut ReportData) -> anyhow::Result<()> {
+        for pattern in &self.metrics {
+            if !data.metrics.iter().any(|m| matches_metric(m, pattern)) {
+                anyhow::bail!("No `{}` results found in run `{}`", pattern, run.runid);
+            }
+        }
+        let selected = if self.metrics.is_empty() {
+            &run.profile.report_metrics
+        } else {
+            &self.metrics
+        };
+        data.retain_metrics(|m| {
+            (selected.is_empty() || selected.iter().any(|p| matches_metric(m, p)))
+                && !self.exclude_metrics.iter().any(|p| matches_metric(m, p))
+        });
+        if data.metrics.is_empty() {
+            anyhow::bail!("No metrics of run `{}` are selected", run.runid);
+        }
+        Ok(())
+    }
+
+    /// The differences of the platforms of two runs, e.g. a different CPU model
+    fn platform_differences(base: &RunInfo, run: &RunInfo) -> Vec<String> {
+        let (a, b) = (&base.system, &run.system);
+        let mut diffs = vec![];
+        let mut check = |name: &str, a: String, b: String| {
+            if a != b {
+                diffs.push(format!("{}: {} ➔ {}", name, a, b));
+            }
+        };
+        check("Host", a.host.clone(), b.host.clone());
+        check("OS", a.os.clone(), b.os.clone());
+        check("Arch", a.arch.clone(), b.arch.clone());
+        check("Kernel", a.kernel.clone(), b.kernel.clone());
+        check("CPU", a.cpu_model.clone(), b.cpu_model.clone());
+        let to_gb = |x: usize| format!("{:.1}GB", x as f64 / 1024.0 / 1024.0);
+        check("Memory", to_gb(a.memory_size), to_gb(b.memory_size));
+        check("Rust Version", a.rustc.clone(), b.rustc.clone());
+        diffs
+    }
+
+    /// Load the baseline build of `--baseline-run` into the reported run. Returns the name of the baseline build.
+    fn load_baseline_run(
+        &self,
+        logs_dir: &Path,
+        baseline_run: &str,
+        run: &RunInfo,
+        data: &mut ReportData,
+    ) -> anyhow::Result<String> {
+        let Some((run_id, build)) = baseline_run.split_once(':') else {
+            anyhow::bail!(
+                "Invalid baseline run `{}`. Expected `<RUNID>:<BUILD>`",
+                baseline_run
+            );
+        };
+        let (base_run, base) = self.load(logs_dir, run_id)?;
+        if !base.builds.contains(build) {
+            anyhow::bail!("Build `{}` not found in run `{}`", build, base_run.runid);
+        }
+        let name = format!("{}:{}", base_run.runid, build);
+        if data.import_build(&base, build, &name) == 0 {
+            anyhow::bail!(
+                "Build `{}` of run `{}` has no benchmarks in common with run `{}`",
+                build,
+                base_run.runid,
+                run.runid
+            );
+        }
+        let diffs = Self::platform_differences(&base_run, run);
+        if !diffs.is_empty() {
+            eprintln!(
+                "{}",
+                format!(
+                    "WARNING: Run `{}` was measured on a different platform from the baseline run `{}`:\n  {}",
+                    run.runid,
+                    base_run.runid,
+                    diffs.join("\n  ")
+                )
+                .yellow()
+            );
+        }
+        Ok(name)
+    }
+
+    fn value_notes(&self, run: &RunInfo) -> String {
+        format!(
+            "* values: mean [{}% bootstrap confidence interval] over the successful invocations{}\n",
+            self.confidence * 100.0,
+            match run.profile.warmup_invocations {
+                0 => String::new(),
+                n => format!(", excluding the first {} warm-up invocations", n),
+            }
+        )
+    }
+
+    fn highlight_notes(&self) -> String {
+        format!(
+            "* highlights: `▲` and `▼` mark significant differences larger than {}%",
+            self.threshold * 100.0
+        )
+    }
+
+    fn outlier_notes(&self) -> String {
+        format!(
+            "* outliers: `⚠N` marks N invocations outside the Tukey fences (1.5 × IQR){}\n\n",
+            if self.drop_outliers {
+                ". They are excluded from the summary"
+            } else {
+                ""
+            }
+        )
+    }
+
+    fn outliers(runid: &str, data: &ReportData) -> String {
+        if data.outliers.is_empty() {
+            return String::new();
+        }
+        let mut md = format!("## Outliers in `{}`\n\n", runid);
+        md += "|metric|bench|build|invocation|value|\n";
+        md += "|:-|:-|:-|-:|-:|\n";
+        for ((bench, build, metric), outliers) in &data.outliers {
+            for (invocation, value) in outliers {
+                md += &format!(
+                    "|{}|{}|{}|{}|{:.3}|\n",
+                    metric, bench, build, invocation, value
+                );
+            }
+        }
+        md + "\n"
+    }
+
+    /// The GitHub Actions job summary file
+    fn github_summary_path() -> anyhow::Result<PathBuf> {
+        match std::env::var_os("GITHUB_STEP_SUMMARY") {
+            Some(p) if !p.is_empty() => Ok(PathBuf::from(p)),
+            _ => anyhow::bail!("`--github-summary` requires `$GITHUB_STEP_SUMMARY`, which is only set in GitHub Actions"),
+        }
+    }
+
+    /// Print the report or write it to the `--output` file, and append it to the GitHub Actions job summary if requested
+    fn output(&self, log_dir: &Path, md: &str) -> anyhow::Result<()> {
+        match (&self.output, self.format) {
+            (Some(path), _) => {
+                let path = path
+                    .clone()
+                    .unwrap_or_else(|| log_dir.join(format!("report.{}", self.format.extension())));
+                std::fs::write(&path, md)?;
+                println!("Report written to {}", path.display());
+            }
+            (None, ReportFormat::Markdown) => print_md(md),
+            // Printed as is, to be posted by a bot or consumed by a script
+            (None, _) => println!("{}", md),
+        }
+        if self.github_summary {
+            // The `*` significance markers are escaped, as GitHub may render them as emphasis
+            let md = md.replace(" *|", " \\*|");
+            let path = Self::github_summary_path()?;
+            let mut file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)?;
+            writeln!(file, "{}", md.trim_end())?;
+            writeln!(file)?;
+        }
+        Ok(())
+    }
+
+    fn format_value(c: &Comparison) -> String {
+        let mut s = Self::format_ci(c.summary.mean, c.ci);
+        if c.outliers > 0 {
+            s += &format!(" ⚠{}", c.outliers);
+        }
+        s
+    }
+
+    /// The header, the rows, and the geomean row of the summary table of a metric
+    fn summary_table(
+        &self,
+        others: &[&String],
+        summary: &MetricSummary,
+    ) -> (Vec<String>, Vec<Vec<String>>, Vec<String>) {
+        let baseline = summary.baseline.as_str();
+        let mut header = vec!["bench".to_owned(), baseline.to_owned()];
+        for build in others {
+            header.extend([build.to_string(), "ratio".to_owned(), "p".to_owned()]);
+        }
+        let mut rows = vec![];
+        for (bench, builds) in &summary.benches {
+            // The best build has the lowest mean, as lower values are better for most metrics
+            let best = (builds.len() > 1).then(|| {
+                builds
+                    .values()
+                    .map(|c| c.summary.mean)
+                    .fold(f64::NAN, f64::min)
+            });
+            let format_value = |c: &Comparison| match best {
+                Some(b) if c.summary.mean == b => format!("**{}**", Self::format_value(c)),
+                _ => Self::format_value(c),
+            };
+            let mut row = vec![bench.clone()];
+            match builds.get(baseline) {
+                Some(c) => row.push(format_value(c)),
+                None => row.push("-".to_owned()),
+            }
+            for build in others {
+                match builds.get(*build) {
+                    Some(c) => {
+                        row.extend([format_value(c), self.format_ratio(c), Self::format_p(c)])
+                    }
+                    None => row.extend(["-".to_owned(), "-".to_owned(), "-".to_owned()]),
+                }
+            }
+            rows.push(row);
+        }
+        let mut geomean = vec!["geomean".to_owned(), String::new()];
+        for build in others {
+            let g = summary
+                .geomeans
+                .get(*build)
+                .map_or("-".to_owned(), |g| Self::format_ci(g.value, g.ci));
+            geomean.extend([String::new(), g, String::new()]);
+        }
+        (header, rows, geomean)
+    }
+
+    /// The builds that are compared against the baseline of a metric
+    fn others<'a>(data: &'a ReportData, summary: &MetricSummary) -> Vec<&'a String> {
+        data.builds
+            .iter()
+            .filter(|b| **b != summary.baseline)
+            .collect()
+    }
+
+    /// Parse a `--norm-to` value into the metric, if any, and the build.
+    /// Build names may contain `=` as well (e.g. `a.THREADS=8`), so an existing build name is never split.
+    fn parse_norm_to<'a>(data: &ReportData, n: &'a str) -> (Option<&'a str>, &'a str) {
+        match n.split_once('=') {
+            Some((metric, build)) if !data.builds.contains(n) => (Some(metric), build),
+            _ => (None, n),
+        }
+    }
+
+    /// The build to normalize each metric to: `--norm-to`, then the `norm-to` builds of the profile, then the baseline
+    fn norm_builds(
+        &self,
+        run: &RunInfo,
+        data: &ReportData,
+        baseline: &str,
+    ) -> anyhow::Result<BTreeMap<String, String>> {
+        let mut all = None;
+        let mut per_metric = BTreeMap::new();
+        for n in &self.norm_to {
+            let (metric, build) = Self::parse_norm_to(data, n);
+            if !data.builds.contains(build) {
+                anyhow::bail!("Build `{}` not found", build);
+            }
+            match metric {
+                Some(m) if !data.metrics.contains(m) => {
+                    anyhow::bail!("No `{}` results found in run `{}`", m, run.runid)
+                }
+                Some(m) => {
+                    per_metric.insert(m.to_owned(), build.to_owned());
+                }
+                None if all.is_some() => {
+                    anyhow::bail!("`--norm-to` accepts only one build for all metrics")
+                }
+                None => all = Some(build.to_owned()),
+            }
+        }
+        let mut builds = BTreeMap::new();
+        for metric in &data.metrics {
+            let build = if let Some(b) = per_metric.get(metric).or(all.as_ref()) {
+                b.clone()
+            } else if let Some(b) = run.profile.norm_to.get(metric) {
+                if !data.builds.contains(b) {
+                    anyhow::bail!(
+                        "Build `{}` in the `norm-to` of profile `{}` not found",
+                        b,
+                        run.profile.name
+                    );
+                }
+                b.clone()
+            } else {
+                baseline.to_owned()
+            };
+            builds.insert(metric.clone(), build);
+        }
+        Ok(builds)
+    }
+
+    /// Render the full markdown report
+    fn render_markdown(
+        &self,
+        run: &RunInfo,
+        data: &ReportData,
+        baseline: &String,
+        summaries: &[(String, MetricSummary)],
+    ) -> String {
+        let mut md = "# Benchmark Results\n\n".to_owned();
+        md += &format!("* run: `{}`\n", run.runid);
+        md += &format!("* baseline: `{}`\n", baseline);
+        md += &self.value_notes(run);
+        md += &format!(
+            "* ratios: mean of the build divided by the mean of `{}`. `*` marks a significant difference ({}, p < {})\n",
+            baseline,
+            self.stat_test.name(),
+            self.alpha
+        );
+        md += &format!(
+            "{}. The best (lowest) mean of each benchmark is in bold\n",
+            self.highlight_notes()
+        );
+        md += &self.outlier_notes();
+        for (metric, summary) in summaries {
+            md += &format!("## {}\n\n", metric);
+            if summary.baseline != *baseline {
+                md += &format!("* normalized to `{}`\n\n", summary.baseline);
+            }
+            let others = Self::others(data, summary);
+            let (mut header, mut rows, geomean) = self.summary_table(&others, summary);
+            rows.push(geomean);
+            rows.last_mut().unwrap()[0] = "*geomean*".to_owned();
+            // workspace mode: the package of each benchmark
+            let label_columns = if run.crate_info.packages.is_empty() {
+                1
+            } else {
+                header.insert(0, "package".to_owned());
+                for row in &mut rows {
+                    let package = run.crate_info.package(&row[0]).unwrap_or_default();
+                    row.insert(0, package.to_owned());
+                }
+                2
+            };
+            let mut table = format!("|{}|\n", header.join("|"));
+            table += &"|:-".repeat(label_columns);
+            table += "|";
+            table += &"-:|".repeat(header.len() - label_columns);
+            table += "\n";
+            for row in rows {
+                table += &format!("|{}|\n", row.join("|"));
+            }
+            md += &format!("{}\n", table);
+        }
+        for (metric, _) in summaries {
+            md += &Self::scaling(run, data, metric);
+        }
+        md += &Self::build_times(run);
+        md += &Self::outliers(&run.runid, data);
+        md
+    }
+
+    fn format_seconds(secs: Option<f64>) -> String {
+        secs.map_or("-".to_owned(), |s| format!("{:.2}s", s))
+    }
+
+    /// The compile time table of the builds. Empty if the run has no recorded build times.
+    fn build_times(run: &RunInfo) -> String {
+        if run.build_times.is_empty() {
+            return String::new();
+        }
+        let mut md = "## build time\n\n".to_owned();
+        md += "|build|wall time|cargo|\n|:-|-:|-:|\n";
+        for (build, t) in &run.build_times {
+            md += &format!(
+                "|{}|{}|{}|\n",
+                build,
+                Self::format_seconds(Some(t.walltime)),
+                Self::format_seconds(t.cargo)
+            );
+        }
+        md + "\n"
+    }
+
+    /// The compile time of each build in both runs. Empty if neither run has recorded build times.
+    fn build_time_comparison(base_run: &RunInfo, run: &RunInfo) -> String {
+        let builds = base_run
+            .build_times
+            .keys()
+            .chain(run.build_times.keys())
+            .collect::<BTreeSet<_>>();
+        if builds.is_empty() {
+            return String::new();
+        }
+        let mut md = "## build time\n\n".to_owned();
+        md += &format!(
+            "|build|{}|{}|ratio|\n|:-|-:|-:|-:|\n",
+            base_run.runid, run.runid
+        );
+        for build in builds {
+            let b = base_run.build_times.get(build).map(|t| t.walltime);
+            let c = run.build_times.get(build).map(|t| t.walltime);
+            let ratio = match (b, c) {
+                (Some(b), Some(c)) if b > 0.0 => format!("{:.3}×", c / b),
+                _ => "-".to_owned(),
+            };
+            md += &format!(
+                "|{}|{}|{}|{}|\n",
+                build,
+                Self::format_seconds(b),
+                Self::format_seconds(c),
+                ratio
+            );
+        }
+        md + "\n"
+    }
+
+    /// The scaling table of a metric over the thread counts of the `threads` sweep. Empty if the run has no sweep.
+    fn scaling(run: &RunInfo, data: &ReportData, metric: &str) -> String {
+        let Some(scaling) = data.scaling(run, metric) else {
+            return String::new();
+        };
+        let mut md = format!("## {} scaling\n\n", metric);
+        md += &format!(
+            "* values: mean (ratio to {} threads) of each thread count\n\n",
+            scaling.threads[0]
+        );
+        md += "|bench|build|";
+        for n in &scaling.threads {
+            md += &format!("{} threads|", n);
+        }
+        md += "\n|:-|:-|";
+        md += &"-:|".repeat(scaling.threads.len());
+        md += "\n";
+        for ((bench, build), means) in &scaling.curves {
+            md += &format!("|{}|{}|", bench, build);
+            for (m, r) in means.iter().zip(Scaling::ratios(means)) {
+                md += &match (m, r) {
+                    (Some(m), Some(r)) => format!("{:.3} ({:.2}x)|", m, r),
+                    (Some(m), None) => format!("{:.3}|", m),
+                    _ => "-|".to_owned(),
+                };
+            }
+            md += "\n";
+        }
+        md + "\n"
+    }
+
+    /// Report the differences between two runs
+    fn report_comparison(
+        &self,
+        logs_dir: &Path,
+        run_id: &str,
+        compare: &str,
+        bootstrap: Bootstrap,
+    ) -> anyhow::Result<String> {
+        let (base_run, base) = self.load(logs_dir, run_id)?;
+        let (run, data) = self.load(logs_dir, compare)?;
+        if base.benches.is_disjoint(&data.benches) || base.builds.is_disjoint(&data.builds) {
+            anyhow::bail!(
+                "Runs `{}` and `{}` have no benchmarks and builds in common",
+                base_run.runid,
+                run.runid
+            );
+        }
+        if self.format == ReportFormat::PrComment {
+            let comparisons = data
+                .metrics
+                .intersection(&base.metrics)
+                .map(|m| {
+                    let c = data.per_metric_run_comparison(&base, m, self.stat_test, bootstrap);
+                    (m, c)
+                })
+                .collect::<Vec<_>>();
+            let diffs = comparisons
+                .iter()
+                .flat_map(|(metric, comparison)| {
+                    comparison.geomeans.keys().map(|build| Diff {
+                        metric,
+                        baseline: format!("{}:{}", base_run.runid, build),
+                        candidate: format!("{}:{}", run.runid, build),
+                        benches: comparison
+                            .pairs
+                            .iter()
+                            .filter(|((_, b), _)| b == build)
+                            .map(|((bench, _), (b, c))| (bench.as_str(), b, c))
+                            .collect(),
+                        geomean: comparison.geomeans.get(build),
+                    })
+                })
+                .collect::<Vec<_>>();
+            let title = format!("Run Comparison: `{}` vs `{}`", run.runid, base_run.runid);
+            return Ok(self.render_pr_comment(&title, &diffs));
+        }
+        let mut md = "# Run Comparison\n\n".to_owned();
+        md += &format!("* base run: `{}`\n", base_run.runid);
+        md += &format!("* compared run: `{}`\n", run.runid);
+        md += &self.value_notes(&run);
+        md += &format!(
+            "* delta and ratio: mean in `{}` minus and divided by the mean in `{}`. `*` marks a significant difference ({}, p < {})\n",
+            run.runid,
+            base_run.runid,
+            self.stat_test.name(),
+            self.alpha
+        );
+        md += &format!("{}\n", self.highlight_notes());
+        md += &self.outlier_notes();
+        for metric in data.metrics.intersection(&base.metrics) {
+            let comparison =
+                data.per_metric_run_comparison(&base, metric, self.stat_test, bootstrap);
+            if comparison.pairs.is_empty() {
+                continue;
+            }
+            md += &format!("## {}\n\n", metric);
+            let mut table = format!(
+                "|bench|build|{}|{}|delta|ratio|p|\n",
+                base_run.runid, run.runid
+            );
+            table += "|:-|:-|-:|-:|-:|-:|-:|\n";
+            for ((bench, build), (b, c)) in &comparison.pairs {
+                table += &format!(
+                    "|{}|{}|{}|{}|{:+.3}|{}|{}|\n",
+                    bench,
+                    build,
+                    Self::format_value(b),
+                    Self::format_value(c),
+                    c.summary.mean - b.summary.mean,
+                    self.format_ratio(c),
+                    Self::format_p(c)
+                );
+            }
+            for (build, g) in &comparison.geomeans {
+                table += &format!(
+                    "|*geomean*|{}||||{}||\n",
+                    build,
+                    Self::format_ci(g.value, g.ci)
+                );
+            }
+            md += &format!("{}\n", table);
+        }
+        md += &Self::build_time_comparison(&base_run, &run);
+        md += &Self::outliers(&base_run.runid, &base);
+        md += &Self::outliers(&run.runid, &data);
+        Ok(md)
+    }
+
+    pub fn run(&self) -> anyhow::Result<()> {
+        if !(self.alpha > 0.0 && self.alpha < 1.0) {
+            anyhow::bail!("The significance level must be between 0 and 1");
+        }
+        if !(self.confidence > 0.0 && self.confidence < 1.0) {
+            anyhow::bail!("The confidence level must be between 0 and 1");
+        }
+        if self.resamples == 0 {
+            anyhow::bail!("The number of resamples must be greater than 0");
+        }
+        if self.github_summary {
+            if matches!(self.format, ReportFormat::Html | ReportFormat::Json) {
+                anyhow::bail!("`--github-summary` only supports the markdown formats");
+            }
+            Self::github_summary_path()?;
+        }
+        let bootstrap = Bootstrap {
+            confidence: self.confidence,
+            resamples: self.resamples,
+        };
+        let logs_dir = CrateInfo::get_target_path()?.join("harness").join("logs");
+        let run_id = self.run_id.as_deref().unwrap_or("latest");
+        if let Some(compare) = &self.compare {
+            if self.baseline.is_some() {
+                anyhow::bail!("`--baseline` cannot be used with `--compare`");
+            }
+            if self.baseline_run.is_some() {
+                anyhow::bail!("`--baseline-run` cannot be used with `--compare`");
+            }
+            if self.html.is_some() || self.format == ReportFormat::Html {
+                anyhow::bail!("`--html` and `--format html` cannot be used with `--compare`");
+            }
+            if self.format == ReportFormat::Json {
+                anyhow::bail!("`--format json` cannot be used with `--compare`");
+            }
+            if !self.norm_to.is_empty() {
+                anyhow::bail!("`--norm-to` cannot be used with `--compare`");
+            }
+            let md = self.report_comparison(&logs_dir, run_id, compare, bootstrap)?;
+            return self.output(&logs_dir.join(run_id), &md);
+        }
+        let (run, mut data) = self.load(&logs_dir, run_id)?;
+        let baseline = match (&self.baseline, &self.baseline_run) {
+            (Some(_), Some(_)) => {
+                anyhow::bail!("`--baseline` cannot be used with `--baseline-run`")
+            }
+            (_, Some(baseline_run)) => {
+                self.load_baseline_run(&logs_dir, baseline_run, &run, &mut data)?
+            }
+            (Some(b), _) if !data.builds.contains(b) => anyhow::bail!("Build `{}` not found", b),
+            (Some(b), _) => b.clone(),
+            (None, None) => data.builds.first().unwrap().clone(),
+        };
+        // `--norm-to <BUILD>` replaces the baseline of all metrics
+        let norm_to_all = self
+            .norm_to
+            .iter()
+            .find(|n| Self::parse_norm_to(&data, n).0.is_none());
+        let baseline = match norm_to_all {
+            Some(_) if self.baseline.is_some() => {
+                anyhow::bail!("`--baseline` cannot be used with `--norm-to <BUILD>`. Use `--norm-to <METRIC>=<BUILD>` to normalize a single metric")
+            }
+            Some(b) => b.clone(),
+            None => baseline,
+        };
+        let norm_builds = self.norm_builds(&run, &data, &baseline)?;
+        let summaries = norm_builds
+            .iter()
+            .map(|(m, build)| {
+                let summary = data.per_metric_summary(m, build, self.stat_test, bootstrap);
+                (m.clone(), summary)
+            })
+            .filter(|(_, s)| !s.benches.is_empty())
+            .collect::<Vec<_>>();
+        let report = match self.format {
+            ReportFormat::PrComment => {
+                let diffs = summaries
+                    .iter()
+                    .flat_map(|(metric, summary)| {
+                        Self::others(&data, summary).into_iter().map(|build| Diff {
+                            metric,
+                            baseline: summary.baseline.clone(),
+                            candidate: build.to_string(),
+                            benches: summary
+                                .benches
+                                .iter()
+                                .filter_map(|(bench, builds)| {
+                                    Some((
+                                        bench.as_str(),
+                                        builds.get(&summary.baseline)?,
+                                        builds.get(build)?,
+                                    ))
+                                })
+                                .collect(),
+                            geomean: summary.geomeans.get(build),
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                self.render_pr_comment(&format!("Benchmark Results: `{}`", run.runid), &diffs)
+            }
+            ReportFormat::Markdown => self.render_markdown(&run, &data, &baseline, &summaries),
+            ReportFormat::Html => self.render_html(&run, &data, &baseline, &summaries),
+            ReportFormat::Json => self.render_json(&run, &data, &baseline, &summaries),
+        };
+        self.output(&logs_dir.join(&run.runid), &report)?;
+        if let Some(path) = &self.html {
+            let html = self.render_html(&run, &data, &baseline, &summaries);
+            std::fs::write(path, html)?;
+            println!("HTML report written to {}", path.display());
+        }
+        Ok(())
+    }
+}
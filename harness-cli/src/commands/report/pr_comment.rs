@@ -0,0 +1,131 @@
+use super::{
+    data::{Comparison, Geomean},
+    ReportArgs,
+};
+
+/// The maximum length of a GitHub comment body
+const MAX_COMMENT_LENGTH: usize = 65536;
+
+/// The differences of a metric between a baseline and a candidate, i.e. two builds, or the same build in two runs
+pub(super) struct Diff<'a> {
+    pub metric: &'a str,
+    pub baseline: String,
+    pub candidate: String,
+    /// (bench, baseline, candidate). The candidate ratios are relative to the baseline.
+    pub benches: Vec<(&'a str, &'a Comparison, &'a Comparison)>,
+    pub geomean: Option<&'a Geomean>,
+}
+
+fn format_change(ratio: f64) -> String {
+    format!("{:+.1}%", (ratio - 1.0) * 100.0)
+}
+
+impl ReportArgs {
+    fn is_significant(&self, c: &Comparison) -> bool {
+        c.test.is_some_and(|t| t.p < self.alpha)
+    }
+
+    fn is_regression(&self, c: &Comparison) -> bool {
+        c.ratio.is_some_and(|r| r > 1.0) && self.is_significant(c)
+    }
+
+    fn is_improvement(&self, c: &Comparison) -> bool {
+        c.ratio.is_some_and(|r| r < 1.0) && self.is_significant(c)
+    }
+
+    fn diff_details(&self, diff: &Diff) -> String {
+        let mut md = format!(
+            "<details>\n<summary>{}: <code>{}</code> vs <code>{}</code></summary>\n\n",
+            diff.metric, diff.candidate, diff.baseline
+        );
+        md += &format!(
+            "|bench|{}|{}|change|p||\n|:-|-:|-:|-:|-:|:-:|\n",
+            diff.baseline, diff.candidate
+        );
+        for (bench, b, c) in &diff.benches {
+            md += &format!(
+                "|{}|{:.3}|{:.3}|{}|{}|{}|\n",
+                bench,
+                b.summary.mean,
+                c.summary.mean,
+                c.ratio.map_or("-".to_owned(), format_change),
+                Self::format_p(c),
+                if self.is_regression(c) {
+                    "⚠️"
+                } else {
+                    "✅"
+                }
+            );
+        }
+        md += "\n</details>\n\n";
+        md
+    }
+
+    /// Render a compact markdown report for a PR comment: one summary row per diff, and collapsible per-benchmark details.
+    /// Details are omitted from the end if the comment would exceed the GitHub comment length limit.
+    pub(super) fn render_pr_comment(&self, title: &str, diffs: &[Diff]) -> String {
+        let mut md = format!("### {}\n\n", title);
+        md += "|metric|baseline|candidate|geomean change|regressed|improved||\n";
+        md += "|:-|:-|:-|-:|-:|-:|:-:|\n";
+        for diff in diffs {
+            let regressed = diff
+                .benches
+                .iter()
+                .filter(|(_, _, c)| self.is_regression(c))
+                .count();
+            let improved = diff
+                .benches
+                .iter()
+                .filter(|(_, _, c)| self.is_improvement(c))
+                .count();
+            let geomean = match diff.geomean {
+                Some(g) => match g.ci {
+                    Some((lo, hi)) => format!(
+                        "{} [{}, {}]",
+                        format_change(g.value),
+                        format_change(lo),
+                        format_change(hi)
+                    ),
+                    None => format_change(g.value),
+                },
+                None => "-".to_owned(),
+            };
+            md += &format!(
+                "|{}|`{}`|`{}`|{}|{}/{}|{}/{}|{}|\n",
+                diff.metric,
+                diff.baseline,
+                diff.candidate,
+                geomean,
+                regressed,
+                diff.benches.len(),
+                improved,
+                diff.benches.len(),
+                if regressed > 0 { "⚠️" } else { "✅" }
+            );
+        }
+        md += "\n";
+        let footer = format!(
+            "<sub>⚠️ marks a significant increase ({}, p < {}), assuming lower values are better. The geomean change comes with a {}% bootstrap confidence interval.</sub>\n",
+            self.stat_test.name(),
+            self.alpha,
+            self.confidence * 100.0
+        );
+        let mut omitted = 0;
+        for diff in diffs {
+            let details = self.diff_details(diff);
+            // Leave room for the footer and the omission note
+            if omitted > 0 || md.len() + details.len() + footer.len() + 200 > MAX_COMMENT_LENGTH {
+                omitted += 1;
+                continue;
+            }
+            md += &details;
+        }
+        if omitted > 0 {
+            md += &format!(
+                "*{} per-benchmark tables are omitted to fit the comment length limit.*\n\n",
+                omitted
+            );
+        }
+        md + &footer
+    }
+}
@@ -22,6 +22,6 @@ pub fn run_all_checks(args: &RunArgs, run: &RunInfo, old: Option<&RunInfo>) -> a
     if let Some(old) = old {
         reproducibility::check(old, run)?;
     }
-    pre_bench::check(args, run)?;
+    pre_bench::check(args, run, old)?;
     Ok(())
 }
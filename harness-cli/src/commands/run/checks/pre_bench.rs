@@ -1,12 +1,41 @@
 use colored::{Colorize, CustomColor};
 use once_cell::sync::Lazy;
 
-use crate::{commands::run::RunArgs, configs::run_info::RunInfo};
+use crate::{
+    commands::run::RunArgs,
+    configs::{harness::CheckSeverity, run_info::RunInfo},
+    utils::{self, bench_cmd::get_shell_command},
+};
 
 use super::super::runner::BenchRunner;
 
 static BG: Lazy<CustomColor> = Lazy::new(|| CustomColor::new(0x23, 0x23, 0x23));
 
+/// The CPU temperature limit in °C, if the sensors do not report one
+const DEFAULT_CPU_TEMPERATURE_LIMIT: f64 = 95.0;
+/// Warn if the CPU temperature is within this many degrees of the limit
+const CPU_TEMPERATURE_MARGIN: f64 = 10.0;
+
+/// The built-in checks whose severities can be changed in the profile.
+/// Some of them only run on a specific OS, but are accepted on all of them so that a profile can be shared across machines.
+const BUILTIN_CHECKS: [&str; 15] = [
+    "dirty-worktree",
+    "logged-in-users",
+    "scaling-governor",
+    "power-plan",
+    "low-power-mode",
+    "power-source",
+    "cpu-temperature",
+    "turbo-boost",
+    "perf-event-paranoid",
+    "hybrid-cpu",
+    "thermal-throttling",
+    "spotlight-indexing",
+    "single-bench",
+    "single-build",
+    "identical-builds",
+];
+
 struct PreBenchmarkingChecker<'a> {
     warnings: Vec<String>,
     allow_dirty: bool,
@@ -14,25 +43,28 @@ struct PreBenchmarkingChecker<'a> {
     allow_multi_user: bool,
     #[allow(unused)]
     allow_any_scaling_governor: bool,
+    allow_battery: bool,
+    allow_high_temperature: bool,
+    allow_turbo_change: bool,
     run: &'a RunInfo,
+    /// The run being reproduced
+    old: Option<&'a RunInfo>,
     upload: bool,
 }
 
 impl<'a> PreBenchmarkingChecker<'a> {
-    fn new(
-        run: &'a RunInfo,
-        allow_dirty: bool,
-        allow_multi_user: bool,
-        allow_any_scaling_governor: bool,
-        upload: bool,
-    ) -> Self {
+    fn new(args: &RunArgs, run: &'a RunInfo, old: Option<&'a RunInfo>) -> Self {
         Self {
             warnings: Vec::new(),
-            allow_dirty,
-            allow_multi_user,
-            allow_any_scaling_governor,
+            allow_dirty: args.allow_dirty,
+            allow_multi_user: args.allow_multiple_users,
+            allow_any_scaling_governor: args.allow_any_scaling_governor,
+            allow_battery: args.allow_battery,
+            allow_high_temperature: args.allow_high_temperature,
+            allow_turbo_change: args.allow_turbo_change,
             run,
-            upload,
+            old,
+            upload: args.upload,
         }
     }
 
@@ -40,13 +72,93 @@ impl<'a> PreBenchmarkingChecker<'a> {
         self.warnings.push(msg.as_ref().to_owned());
     }
 
+    /// Report a built-in check that did not pass, with the severity set in the profile or the default severity.
+    /// A failure is downgraded to a warning if the check is explicitly allowed on the command line.
+    fn report(
+        &mut self,
+        check: &str,
+        default: CheckSeverity,
+        allowed: bool,
+        msg: impl AsRef<str>,
+    ) -> anyhow::Result<()> {
+        let severity = self.run.profile.checks.get(check).copied();
+        match severity.unwrap_or(default) {
+            CheckSeverity::Off => Ok(()),
+            CheckSeverity::Fail if !allowed => anyhow::bail!("{}", msg.as_ref()),
+            _ => {
+                self.warn(msg);
+                Ok(())
+            }
+        }
+    }
+
+    /// Report a check that fails by default, or only warns if the check is explicitly allowed
+    fn fail_or_warn(
+        &mut self,
+        check: &str,
+        allowed: bool,
+        msg: impl AsRef<str>,
+    ) -> anyhow::Result<()> {
+        self.report(check, CheckSeverity::Fail, allowed, msg)
+    }
+
+    /// Report a check that only warns by default
+    fn warn_or_fail(&mut self, check: &str, msg: impl AsRef<str>) -> anyhow::Result<()> {
+        self.report(check, CheckSeverity::Warn, false, msg)
+    }
+
+    /// Check if the checks in the profile are all known
+    fn check_check_names(&mut self) -> anyhow::Result<()> {
+        for name in self.run.profile.checks.keys() {
+            if !BUILTIN_CHECKS.contains(&name.as_str()) {
+                anyhow::bail!(
+                    "Unknown check `{}` in the profile. Available checks: {}.",
+                    name,
+                    BUILTIN_CHECKS.join(", ")
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Run the user-defined checks in the profile
+    fn check_custom_checks(&mut self) -> anyhow::Result<()> {
+        let profile = &self.run.profile;
+        for check in &profile.custom_checks {
+            if check.severity == CheckSeverity::Off {
+                continue;
+            }
+            let out = get_shell_command(profile, &check.command)
+                .stdin(std::process::Stdio::null())
+                .output()?;
+            if out.status.success() {
+                continue;
+            }
+            let mut msg = format!("Custom check `{}` failed ({}).", check.name, out.status);
+            // The last line of the outputs usually explains the failure
+            let last_line = |bytes: &[u8]| {
+                let outputs = String::from_utf8_lossy(bytes);
+                let line = outputs.lines().rev().find(|l| !l.trim().is_empty());
+                line.map(|l| l.trim().to_owned())
+            };
+            if let Some(line) = last_line(&out.stderr).or_else(|| last_line(&out.stdout)) {
+                msg += &format!(" {}", line.on_custom_color(*BG));
+            }
+            if check.severity == CheckSeverity::Fail {
+                anyhow::bail!("{}", msg);
+            }
+            self.warn(msg);
+        }
+        Ok(())
+    }
+
     fn check_bench_configs(&mut self) -> anyhow::Result<()> {
         let benches = self.run.crate_info.benches.len();
         if benches == 0 {
             anyhow::bail!("No benchmarks found.");
         }
         if benches == 1 {
-            self.warn("Only one benchmark is probably not enough.");
+            self.warn_or_fail("single-bench", "Only one benchmark is probably not enough.")?;
         }
         Ok(())
     }
@@ -59,7 +171,10 @@ impl<'a> PreBenchmarkingChecker<'a> {
             anyhow::bail!("No builds found in the profile.");
         }
         if builds == 1 {
-            self.warn("It's recommended to always have more than one builds.");
+            self.warn_or_fail(
+                "single-build",
+                "It's recommended to always have more than one builds.",
+            )?;
         }
         if builds >= BenchRunner::MAX_SUPPORTED_BUILDS {
             anyhow::bail!(
@@ -73,14 +188,27 @@ impl<'a> PreBenchmarkingChecker<'a> {
             for j in i + 1..names.len() {
                 let (n1, n2) = (&names[i], &names[j]);
                 if self.run.profile.builds[n1] == self.run.profile.builds[n2] {
-                    self.warn(format!(
-                        "Builds {} and {} are identical.",
-                        n1.italic(),
-                        n2.italic(),
-                    ));
+                    self.warn_or_fail(
+                        "identical-builds",
+                        format!("Builds {} and {} are identical.", n1.italic(), n2.italic()),
+                    )?;
                 }
             }
         }
+        // cargo args that conflict with the ones passed by harness?
+        for (name, build) in &self.run.profile.builds {
+            if let Some(arg) = build
+                .cargo_args
+                .iter()
+                .find(|a| ["--", "--bench", "--no-run"].contains(&a.as_str()))
+            {
+                anyhow::bail!(
+                    "`{}` is not allowed in the cargo-args of build `{}`.",
+                    arg,
+                    name
+                );
+            }
+        }
         // git commit exists?
         for (name, build) in &self.run.profile.builds {
             if let Some(mut commit) = build.commit.clone() {
@@ -110,10 +238,10 @@ impl<'a> PreBenchmarkingChecker<'a> {
         let perf_event_paranoid = std::fs::read_to_string("/proc/sys/kernel/perf_event_paranoid")?;
         let perf_event_paranoid = perf_event_paranoid.trim().parse::<i32>()?;
         if perf_event_paranoid != -1 {
-            self.warn(format!(
+            self.warn_or_fail("perf-event-paranoid", format!(
                 "/proc/sys/kernel/perf_event_paranoid is {}. This may cause permission issues when reading performance counters.",
                 perf_event_paranoid
-            ));
+            ))?;
         }
         Ok(())
     }
@@ -124,10 +252,7 @@ impl<'a> PreBenchmarkingChecker<'a> {
             anyhow::bail!("No git repo found");
         };
         if dirty {
-            if !self.allow_dirty {
-                anyhow::bail!("Git worktree is dirty.");
-            }
-            self.warn("Git worktree is dirty.");
+            self.fail_or_warn("dirty-worktree", self.allow_dirty, "Git worktree is dirty.")?;
             if self.upload {
                 anyhow::bail!("Cannot upload results with a dirty git worktree.");
             }
@@ -135,18 +260,222 @@ impl<'a> PreBenchmarkingChecker<'a> {
         Ok(())
     }
 
+    fn check_cachegrind(&mut self) -> anyhow::Result<()> {
+        // valgrind is installed in the image of a container run
+        if self.run.container.is_some() {
+            return Ok(());
+        }
+        if self.run.profile.cachegrind && !utils::cachegrind::valgrind_exists() {
+            anyhow::bail!("Cachegrind is enabled, but valgrind is not installed.");
+        }
+        Ok(())
+    }
+
+    fn check_cgroup(&mut self) -> anyhow::Result<()> {
+        let Some(cgroup) = &self.run.profile.cgroup else {
+            return Ok(());
+        };
+        if !cfg!(target_os = "linux") {
+            anyhow::bail!("Cgroup limits are only supported on Linux.");
+        }
+        if let Some(memory) = &cgroup.memory_max {
+            utils::cgroup::validate_memory_size(memory)?;
+        }
+        if cgroup.cpu_quota.is_some_and(|c| c <= 0.0) {
+            anyhow::bail!("The cgroup CPU quota must be positive.");
+        }
+        // The container engine applies the limits to a container run
+        if self.run.container.is_none() && !utils::cgroup::systemd_run_exists() {
+            anyhow::bail!("Cgroup limits are enabled, but systemd-run is not installed.");
+        }
+        Ok(())
+    }
+
+    fn check_aslr(&mut self) -> anyhow::Result<()> {
+        if self.run.profile.disable_aslr && !cfg!(target_os = "linux") {
+            anyhow::bail!("Disabling ASLR is only supported on Linux.");
+        }
+        Ok(())
+    }
+
+    /// Check if the nice level and the real-time priority are valid, and if we have the privileges to set them
+    fn check_priority(&mut self) -> anyhow::Result<()> {
+        let (nice, priority) = (self.run.profile.nice, self.run.profile.realtime_priority);
+        if nice.is_none() && priority.is_none() {
+            return Ok(());
+        }
+        if !cfg!(target_os = "linux") {
+            anyhow::bail!("Setting the scheduling priority is only supported on Linux.");
+        }
+        if nice.is_some_and(|n| !(-20..=19).contains(&n)) {
+            anyhow::bail!("The nice level must be between -20 and 19.");
+        }
+        if priority.is_some_and(|p| !(1..=99).contains(&p)) {
+            anyhow::bail!("The real-time priority must be between 1 and 99.");
+        }
+        #[cfg(target_os = "linux")]
+        utils::sys::check_priority_privileges(nice, priority)?;
+        Ok(())
+    }
+
+    /// Check the process settings that cannot be applied to the benchmarks inside a container
+    fn check_container(&mut self) -> anyhow::Result<()> {
+        if self.run.container.is_none() {
+            return Ok(());
+        }
+        let profile = &self.run.profile;
+        if profile.disable_aslr {
+            anyhow::bail!("Disabling ASLR is not supported in container runs.");
+        }
+        if profile.nice.is_some() || profile.realtime_priority.is_some() {
+            anyhow::bail!("Setting the scheduling priority is not supported in container runs.");
+        }
+        Ok(())
+    }
+
+    fn check_cpu_monitor(&mut self) -> anyhow::Result<()> {
+        if self.run.profile.cpu_monitor && !utils::cpu_monitor::cpu_frequency_available() {
+            self.warn("CPU frequency is not available on this machine, and will not be recorded.");
+        }
+        Ok(())
+    }
+
+    fn check_numa_nodes(&mut self) -> anyhow::Result<()> {
+        let profile = &self.run.profile;
+        let nodes = profile
+            .builds
+            .values()
+            .filter_map(|b| b.numa_node.or(profile.numa_node))
+            .collect::<Vec<_>>();
+        if nodes.is_empty() {
+            return Ok(());
+        }
+        if !cfg!(target_os = "linux") {
+            anyhow::bail!("NUMA binding is only supported on Linux.");
+        }
+        if profile.parallelism > 1 {
+            anyhow::bail!("NUMA binding cannot be used with parallel invocations.");
+        }
+        for node in nodes {
+            let Some(n) = self.run.system.numa_nodes.iter().find(|n| n.id == node) else {
+                anyhow::bail!("NUMA node {} does not exist.", node);
+            };
+            if let Some(cpus) = &self.run.cpu_affinity {
+                if !n.cpus.iter().any(|c| cpus.contains(c)) {
+                    anyhow::bail!("None of the pinned CPUs are on NUMA node {}.", node);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check if the benchmarks may be scheduled on more than one core class
+    fn check_hybrid_cpu(&mut self) -> anyhow::Result<()> {
+        let sys = &self.run.system;
+        let pin = self.run.profile.pin_performance_cores;
+        if !cfg!(target_os = "linux") && pin {
+            anyhow::bail!("Pinning to performance cores is only supported on Linux.");
+        }
+        if !sys.is_hybrid_cpu() {
+            if pin {
+                self.warn("No hybrid CPU detected. Performance core pinning is ignored.");
+            }
+            return Ok(());
+        }
+        if !pin {
+            let msg =
+                "Hybrid CPU detected. Benchmarks may run on both performance and efficiency cores.";
+            if cfg!(target_os = "linux") {
+                self.warn_or_fail(
+                    "hybrid-cpu",
+                    format!(
+                        "{} Use {} to pin them to the performance cores.",
+                        msg,
+                        "--pin-performance-cores".italic()
+                    ),
+                )?;
+            } else {
+                self.warn_or_fail("hybrid-cpu", msg)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Check if the machine is running on battery, which may throttle the CPU to save power
+    fn check_power_source(&mut self) -> anyhow::Result<()> {
+        if self.run.system.on_battery != Some(true) {
+            return Ok(());
+        }
+        let msg = format!(
+            "The machine is running on battery. Plug in the power adapter, or use {} to ignore this.",
+            "--allow-battery".italic()
+        );
+        self.fail_or_warn("power-source", self.allow_battery, msg)
+    }
+
+    /// Check if the CPU is already close to the temperature at which it is throttled
+    fn check_cpu_temperature(&mut self) -> anyhow::Result<()> {
+        let sys = &self.run.system;
+        let Some(temperature) = sys.cpu_temperature else {
+            return Ok(());
+        };
+        let limit = sys
+            .cpu_temperature_limit
+            .unwrap_or(DEFAULT_CPU_TEMPERATURE_LIMIT);
+        if temperature + CPU_TEMPERATURE_MARGIN < limit {
+            return Ok(());
+        }
+        let msg = format!(
+            "The CPU temperature is {}, close to the throttling limit of {:.0}°C. Let the machine cool down, or use {} to ignore this.",
+            format!("{:.0}°C", temperature).on_custom_color(*BG),
+            limit,
+            "--allow-high-temperature".italic()
+        );
+        self.fail_or_warn("cpu-temperature", self.allow_high_temperature, msg)
+    }
+
+    /// Check if turbo boost is in the same state as in the run being reproduced
+    fn check_turbo_boost(&mut self) -> anyhow::Result<()> {
+        let old = self.old.and_then(|r| r.system.turbo_boost);
+        let (Some(old), Some(new)) = (old, self.run.system.turbo_boost) else {
+            return Ok(());
+        };
+        if old == new {
+            return Ok(());
+        }
+        let state = |enabled: bool| if enabled { "enabled" } else { "disabled" };
+        let msg = format!(
+            "Turbo boost is {}, but was {} in the reproduced run. Use {} to ignore this.",
+            state(new).on_custom_color(*BG),
+            state(old).on_custom_color(*BG),
+            "--allow-turbo-change".italic()
+        );
+        self.fail_or_warn("turbo-boost", self.allow_turbo_change, msg)
+    }
+
     fn check_common(&mut self) -> anyhow::Result<()> {
+        self.check_check_names()?;
         self.check_dirty_git_worktree()?;
+        self.check_cachegrind()?;
+        self.check_cgroup()?;
+        self.check_aslr()?;
+        self.check_numa_nodes()?;
+        self.check_priority()?;
+        self.check_container()?;
+        self.check_cpu_monitor()?;
+        self.check_hybrid_cpu()?;
+        self.check_power_source()?;
+        self.check_cpu_temperature()?;
+        self.check_turbo_boost()?;
         self.check_bench_configs()?;
         self.check_build_configs()?;
+        self.check_custom_checks()?;
         Ok(())
     }
 
-    #[cfg(target_os = "linux")]
-    fn check(&mut self) -> anyhow::Result<()> {
-        self.check_common()?;
-        self.check_perf_event()?;
-        // Check if the current user is the only one logged in
+    /// Check if the current user is the only one logged in
+    #[cfg(any(target_os = "linux", target_os = "windows"))]
+    fn check_logged_in_users(&mut self) -> anyhow::Result<()> {
         let sys = &self.run.system;
         if sys.users.len() > 1 {
             let msg = format!(
@@ -157,13 +486,18 @@ impl<'a> PreBenchmarkingChecker<'a> {
                     .collect::<Vec<_>>()
                     .join(", ")
             );
-            if self.allow_multi_user {
-                self.warn(msg);
-            } else {
-                anyhow::bail!("{}", msg);
-            }
+            self.fail_or_warn("logged-in-users", self.allow_multi_user, msg)?;
         }
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn check(&mut self) -> anyhow::Result<()> {
+        self.check_common()?;
+        self.check_perf_event()?;
+        self.check_logged_in_users()?;
         // Check if all the scaling governors are set to `performance`
+        let sys = &self.run.system;
         if !sys.scaling_governor.iter().all(|g| g == "performance") {
             let sg = sys.scaling_governor.clone();
             let mut sg_dedup = sg.clone();
@@ -180,30 +514,79 @@ impl<'a> PreBenchmarkingChecker<'a> {
                 sg_info.italic(),
                 "https://wiki.archlinux.org/title/CPU_frequency_scaling".italic().underline()
             );
-            if self.allow_any_scaling_governor {
-                self.warn(msg);
-            } else {
-                anyhow::bail!("{}", msg);
-            }
+            self.fail_or_warn("scaling-governor", self.allow_any_scaling_governor, msg)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(target_os = "windows")]
+    fn check(&mut self) -> anyhow::Result<()> {
+        self.check_common()?;
+        self.check_logged_in_users()?;
+        // Check if the active power plan is `High performance` or `Ultimate Performance`
+        let plan = self.run.system.power_plan.clone();
+        let is_performance = plan.as_ref().is_some_and(|p| {
+            let p = p.to_lowercase();
+            p.contains("high performance") || p.contains("ultimate performance")
+        });
+        if !is_performance {
+            let msg = format!(
+                "The active power plan is not high performance: {}. Use {} to switch the power plan.",
+                plan.as_deref()
+                    .unwrap_or("<unknown>")
+                    .on_custom_color(*BG)
+                    .italic(),
+                "powercfg /setactive SCHEME_MIN".italic()
+            );
+            self.fail_or_warn("power-plan", self.allow_any_scaling_governor, msg)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(target_os = "macos")]
+    fn check(&mut self) -> anyhow::Result<()> {
+        self.check_common()?;
+        let sys = &self.run.system;
+        // Low power mode caps the CPU frequency
+        if sys.low_power_mode == Some(true) {
+            let msg = format!(
+                "Low power mode is enabled. Use {} to disable it.",
+                "sudo pmset -a lowpowermode 0".italic()
+            );
+            self.fail_or_warn("low-power-mode", self.allow_any_scaling_governor, msg)?;
+        }
+        // The CPU is throttled due to thermal pressure
+        if let Some(limit) = sys.cpu_speed_limit.filter(|l| *l < 100) {
+            self.warn_or_fail(
+                "thermal-throttling",
+                format!(
+                    "The CPU speed is limited to {}% due to thermal pressure.",
+                    limit.to_string().on_custom_color(*BG)
+                ),
+            )?;
+        }
+        // Spotlight indexing may wake up and compete for the CPU and IO at any time
+        if sys.spotlight_indexing == Some(true) {
+            self.warn_or_fail(
+                "spotlight-indexing",
+                format!(
+                    "Spotlight indexing is enabled. Use {} to disable it.",
+                    "sudo mdutil -a -i off".italic()
+                ),
+            )?;
         }
         Ok(())
     }
 
-    #[cfg(not(target_os = "linux"))]
+    #[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
     fn check(&mut self) -> anyhow::Result<()> {
         self.check_common()?;
         Ok(())
     }
 }
 
-pub fn check(args: &RunArgs, run: &RunInfo) -> anyhow::Result<()> {
-    let mut checker = PreBenchmarkingChecker::new(
-        run,
-        args.allow_dirty,
-        args.allow_multiple_users,
-        args.allow_any_scaling_governor,
-        args.upload,
-    );
+pub fn check(args: &RunArgs, run: &RunInfo, old: Option<&RunInfo>) -> anyhow::Result<()> {
+    let mut checker = PreBenchmarkingChecker::new(args, run, old);
     checker.check()?;
     super::dump_warnings("WARNINGS", &checker.warnings);
     Ok(())
@@ -3,7 +3,8 @@ use std::cell::RefCell;
 use colored::{Colorize, CustomColor};
 use once_cell::sync::Lazy;
 
-use crate::configs::run_info::RunInfo;
+use crate::configs::run_info::{NumaNode, RunInfo};
+use crate::utils::toolchain;
 
 static BG: Lazy<CustomColor> = Lazy::new(|| CustomColor::new(0x23, 0x23, 0x23));
 
@@ -64,6 +65,26 @@ impl<'a, 'b> ReproducibilityChecker<'a, 'b> {
         self.check_changed_mem("Memory", old.system.memory_size, new.system.memory_size);
         self.check_changed_mem("Swap", old.system.swap_size, new.system.swap_size);
         self.check_changed("Rust Version", &old.system.rustc, &new.system.rustc);
+        let toolchain_changed = match (&old.system.toolchain, &new.system.toolchain) {
+            (Some(a), Some(b)) => !toolchain::is_same(a, b),
+            (Some(_), None) => true,
+            _ => false,
+        };
+        if toolchain_changed {
+            if let (Some(a), Some(b)) = (&old.system.toolchain, &new.system.toolchain) {
+                if old.system.rustc == new.system.rustc {
+                    self.check_changed(
+                        "Rust Commit",
+                        a.commit_hash.as_deref().unwrap_or_default(),
+                        b.commit_hash.as_deref().unwrap_or_default(),
+                    );
+                }
+            }
+            self.warn(format!(
+                "Rust toolchain changed. Use {} to reproduce with the recorded toolchain.",
+                "--strict-toolchain".italic().on_custom_color(*BG)
+            ));
+        }
         if old.system.env != new.system.env {
             let mut s = "Environment Variables Changed:\n".to_owned();
             let mut list_env = |name: &str, old: &str, new: &str| {
@@ -88,7 +109,6 @@ impl<'a, 'b> ReproducibilityChecker<'a, 'b> {
             }
             self.warn(s.trim_end());
         }
-        #[cfg(target_os = "linux")]
         if old.system.scaling_governor != new.system.scaling_governor {
             let sg_summary = |sg: &[String]| {
                 let mut dedup = sg.to_vec();
@@ -106,6 +126,43 @@ impl<'a, 'b> ReproducibilityChecker<'a, 'b> {
                 sg_summary(&new.system.scaling_governor),
             );
         }
+        if old.system.power_plan != new.system.power_plan {
+            let unknown = "<unknown>".to_owned();
+            self.warn_changed(
+                "Power Plan",
+                old.system.power_plan.as_ref().unwrap_or(&unknown),
+                new.system.power_plan.as_ref().unwrap_or(&unknown),
+            );
+        }
+        if old.system.numa_nodes != new.system.numa_nodes {
+            let topology = |nodes: &[NumaNode]| {
+                nodes
+                    .iter()
+                    .map(|n| format!("node{}: {} CPUs", n.id, n.cpus.len()))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            self.warn_changed(
+                "NUMA Topology",
+                topology(&old.system.numa_nodes),
+                topology(&new.system.numa_nodes),
+            );
+        }
+        if let (Some(a), Some(b)) = (old.system.smt, new.system.smt) {
+            let state = |enabled: bool| if enabled { "enabled" } else { "disabled" };
+            self.check_changed("SMT", state(a), state(b));
+        }
+        if let (Some(a), Some(b)) = (old.system.randomize_va_space, new.system.randomize_va_space) {
+            self.check_changed_int("ASLR Setting (randomize_va_space)", a, b);
+        }
+        if old.system.aslr_disabled != new.system.aslr_disabled {
+            let state = |disabled: bool| if disabled { "disabled" } else { "enabled" };
+            self.warn_changed(
+                "ASLR",
+                state(old.system.aslr_disabled),
+                state(new.system.aslr_disabled),
+            );
+        }
         if old.profile.invocations != new.profile.invocations {
             self.check_changed_int(
                 "Invocations",
@@ -113,8 +170,29 @@ impl<'a, 'b> ReproducibilityChecker<'a, 'b> {
                 new.profile.invocations,
             );
         }
-        if old.profile.iterations != new.profile.iterations {
-            self.check_changed_int("Iterations", old.profile.iterations, new.profile.iterations);
+        if old.profile.warmup_iterations != new.profile.warmup_iterations {
+            self.check_changed_int(
+                "Warm-up Iterations",
+                old.profile.warmup_iterations,
+                new.profile.warmup_iterations,
+            );
+        }
+        if old.profile.measured_iterations != new.profile.measured_iterations {
+            self.check_changed_int(
+                "Measured Iterations",
+                old.profile.measured_iterations,
+                new.profile.measured_iterations,
+            );
+        }
+        self.check_changed(
+            "Run Order",
+            old.profile.run_order.name(),
+            new.profile.run_order.name(),
+        );
+        for (name, a) in &old.datasets {
+            if let Some(b) = new.datasets.get(name) {
+                self.check_changed(format!("Dataset `{}`", name), &a.sha256, &b.sha256);
+            }
         }
         if old.commit.ends_with("-dirty") {
             self.warn(format!(
@@ -1,46 +1,85 @@
-use std::path::{Path, PathBuf};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
 
 use chrono::{DateTime, Local};
-use clap::Parser;
+use clap::{ArgGroup, Parser};
 use termimad::crossterm::style::Stylize;
 
 use crate::{
     configs::{
-        harness::{BuildConfig, HarnessConfig, Profile},
-        run_info::{CrateInfo, RunInfo},
+        harness::{BuildConfig, HarnessConfig, Profile, RunOrder},
+        run_info::{BenchFilter, CrateInfo, RunInfo},
     },
     utils::{self, git::TempGitCommitGuard},
 };
 
-use super::upload::UploadResultsArgs;
+use super::upload::{self, UploadResultsArgs};
 
 mod checks;
+mod preview;
 pub(crate) mod runner;
+mod tui;
 
 /// Start a benchmarking run
 #[derive(Parser)]
+#[command(group(ArgGroup::new("reproduce").args(["config", "from_remote"])))]
 pub struct RunArgs {
-    /// Number of iterations. Default is 5, or the value specified in the profile.
-    #[arg(short = 'n', long)]
+    /// Number of warm-up iterations at the start of each invocation. Default is 4, or the value specified in the profile.
+    #[arg(short = 'w', long)]
+    pub warmup_iterations: Option<usize>,
+    /// Number of measured iterations after the warm-up iterations. Default is 1, or the value specified in the profile.
+    #[arg(short = 'm', long, alias = "timing-iterations")]
+    pub measured_iterations: Option<usize>,
+    /// Deprecated. The total number of iterations, including the measured iterations. Use `--warmup-iterations` instead.
+    #[arg(short = 'n', long, hide = true, conflicts_with = "warmup_iterations")]
     pub iterations: Option<usize>,
     /// Number of invocations. Default is 10, or the value specified in the profile.
     #[arg(short = 'i', long)]
     pub invocations: Option<usize>,
+    /// Number of invocations at the start of each benchmark and build that are excluded from the reports. Default is 0, or the value specified in the profile.
+    #[arg(long)]
+    pub warmup_invocations: Option<usize>,
     /// Benchmarking profile
     #[arg(short, long, default_value = "default")]
     pub profile: String,
     /// Allow dirty working directories
     #[arg(long, default_value = "false")]
     pub allow_dirty: bool,
-    /// (Linux only) Allow benchmarking even when multiple users are logged in
+    /// (Linux and Windows only) Allow benchmarking even when multiple users are logged in
     #[arg(long, default_value = "false")]
     pub allow_multiple_users: bool,
-    /// (Linux only) Allow any scaling governor value, instead of only `performance`
+    /// Allow any scaling governor value (Linux), any power plan (Windows), or the low power mode (macOS), instead of only `performance`
     #[arg(long, default_value = "false")]
     pub allow_any_scaling_governor: bool,
-    /// Specify a path to the config file, or the run id to reproduce a previous run.
+    /// (Linux and macOS only) Allow benchmarking on battery power
+    #[arg(long, default_value = "false")]
+    pub allow_battery: bool,
+    /// (Linux only) Allow benchmarking when the CPU temperature is already close to the throttling limit
+    #[arg(long, default_value = "false")]
+    pub allow_high_temperature: bool,
+    /// (Linux only) Allow reproducing a run with turbo boost in a different state
+    #[arg(long, default_value = "false", requires = "reproduce")]
+    pub allow_turbo_change: bool,
+    /// Specify a path to the config file, the run id, or the link to the uploaded results (e.g. `https://r.harness.rs/?id=<HASH>`) to reproduce a previous run.
     #[arg(long)]
     pub config: Option<String>,
+    /// Reproduce a run uploaded to the harness server, by its upload hash. The server is `upload.remote` in the user config, or https://r.harness.rs
+    #[arg(long, value_name = "HASH")]
+    pub from_remote: Option<String>,
+    /// Run the shell commands of a config downloaded from the harness server, e.g. its hooks, custom checks, and services,
+    /// and use its settings that can run arbitrary code, e.g. its environment variables, remote build sources, and container image.
+    /// Without this flag, reproducing a downloaded config that has any of them fails, after listing them.
+    #[arg(long, default_value = "false", requires = "reproduce")]
+    pub trust_remote_config: bool,
+    /// When reproducing a previous run, install and use its exact Rust toolchain with rustup, or fail if this is not possible.
+    #[arg(long, default_value = "false", requires = "reproduce")]
+    pub strict_toolchain: bool,
+    /// Build and run the benchmarks inside a container of this Docker or Podman image (e.g. `rust:1.78`).
+    /// The image digest is recorded, and a reproduced run uses the same image.
+    #[arg(long, conflicts_with = "reproduce")]
+    pub container: Option<String>,
     /// Do an one-shot test run on a single benchmark.
     #[arg(long)]
     pub bench: Option<String>,
@@ -51,6 +90,96 @@ pub struct RunArgs {
     /// Upload the benchmark results to https://reports.harness.rs after the run.
     #[arg(long, default_value = "false")]
     pub upload: bool,
+    /// Do not show the timing results of the running and finished invocations in the progress output.
+    #[arg(long, default_value = "false")]
+    pub no_live_preview: bool,
+    /// Run the benchmarks under cachegrind, and record the instruction and cache-miss counts.
+    #[arg(long, default_value = "false")]
+    pub cachegrind: bool,
+    /// Record the sizes of the bench binaries of each build.
+    #[arg(long, default_value = "false")]
+    pub binary_size: bool,
+    /// Cache the bench binaries of each build, and run them without checking out the commits again.
+    #[arg(long, default_value = "false")]
+    pub build_cache: bool,
+    /// (Linux only) On hybrid CPUs, pin the benchmarks to the performance cores.
+    #[arg(long, default_value = "false")]
+    pub pin_performance_cores: bool,
+    /// Enable the probes for the warm-up iterations as well, not only the timing iteration.
+    #[arg(long, default_value = "false")]
+    pub measure_all_iterations: bool,
+    /// Dump the histogram of the latencies recorded by the benchmarks to the logs directory.
+    #[arg(long, default_value = "false")]
+    pub latency_histogram: bool,
+    /// Kill an invocation if it does not finish within this duration (e.g. `300s`). Overrides the profile timeout.
+    #[arg(long)]
+    pub timeout: Option<String>,
+    /// Stop starting new invocations once the run has taken this long (e.g. `6h`), and record the remaining ones as skipped. Overrides the profile max duration.
+    #[arg(long)]
+    pub max_duration: Option<String>,
+    /// Number of times to retry a failed invocation. Default is 0, or the value specified in the profile.
+    #[arg(long)]
+    pub retries: Option<usize>,
+    /// The order of running invocations, benchmarks and builds. Default is `inv-bench-build`, or the value specified in the profile.
+    #[arg(long)]
+    pub run_order: Option<RunOrder>,
+    /// (Linux only) Number of invocations to run concurrently, each on its own exclusive set of CPUs. Default is 1, or the value specified in the profile.
+    #[arg(short = 'j', long)]
+    pub parallelism: Option<usize>,
+    /// (Linux only) Pin the benchmark processes to these CPUs (e.g. `0,1,2,3`). Overrides the CPUs specified in the profile.
+    #[arg(long, value_delimiter = ',')]
+    pub cpus: Option<Vec<usize>>,
+    /// Run each build with these thread counts (e.g. `1,2,4,8`). Overrides the `threads` sweep of the profile.
+    #[arg(long, value_delimiter = ',', conflicts_with = "reproduce")]
+    pub threads: Option<Vec<usize>>,
+    /// (Linux only) Pin the benchmark processes to the CPUs isolated by the kernel (`isolcpus`).
+    #[arg(long, default_value = "false")]
+    pub isolate_cores: bool,
+    /// (Linux only) Run each invocation in a cgroup with this memory limit (e.g. `4G`). Overrides the limit specified in the profile.
+    #[arg(long)]
+    pub memory_max: Option<String>,
+    /// (Linux only) Run each invocation in a cgroup with this CPU quota, in number of CPUs (e.g. `1.5`). Overrides the quota specified in the profile.
+    #[arg(long)]
+    pub cpu_quota: Option<f64>,
+    /// (Linux only) Launch the benchmark processes with address space layout randomization disabled.
+    #[arg(long, default_value = "false")]
+    pub disable_aslr: bool,
+    /// (Linux only) The nice level of the benchmark processes, from -20 to 19. Overrides the nice level specified in the profile.
+    #[arg(long, allow_hyphen_values = true)]
+    pub nice: Option<i32>,
+    /// (Linux only) Run the benchmark processes in the `SCHED_FIFO` real-time class with this priority, from 1 to 99.
+    #[arg(long)]
+    pub realtime_priority: Option<u32>,
+    /// (Linux only) Record the CPU frequency and the thermal throttling events during each invocation.
+    #[arg(long, default_value = "false")]
+    pub cpu_monitor: bool,
+    /// (Linux only) Collect the core dumps of crashed invocations into the log directory.
+    #[arg(long, default_value = "false")]
+    pub core_dumps: bool,
+    /// Build and run the benchmarks with a minimal environment, instead of inheriting the current one.
+    #[arg(long, default_value = "false")]
+    pub clean_env: bool,
+    /// Only run the benchmarks matching these names or glob patterns (e.g. `foo,bar*`).
+    #[arg(long, value_delimiter = ',')]
+    pub benches: Vec<String>,
+    /// Skip the benchmarks matching these names or glob patterns.
+    #[arg(long, value_delimiter = ',')]
+    pub exclude_benches: Vec<String>,
+    /// Run the benchmarks of all the workspace members, instead of only the root package.
+    #[arg(long, default_value = "false")]
+    pub workspace: bool,
+    /// Only run the benchmarks of these workspace members (e.g. `core,cli`).
+    #[arg(long, value_delimiter = ',')]
+    pub package: Vec<String>,
+    /// Only run these builds of the profile (e.g. `HEAD,my-opt`).
+    #[arg(long, value_delimiter = ',')]
+    pub builds: Vec<String>,
+    /// Run the checks and print the planned schedule with estimated durations, without running any benchmarks.
+    #[arg(long, default_value = "false")]
+    pub dry_run: bool,
+    /// Show the progress in an interactive view, with the duration of each invocation and the ETA of the whole run.
+    #[arg(long, default_value = "false")]
+    pub tui: bool,
 }
 
 impl RunArgs {
@@ -74,8 +203,19 @@ impl RunArgs {
                 std::fs::remove_file(&latest_log_dir)?;
             }
         }
+        // Creating symlinks requires the developer mode or admin privileges on Windows
         #[cfg(target_os = "windows")]
-        std::os::windows::fs::symlink_dir(&log_dir, latest_log_dir)?;
+        if let Err(e) = std::os::windows::fs::symlink_dir(&log_dir, &latest_log_dir) {
+            eprintln!(
+                "{}",
+                format!(
+                    "WARNING: Failed to create {}: {}",
+                    latest_log_dir.display(),
+                    e
+                )
+                .yellow()
+            );
+        }
         #[cfg(not(target_os = "windows"))]
         std::os::unix::fs::symlink(&log_dir, latest_log_dir)?;
         Ok(log_dir)
@@ -98,20 +238,173 @@ impl RunArgs {
         Ok(())
     }
 
+    /// Overwrite the warm-up and measured iterations of the profile
+    fn override_iterations(&self, profile: &mut Profile) {
+        if let Some(measured_iterations) = self.measured_iterations {
+            profile.measured_iterations = measured_iterations;
+        }
+        if let Some(warmup_iterations) = self.warmup_iterations {
+            profile.warmup_iterations = warmup_iterations;
+        }
+        if let Some(iterations) = self.iterations {
+            profile.warmup_iterations = iterations.saturating_sub(profile.measured_iterations);
+        }
+    }
+
+    /// Check the measured iterations, and the invocations of each benchmark
+    fn check_iterations(profile: &Profile, benches: &[String]) -> anyhow::Result<()> {
+        if profile.measured_iterations == 0 {
+            anyhow::bail!("The number of measured iterations must be at least 1");
+        }
+        if profile.sample_interval == Some(0) {
+            anyhow::bail!("The sample interval must be at least 1ms");
+        }
+        for bench in benches {
+            if profile.get_invocations(bench) == 0 {
+                anyhow::bail!(
+                    "The number of invocations of `{}` must be at least 1",
+                    bench
+                );
+            }
+            if profile.warmup_invocations >= profile.get_invocations(bench) {
+                anyhow::bail!(
+                    "The number of warm-up invocations must be less than the invocations of `{}`",
+                    bench
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Add the external benchmarks of the profile to the benchmarks of the crate
+    fn add_external_benches(crate_info: &mut CrateInfo, profile: &Profile) -> anyhow::Result<()> {
+        let mut names = profile.external_benches.keys().collect::<Vec<_>>();
+        names.sort();
+        for name in names {
+            profile.external_benches[name].validate(name)?;
+            if crate_info.benches.contains(name) {
+                anyhow::bail!(
+                    "External benchmark `{}` conflicts with a cargo benchmark of the same name",
+                    name
+                );
+            }
+            crate_info.benches.push(name.clone());
+        }
+        Ok(())
+    }
+
     fn run_benchmarks(
         &self,
-        crate_info: CrateInfo,
+        mut crate_info: CrateInfo,
         mut profile: Profile,
         profile_name: String,
         project: Option<String>,
         old_run: Option<&RunInfo>,
     ) -> anyhow::Result<String> {
+        // A reproduced run already has the external benchmarks in its crate info
+        if old_run.is_none() {
+            Self::add_external_benches(&mut crate_info, &profile)?;
+        }
         // Overwrite invocations and iterations
         if let Some(invocations) = self.invocations {
             profile.invocations = invocations;
         }
-        if let Some(iterations) = self.iterations {
-            profile.iterations = iterations;
+        if let Some(warmup_invocations) = self.warmup_invocations {
+            profile.warmup_invocations = warmup_invocations;
+        }
+        self.override_iterations(&mut profile);
+        Self::check_iterations(&profile, &crate_info.benches)?;
+        if self.no_live_preview {
+            profile.live_preview = false;
+        }
+        if self.pin_performance_cores {
+            profile.pin_performance_cores = true;
+        }
+        if self.measure_all_iterations {
+            profile.measure_all_iterations = true;
+        }
+        if self.cachegrind {
+            profile.cachegrind = true;
+        }
+        if self.binary_size {
+            profile.binary_size = true;
+        }
+        if self.build_cache {
+            profile.build_cache = true;
+        }
+        if self.latency_histogram {
+            profile.latency_histogram = true;
+        }
+        if self.disable_aslr {
+            profile.disable_aslr = true;
+        }
+        if self.cpu_monitor {
+            profile.cpu_monitor = true;
+        }
+        if self.core_dumps {
+            profile.core_dumps = true;
+        }
+        if self.clean_env {
+            profile.clean_env = true;
+        }
+        if let Some(nice) = self.nice {
+            profile.nice = Some(nice);
+        }
+        if let Some(priority) = self.realtime_priority {
+            profile.realtime_priority = Some(priority);
+        }
+        if let Some(timeout) = &self.timeout {
+            profile.timeout = Some(timeout.clone());
+        }
+        if let Some(max_duration) = &self.max_duration {
+            profile.max_duration = Some(max_duration.clone());
+        }
+        profile.get_max_duration()?;
+        if let Some(retries) = self.retries {
+            profile.retries = retries;
+        }
+        if let Some(run_order) = self.run_order {
+            profile.run_order = run_order;
+        }
+        if let Some(parallelism) = self.parallelism {
+            profile.parallelism = parallelism;
+        }
+        if profile.parallelism == 0 {
+            anyhow::bail!("Parallelism must be at least 1");
+        }
+        if let Some(cpus) = &self.cpus {
+            profile.cpus = Some(cpus.clone());
+            profile.isolate_cores = false;
+        }
+        if self.isolate_cores {
+            profile.cpus = None;
+            profile.isolate_cores = true;
+        }
+        if let Some(memory_max) = &self.memory_max {
+            let cgroup = profile.cgroup.get_or_insert_with(Default::default);
+            cgroup.memory_max = Some(memory_max.clone());
+        }
+        if let Some(cpu_quota) = self.cpu_quota {
+            let cgroup = profile.cgroup.get_or_insert_with(Default::default);
+            cgroup.cpu_quota = Some(cpu_quota);
+        }
+        if profile.cpus.is_some() && profile.isolate_cores {
+            anyhow::bail!("`cpus` and `isolate-cores` cannot be used together");
+        }
+        for bench in &crate_info.benches {
+            profile.get_timeout(bench)?;
+        }
+        for (bench, config) in &profile.benches {
+            for (name, service) in &config.services {
+                service.get_ready_timeout().map_err(|e| {
+                    anyhow::anyhow!(
+                        "Invalid ready-timeout of service `{}` of benchmark `{}`: {}",
+                        name,
+                        bench,
+                        e
+                    )
+                })?;
+            }
         }
         // Default build configs
         if profile.builds.is_empty() {
@@ -126,15 +419,51 @@ impl RunArgs {
             };
             profile.builds.insert("HEAD~1".to_owned(), head_1);
         }
+        // Thread-count variants of the builds
+        if let Some(threads) = &self.threads {
+            profile.threads = threads.clone();
+        }
+        if profile.threads.contains(&0) {
+            anyhow::bail!("The thread counts of the `threads` sweep must be at least 1");
+        }
+        if profile.threads.iter().collect::<HashSet<_>>().len() != profile.threads.len() {
+            anyhow::bail!("Duplicate thread counts in the `threads` sweep");
+        }
+        profile.expand_threads();
+        // Filter builds
+        if !self.builds.is_empty() {
+            for build in &self.builds {
+                if !profile.builds.contains_key(build) {
+                    anyhow::bail!(
+                        "Could not find build `{}` in the profile `{}`",
+                        build,
+                        profile_name
+                    );
+                }
+            }
+            profile.builds.retain(|name, _| self.builds.contains(name));
+        }
+        Self::resolve_build_sources(&mut profile, &crate_info)?;
         // If this is a reproduced run, use the old crate info
-        let crate_info = if let Some(old) = old_run {
+        let mut crate_info = if let Some(old) = old_run {
             old.crate_info.clone()
         } else {
             crate_info
         };
+        // Filter benchmarks
+        let bench_filter = if self.benches.is_empty() && self.exclude_benches.is_empty() {
+            None
+        } else {
+            let filter = BenchFilter {
+                include: self.benches.clone(),
+                exclude: self.exclude_benches.clone(),
+            };
+            crate_info.benches = filter.apply(&crate_info.benches)?;
+            Some(filter)
+        };
         // Create a new run
         let (runid, start_time) = self.generate_runid();
-        let run_info = RunInfo::new_v0(
+        let mut run_info = RunInfo::new_v0(
             crate_info,
             profile,
             runid.clone(),
@@ -142,36 +471,101 @@ impl RunArgs {
             project,
             start_time,
         )?;
+        run_info.bench_filter = bench_filter;
+        // A reproduced run uses the pinned image of the old run
+        if let Some(container) = old_run.and_then(|old| old.container.as_ref()) {
+            utils::container::prepare(container)?;
+            run_info.container = Some(container.clone());
+        } else if let Some(image) = &self.container {
+            run_info.container = Some(utils::container::resolve(image)?);
+        }
+        // Download the datasets before the checks, so a reproduced run can compare their checksums
+        if !self.dry_run {
+            run_info.datasets = utils::dataset::resolve_all(&run_info)?;
+        }
         // Run checks
         checks::run_all_checks(self, &run_info, old_run)?;
+        if self.dry_run {
+            runner::BenchRunner::new(&run_info).dry_run()?;
+            return Ok(runid);
+        }
         // Initialize logs dir
         let log_dir = self.prepare_logs_dir(&run_info.crate_info, &runid)?;
         // Run benchmarks
         self.dump_metadata(&log_dir, &run_info)?;
         let mut runner = runner::BenchRunner::new(&run_info);
-        runner.run(&log_dir)?;
+        runner.run(&log_dir, self.tui)?;
+        let skipped = runner.take_skipped();
+        run_info.build_times = runner.take_build_times();
+        run_info.skipped = skipped;
         self.update_metadata_on_finish(&log_dir, run_info)?;
         Ok(runid)
     }
 
+    /// Check the sources of the builds, and fetch the commits of the builds from remote git repositories.
+    /// A reproduced run fetches the recorded commit again, unless it's already in the local repository.
+    fn resolve_build_sources(profile: &mut Profile, crate_info: &CrateInfo) -> anyhow::Result<()> {
+        for (name, build) in profile.builds.iter_mut() {
+            if build.version.is_some() {
+                if build.commit.is_some() || build.git.is_some() {
+                    anyhow::bail!(
+                        "Build `{}` cannot have both a crates.io `version` and a git `commit` or `git` repository",
+                        name
+                    );
+                }
+                if !crate_info.packages.is_empty() {
+                    anyhow::bail!(
+                        "Build `{}` uses a crates.io `version`, which is not supported in workspace mode",
+                        name
+                    );
+                }
+            }
+            let Some(url) = &build.git else {
+                if build.rev.is_some() {
+                    anyhow::bail!("The `rev` of build `{}` requires a `git` repository", name);
+                }
+                continue;
+            };
+            let commit = match &build.commit {
+                Some(commit) if utils::git::has_commit(commit) => commit.clone(),
+                Some(commit) => utils::git::fetch(url, commit)?,
+                None => utils::git::fetch(url, build.rev.as_deref().unwrap_or("HEAD"))?,
+            };
+            build.commit = Some(commit);
+        }
+        Ok(())
+    }
+
     #[allow(clippy::assigning_clones)]
     fn prepare_reproduced_run(
         &self,
         crate_info: &CrateInfo,
     ) -> anyhow::Result<(RunInfo, TempGitCommitGuard)> {
         // Load config and previous machine info
-        let config_path_or_runid = self.config.as_ref().unwrap();
-        let config_path = if config_path_or_runid.ends_with(".toml") {
-            PathBuf::from(config_path_or_runid)
+        let link = self.config.as_deref().and_then(upload::parse_results_link);
+        let remote = self.from_remote.is_some() || link.is_some();
+        let config_path = if let Some(hash) = &self.from_remote {
+            upload::download_config(None, hash, &crate_info.target_dir)?
+        } else if let Some((remote, hash)) = link {
+            // A link to the uploaded results
+            upload::download_config(Some(&remote), &hash, &crate_info.target_dir)?
         } else {
-            crate_info
-                .target_dir
-                .join("harness")
-                .join("logs")
-                .join(config_path_or_runid)
-                .join("config.toml")
+            let config_path_or_runid = self.config.as_ref().unwrap();
+            if config_path_or_runid.ends_with(".toml") {
+                PathBuf::from(config_path_or_runid)
+            } else {
+                crate_info
+                    .target_dir
+                    .join("harness")
+                    .join("logs")
+                    .join(config_path_or_runid)
+                    .join("config.toml")
+            }
         };
         let run_info = RunInfo::load(&config_path)?;
+        if remote && !self.trust_remote_config {
+            Self::check_remote_config(&run_info)?;
+        }
         println!(
             "{}",
             format!("Reproduce Run: {}\n", run_info.runid.clone().italic())
@@ -184,14 +578,83 @@ impl RunArgs {
         }
         println!("{}", format!("Checkout git commit: {}\n", commit).magenta());
         let guard = utils::git::checkout(&run_info.commit)?;
+        if self.strict_toolchain {
+            Self::use_recorded_toolchain(&run_info)?;
+        }
         Ok((run_info, guard))
     }
 
+    /// Refuse to run the shell commands of a downloaded config, or use its settings that can run arbitrary code,
+    /// unless they are trusted with `--trust-remote-config`
+    fn check_remote_config(run_info: &RunInfo) -> anyhow::Result<()> {
+        let commands = run_info.profile.shell_commands();
+        let mut settings = run_info.profile.code_settings();
+        if let Some(container) = &run_info.container {
+            settings.push(("container".to_owned(), container.image.clone()));
+        }
+        if commands.is_empty() && settings.is_empty() {
+            return Ok(());
+        }
+        if !commands.is_empty() {
+            eprintln!(
+                "{}",
+                "The downloaded config runs these shell commands:".yellow()
+            );
+            for (field, command) in &commands {
+                eprintln!("  {} {}", format!("{}:", field).bold(), command);
+            }
+            eprintln!();
+        }
+        if !settings.is_empty() {
+            eprintln!(
+                "{}",
+                "The downloaded config has these settings, which can run arbitrary code:".yellow()
+            );
+            for (field, value) in &settings {
+                eprintln!("  {} {}", format!("{}:", field).bold(), value);
+            }
+            eprintln!();
+        }
+        anyhow::bail!(
+            "Refusing to reproduce a downloaded config that can run arbitrary code. Review it, and rerun with `--trust-remote-config` to run it."
+        )
+    }
+
+    fn use_recorded_toolchain(run_info: &RunInfo) -> anyhow::Result<()> {
+        // The toolchain of a container run is pinned by its image
+        if run_info.container.is_some() {
+            return Ok(());
+        }
+        let Some(toolchain) = run_info.system.toolchain.as_ref() else {
+            anyhow::bail!(
+                "Run `{}` did not record its Rust toolchain: {}",
+                run_info.runid,
+                run_info.system.rustc
+            );
+        };
+        if utils::toolchain::current().is_some_and(|t| utils::toolchain::is_same(&t, toolchain)) {
+            return Ok(());
+        }
+        println!(
+            "{}",
+            format!(
+                "Use Rust toolchain: {} ({})\n",
+                toolchain.version,
+                toolchain
+                    .commit_hash
+                    .as_deref()
+                    .unwrap_or(&toolchain.channel)
+            )
+            .magenta()
+        );
+        utils::toolchain::install_and_use(toolchain)
+    }
+
     pub fn test_run(&self, crate_info: &CrateInfo) -> anyhow::Result<()> {
         if self.invocations.is_some() {
             anyhow::bail!("Cannot specify invocations for a single-shot test run");
         }
-        if self.config.is_some() {
+        if self.config.is_some() || self.from_remote.is_some() {
             anyhow::bail!("Cannot specify config for a single-shot test run");
         }
         let bench = self.bench.as_ref().unwrap();
@@ -199,34 +662,33 @@ impl RunArgs {
         let Some(mut profile) = config.profiles.get(&self.profile).cloned() else {
             anyhow::bail!("Could not find harness profile `{}`", self.profile);
         };
-        if self.build.is_some()
-            && !profile
-                .builds
-                .contains_key(self.build.as_ref().unwrap().as_str())
-        {
-            anyhow::bail!(
-                "Could not find build `{}` in the profile `{}`",
-                self.build.as_ref().unwrap(),
-                self.profile
-            );
+        if let Some(build) = &self.build {
+            if !profile.builds.contains_key(build.as_str()) {
+                anyhow::bail!(
+                    "Could not find build `{}` in the profile `{}`",
+                    build,
+                    self.profile
+                );
+            }
         }
+        let mut crate_info = crate_info.clone();
+        Self::add_external_benches(&mut crate_info, &profile)?;
         if !crate_info.benches.contains(bench) {
             anyhow::bail!("Could not find benchmark `{}` in the crate", bench);
         }
-        if let Some(iterations) = self.iterations {
-            profile.iterations = iterations;
-        }
-        let build = if self.build.is_none() {
+        self.override_iterations(&mut profile);
+        Self::check_iterations(&profile, std::slice::from_ref(bench))?;
+        let build = if let Some(build) = &self.build {
+            build.as_str()
+        } else {
             let test_build_name = "@test";
             profile
                 .builds
                 .insert(test_build_name.to_owned(), BuildConfig::default());
             test_build_name
-        } else {
-            self.build.as_ref().unwrap()
         };
         let (runid, start_time) = self.generate_runid();
-        let run_info = RunInfo::new_v0(
+        let mut run_info = RunInfo::new_v0(
             crate_info.clone(),
             profile,
             runid.clone(),
@@ -234,18 +696,22 @@ impl RunArgs {
             config.project.clone(),
             start_time,
         )?;
+        if let Some(image) = &self.container {
+            run_info.container = Some(utils::container::resolve(image)?);
+        }
         let runner = runner::BenchRunner::new(&run_info);
         runner.test_run(bench, build)?;
         Ok(())
     }
 
     pub fn run(&self) -> anyhow::Result<()> {
-        let crate_info = CrateInfo::load()?;
+        let crate_info = CrateInfo::load(self.workspace, &self.package)?;
 
         if self.bench.is_some() {
             return self.test_run(&crate_info);
         }
-        let (project, profile, profile_name, old_run, _guard) = if self.config.is_some() {
+        let reproduce = self.config.is_some() || self.from_remote.is_some();
+        let (project, profile, profile_name, old_run, _guard) = if reproduce {
             // Reproduce a previous run
             let (old_run, guard) = self.prepare_reproduced_run(&crate_info)?;
             let profile = old_run.profile.clone();
@@ -270,6 +736,9 @@ impl RunArgs {
                 None,
             )
         };
+        if self.dry_run && self.upload {
+            anyhow::bail!("Cannot upload the results of a dry run");
+        }
         let runid =
             self.run_benchmarks(crate_info, profile, profile_name, project, old_run.as_ref())?;
         // Report
@@ -277,6 +746,8 @@ impl RunArgs {
             let report = UploadResultsArgs {
                 run_id: Some(runid),
                 remote: None,
+                project: None,
+                private: false,
             };
             println!();
             report.run()?;
@@ -0,0 +1,100 @@
+use std::{
+    io::{self, IsTerminal, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+};
+
+use colored::Colorize;
+
+/// The live preview of a running invocation in the progress output.
+///
+/// Follows the `.jsonl` stats file that the benchmark appends to after each iteration,
+/// and shows the walltime of the latest iteration in place, e.g. `:42.1ms`.
+pub struct LivePreview {
+    path: PathBuf,
+    /// The length of the complete lines read so far
+    offset: u64,
+    /// The walltime of each finished iteration
+    times: Vec<f64>,
+    /// Whether the progress output is a terminal, so the preview can be updated in place
+    interactive: bool,
+    /// The number of characters printed by the last update, to be erased before the next one
+    printed: usize,
+}
+
+impl LivePreview {
+    pub fn new(json: PathBuf) -> Self {
+        Self {
+            path: json,
+            offset: 0,
+            times: vec![],
+            interactive: io::stdout().is_terminal(),
+            printed: 0,
+        }
+    }
+
+    /// Read the iterations appended since the last poll
+    pub fn poll(&mut self) {
+        let Ok(mut file) = std::fs::File::open(&self.path) else {
+            return;
+        };
+        let mut content = String::new();
+        if file.seek(SeekFrom::Start(self.offset)).is_err()
+            || file.read_to_string(&mut content).is_err()
+        {
+            return;
+        }
+        // Only read complete lines. The last one may still be written.
+        let Some(end) = content.rfind('\n') else {
+            return;
+        };
+        for line in content[..end].lines() {
+            let Ok(record) = serde_json::from_str::<serde_json::Value>(line) else {
+                continue;
+            };
+            if let Some(time) = record.get("time").and_then(|t| t.as_f64()) {
+                self.times.push(time);
+            }
+        }
+        self.offset += end as u64 + 1;
+    }
+
+    /// Poll the stats file, and show the walltime of the latest iteration in place of the previous one
+    pub fn update(&mut self) {
+        if !self.interactive {
+            return;
+        }
+        let count = self.times.len();
+        self.poll();
+        if self.times.len() == count {
+            return;
+        }
+        let text = format!(":{:.1}ms", self.times.last().unwrap());
+        self.erase();
+        print!("{}", text.bright_black());
+        self.printed = text.chars().count();
+        io::stdout().flush().unwrap();
+    }
+
+    /// Erase the preview from the progress output
+    pub fn erase(&mut self) {
+        if self.printed > 0 {
+            let back = "\x08".repeat(self.printed);
+            print!("{}{}{}", back, " ".repeat(self.printed), back);
+            io::stdout().flush().unwrap();
+            self.printed = 0;
+        }
+    }
+
+    /// The mean walltime of the timing iterations, i.e. the last `measured_iterations` iterations
+    pub fn timing_time(&mut self, measured_iterations: usize) -> Option<f64> {
+        self.poll();
+        let timing = &self.times[self.times.len().saturating_sub(measured_iterations.max(1))..];
+        (!timing.is_empty()).then(|| timing.iter().sum::<f64>() / timing.len() as f64)
+    }
+}
+
+impl Drop for LivePreview {
+    fn drop(&mut self) {
+        self.erase();
+    }
+}
@@ -1,23 +1,48 @@
 use std::{
-    fs::OpenOptions,
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    fs::{File, OpenOptions},
     io::{self, Write},
     path::{Path, PathBuf},
-    process::Command,
+    process::{Command, ExitStatus},
+    sync::{mpsc::RecvTimeoutError, Condvar, Mutex},
+    time::{Duration, Instant, SystemTime},
 };
 
 use cargo_metadata::MetadataCommand;
 use colored::Colorize;
 
+use super::{
+    preview::LivePreview,
+    tui::{format_duration, Tui},
+};
 use crate::{
-    configs::{harness::BuildConfig, run_info::RunInfo},
+    configs::{
+        harness::{BuildConfig, Profile, RunOrder},
+        run_info::{BuildTime, RunInfo, SkippedInvocation},
+    },
     print_md,
     utils::{
         self,
-        bench_cmd::{get_bench_build_command, get_bench_run_command},
-        lockfile::replay_lockfile,
+        bench_cmd::{
+            get_bench_build_command, get_bench_run_command, get_cached_bench_run_command,
+            get_cachegrind_out_file, get_external_bench_run_command, get_shell_command,
+        },
+        binary_size, build_cache, cachegrind, checksum, container,
+        cpu_monitor::{self, CpuMonitor},
+        crash::CrashReport,
+        events::{self, Event, FailureKind},
+        invocation_log::{self, InvocationRecord},
+        lockfile::{self, replay_lockfile},
+        results,
+        service::Service,
+        sys::ResourceUsage,
+        worktree::Snapshot,
     },
 };
 
+/// How often the live preview of a running invocation is updated
+const PREVIEW_INTERVAL: Duration = Duration::from_millis(100);
+
 /// Benchmark running info
 #[derive(Debug)]
 pub struct BenchRunner<'a> {
@@ -30,8 +55,46 @@ pub struct BenchRunner<'a> {
     log_dir: Option<PathBuf>,
     scratch_dir: PathBuf,
     cache_dir: PathBuf,
+    /// Serializes the updates of results.csv from parallel invocations
+    results_lock: Mutex<()>,
+    /// (bench, build, invocation) -> the walltime of a finished invocation, to be shown in the progress output
+    preview_times: Mutex<HashMap<(String, String, usize), f64>>,
+    /// The interactive progress view, replacing the progress labels if enabled
+    tui: Option<Mutex<Tui>>,
+    /// The size stats of the bench binaries, by `(build, bench)`, if `binary-size` is enabled
+    binary_sizes: HashMap<(String, String), Vec<(String, String)>>,
+    /// The compile time of each build
+    build_times: BTreeMap<String, BuildTime>,
+    /// The cached bench binaries, by `(build, bench)`, if `build-cache` is enabled
+    binaries: HashMap<(String, String), PathBuf>,
+    /// The directory of the package of each benchmark, where `cargo bench` runs it
+    package_dirs: HashMap<String, PathBuf>,
+    /// The worktree snapshot of each clean commit of the builds
+    snapshots: HashMap<String, Snapshot>,
+    /// The directory where cargo runs, i.e. the current directory, or its counterpart in a snapshot
+    crate_dir: PathBuf,
+    /// The end of the time budget of the run, if `max-duration` is set
+    deadline: Option<Instant>,
+    /// The started invocations, to find the ones skipped after the time budget is used up
+    started: Mutex<HashSet<Job>>,
+}
+
+/// A worker of the parallel scheduler, with its own exclusive set of CPUs and scratch directory
+#[derive(Debug)]
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+struct Worker {
+    cpus: Vec<usize>,
+    /// The NUMA node to bind the memory to, if all the CPUs are on the same node
+    numa_node: Option<usize>,
+    scratch_dir: PathBuf,
 }
 
+/// A single invocation of a benchmark and a build: `(invocation, bench index, build index)`
+type Job = (usize, usize, usize);
+
+/// Pending jobs, and the `(bench index, build index)` pairs currently running
+type JobQueue = Mutex<(VecDeque<Job>, HashSet<(usize, usize)>)>;
+
 impl<'a> BenchRunner<'a> {
     const BUILD_LABELS: &'static str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
     pub const MAX_SUPPORTED_BUILDS: usize = Self::BUILD_LABELS.len();
@@ -46,7 +109,65 @@ impl<'a> BenchRunner<'a> {
             log_dir: None,
             scratch_dir: run.crate_info.target_dir.join("harness").join("scratch"),
             cache_dir: run.crate_info.target_dir.join("harness").join("cache"),
+            results_lock: Mutex::new(()),
+            preview_times: Mutex::new(HashMap::new()),
+            tui: None,
+            binary_sizes: HashMap::new(),
+            build_times: BTreeMap::new(),
+            binaries: HashMap::new(),
+            package_dirs: HashMap::new(),
+            snapshots: HashMap::new(),
+            crate_dir: std::env::current_dir().unwrap_or_default(),
+            deadline: None,
+            started: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// The commit of a build
+    fn commit<'b>(&'b self, build: &'b BuildConfig) -> &'b str {
+        build.commit.as_deref().unwrap_or(self.run.commit.as_str())
+    }
+
+    /// The source of a build: its commit, or `<crate>@<version>` for a crates.io build
+    fn source(&self, build: &BuildConfig) -> String {
+        match &build.version {
+            Some(version) => format!("{}@{}", self.run.crate_info.name, version),
+            None => self.commit(build).to_owned(),
+        }
+    }
+
+    /// The snapshot of a build, if its commit is clean or it's a crates.io build
+    fn snapshot(&self, build: &BuildConfig) -> Option<&Snapshot> {
+        self.snapshots.get(&self.source(build))
+    }
+
+    /// Check out the clean commits of the builds into their worktrees and write their recorded lockfiles there,
+    /// and download the crates.io versions of the builds
+    fn create_snapshots(&mut self) -> anyhow::Result<()> {
+        let target_dir = &self.run.crate_info.target_dir;
+        for build_name in &self.build_names {
+            let build = &self.run.profile.builds[build_name];
+            let source = self.source(build);
+            if source.ends_with("-dirty") || self.snapshots.contains_key(&source) {
+                continue;
+            }
+            let snapshot = match &build.version {
+                Some(version) => {
+                    Snapshot::download(target_dir, &self.run.crate_info.name, version)?
+                }
+                None => {
+                    let snapshot = Snapshot::create(target_dir, &source)?;
+                    lockfile::write_lockfile(
+                        self.run,
+                        &source,
+                        &snapshot.path(&self.run.crate_info.workspace_root),
+                    )?;
+                    snapshot
+                }
+            };
+            self.snapshots.insert(source, snapshot);
         }
+        Ok(())
     }
 
     fn get_log_file(&self, bench: &str, build: &str) -> PathBuf {
@@ -71,11 +192,97 @@ impl<'a> BenchRunner<'a> {
         Ok(())
     }
 
-    fn setup_before_invocation(&self) -> anyhow::Result<()> {
-        if self.scratch_dir.exists() {
-            std::fs::remove_dir_all(&self.scratch_dir)?;
+    /// The CPUs that the benchmarks may run on: the pinned CPUs, or the performance cores with `pin-performance-cores`.
+    /// `None` if they may run on all CPUs.
+    fn allowed_cpus(&self) -> Option<Vec<usize>> {
+        if let Some(cpus) = &self.run.cpu_affinity {
+            return Some(cpus.clone());
         }
-        std::fs::create_dir_all(&self.scratch_dir)?;
+        (self.run.profile.pin_performance_cores && self.run.system.is_hybrid_cpu())
+            .then(|| self.run.system.performance_cpus.clone())
+    }
+
+    /// The CPUs and the NUMA node that a benchmark process is bound to, if any
+    fn cpu_binding(
+        &self,
+        build: &BuildConfig,
+        worker: Option<&Worker>,
+    ) -> Option<(Vec<usize>, Option<usize>)> {
+        let numa_node = build.numa_node.or(self.run.profile.numa_node);
+        if let Some(worker) = worker {
+            Some((worker.cpus.clone(), worker.numa_node))
+        } else if let Some(node) = numa_node {
+            // Run on the CPUs of the node, within the pinned CPUs if any
+            let node_cpus = self
+                .run
+                .system
+                .numa_nodes
+                .iter()
+                .find(|n| n.id == node)
+                .map(|n| n.cpus.clone())
+                .unwrap_or_default();
+            let cpus = match self.allowed_cpus() {
+                Some(cpus) => node_cpus.into_iter().filter(|c| cpus.contains(c)).collect(),
+                None => node_cpus,
+            };
+            Some((cpus, Some(node)))
+        } else {
+            self.allowed_cpus().map(|cpus| (cpus, None))
+        }
+    }
+
+    /// Set up the CPU affinity, NUMA binding, ASLR, and scheduling priority of the benchmark process
+    fn setup_process(&self, _cmd: &mut Command, _build: &BuildConfig, _worker: Option<&Worker>) {
+        // The container engine applies the CPU binding to a container run instead
+        if self.run.container.is_some() {
+            return;
+        }
+        #[cfg(target_os = "linux")]
+        {
+            if let Some((cpus, numa_node)) = self.cpu_binding(_build, _worker) {
+                utils::sys::bind_command(_cmd, &cpus, numa_node);
+            }
+            if self.run.profile.disable_aslr {
+                utils::sys::disable_aslr(_cmd);
+            }
+            let profile = &self.run.profile;
+            if profile.nice.is_some() || profile.realtime_priority.is_some() {
+                utils::sys::set_priority(_cmd, profile.nice, profile.realtime_priority);
+            }
+            if profile.core_dumps {
+                utils::crash::enable_core_dumps(_cmd);
+            }
+        }
+    }
+
+    /// Run the benchmark process inside the container of a container run, with its CPU binding and cgroup limits.
+    ///
+    /// Returns the container name as well, to kill the container if the invocation times out.
+    fn containerize(
+        &self,
+        cmd: Command,
+        build: &BuildConfig,
+        worker: Option<&Worker>,
+        stdin: bool,
+    ) -> (Command, Option<String>) {
+        if self.run.container.is_none() {
+            return (cmd, None);
+        }
+        let binding = self.cpu_binding(build, worker);
+        let limits = container::Limits {
+            cpus: binding.as_ref().map(|(cpus, _)| cpus.as_slice()),
+            numa_node: binding.as_ref().and_then(|(_, node)| *node),
+            cgroup: self.run.profile.cgroup.as_ref(),
+        };
+        let (cmd, name) = container::wrap(&cmd, self.run, limits, stdin);
+        (cmd, Some(name))
+    }
+
+    fn setup_before_invocation(&self, scratch_dir: &Path) -> anyhow::Result<()> {
+        if scratch_dir.exists() {
+            std::fs::remove_dir_all(scratch_dir)?;
+        }
+        std::fs::create_dir_all(scratch_dir)?;
         Ok(())
     }
 
@@ -85,14 +292,29 @@ impl<'a> BenchRunner<'a> {
             .manifest_path("./Cargo.toml")
             .exec()
             .unwrap();
-        let Some(pkg) = meta.root_package() else {
-            anyhow::bail!("No root package found");
-        };
         for name in &self.run.crate_info.benches {
+            if self.run.profile.external_benches.contains_key(name) {
+                self.benches.push(name.clone());
+                continue;
+            }
+            let pkg = match self.run.crate_info.package(name) {
+                Some(package) => meta
+                    .workspace_packages()
+                    .into_iter()
+                    .find(|p| p.name == package),
+                None => meta.root_package(),
+            };
+            let Some(pkg) = pkg else {
+                anyhow::bail!("No package found for {}", name);
+            };
             let target = pkg.targets.iter().find(|t| &t.name == name && t.is_bench());
             if target.is_none() {
                 anyhow::bail!("No bench target found for {}", name);
             }
+            if let Some(dir) = pkg.manifest_path.parent() {
+                self.package_dirs
+                    .insert(name.clone(), dir.as_std_path().to_owned());
+            }
             self.benches.push(name.clone());
         }
         Ok(())
@@ -104,6 +326,7 @@ impl<'a> BenchRunner<'a> {
         &self,
         f: &mut impl Write,
         cmd: &Command,
+        bench: &str,
         build: &BuildConfig,
     ) -> anyhow::Result<()> {
         writeln!(f, "---")?;
@@ -116,46 +339,304 @@ impl<'a> BenchRunner<'a> {
         writeln!(f, "command: {} {}", prog.as_ref(), args.join(" "))?;
         // env variable
         writeln!(f, "env:")?;
-        let mut envs = self.run.profile.env.clone();
-        for (k, v) in &build.env {
-            envs.insert(k.clone(), v.clone());
-        }
-        for (k, v) in &envs {
+        for (k, v) in &Self::invocation_env(&self.run.profile, bench, build) {
             writeln!(f, "  {}: {}", k, v)?;
         }
         // cargo features
         writeln!(f, "features: {}", build.features.join(","))?;
+        if !build.cargo_args.is_empty() {
+            writeln!(f, "cargo-args: {}", build.cargo_args.join(" "))?;
+        }
         // git commit
-        writeln!(
-            f,
-            "commit: {}",
-            git_info2::get()
-                .head
-                .last_commit_hash
-                .unwrap_or_else(|| "unknown".to_owned())
-        )?;
+        writeln!(f, "commit: {}", self.current_commit(build))?;
         writeln!(f, "---")?;
         Ok(())
     }
 
-    fn test_build(&self) -> anyhow::Result<()> {
+    /// The environment variables set for a benchmark by the profile, the benchmark config, and the build
+    fn invocation_env(
+        profile: &Profile,
+        bench: &str,
+        build: &BuildConfig,
+    ) -> BTreeMap<String, String> {
+        let mut envs = profile.env.clone().into_iter().collect::<BTreeMap<_, _>>();
+        if let Some(bench) = profile.benches.get(bench) {
+            for (k, v) in &bench.env {
+                envs.insert(k.clone(), v.clone());
+            }
+        }
+        for (k, v) in &build.env {
+            envs.insert(k.clone(), v.clone());
+        }
+        envs
+    }
+
+    /// The commit that a build is running at, i.e. the commit of its snapshot, or the current commit of the worktree
+    fn current_commit(&self, build: &BuildConfig) -> String {
+        if let Some(snapshot) = self.snapshot(build) {
+            return snapshot.commit.clone();
+        }
+        git_info2::get()
+            .head
+            .last_commit_hash
+            .unwrap_or_else(|| "unknown".to_owned())
+    }
+
+    /// The `metadata` record of an attempt in the structured invocation log
+    fn invocation_log_metadata(
+        &self,
+        cmd: &Command,
+        bench: &str,
+        build_name: &str,
+        invocation: usize,
+        attempt: usize,
+    ) -> InvocationRecord {
+        let build = &self.run.profile.builds[build_name];
+        let command = std::iter::once(cmd.get_program())
+            .chain(cmd.get_args())
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect();
+        InvocationRecord::Metadata {
+            bench: bench.to_owned(),
+            build: build_name.to_owned(),
+            invocation,
+            attempt,
+            command,
+            env: Self::invocation_env(&self.run.profile, bench, build),
+            features: build.features.clone(),
+            cargo_args: build.cargo_args.clone(),
+            commit: self.current_commit(build),
+            timestamp_utc: chrono::Utc::now().timestamp(),
+        }
+    }
+
+    /// Append the iterations and the status of a finished attempt to the structured invocation log
+    #[allow(clippy::too_many_arguments)]
+    fn log_invocation_end(
+        &self,
+        log_dir: &Path,
+        bench: &str,
+        build_name: &str,
+        invocation: usize,
+        csv: &Path,
+        attempt: usize,
+        status: &str,
+        exit_status: Option<&ExitStatus>,
+        walltime: Duration,
+        retried: bool,
+    ) -> anyhow::Result<()> {
+        let mut records =
+            results::load_invocation_json_records(csv, bench, build_name, invocation)?
+                .into_iter()
+                .map(|stats| InvocationRecord::Iteration { stats })
+                .collect::<Vec<_>>();
+        records.push(InvocationRecord::Status {
+            attempt,
+            status: status.to_owned(),
+            exit_code: exit_status.and_then(|s| s.code()),
+            walltime_ms: walltime.as_secs_f64() * 1000.0,
+            retried,
+            timestamp_utc: chrono::Utc::now().timestamp(),
+        });
+        let file = invocation_log::log_file(log_dir, bench, build_name, invocation);
+        invocation_log::append(&file, &records)
+    }
+
+    /// Compile a build in the snapshot of its commit, or check out its commit in the current worktree if it's dirty.
+    /// Returns the bench binaries reported by cargo, and the compile time.
+    fn compile(&self, build_name: &str) -> anyhow::Result<(Vec<(String, PathBuf)>, BuildTime)> {
+        let build = &self.run.profile.builds[build_name];
+        let commit = self.commit(build);
+        let snapshot = self.snapshot(build);
+        let _git_guard = match snapshot {
+            Some(_) => None,
+            None => Some(utils::git::checkout(commit)?),
+        };
+        let _lock_guard = match snapshot {
+            Some(_) => None,
+            None => Some(replay_lockfile(self.run, commit)?),
+        };
+        let mut cmd = get_bench_build_command(&self.run.crate_info, &self.run.profile, build_name);
+        if let Some(snapshot) = snapshot {
+            cmd.current_dir(snapshot.path(&self.crate_dir));
+        }
+        if self.run.container.is_some() {
+            cmd = container::wrap(&cmd, self.run, Default::default(), false).0;
+        }
+        let start = Instant::now();
+        let out = cmd
+            .output()
+            .map_err(|e| anyhow::anyhow!("Failed to build `{}`: {}", build_name, e))?;
+        let walltime = start.elapsed();
+        if !out.status.success() {
+            eprintln!("{}", String::from_utf8_lossy(&out.stderr));
+            anyhow::bail!("Failed to build `{}`", build_name,);
+        }
+        let cargo_stderr = String::from_utf8_lossy(&out.stderr);
+        let build_time = BuildTime {
+            walltime: walltime.as_secs_f64(),
+            cargo: Self::cargo_build_time(&cargo_stderr),
+        };
+        Ok((checksum::bench_executables(&cargo_stderr), build_time))
+    }
+
+    /// Compile all the builds, or load them from the build cache if `build-cache` is enabled
+    fn test_build(&mut self) -> anyhow::Result<()> {
+        self.create_snapshots()?;
+        // The bench binaries of a container run only exist in the container
+        let use_cache = self.run.profile.build_cache && self.run.container.is_none();
+        let builds_dir = build_cache::builds_dir(&self.run.crate_info.target_dir);
+        let cargo_benches = self
+            .benches
+            .iter()
+            .filter(|b| !self.run.profile.external_benches.contains_key(*b))
+            .cloned()
+            .collect::<Vec<_>>();
         for build_name in &self.build_names {
-            let build = &self.run.profile.builds[build_name];
-            let commit = build.commit.as_deref().unwrap_or(self.run.commit.as_str());
-            let _git_guard = utils::git::checkout(commit)?;
-            let _lock_guard = replay_lockfile(self.run, commit)?;
-            let mut cmd = get_bench_build_command(&self.run.profile, build_name);
-            let out = cmd
-                .output()
-                .map_err(|e| anyhow::anyhow!("Failed to build `{}`: {}", build_name, e))?;
-            if !out.status.success() {
-                eprintln!("{}", String::from_utf8_lossy(&out.stderr));
-                anyhow::bail!("Failed to build `{}`", build_name,);
+            let fingerprint = use_cache
+                .then(|| build_cache::fingerprint(self.run, build_name))
+                .transpose()?;
+            let cached = fingerprint
+                .as_ref()
+                .and_then(|f| build_cache::load(&builds_dir, f, &cargo_benches));
+            let executables = match cached {
+                Some(executables) => executables,
+                None => {
+                    let (executables, build_time) = self.compile(build_name)?;
+                    self.build_times.insert(build_name.clone(), build_time);
+                    match &fingerprint {
+                        Some(f) => build_cache::store(&builds_dir, f, &executables)?,
+                        None => executables,
+                    }
+                }
+            };
+            if let Some(log_dir) = &self.log_dir {
+                checksum::record_binaries(log_dir, build_name, &executables)?;
+            }
+            if self.run.profile.binary_size {
+                for (bench, path) in &executables {
+                    let sizes = binary_size::measure(path, &self.scratch_dir);
+                    self.binary_sizes
+                        .insert((build_name.clone(), bench.clone()), sizes);
+                }
+            }
+            if use_cache {
+                for (bench, path) in executables {
+                    self.binaries.insert((build_name.clone(), bench), path);
+                }
             }
         }
         Ok(())
     }
 
+    /// Parse the build time from the last `Finished ... in 1m 02s` (or `in 3.45s`) line of cargo's stderr output, in seconds
+    fn cargo_build_time(cargo_stderr: &str) -> Option<f64> {
+        let line = cargo_stderr
+            .lines()
+            .rev()
+            .find(|l| l.trim_start().starts_with("Finished "))?;
+        let (_, elapsed) = line.rsplit_once(" in ")?;
+        elapsed
+            .split_whitespace()
+            .map(|part| {
+                if let Some(m) = part.strip_suffix('m') {
+                    m.parse::<f64>().ok().map(|m| m * 60.0)
+                } else {
+                    part.strip_suffix('s')?.parse::<f64>().ok()
+                }
+            })
+            .sum()
+    }
+
+    /// Whether the invocation only warms up the machine, and is excluded from the reports
+    fn is_warmup(&self, invocation: usize) -> bool {
+        invocation < self.run.profile.warmup_invocations
+    }
+
+    /// Mark an invocation as started.
+    /// Returns false, without marking it, if the time budget of the run is used up.
+    fn start_job(&self, job: Job) -> bool {
+        if self.deadline.is_some_and(|d| Instant::now() >= d) {
+            return false;
+        }
+        self.started.lock().unwrap().insert(job);
+        true
+    }
+
+    /// The invocations that were not started because the run exceeded its time budget
+    pub fn take_skipped(&mut self) -> Vec<SkippedInvocation> {
+        let started = std::mem::take(&mut *self.started.lock().unwrap());
+        if self.deadline.is_none() {
+            return vec![];
+        }
+        self.get_jobs()
+            .into_iter()
+            .filter(|job| !started.contains(job))
+            .map(|(i, bench, build)| SkippedInvocation {
+                bench: self.benches[bench].clone(),
+                build: self.build_names[build].clone(),
+                invocation: i,
+            })
+            .collect()
+    }
+
+    /// The compile time of each build, measured by the last run
+    pub fn take_build_times(&mut self) -> BTreeMap<String, BuildTime> {
+        std::mem::take(&mut self.build_times)
+    }
+
+    /// Open the file fed to the stdin of a benchmark, if any.
+    /// A relative path is in the crate directory, or in the snapshot of the build if the file is there.
+    fn open_stdin(&self, bench: &str, snapshot: Option<&Snapshot>) -> anyhow::Result<Option<File>> {
+        let Some(stdin) = self
+            .run
+            .profile
+            .benches
+            .get(bench)
+            .and_then(|b| b.stdin.as_ref())
+        else {
+            return Ok(None);
+        };
+        let path = self.crate_dir.join(stdin);
+        let path = snapshot
+            .map(|s| s.path(&path))
+            .filter(|p| p.exists())
+            .unwrap_or(path);
+        let file = File::open(&path).map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to open the stdin of `{}`: {}: {}",
+                bench,
+                path.display(),
+                e
+            )
+        })?;
+        Ok(Some(file))
+    }
+
+    /// Start the companion processes of a benchmark, and wait until they are ready.
+    /// Their outputs are appended to `<bench>.<build>.<service>.service.log` in the log directory.
+    fn start_services(
+        &self,
+        bench: &str,
+        build_name: &str,
+        envs: &[(&str, String)],
+    ) -> anyhow::Result<Vec<Service>> {
+        let Some(config) = self.run.profile.benches.get(bench) else {
+            return Ok(vec![]);
+        };
+        config
+            .services
+            .iter()
+            .map(|(name, service)| {
+                let log_file = self
+                    .log_dir
+                    .as_ref()
+                    .map(|d| d.join(format!("{}.{}.{}.service.log", bench, build_name, name)));
+                Service::start(&self.run.profile, name, service, log_file.as_deref(), envs)
+            })
+            .collect()
+    }
+
     /// Run one benchmark with one build, for N iterations.
     pub fn test_run(&self, bench: &str, build_name: &str) -> anyhow::Result<()> {
         print_md!(
@@ -164,8 +645,25 @@ impl<'a> BenchRunner<'a> {
             build_name
         );
         self.setup_env_before_benchmarking()?;
-        self.setup_before_invocation()?;
-        let mut cmd = get_bench_run_command(self.run, bench, build_name, 0, None);
+        self.setup_before_invocation(&self.scratch_dir)?;
+        let cmd = if self.run.profile.external_benches.contains_key(bench) {
+            get_external_bench_run_command(self.run, bench, build_name, 0)
+        } else {
+            get_bench_run_command(self.run, bench, build_name, 0, None, None)
+        };
+        let build = &self.run.profile.builds[build_name];
+        let stdin = self.open_stdin(bench, self.snapshot(build))?;
+        let (mut cmd, _) = self.containerize(cmd, build, None, stdin.is_some());
+        if let Some(stdin) = stdin {
+            cmd.stdin(stdin);
+        }
+        self.setup_process(&mut cmd, build, None);
+        let envs = [
+            ("HARNESS_BENCH_NAME", bench.to_owned()),
+            ("HARNESS_BENCH_BUILD", build_name.to_owned()),
+            ("HARNESS_BENCH_INVOCATION", "0".to_owned()),
+        ];
+        let _services = self.start_services(bench, build_name, &envs)?;
         if cmd.status()?.success() {
             Ok(())
         } else {
@@ -178,6 +676,12 @@ impl<'a> BenchRunner<'a> {
     }
 
     /// Run one benchmark with one build, for N iterations.
+    /// A failed invocation is re-executed up to `retries` times before it is recorded as a failure.
+    ///
+    /// Returns the number of retries of a successful invocation.
+    ///
+    /// The results are first written to a separate csv file, and merged into results.csv by column name after the invocation is finished.
+    /// So the records of benchmarks built with older versions of harness, which append the values by position, are never misaligned.
     fn run_one(
         &self,
         build_name: &str,
@@ -185,46 +689,524 @@ impl<'a> BenchRunner<'a> {
         bench: &str,
         log_dir: &Path,
         invocation: usize,
+        worker: Option<&Worker>,
+    ) -> anyhow::Result<usize> {
+        let results_csv = log_dir.join("results.csv");
+        let csv = log_dir.join(format!(
+            "{}.{}.{}.results.csv",
+            bench, build_name, invocation
+        ));
+        let result =
+            self.run_one_with_retries(build_name, build, bench, log_dir, invocation, &csv, worker);
+        if self.run.profile.live_preview && result.is_ok() {
+            let mut preview = LivePreview::new(results::json_path(&csv));
+            if let Some(time) = preview.timing_time(self.run.profile.measured_iterations) {
+                self.preview_times
+                    .lock()
+                    .unwrap()
+                    .insert((bench.to_owned(), build_name.to_owned(), invocation), time);
+            }
+        }
+        let _lock = self.results_lock.lock().unwrap();
+        results::merge(&csv, &results_csv)?;
+        if csv.exists() {
+            std::fs::remove_file(&csv)?;
+        }
+        result
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn run_one_with_retries(
+        &self,
+        build_name: &str,
+        build: &BuildConfig,
+        bench: &str,
+        log_dir: &Path,
+        invocation: usize,
+        csv: &Path,
+        worker: Option<&Worker>,
+    ) -> anyhow::Result<usize> {
+        let mut attempt = 0;
+        loop {
+            let will_retry = attempt < self.run.profile.retries;
+            match self.run_one_attempt(
+                build_name, build, bench, log_dir, invocation, csv, worker, attempt, will_retry,
+            ) {
+                Ok(_) => return Ok(attempt),
+                Err(e) if !will_retry => return Err(e),
+                Err(e) => {
+                    let mut outputs = OpenOptions::new()
+                        .append(true)
+                        .create(true)
+                        .open(self.get_log_file(bench, build_name))?;
+                    writeln!(
+                        outputs,
+                        "⚠ RETRY {}/{}: {}\n\n\n",
+                        attempt + 1,
+                        self.run.profile.retries,
+                        e
+                    )?;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Run a single attempt of an invocation.
+    /// If the attempt failed and `will_retry` is true, its partial results are discarded.
+    #[allow(clippy::too_many_arguments)]
+    fn run_one_attempt(
+        &self,
+        build_name: &str,
+        build: &BuildConfig,
+        bench: &str,
+        log_dir: &Path,
+        invocation: usize,
+        csv: &Path,
+        worker: Option<&Worker>,
+        attempt: usize,
+        will_retry: bool,
     ) -> anyhow::Result<()> {
         std::fs::create_dir_all(log_dir)?;
-        self.setup_before_invocation()?;
+        let scratch_dir = worker.map_or(&self.scratch_dir, |w| &w.scratch_dir);
+        self.setup_before_invocation(scratch_dir)?;
         let log_file = self.get_log_file(bench, build_name);
-        // Checkout the given commit if it's specified
-        let commit = build.commit.as_deref().unwrap_or(self.run.commit.as_str());
-        let _git_guard = utils::git::checkout(commit)?;
-        let _lock_guard = replay_lockfile(self.run, commit);
+        let cached_binary = self
+            .binaries
+            .get(&(build_name.to_owned(), bench.to_owned()));
+        let snapshot = self.snapshot(build);
+        // Checkout the given commit if it's specified, unless the build runs in its snapshot or the bench binary is cached
+        let commit = self.commit(build);
+        let in_place = snapshot.is_none() && cached_binary.is_none();
+        let _git_guard = match in_place {
+            true => Some(utils::git::checkout(commit)?),
+            false => None,
+        };
+        let _lock_guard = in_place.then(|| replay_lockfile(self.run, commit));
+        let hook_envs = [
+            ("HARNESS_BENCH_NAME", bench.to_owned()),
+            ("HARNESS_BENCH_BUILD", build_name.to_owned()),
+            ("HARNESS_BENCH_INVOCATION", invocation.to_string()),
+        ];
+        if let Some(hook) = &self.run.profile.pre_invocation {
+            self.run_hook("pre-invocation", hook, &log_file, &hook_envs)?;
+        }
+        let services = self.start_services(bench, build_name, &hook_envs)?;
+        let log_offset = std::fs::metadata(&log_file).map(|m| m.len()).unwrap_or(0);
         let outputs = OpenOptions::new()
             .append(true)
             .create(true)
-            .open(log_file)?;
+            .open(&log_file)?;
         let errors = outputs.try_clone()?;
         let mut outputs2 = outputs.try_clone()?;
-        let mut cmd = get_bench_run_command(self.run, bench, build_name, invocation, Some(log_dir));
+        let external = self.run.profile.external_benches.get(bench);
+        let mut cmd = if external.is_some() {
+            get_external_bench_run_command(self.run, bench, build_name, invocation)
+        } else if let Some(binary) = cached_binary {
+            let package_dir = self
+                .package_dirs
+                .get(bench)
+                .map(|d| snapshot.map_or(d.clone(), |s| s.path(d)));
+            get_cached_bench_run_command(
+                self.run,
+                bench,
+                build_name,
+                binary,
+                package_dir.as_deref(),
+                invocation,
+                Some(log_dir),
+                Some(csv),
+            )
+        } else {
+            get_bench_run_command(
+                self.run,
+                bench,
+                build_name,
+                invocation,
+                Some(log_dir),
+                Some(csv),
+            )
+        };
+        if let Some(snapshot) = snapshot.filter(|_| cmd.get_current_dir().is_none()) {
+            cmd.current_dir(snapshot.path(&self.crate_dir));
+        }
+        if worker.is_some() {
+            cmd.env("HARNESS_BENCH_SCRATCH_DIR", scratch_dir);
+        }
+        let stdin = self.open_stdin(bench, snapshot)?;
+        let (mut cmd, container_name) = self.containerize(cmd, build, worker, stdin.is_some());
         cmd.stdout(outputs).stderr(errors);
-        self.dump_metadata_for_single_invocation(&mut outputs2, &cmd, build)?;
-        let out = cmd.status()?;
+        if let Some(stdin) = stdin {
+            cmd.stdin(stdin);
+        }
+        self.setup_process(&mut cmd, build, worker);
+        self.dump_metadata_for_single_invocation(&mut outputs2, &cmd, bench, build)?;
+        let structured_log = invocation_log::log_file(log_dir, bench, build_name, invocation);
+        invocation_log::append(
+            &structured_log,
+            &[self.invocation_log_metadata(&cmd, bench, build_name, invocation, attempt)],
+        )?;
+        let output_offset = std::fs::metadata(&log_file)?.len();
+        let timeout = self.run.profile.get_timeout(bench)?;
+        let monitor = self.run.profile.cpu_monitor.then(|| {
+            let cpus = match (worker, self.allowed_cpus()) {
+                (Some(worker), _) => worker.cpus.clone(),
+                (None, Some(cpus)) => cpus,
+                (None, None) => cpu_monitor::get_all_cpus(),
+            };
+            CpuMonitor::start(cpus)
+        });
+        let started_at = SystemTime::now();
+        let start = Instant::now();
+        // Show the latest iteration while the invocation runs, unless other invocations run in parallel
+        let mut preview = (self.run.profile.live_preview && worker.is_none() && self.tui.is_none())
+            .then(|| LivePreview::new(results::json_path(csv)));
+        let result = Self::run_with_timeout(&mut cmd, timeout, preview.as_mut());
+        drop(preview);
+        let (out, usage) = match result? {
+            Some((status, usage)) => (Some(status), usage),
+            None => (None, None),
+        };
+        let walltime = start.elapsed();
+        // Killing the container engine client does not stop the container
+        if let (None, Some(name)) = (&out, &container_name) {
+            container::kill(self.run.container.as_ref().unwrap(), name);
+        }
+        drop(services);
+        let cpu_stats = monitor.map(|m| m.stop()).unwrap_or_default();
+        let mut succeeded = out.is_some_and(|o| o.success());
+        // Extract the metrics of an external benchmark from its outputs
+        let mut external_stats = vec![];
+        if let Some(external) = external.filter(|_| succeeded) {
+            let log = std::fs::read(&log_file)?;
+            let log = String::from_utf8_lossy(&log[(output_offset as usize).min(log.len())..]);
+            match external.parse_metrics(&log, walltime) {
+                Ok(stats) => external_stats = stats,
+                Err(e) => {
+                    writeln!(outputs2, "error: {}", e)?;
+                    succeeded = false;
+                }
+            }
+        }
         writeln!(outputs2, "\n\n\n")?;
-        if out.success() {
+        if let Some(hook) = &self.run.profile.post_invocation {
+            if let Err(e) = self.run_hook("post-invocation", hook, &log_file, &hook_envs) {
+                writeln!(outputs2, "⚠ {}\n\n\n", e)?;
+            }
+        }
+        if succeeded {
+            let mut stats = external_stats;
+            stats.extend(cpu_stats);
+            if self.run.profile.cachegrind && external.is_none() {
+                let out_file = get_cachegrind_out_file(log_dir, bench, build_name, invocation);
+                stats.extend(cachegrind::parse_summary(&out_file)?);
+            }
+            // Only record the resource usage of the benchmark itself, not of `cargo bench`, valgrind, or the container engine client
+            let direct =
+                external.is_some() || (cached_binary.is_some() && !self.run.profile.cachegrind);
+            if let Some(usage) = usage.filter(|_| direct && container_name.is_none()) {
+                stats.extend(usage.stats());
+            }
+            if let Some(sizes) = self
+                .binary_sizes
+                .get(&(build_name.to_owned(), bench.to_owned()))
+            {
+                stats.extend(sizes.iter().cloned());
+            }
+            stats.push(("status".to_owned(), "ok".to_owned()));
+            if self.run.profile.retries > 0 {
+                stats.push(("retries".to_owned(), attempt.to_string()));
+            }
+            if let Some(package) = self.run.crate_info.package(bench) {
+                stats.push(("package".to_owned(), package.to_owned()));
+            }
+            if let Some(threads) = build.threads {
+                stats.push(("threads".to_owned(), threads.to_string()));
+            }
+            if self.run.profile.warmup_invocations > 0 {
+                stats.push(("warmup".to_owned(), self.is_warmup(invocation).to_string()));
+            }
+            results::append_stats(csv, bench, build_name, invocation, &stats)?;
+            self.log_invocation_end(
+                log_dir,
+                bench,
+                build_name,
+                invocation,
+                csv,
+                attempt,
+                "ok",
+                out.as_ref(),
+                walltime,
+                false,
+            )?;
             Ok(())
         } else {
+            let kind = self.record_failure(
+                bench,
+                build_name,
+                invocation,
+                log_dir,
+                &log_file,
+                log_offset,
+                out.as_ref(),
+                will_retry.then_some(attempt),
+            )?;
+            let mut reason = kind.name().to_owned();
+            if kind == FailureKind::Crash {
+                let report = self.save_crash_report(
+                    bench,
+                    build_name,
+                    invocation,
+                    log_dir,
+                    &log_file,
+                    log_offset,
+                    out.as_ref(),
+                    started_at,
+                )?;
+                if let Some(signal) = &report.signal {
+                    reason = format!("{}: {}", reason, signal);
+                }
+            }
+            self.log_invocation_end(
+                log_dir,
+                bench,
+                build_name,
+                invocation,
+                csv,
+                attempt,
+                kind.name(),
+                out.as_ref(),
+                walltime,
+                will_retry,
+            )?;
+            if will_retry {
+                results::remove_invocation(csv, bench, build_name, invocation)?;
+            } else {
+                let mut stats = vec![("status".to_owned(), kind.name().to_owned())];
+                if self.run.profile.retries > 0 {
+                    stats.push(("retries".to_owned(), attempt.to_string()));
+                }
+                if let Some(package) = self.run.crate_info.package(bench) {
+                    stats.push(("package".to_owned(), package.to_owned()));
+                }
+                if let Some(threads) = build.threads {
+                    stats.push(("threads".to_owned(), threads.to_string()));
+                }
+                if self.run.profile.warmup_invocations > 0 {
+                    stats.push(("warmup".to_owned(), self.is_warmup(invocation).to_string()));
+                }
+                results::append_stats(csv, bench, build_name, invocation, &stats)?;
+            }
             Err(anyhow::anyhow!(
-                "Failed to run bench `{}` with build {:?}",
+                "Failed to run bench `{}` with build {:?} ({})",
                 bench,
-                build
+                build,
+                reason
             ))
         }
     }
 
+    /// Run the command, and kill it together with all its child processes if it does not finish within `timeout`.
+    /// The live preview, if any, is updated while the command runs.
+    ///
+    /// Returns `None` if the command timed out. Otherwise, returns the exit status,
+    /// and the resource usage of the process and its descendants if available.
+    fn run_with_timeout(
+        cmd: &mut Command,
+        timeout: Option<Duration>,
+        mut preview: Option<&mut LivePreview>,
+    ) -> anyhow::Result<Option<(ExitStatus, Option<ResourceUsage>)>> {
+        if timeout.is_none() && preview.is_none() {
+            return Ok(Some(utils::sys::wait_with_rusage(&mut cmd.spawn()?)?));
+        }
+        // Put the cargo process and the benchmark process in a new process group, so they can be killed together
+        #[cfg(unix)]
+        if timeout.is_some() {
+            std::os::unix::process::CommandExt::process_group(cmd, 0);
+        }
+        let mut child = cmd.spawn()?;
+        let pid = child.id();
+        let _guard = timeout.map(|_| utils::sys::forward_interrupts(pid));
+        // Wait on a separate thread, so that the exit is noticed immediately, e.g. for the walltime of external benchmarks
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = tx.send(utils::sys::wait_with_rusage(&mut child));
+        });
+        let deadline = timeout.map(|t| Instant::now() + t);
+        loop {
+            let mut wait = deadline.map_or(PREVIEW_INTERVAL, |d| {
+                d.saturating_duration_since(Instant::now())
+            });
+            if preview.is_some() {
+                wait = wait.min(PREVIEW_INTERVAL);
+            }
+            match rx.recv_timeout(wait) {
+                Ok(status) => return Ok(Some(status?)),
+                Err(RecvTimeoutError::Timeout) if deadline.is_none_or(|d| Instant::now() < d) => {
+                    if let Some(preview) = preview.as_deref_mut() {
+                        preview.update();
+                    }
+                }
+                Err(_) => {
+                    utils::sys::kill_process_tree(pid)?;
+                    // Reap the killed process
+                    rx.recv()??;
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    /// Save the diagnostics of a crashed invocation next to its log, and collect its core dump if enabled
+    #[allow(clippy::too_many_arguments)]
+    fn save_crash_report(
+        &self,
+        bench: &str,
+        build_name: &str,
+        invocation: usize,
+        log_dir: &Path,
+        log_file: &Path,
+        log_offset: u64,
+        status: Option<&ExitStatus>,
+        _started_at: SystemTime,
+    ) -> anyhow::Result<CrashReport> {
+        let log = std::fs::read(log_file)?;
+        let log = String::from_utf8_lossy(&log[(log_offset as usize).min(log.len())..]);
+        #[allow(unused_mut)]
+        let mut report = CrashReport::new(bench, build_name, invocation, status, &log);
+        #[cfg(target_os = "linux")]
+        if self.run.profile.core_dumps && self.run.container.is_none() {
+            let dest = log_dir.join(format!("{}.{}.{}.core", bench, build_name, invocation));
+            match utils::crash::collect_core_dump(_started_at, &dest) {
+                Ok(core) => report.core_dump = Some(core),
+                Err(e) => report.core_dump_error = Some(e.to_string()),
+            }
+        }
+        let file = report.save(log_dir)?;
+        let mut outputs = OpenOptions::new().append(true).open(log_file)?;
+        writeln!(
+            outputs,
+            "Crash diagnostics saved to {}\n\n\n",
+            file.display()
+        )?;
+        Ok(report)
+    }
+
+    /// Classify a failed invocation and record it in the events log.
+    /// `status` is `None` if the invocation timed out. `retry_attempt` is the failed attempt if the invocation will be retried.
+    #[allow(clippy::too_many_arguments)]
+    fn record_failure(
+        &self,
+        bench: &str,
+        build_name: &str,
+        invocation: usize,
+        log_dir: &Path,
+        log_file: &Path,
+        log_offset: u64,
+        status: Option<&ExitStatus>,
+        retry_attempt: Option<usize>,
+    ) -> anyhow::Result<FailureKind> {
+        // Only look at the outputs of the current invocation
+        let log = std::fs::read(log_file)?;
+        let log = String::from_utf8_lossy(&log[(log_offset as usize).min(log.len())..]);
+        let kind = match status {
+            Some(status) => {
+                #[cfg(unix)]
+                let signal = std::os::unix::process::ExitStatusExt::signal(status);
+                #[cfg(not(unix))]
+                let signal = None;
+                FailureKind::classify(signal, &log)
+            }
+            // No exit status: the invocation was killed after the timeout
+            None => FailureKind::Timeout,
+        };
+        let message = log
+            .lines()
+            .find(|l| l.contains("panicked at") || l.starts_with("error"))
+            .unwrap_or_default()
+            .trim()
+            .to_owned();
+        let timestamp_utc = chrono::Local::now().to_utc().timestamp();
+        let event = match retry_attempt {
+            Some(attempt) => Event::InvocationRetried {
+                bench: bench.to_owned(),
+                build: build_name.to_owned(),
+                invocation,
+                attempt,
+                kind,
+                message,
+                timestamp_utc,
+            },
+            None => Event::InvocationFailed {
+                bench: bench.to_owned(),
+                build: build_name.to_owned(),
+                invocation,
+                kind,
+                message,
+                timestamp_utc,
+            },
+        };
+        events::append(log_dir, &event)?;
+        Ok(kind)
+    }
+
     fn print_before_run(&self) {
         print_md!("# {}\n\n", self.run.runid);
-        print_md!(
-            "* logs: `{}`",
-            self.log_dir.as_ref().unwrap().to_str().unwrap()
-        );
+        if let Some(log_dir) = &self.log_dir {
+            print_md!("* logs: `{}`", log_dir.to_str().unwrap());
+        }
+        print_md!("* run order: `{}`", self.run.profile.run_order.name());
+        if let Some(cpus) = &self.run.cpu_affinity {
+            let cpus = cpus.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+            print_md!("* cpus: `{}`", cpus.join(","));
+        }
+        if self.run.profile.parallelism > 1 {
+            print_md!("* parallelism: `{}`", self.run.profile.parallelism);
+        }
+        if let Some(max_duration) = &self.run.profile.max_duration {
+            print_md!("* max duration: `{}`", max_duration);
+        }
+        if let Some(container) = &self.run.container {
+            print_md!("* container: `{}` ({})", container.image, container.digest);
+        }
         let probe_names = self.run.profile.probes.keys().cloned().collect::<Vec<_>>();
         print_md!("* probes: `{}`", probe_names.join(", "));
-        print_md!("* iterations: `{}`", self.run.profile.iterations);
-        let i = self.run.profile.invocations;
+        if let Some(steady_state) = &self.run.profile.steady_state {
+            print_md!(
+                "* warm-up iterations: `steady state` {} cv < {}, window = {}, max warm-up = {}",
+                "---".bright_black(),
+                steady_state.cv,
+                steady_state.window,
+                steady_state.max_warmup_iterations
+            );
+        } else {
+            print_md!(
+                "* warm-up iterations: `{}`",
+                self.run.profile.warmup_iterations
+            );
+        }
+        // Per-benchmark overrides
+        for bench in &self.benches {
+            let (warmup_iterations, invocations) = (
+                self.run.profile.get_warmup_iterations(bench),
+                self.run.profile.get_invocations(bench),
+            );
+            if warmup_iterations != self.run.profile.warmup_iterations
+                || invocations != self.run.profile.invocations
+            {
+                print_md!(
+                    "  * `{}`: warm-up iterations `{}`, invocations `{}`",
+                    bench,
+                    warmup_iterations,
+                    invocations
+                );
+            }
+        }
+        print_md!(
+            "* measured iterations: `{}`",
+            self.run.profile.measured_iterations
+        );
+        let i = self.max_invocations();
         let w = (i - 1).to_string().len();
         print_md!(
             "* invocations: `{}` {} {}{}{}",
@@ -234,6 +1216,23 @@ impl<'a> BenchRunner<'a> {
             " ~ ".bold().cyan(),
             format!("#{}", i - 1).to_string().bold().on_cyan()
         );
+        if self.run.profile.warmup_invocations > 0 {
+            print_md!(
+                "* warm-up invocations: `{}` (excluded from the reports)",
+                self.run.profile.warmup_invocations
+            );
+        }
+        let packages = self.run.crate_info.all_packages();
+        if !packages.is_empty() {
+            print_md!(
+                "* packages: {}",
+                packages
+                    .iter()
+                    .map(|p| format!("`{}`", p))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
         // dump plain output
         print_md!(
             "* benchmarks: {}",
@@ -264,18 +1263,64 @@ impl<'a> BenchRunner<'a> {
                 .join(", ")
         );
         println!();
-        println!("{}\n", "Running Benchmarks...".blue());
     }
 
     fn print_after_run(&self) {
         println!("\n{}\n", "✔ Benchmarking Finished.".green());
         let csv_path = self.log_dir.as_ref().unwrap().join("results.csv");
         print_md!("Raw benchmark results at:\n");
-        print_md!("* `{}`\n\n", csv_path.display());
+        #[allow(unused_mut)]
+        let mut files = format!("* `{}`\n", csv_path.display());
+        #[cfg(feature = "parquet")]
+        files.push_str(&format!(
+            "* `{}`\n",
+            results::parquet_path(&csv_path).display()
+        ));
+        print_md!("{}\n", files);
+        if let Some(max_duration) = &self.run.profile.max_duration {
+            let skipped = self.get_jobs().len() - self.started.lock().unwrap().len();
+            if skipped > 0 {
+                let msg = format!(
+                    "⚠ The run exceeded its max duration of {}. {} invocations are skipped.\n",
+                    max_duration, skipped
+                );
+                println!("{}", msg.yellow());
+            }
+        }
+        let mut crash_reports = std::fs::read_dir(self.log_dir.as_ref().unwrap())
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|e| e.path())
+                    .filter(|p| p.to_string_lossy().ends_with(".crash.toml"))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        if !crash_reports.is_empty() {
+            crash_reports.sort();
+            print_md!("Crash diagnostics at:\n");
+            for report in crash_reports {
+                print_md!("* `{}`\n", report.display());
+            }
+            println!();
+        }
+    }
+
+    /// The number of invocations of each benchmark, in the order of `self.benches`
+    fn invocations(&self) -> Vec<usize> {
+        self.benches
+            .iter()
+            .map(|b| self.run.profile.get_invocations(b))
+            .collect()
+    }
+
+    /// The largest number of invocations of all benchmarks
+    fn max_invocations(&self) -> usize {
+        self.invocations().into_iter().max().unwrap_or(1)
     }
 
     fn get_inv_label(&self, index: usize, is_row_label: bool) -> String {
-        let max = self.run.profile.invocations - 1;
+        let max = self.max_invocations() - 1;
         let max_w = max.to_string().len();
         let w = index.to_string().len();
         let label = if is_row_label {
@@ -333,19 +1378,56 @@ impl<'a> BenchRunner<'a> {
         io::stdout().flush().unwrap();
     }
 
+    /// Mark an invocation that only succeeded after retrying.
+    fn print_retry_mark(&self, retries: usize) {
+        if retries > 0 {
+            print!("{}", "↻".yellow());
+            io::stdout().flush().unwrap();
+        }
+    }
+
+    /// Get the walltime of the timing iterations of a finished invocation, as recorded by `run_one`.
+    fn get_preview_time(&self, bench: &str, build: &str, invocation: usize) -> Option<f64> {
+        self.preview_times
+            .lock()
+            .unwrap()
+            .remove(&(bench.to_owned(), build.to_owned(), invocation))
+    }
+
+    /// Print the walltime of a finished invocation next to its progress label.
+    fn print_live_preview(&self, bench: &str, build: &str, invocation: usize) {
+        if !self.run.profile.live_preview {
+            return;
+        }
+        if let Some(time) = self.get_preview_time(bench, build, invocation) {
+            print!("{} ", format!(":{:.1}ms", time).bright_black());
+            io::stdout().flush().unwrap();
+        }
+    }
+
     fn run_inv_bench_build(&mut self, log_dir: &Path) -> anyhow::Result<()> {
-        for i in 0..self.run.profile.invocations {
+        for i in 0..self.max_invocations() {
             // Start of an invocation
             self.print_invoc_label(i, true);
             for (bench_index, bench) in self.benches.iter().enumerate() {
+                if i >= self.run.profile.get_invocations(bench) {
+                    continue;
+                }
                 // Start of a benchmark
                 self.print_bench_label(bench_index, false);
                 // Run the benchmark for each build
                 for (build_index, build_name) in self.build_names.iter().enumerate() {
                     // Start of a build
+                    if !self.start_job((i, bench_index, build_index)) {
+                        return Ok(());
+                    }
                     let build = &self.run.profile.builds[build_name];
-                    match self.run_one(build_name, build, bench, log_dir, i) {
-                        Ok(_) => self.print_build_label(build_index),
+                    match self.run_one(build_name, build, bench, log_dir, i, None) {
+                        Ok(retries) => {
+                            self.print_retry_mark(retries);
+                            self.print_build_label(build_index);
+                            self.print_live_preview(bench, build_name, i);
+                        }
                         Err(e) => self.report_error_and_print_cross(bench, build_name, e)?,
                     }
                 }
@@ -359,13 +1441,20 @@ impl<'a> BenchRunner<'a> {
     fn run_bench_inv_build(&mut self, log_dir: &Path) -> anyhow::Result<()> {
         for (bench_index, bench) in self.benches.iter().enumerate() {
             self.print_bench_label(bench_index, true);
-            for i in 0..self.run.profile.invocations {
+            for i in 0..self.run.profile.get_invocations(bench) {
                 self.print_invoc_label(i, false);
                 for (build_index, build_name) in self.build_names.iter().enumerate() {
                     // Start of a build
+                    if !self.start_job((i, bench_index, build_index)) {
+                        return Ok(());
+                    }
                     let build = &self.run.profile.builds[build_name];
-                    match self.run_one(build_name, build, bench, log_dir, i) {
-                        Ok(_) => self.print_build_label(build_index),
+                    match self.run_one(build_name, build, bench, log_dir, i, None) {
+                        Ok(retries) => {
+                            self.print_retry_mark(retries);
+                            self.print_build_label(build_index);
+                            self.print_live_preview(bench, build_name, i);
+                        }
                         Err(e) => self.report_error_and_print_cross(bench, build_name, e)?,
                     }
                 }
@@ -381,10 +1470,17 @@ impl<'a> BenchRunner<'a> {
             self.print_bench_label(bench_index, true);
             for (build_index, build_name) in self.build_names.iter().enumerate() {
                 self.print_build_label(build_index);
-                for i in 0..self.run.profile.invocations {
+                for i in 0..self.run.profile.get_invocations(bench) {
+                    if !self.start_job((i, bench_index, build_index)) {
+                        return Ok(());
+                    }
                     let build = &self.run.profile.builds[build_name];
-                    match self.run_one(build_name, build, bench, log_dir, i) {
-                        Ok(_) => self.print_invoc_label(i, false),
+                    match self.run_one(build_name, build, bench, log_dir, i, None) {
+                        Ok(retries) => {
+                            self.print_retry_mark(retries);
+                            self.print_invoc_label(i, false);
+                            self.print_live_preview(bench, build_name, i);
+                        }
                         Err(e) => self.report_error_and_print_cross(bench, build_name, e)?,
                     }
                 }
@@ -395,13 +1491,218 @@ impl<'a> BenchRunner<'a> {
         Ok(())
     }
 
-    fn report_error_and_print_cross(
+    /// All invocations of all benchmarks and builds, in the configured run order
+    fn get_jobs(&self) -> Vec<Job> {
+        let (invocations, benches, builds) = (
+            self.invocations(),
+            self.benches.len(),
+            self.build_names.len(),
+        );
+        let mut jobs = vec![];
+        match self.run.profile.run_order {
+            RunOrder::InvBenchBuild => {
+                for i in 0..self.max_invocations() {
+                    for b in (0..benches).filter(|b| i < invocations[*b]) {
+                        jobs.extend((0..builds).map(|build| (i, b, build)));
+                    }
+                }
+            }
+            RunOrder::BenchInvBuild => {
+                for (b, n) in invocations.iter().enumerate() {
+                    for i in 0..*n {
+                        jobs.extend((0..builds).map(|build| (i, b, build)));
+                    }
+                }
+            }
+            RunOrder::BenchBuildInv => {
+                for (b, n) in invocations.iter().enumerate() {
+                    for build in 0..builds {
+                        jobs.extend((0..*n).map(|i| (i, b, build)));
+                    }
+                }
+            }
+        }
+        jobs
+    }
+
+    /// Split the available CPUs into `parallelism` disjoint sets of the same size.
+    /// CPUs are grouped by NUMA node, so that each worker spans as few nodes as possible.
+    #[cfg(target_os = "linux")]
+    fn create_workers(&self) -> anyhow::Result<Vec<Worker>> {
+        let n = self.run.profile.parallelism;
+        let nodes = &self.run.system.numa_nodes;
+        let node_of = |cpu: usize| {
+            nodes
+                .iter()
+                .find(|node| node.cpus.contains(&cpu))
+                .map(|node| node.id)
+        };
+        let mut cpus = match self.allowed_cpus() {
+            Some(cpus) => cpus,
+            None => utils::sys::get_cpu_affinity()?,
+        };
+        if cpus.len() < n {
+            anyhow::bail!(
+                "Parallelism {} is larger than the number of available CPUs ({})",
+                n,
+                cpus.len()
+            );
+        }
+        cpus.sort_by_key(|cpu| (node_of(*cpu), *cpu));
+        let cpus_per_worker = cpus.len() / n;
+        let workers = cpus
+            .chunks_exact(cpus_per_worker)
+            .take(n)
+            .enumerate()
+            .map(|(i, cpus)| {
+                let node = node_of(cpus[0]);
+                let same_node = cpus.iter().all(|cpu| node_of(*cpu) == node);
+                Worker {
+                    cpus: cpus.to_vec(),
+                    numa_node: if nodes.len() > 1 && same_node {
+                        node
+                    } else {
+                        None
+                    },
+                    scratch_dir: self.scratch_dir.join(format!("worker-{}", i)),
+                }
+            })
+            .collect();
+        Ok(workers)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn create_workers(&self) -> anyhow::Result<Vec<Worker>> {
+        anyhow::bail!("Parallel invocations are only supported on Linux");
+    }
+
+    /// Run the invocations on all workers concurrently.
+    ///
+    /// Each worker picks the next invocation in the run order, skipping the benchmark and build pairs
+    /// that are already running on other workers.
+    fn run_parallel(&self, log_dir: &Path) -> anyhow::Result<()> {
+        // The builds outside snapshots share the working tree, which cannot be checked out to different commits at the same time
+        let trim = |c: &str| c.trim_end_matches("-dirty").to_owned();
+        for (name, build) in &self.run.profile.builds {
+            if let Some(commit) = build
+                .commit
+                .as_ref()
+                .filter(|_| self.snapshot(build).is_none())
+            {
+                if trim(commit) != trim(&self.run.commit) {
+                    anyhow::bail!(
+                        "Parallel invocations require all builds to use the current commit, but build `{}` uses commit `{}`",
+                        name,
+                        commit
+                    );
+                }
+            }
+        }
+        let workers = self.create_workers()?;
+        for (i, w) in workers.iter().enumerate() {
+            print_md!(
+                "* worker #{}: cpus `{}`{}",
+                i,
+                w.cpus
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect::<Vec<_>>()
+                    .join(","),
+                w.numa_node
+                    .map(|n| format!(", numa node `{}`", n))
+                    .unwrap_or_default()
+            );
+        }
+        println!();
+        // Replay the lockfile once for all workers, unless the current commit runs in its snapshot
+        let _lock_guard = match self.snapshots.contains_key(&self.run.commit) {
+            true => None,
+            false => Some(replay_lockfile(self.run, &self.run.commit)?),
+        };
+        let queue = Mutex::new((
+            VecDeque::from(self.get_jobs()),
+            HashSet::<(usize, usize)>::new(),
+        ));
+        let cond = Condvar::new();
+        std::thread::scope(|s| {
+            let handles = workers
+                .iter()
+                .map(|w| s.spawn(|| self.run_worker(w, log_dir, &queue, &cond)))
+                .collect::<Vec<_>>();
+            for h in handles {
+                h.join().unwrap()?;
+            }
+            Ok::<_, anyhow::Error>(())
+        })?;
+        println!();
+        Ok(())
+    }
+
+    fn run_worker(
         &self,
-        bench: &str,
-        build: &str,
-        e: anyhow::Error,
+        worker: &Worker,
+        log_dir: &Path,
+        queue: &JobQueue,
+        cond: &Condvar,
     ) -> anyhow::Result<()> {
-        // Report error
+        loop {
+            let (i, bench_index, build_index) = {
+                let mut queue = queue.lock().unwrap();
+                loop {
+                    let (jobs, running) = &mut *queue;
+                    if jobs.is_empty() {
+                        return Ok(());
+                    }
+                    if let Some(index) = jobs
+                        .iter()
+                        .position(|(_, b, build)| !running.contains(&(*b, *build)))
+                    {
+                        if !self.start_job(jobs[index]) {
+                            return Ok(());
+                        }
+                        let job = jobs.remove(index).unwrap();
+                        running.insert((job.1, job.2));
+                        break job;
+                    }
+                    queue = cond.wait(queue).unwrap();
+                }
+            };
+            let bench = &self.benches[bench_index];
+            let build_name = &self.build_names[build_index];
+            let build = &self.run.profile.builds[build_name];
+            if let Some(tui) = &self.tui {
+                tui.lock().unwrap().start((i, bench_index, build_index));
+            }
+            let result = self.run_one(build_name, build, bench, log_dir, i, Some(worker));
+            if let Some(tui) = &self.tui {
+                let error = result.as_ref().err().map(|e| e.to_string());
+                if let Err(e) = result {
+                    self.report_error(bench, build_name, &e)?;
+                }
+                tui.lock()
+                    .unwrap()
+                    .finish((i, bench_index, build_index), error);
+            } else {
+                let _stdout = io::stdout().lock();
+                self.print_invoc_label(i, false);
+                self.print_bench_label(bench_index, false);
+                match result {
+                    Ok(retries) => {
+                        self.print_retry_mark(retries);
+                        self.print_build_label(build_index);
+                        self.print_live_preview(bench, build_name, i);
+                    }
+                    Err(e) => self.report_error_and_print_cross(bench, build_name, e)?,
+                }
+                print!(" ");
+                io::stdout().flush()?;
+            }
+            queue.lock().unwrap().1.remove(&(bench_index, build_index));
+            cond.notify_all();
+        }
+    }
+
+    fn report_error(&self, bench: &str, build: &str, e: &anyhow::Error) -> anyhow::Result<()> {
         let log_file = self.get_log_file(bench, build);
         let mut outputs = OpenOptions::new()
             .append(true)
@@ -409,28 +1710,285 @@ impl<'a> BenchRunner<'a> {
             .open(log_file)?;
         writeln!(outputs, "\n\n\n")?;
         writeln!(outputs, "❌ ERROR: {}", e)?;
+        Ok(())
+    }
+
+    fn report_error_and_print_cross(
+        &self,
+        bench: &str,
+        build: &str,
+        e: anyhow::Error,
+    ) -> anyhow::Result<()> {
+        self.report_error(bench, build, &e)?;
         // Print cross
         print!("{}", "✘".red());
         io::stdout().flush()?;
         Ok(())
     }
 
+    /// Estimate the duration of a single invocation of each benchmark and build,
+    /// from the walltime of all the iterations in the most recent previous run that has its results.
+    fn estimate_invocation_times(&self) -> HashMap<(String, String), Duration> {
+        let logs_dir = self.run.crate_info.target_dir.join("harness").join("logs");
+        let mut csvs = std::fs::read_dir(logs_dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter(|e| e.file_name() != "latest")
+                    .map(|e| e.path().join("results.csv"))
+                    .filter_map(|csv| Some((std::fs::metadata(&csv).ok()?.modified().ok()?, csv)))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        csvs.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+        let mut estimates = HashMap::new();
+        for (_, csv) in csvs {
+            let Ok(records) = results::load_iteration_records(&csv) else {
+                continue;
+            };
+            // Walltimes (ms) of all the iterations of each successful invocation
+            let mut invocations = HashMap::<(String, String, usize), Vec<f64>>::new();
+            let mut failed = HashSet::new();
+            for r in records {
+                let key = (r.bench.clone(), r.build.clone(), r.invocation);
+                if !r.status.is_empty() && r.status != "ok" {
+                    failed.insert(key);
+                } else if let Some(time) = r.values.get("time") {
+                    invocations.entry(key).or_default().push(*time);
+                }
+            }
+            let mut found = HashMap::<(String, String), Vec<f64>>::new();
+            for ((bench, build, inv), times) in invocations {
+                if failed.contains(&(bench.clone(), build.clone(), inv))
+                    || estimates.contains_key(&(bench.clone(), build.clone()))
+                {
+                    continue;
+                }
+                let total = if self.run.profile.steady_state.is_some() {
+                    times.iter().sum::<f64>()
+                } else {
+                    // Scale to the current number of iterations
+                    times.iter().sum::<f64>() / times.len() as f64
+                        * (self.run.profile.get_warmup_iterations(&bench)
+                            + self.run.profile.measured_iterations) as f64
+                };
+                found.entry((bench, build)).or_default().push(total);
+            }
+            for (key, totals) in found {
+                let mean = totals.iter().sum::<f64>() / totals.len() as f64;
+                estimates.insert(key, Duration::from_secs_f64(mean / 1000.0));
+            }
+        }
+        estimates
+    }
+
+    /// Print the resolved builds and the full schedule of invocations with their estimated durations, without running anything.
+    pub fn dry_run(&mut self) -> anyhow::Result<()> {
+        self.collect_benches()?;
+        self.print_before_run();
+        print_md!("## Builds\n");
+        for (i, name) in self.build_names.iter().enumerate() {
+            let build = &self.run.profile.builds[name];
+            if let Some(version) = &build.version {
+                print_md!(
+                    "* {} *{}*: crates.io version `{}`",
+                    self.get_build_label(i).green(),
+                    name,
+                    version
+                );
+                continue;
+            }
+            let commit = build.commit.as_deref().unwrap_or(self.run.commit.as_str());
+            let commit = commit.trim_end_matches("-dirty");
+            let lockfile = if self.run.lockfiles.lockfiles.contains_key(commit) {
+                "lockfile recorded"
+            } else {
+                "no lockfile"
+            };
+            print_md!(
+                "* {} *{}*: commit `{}` ({})",
+                self.get_build_label(i).green(),
+                name,
+                commit,
+                lockfile
+            );
+        }
+        println!();
+        print_md!("## Schedule\n");
+        let estimates = self.estimate_invocation_times();
+        let timeouts = self
+            .benches
+            .iter()
+            .map(|b| Ok((b.clone(), self.run.profile.get_timeout(b)?)))
+            .collect::<anyhow::Result<HashMap<_, _>>>()?;
+        let jobs = self.get_jobs();
+        let mut total = Duration::ZERO;
+        let mut unknown = 0;
+        let mut table = "|invocation|bench|build|estimated time|\n|-:|:-|:-|-:|\n".to_owned();
+        for (i, bench_index, build_index) in &jobs {
+            let bench = &self.benches[*bench_index];
+            let build = &self.build_names[*build_index];
+            let estimate = match estimates.get(&(bench.clone(), build.clone())) {
+                Some(d) => {
+                    let d = timeouts[bench].map_or(*d, |t| t.min(*d));
+                    total += d;
+                    format_duration(d)
+                }
+                None => {
+                    unknown += 1;
+                    match timeouts[bench] {
+                        Some(t) => format!("≤ {}", format_duration(t)),
+                        None => "?".to_owned(),
+                    }
+                }
+            };
+            table += &format!("|#{}|{}|{}|{}|\n", i, bench, build, estimate);
+        }
+        print_md!("{}", table);
+        let total = total / self.run.profile.parallelism as u32;
+        print_md!(
+            "* invocations: `{}`\n* estimated total time: `{}`",
+            jobs.len(),
+            format_duration(total)
+        );
+        if let Some(max_duration) = self.run.profile.get_max_duration()? {
+            if total > max_duration {
+                print_md!(
+                    "* the estimated total time exceeds the max duration `{}`, so the last invocations will be skipped",
+                    format_duration(max_duration)
+                );
+            }
+        }
+        if unknown > 0 {
+            print_md!(
+                "* `{}` invocations have no previous results, and are not included in the estimate",
+                unknown
+            );
+        }
+        println!();
+        Ok(())
+    }
+
     /// Run all benchmarks with all builds.
     /// Benchmarks are invoked one by one.
-    pub fn run(&mut self, log_dir: &Path) -> anyhow::Result<()> {
+    pub fn run(&mut self, log_dir: &Path, tui: bool) -> anyhow::Result<()> {
+        self.deadline = self
+            .run
+            .profile
+            .get_max_duration()?
+            .map(|d| Instant::now() + d);
         self.log_dir = Some(log_dir.to_owned());
         self.collect_benches()?;
         self.print_before_run();
+        println!("{}\n", "Running Benchmarks...".blue());
         self.setup_env_before_benchmarking()?;
+        let run = self.run;
+        let hooks_log = log_dir.join("hooks.log");
+        if let Some(hook) = &run.profile.pre_run {
+            self.run_hook("pre-run", hook, &hooks_log, &[])?;
+        }
+        let result = self.run_all(log_dir, tui);
+        // The post-run hook runs even if the run has failed, e.g. to stop the started services
+        let post_run = match &run.profile.post_run {
+            Some(hook) => self.run_hook("post-run", hook, &hooks_log, &[]),
+            None => Ok(()),
+        };
+        result.and(post_run)?;
+        #[cfg(feature = "parquet")]
+        results::write_parquet(&log_dir.join("results.csv"))?;
+        self.print_after_run();
+        Ok(())
+    }
+
+    /// Run a user-defined hook of the profile with the system shell, and append its outputs to `log_file`.
+    fn run_hook(
+        &self,
+        name: &str,
+        hook: &str,
+        log_file: &Path,
+        envs: &[(&str, String)],
+    ) -> anyhow::Result<()> {
+        let mut outputs = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(log_file)?;
+        writeln!(outputs, "[harness] {} hook: {}", name, hook)?;
+        let mut cmd = get_shell_command(&self.run.profile, hook);
+        cmd.envs(envs.iter().map(|(k, v)| (k, v)))
+            .stdin(std::process::Stdio::null())
+            .stdout(outputs.try_clone()?)
+            .stderr(outputs);
+        let status = cmd.status()?;
+        if !status.success() {
+            anyhow::bail!("The {} hook `{}` failed: {}", name, hook, status);
+        }
+        Ok(())
+    }
+
+    /// Test the builds and run all the invocations
+    fn run_all(&mut self, log_dir: &Path, tui: bool) -> anyhow::Result<()> {
         self.test_build()?;
-        if cfg!(feature = "run_order_bench_inv_build") {
-            self.run_bench_inv_build(log_dir)?;
-        } else if cfg!(feature = "run_order_bench_build_inv") {
-            self.run_bench_build_inv(log_dir)?;
+        if tui {
+            self.tui = Some(Mutex::new(Tui::new(
+                self.benches.clone(),
+                self.build_names.clone(),
+                self.invocations(),
+                self.run.profile.parallelism,
+            )));
+            self.run_with_tui(log_dir)?;
+        } else if self.run.profile.parallelism > 1 {
+            self.run_parallel(log_dir)?;
         } else {
-            self.run_inv_bench_build(log_dir)?;
+            match self.run.profile.run_order {
+                RunOrder::InvBenchBuild => self.run_inv_bench_build(log_dir)?,
+                RunOrder::BenchInvBuild => self.run_bench_inv_build(log_dir)?,
+                RunOrder::BenchBuildInv => self.run_bench_build_inv(log_dir)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Run all the invocations in the run order, and redraw the TUI every second to update the elapsed times
+    fn run_with_tui(&self, log_dir: &Path) -> anyhow::Result<()> {
+        let tui = self.tui.as_ref().unwrap();
+        let (stop, ticks) = std::sync::mpsc::channel::<()>();
+        std::thread::scope(|s| {
+            s.spawn(move || {
+                while let Err(RecvTimeoutError::Timeout) =
+                    ticks.recv_timeout(Duration::from_secs(1))
+                {
+                    let _ = tui.lock().unwrap().draw();
+                }
+            });
+            let result = if self.run.profile.parallelism > 1 {
+                self.run_parallel(log_dir)
+            } else {
+                self.run_sequential_with_tui(log_dir)
+            };
+            drop(stop);
+            result
+        })
+    }
+
+    fn run_sequential_with_tui(&self, log_dir: &Path) -> anyhow::Result<()> {
+        let tui = self.tui.as_ref().unwrap();
+        for (i, bench_index, build_index) in self.get_jobs() {
+            if !self.start_job((i, bench_index, build_index)) {
+                break;
+            }
+            let bench = &self.benches[bench_index];
+            let build_name = &self.build_names[build_index];
+            let build = &self.run.profile.builds[build_name];
+            tui.lock().unwrap().start((i, bench_index, build_index));
+            let result = self.run_one(build_name, build, bench, log_dir, i, None);
+            let error = result.as_ref().err().map(|e| e.to_string());
+            if let Err(e) = result {
+                self.report_error(bench, build_name, &e)?;
+            }
+            tui.lock()
+                .unwrap()
+                .finish((i, bench_index, build_index), error);
         }
-        self.print_after_run();
         Ok(())
     }
 }
@@ -0,0 +1,243 @@
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use colored::Colorize;
+use termimad::crossterm::{cursor, terminal, QueueableCommand};
+
+/// A single invocation: `(invocation, bench index, build index)`
+type Job = (usize, usize, usize);
+
+/// The state of a single invocation
+#[derive(Debug)]
+enum Cell {
+    Running(Instant),
+    Done(Duration),
+    Failed(Duration),
+}
+
+/// The interactive progress view of `cargo harness run --tui`.
+///
+/// Shows a live matrix of all (bench, build) pairs and invocations with the duration of each finished cell,
+/// the currently running invocations, and the ETA of the whole run. The view is redrawn in place on every update.
+#[derive(Debug)]
+pub struct Tui {
+    benches: Vec<String>,
+    builds: Vec<String>,
+    /// The number of invocations of each benchmark
+    invocations: Vec<usize>,
+    parallelism: usize,
+    cells: HashMap<Job, Cell>,
+    start: Instant,
+    last_error: Option<String>,
+    /// Number of lines drawn last time, to be cleared before the next redraw
+    lines: u16,
+}
+
+/// Format a duration as e.g. `12.3s`, `4m05s`, or `1h02m03s`
+pub fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    if secs >= 3600 {
+        format!("{}h{:02}m{:02}s", secs / 3600, secs % 3600 / 60, secs % 60)
+    } else if secs >= 60 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{:.1}s", d.as_secs_f64())
+    }
+}
+
+/// The number of characters of a string, excluding the ANSI color escape sequences
+fn visible_len(s: &str) -> usize {
+    let mut len = 0;
+    let mut in_escape = false;
+    for c in s.chars() {
+        match c {
+            '\x1b' => in_escape = true,
+            'm' if in_escape => in_escape = false,
+            _ if !in_escape => len += 1,
+            _ => {}
+        }
+    }
+    len
+}
+
+impl Tui {
+    const CELL_WIDTH: usize = 9;
+
+    pub fn new(
+        benches: Vec<String>,
+        builds: Vec<String>,
+        invocations: Vec<usize>,
+        parallelism: usize,
+    ) -> Self {
+        Self {
+            benches,
+            builds,
+            invocations,
+            parallelism,
+            cells: HashMap::new(),
+            start: Instant::now(),
+            last_error: None,
+            lines: 0,
+        }
+    }
+
+    pub fn start(&mut self, job: Job) {
+        self.cells.insert(job, Cell::Running(Instant::now()));
+        let _ = self.draw();
+    }
+
+    pub fn finish(&mut self, job: Job, error: Option<String>) {
+        let elapsed = match self.cells.get(&job) {
+            Some(Cell::Running(t)) => t.elapsed(),
+            _ => Duration::ZERO,
+        };
+        let cell = if let Some(e) = error {
+            let (i, bench, build) = job;
+            self.last_error = Some(format!(
+                "#{} {}/{}: {}",
+                i, self.benches[bench], self.builds[build], e
+            ));
+            Cell::Failed(elapsed)
+        } else {
+            Cell::Done(elapsed)
+        };
+        self.cells.insert(job, cell);
+        let _ = self.draw();
+    }
+
+    /// Estimate the remaining time, from the mean duration of the finished cells of each (bench, build) pair,
+    /// or of all the finished cells if a pair has none.
+    fn eta(&self) -> Option<Duration> {
+        let mut finished = HashMap::<(usize, usize), Vec<Duration>>::new();
+        for ((_, bench, build), cell) in &self.cells {
+            if let Cell::Done(d) | Cell::Failed(d) = cell {
+                finished.entry((*bench, *build)).or_default().push(*d);
+            }
+        }
+        let all = finished.values().flatten().copied().collect::<Vec<_>>();
+        if all.is_empty() {
+            return None;
+        }
+        let mean = |ds: &[Duration]| ds.iter().sum::<Duration>() / ds.len() as u32;
+        let global_mean = mean(&all);
+        let mut remaining = Duration::ZERO;
+        for bench in 0..self.benches.len() {
+            for build in 0..self.builds.len() {
+                let estimate = finished
+                    .get(&(bench, build))
+                    .map_or(global_mean, |ds| mean(ds));
+                for i in 0..self.invocations[bench] {
+                    remaining += match self.cells.get(&(i, bench, build)) {
+                        None => estimate,
+                        Some(Cell::Running(t)) => estimate.saturating_sub(t.elapsed()),
+                        Some(_) => Duration::ZERO,
+                    };
+                }
+            }
+        }
+        Some(remaining / self.parallelism as u32)
+    }
+
+    pub fn draw(&mut self) -> io::Result<()> {
+        let mut out = io::stdout().lock();
+        if self.lines > 0 {
+            out.queue(cursor::MoveUp(self.lines))?;
+            out.queue(cursor::MoveToColumn(0))?;
+            out.queue(terminal::Clear(terminal::ClearType::FromCursorDown))?;
+        }
+        let bench_w = self.benches.iter().map(|b| b.len()).max().unwrap_or(0);
+        let build_w = self.builds.iter().map(|b| b.len()).max().unwrap_or(0);
+        let mut lines = vec![];
+        // Header
+        let mut header = " ".repeat(bench_w + build_w + 3);
+        for i in 0..self.invocations.iter().copied().max().unwrap_or(0) {
+            header += &format!("{:>w$}", format!("#{}", i), w = Self::CELL_WIDTH);
+        }
+        lines.push(header.bold().to_string());
+        // Matrix
+        let mut running = vec![];
+        for (bench_index, bench) in self.benches.iter().enumerate() {
+            for (build_index, build) in self.builds.iter().enumerate() {
+                let mut line = format!(
+                    "{} {}  ",
+                    format!("{:<w$}", bench, w = bench_w).blue(),
+                    format!("{:<w$}", build, w = build_w).green(),
+                );
+                for i in 0..self.invocations[bench_index] {
+                    let w = Self::CELL_WIDTH;
+                    line += &match self.cells.get(&(i, bench_index, build_index)) {
+                        None => format!("{:>w$}", "·").bright_black().to_string(),
+                        Some(Cell::Running(t)) => {
+                            running.push(format!(
+                                "#{} {}/{} ({})",
+                                i,
+                                bench,
+                                build,
+                                format_duration(t.elapsed())
+                            ));
+                            format!("{:>w$}", format!("▶{}", format_duration(t.elapsed())))
+                                .yellow()
+                                .to_string()
+                        }
+                        Some(Cell::Done(d)) => {
+                            format!("{:>w$}", format_duration(*d)).green().to_string()
+                        }
+                        Some(Cell::Failed(d)) => {
+                            format!("{:>w$}", format!("✘{}", format_duration(*d)))
+                                .red()
+                                .to_string()
+                        }
+                    };
+                }
+                lines.push(line);
+            }
+        }
+        // Status
+        lines.push(String::new());
+        if !running.is_empty() {
+            lines.push(format!("{} {}", "Running:".bold(), running.join(", ")));
+        }
+        let total = self.invocations.iter().sum::<usize>() * self.builds.len();
+        let failed = self
+            .cells
+            .values()
+            .filter(|c| matches!(c, Cell::Failed(_)))
+            .count();
+        let finished = failed
+            + self
+                .cells
+                .values()
+                .filter(|c| matches!(c, Cell::Done(_)))
+                .count();
+        let eta = self.eta().map_or("?".to_owned(), format_duration);
+        lines.push(format!(
+            "{} {}/{}{} · elapsed {} · ETA {}",
+            "Progress:".bold(),
+            finished,
+            total,
+            if failed > 0 {
+                format!(" ({} failed)", failed).red().to_string()
+            } else {
+                String::new()
+            },
+            format_duration(self.start.elapsed()),
+            eta
+        ));
+        if let Some(e) = &self.last_error {
+            lines.push(format!("{} {}", "Last error:".red().bold(), e));
+        }
+        // Long lines are wrapped by the terminal, and take more than one row
+        let width = terminal::size().map_or(80, |(w, _)| w.max(1) as usize);
+        let mut rows = 0;
+        for line in &lines {
+            writeln!(out, "{}", line)?;
+            rows += visible_len(line).max(1).div_ceil(width);
+        }
+        out.flush()?;
+        self.lines = rows as u16;
+        Ok(())
+    }
+}
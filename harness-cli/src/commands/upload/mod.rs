@@ -1,23 +1,139 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use clap::Parser;
 use colored::Colorize;
-use reqwest::blocking::Client;
+use reqwest::blocking::{multipart::Part, Client};
 use serde_json::{Map, Value};
+use url::Url;
 
 use crate::{
-    configs::run_info::{CrateInfo, RunInfo},
+    configs::{
+        harness::Profile,
+        run_info::{CrateInfo, RunInfo},
+        user::{UploadConfig, UserConfig},
+    },
     print_md,
 };
 
+/// The environment variable of the upload API token
+const TOKEN_ENV: &str = "HARNESS_UPLOAD_TOKEN";
+
+/// The API token from `$HARNESS_UPLOAD_TOKEN`, or `upload.token` in the user config
+fn get_token(user_config: &UploadConfig) -> Option<String> {
+    std::env::var(TOKEN_ENV)
+        .ok()
+        .filter(|t| !t.is_empty())
+        .or(user_config.token.clone())
+}
+
+/// The URL of the harness server: `remote`, or `upload.remote` in the user config, or https://r.harness.rs
+fn get_remote_url(remote: Option<&str>, user_config: &UploadConfig) -> anyhow::Result<Url> {
+    let remote = remote
+        .or(user_config.remote.as_deref())
+        .unwrap_or("https://r.harness.rs");
+    let remote_url = Url::parse(remote)?;
+    if remote_url.scheme() != "https" && remote_url.scheme() != "http" {
+        anyhow::bail!("Invalid URL: {}", remote_url);
+    }
+    Ok(remote_url)
+}
+
+/// The contents of `config.toml` to upload, with the secret environment variables redacted.
+/// The runs recorded before the redaction was added may still contain them.
+fn redact_config(config_toml: &Path, run_info: &RunInfo) -> anyhow::Result<String> {
+    let mut config = toml::from_str::<toml::Table>(&std::fs::read_to_string(config_toml)?)?;
+    let env = config
+        .get_mut("system")
+        .and_then(|s| s.get_mut("env"))
+        .and_then(|e| e.as_table_mut());
+    if let Some(env) = env {
+        for (k, v) in env.iter_mut() {
+            if run_info.profile.redact_env_var(k) {
+                *v = toml::Value::String(Profile::REDACTED_ENV_VALUE.to_owned());
+            }
+        }
+    }
+    Ok(toml::to_string(&config)?)
+}
+
+/// Parse a link to uploaded results, e.g. `https://r.harness.rs/?id=<HASH>`, into the server URL and the upload hash
+pub fn parse_results_link(link: &str) -> Option<(String, String)> {
+    let mut url = Url::parse(link).ok()?;
+    if url.scheme() != "https" && url.scheme() != "http" {
+        return None;
+    }
+    let hash = url
+        .query_pairs()
+        .find(|(k, _)| k == "id")
+        .map(|(_, v)| v.into_owned())?;
+    url.set_query(None);
+    url.set_fragment(None);
+    Some((url.to_string(), hash))
+}
+
+/// Download the `config.toml` of uploaded results to `target/harness/remote/<HASH>/config.toml`.
+/// The server is `remote`, or the default upload server if not specified.
+/// The API token is only sent if the server is the configured upload server, over https.
+pub fn download_config(
+    remote: Option<&str>,
+    hash: &str,
+    target_dir: &Path,
+) -> anyhow::Result<PathBuf> {
+    if hash.is_empty()
+        || !hash
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        anyhow::bail!("Invalid upload hash: {}", hash);
+    }
+    let user_config = UserConfig::load()?.upload;
+    let remote_url = get_remote_url(remote, &user_config)?;
+    // The token is only sent to the configured server, and never in plain text
+    let configured_url = get_remote_url(None, &user_config)?;
+    let token = get_token(&user_config).filter(|_| {
+        remote_url.scheme() == "https" && remote_url.origin() == configured_url.origin()
+    });
+    let mut request = Client::new().get(format!("{remote_url}api/v1/results/{hash}/config.toml"));
+    if let Some(token) = &token {
+        request = request.bearer_auth(token);
+    }
+    let response = request.send()?;
+    let status = response.status();
+    if !status.is_success() {
+        let msg = response
+            .json::<Map<String, Value>>()
+            .ok()
+            .and_then(|res| res.get("error")?.as_str().map(|e| e.to_owned()))
+            .unwrap_or("Unknown error".to_owned());
+        anyhow::bail!(
+            "Failed to download the config of {}: {} ({})",
+            hash,
+            status,
+            msg
+        );
+    }
+    let config = response.text()?;
+    let dir = target_dir.join("harness").join("remote").join(hash);
+    std::fs::create_dir_all(&dir)?;
+    let config_toml = dir.join("config.toml");
+    std::fs::write(&config_toml, config)?;
+    Ok(config_toml)
+}
+
 /// Upload benchmark results to https://r.harness.rs
 #[derive(Parser)]
 pub struct UploadResultsArgs {
     /// The run id to report. Default to the latest run.
     pub run_id: Option<String>,
-    /// Host url of the harness server. Default to https://r.harness.rs
+    /// Host url of the harness server. Default to `upload.remote` in the user config, or https://r.harness.rs
     #[clap(long)]
     pub remote: Option<String>,
+    /// The slug of the project to upload the results to, e.g. `my-team/my-crate`. Default to `upload.project` in the user config
+    #[clap(long)]
+    pub project: Option<String>,
+    /// Only allow the members of the project to view the results. Requires an API token
+    #[clap(long, default_value = "false")]
+    pub private: bool,
 }
 
 impl UploadResultsArgs {
@@ -35,11 +151,18 @@ impl UploadResultsArgs {
     }
 
     pub fn run(&self) -> anyhow::Result<()> {
-        let mut remote_url =
-            url::Url::parse(self.remote.as_deref().unwrap_or("https://r.harness.rs"))?;
-        if remote_url.scheme() != "https" && remote_url.scheme() != "http" {
-            anyhow::bail!("Invalid URL: {}", remote_url);
+        let user_config = UserConfig::load()?.upload;
+        let token = get_token(&user_config);
+        let project = self.project.clone().or(user_config.project.clone());
+        if self.private && token.is_none() {
+            anyhow::bail!(
+                "Private uploads require an API token. Please set `{}` or `upload.token` in {}",
+                TOKEN_ENV,
+                UserConfig::path()
+                    .map_or("the user config".to_owned(), |p| p.display().to_string())
+            );
         }
+        let mut remote_url = get_remote_url(self.remote.as_deref(), &user_config)?;
         let target_dir = CrateInfo::get_target_path()?;
         let log_dir = self.find_log_dir(target_dir)?;
         let results_csv = log_dir.join("results.csv");
@@ -50,19 +173,34 @@ impl UploadResultsArgs {
         if !config_toml.exists() {
             anyhow::bail!("Config file not found: {}", config_toml.display());
         }
-        let commit = RunInfo::load(&config_toml)?.commit;
-        if commit.ends_with("-dirty") {
+        let run_info = RunInfo::load(&config_toml)?;
+        if run_info.commit.ends_with("-dirty") {
             anyhow::bail!("Cannot upload results with a dirty git worktree.");
         }
+        let config = redact_config(&config_toml, &run_info)?;
 
         let client = Client::new();
-        let form = reqwest::blocking::multipart::Form::new()
+        let mut form = reqwest::blocking::multipart::Form::new()
             .file("files", results_csv)?
-            .file("files", config_toml)?;
-        let response = client
+            .part(
+                "files",
+                Part::text(config)
+                    .file_name("config.toml")
+                    .mime_str("text/plain")?,
+            );
+        if let Some(project) = &project {
+            form = form.text("project", project.clone());
+        }
+        if self.private {
+            form = form.text("private", "true");
+        }
+        let mut request = client
             .put(format!("{remote_url}api/v1/upload-results"))
-            .multipart(form)
-            .send()?;
+            .multipart(form);
+        if let Some(token) = &token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send()?;
         let status = response.status();
         let Ok(res) = response.json::<Map<String, Value>>() else {
             anyhow::bail!("Failed to parse response");
@@ -83,6 +221,10 @@ impl UploadResultsArgs {
         }
         print!("{}", "✔ Benchmark results uploaded: ".green());
         print_md!("`{remote_url}`\n");
+        if token.is_some() {
+            // Authenticated uploads are owned by the token's account, and need no claiming
+            return Ok(());
+        }
         print_md!("Please claim the results by visiting the link above. Failure to claim the results within 7 days will result in automatic deletion.\n");
         Ok(())
     }
@@ -0,0 +1,86 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use clap::Parser;
+use colored::Colorize;
+
+use crate::{
+    configs::run_info::{CrateInfo, RunInfo},
+    print_md,
+    utils::results,
+};
+
+/// Show the warm-up curve of each benchmark: the mean of a metric per iteration, across all invocations
+#[derive(Parser)]
+pub struct WarmupArgs {
+    /// The run id to analyze. Default to the latest run.
+    pub run_id: Option<String>,
+    /// The metric to analyze
+    #[arg(long, default_value = "time")]
+    pub metric: String,
+}
+
+impl WarmupArgs {
+    pub fn run(&self) -> anyhow::Result<()> {
+        let logs_dir = CrateInfo::get_target_path()?.join("harness").join("logs");
+        let log_dir = logs_dir.join(self.run_id.as_deref().unwrap_or("latest"));
+        if !log_dir.exists() {
+            anyhow::bail!("Log dir not found: {}", log_dir.display());
+        }
+        let run = RunInfo::load(&log_dir.join("config.toml"))?;
+        if self.metric != "time" && !run.profile.measure_all_iterations {
+            eprintln!(
+                "{}",
+                format!(
+                    "WARNING: Probes were only enabled for the timing iteration. Use `measure-all-iterations = true` to collect `{}` for the warm-up iterations.",
+                    self.metric
+                )
+                .yellow()
+            );
+        }
+        let records = results::load_iteration_records(&log_dir.join("results.csv"))?;
+        // (bench, build, iteration) -> values of all invocations
+        let mut values = BTreeMap::<(&str, &str, usize), Vec<f64>>::new();
+        for r in &records {
+            if let Some(v) = r.values.get(&self.metric) {
+                values
+                    .entry((&r.bench, &r.build, r.iteration))
+                    .or_default()
+                    .push(*v);
+            }
+        }
+        if values.is_empty() {
+            anyhow::bail!("No `{}` results found in run `{}`", self.metric, run.runid);
+        }
+        let benches = values.keys().map(|k| k.0).collect::<BTreeSet<_>>();
+        let builds = values.keys().map(|k| k.1).collect::<BTreeSet<_>>();
+        let iterations = values.keys().map(|k| k.2).max().unwrap() + 1;
+
+        print_md!("# Warm-up Curves\n\n");
+        print_md!("* run: `{}`\n", run.runid);
+        print_md!("* metric: `{}` (mean of all invocations)\n\n", self.metric);
+        for bench in &benches {
+            print_md!("## {}\n\n", bench);
+            let mut table = "|iteration|".to_owned();
+            for build in &builds {
+                table += &format!("{}|", build);
+            }
+            table += "\n|-:|";
+            table += &"-:|".repeat(builds.len());
+            table += "\n";
+            for i in 0..iterations {
+                table += &format!("|{}|", i);
+                for build in &builds {
+                    match values.get(&(*bench, *build, i)) {
+                        Some(v) => {
+                            table += &format!("{:.3}|", v.iter().sum::<f64>() / v.len() as f64)
+                        }
+                        None => table += "-|",
+                    }
+                }
+                table += "\n";
+            }
+            print_md!("{}\n", table);
+        }
+        Ok(())
+    }
+}
@@ -1,8 +1,12 @@
 //! The harness benchmarking configs
 //!
-//! This should be placed in the `[package.metadata.harness]` section of the `Cargo.toml` file.
+//! This should be placed in the `[package.metadata.harness]` section of the `Cargo.toml` file,
+//! or in a standalone `Harness.toml` (or `harness.toml`) file at the workspace root, without the `package.metadata.harness` prefix.
 //!
-//! If the `harness` section is not present, a default config will be created, which contains
+//! If both are present, they are merged: a profile or a per-benchmark config in `Cargo.toml` replaces the one with the same name in `Harness.toml`,
+//! and the `project` in `Cargo.toml` takes precedence.
+//!
+//! If neither is present, a default config will be created, which contains
 //! a default profile, with two builds: `HEAD` pointing to the current commit, and `HEAD~1` pointing to the previous commit.
 //!
 //! # Example:
@@ -14,8 +18,62 @@
 //!
 //! ```toml
 //! [package.metadata.harness.profiles.default]
-//! iterations = 3 # Optional. Default to 5
+//! warmup-iterations = 2 # Optional. Run 2 warm-up iterations at the start of each invocation. Default to 4
+//! measured-iterations = 2 # Optional. Then measure 2 iterations, and report their mean. Default to 1
 //! invocations = 40 # Optional. Default to 10
+//! warmup-invocations = 2 # Optional. Flag the first 2 invocations of each benchmark and build as warm-up, and exclude them from the reports. Default to 0
+//! # Optional. Warm up until the walltime of the last 3 iterations varies by less than 2%, instead of a fixed number of iterations.
+//! steady-state = { cv = 0.02, window = 3, max-warmup-iterations = 50 }
+//! # Optional. Kill the invocations that run for more than 5 minutes. Default to no timeout
+//! timeout = "300s"
+//! # Optional. Stop starting new invocations after 6 hours, and record the remaining ones as skipped. Default to no limit
+//! max-duration = "6h"
+//! # Optional. Retry a failed invocation up to 2 times. Default to 0
+//! retries = 2
+//! # Optional. The order of running invocations, benchmarks and builds. Default to "inv-bench-build"
+//! run-order = "bench-inv-build"
+//! # Optional. (Linux only) Run 4 invocations at the same time, each on its own set of CPUs. Default to 1
+//! parallelism = 4
+//! # Optional. (Linux only) Pin the benchmark processes to these CPUs. Default to no pinning
+//! cpus = [0, 1, 2, 3]
+//! # Optional. (Linux only) Run each invocation in a cgroup with a 4GB memory limit and 2 CPUs. Default to no limits
+//! cgroup = { memory-max = "4G", cpu-quota = 2.0 }
+//! # Optional. (Linux only) Disable ASLR for the benchmark processes. Default to false
+//! disable-aslr = true
+//! # Optional. (Linux only) Bind the benchmark processes to NUMA node 0. Can be overridden per build. Default to no binding
+//! numa-node = 0
+//! # Optional. (Linux only) Run the benchmark processes with a higher priority. Default to no change
+//! nice = -10
+//! # Optional. (Linux only) Collect the core dumps of crashed invocations into the log directory. Default to false
+//! core-dumps = true
+//! # Optional. Run the benchmarks with a minimal environment, plus the variables in the allowlist. Default to false
+//! clean-env = true
+//! env-allowlist = ["JAVA_HOME", "MY_APP_*"]
+//! # Optional. Record these environment variables as `<redacted>`, in addition to `*TOKEN*`, `*KEY*`, `*SECRET*`, `*PASSWORD*`, and `*CREDENTIAL*`.
+//! # Default to only the built-in patterns
+//! redact-env = ["DATABASE_URL", "MY_APP_*"]
+//! # Optional. Shell commands to run before and after the whole run, and around each invocation. Default to none
+//! pre-run = "docker start my-db"
+//! post-run = "docker stop my-db"
+//! pre-invocation = "sync && echo 3 | sudo tee /proc/sys/vm/drop_caches"
+//! post-invocation = "./scripts/collect-telemetry.sh $HARNESS_BENCH_NAME $HARNESS_BENCH_BUILD $HARNESS_BENCH_INVOCATION"
+//! # Optional. Change the severities of the built-in checks: `off`, `warn`, or `fail`. Default to the built-in severities
+//! checks = { dirty-worktree = "warn", logged-in-users = "off", perf-event-paranoid = "fail" }
+//! # Optional. Extra checks as shell commands. A non-zero exit status blocks the run, or only warns with `severity = "warn"`
+//! custom-checks = [{ name = "no-vpn", command = "! pgrep openvpn" }]
+//! # Optional. Run each build with 1, 2, 4, and 8 threads, as the builds `foo.threads=1`, `foo.threads=2`, etc.
+//! # The benchmarks read the thread count with `bencher.param("threads")`. Default to no sweep
+//! threads = [1, 2, 4, 8]
+//! # Optional. In the report, normalize `time` to the build `foo` and `memory` to the build `bar`. Default to the baseline build
+//! norm-to = { time = "foo", memory = "bar" }
+//! # Optional. Record the sizes of the bench binaries as the `binary-size`, `binary-size-stripped`, and `text-size` metrics. Default to false
+//! binary-size = true
+//! # Optional. Cache the bench binaries of each build, and run them without checking out the commits again. Default to false
+//! build-cache = true
+//! # Optional. Only report these metrics. A trailing `*` matches any suffix. Default to all metrics
+//! report-metrics = ["time", "PERF_COUNT_HW_*"]
+//! # Optional. Per-benchmark configurations, on top of the shared `[package.metadata.harness.benches]` below
+//! benches = { foo = { timeout = "10m" } }
 //! # Additional environment variables to set for all builds and benchmarks
 //! # Optional. Default to no additional environment variables
 //! env = { BAR = "BAZ" }
@@ -33,17 +91,58 @@
 //! baz = { env = { "FOO" = "BAR" } }
 //! # Compile this build with a specific git commit.
 //! qux = { commit = "a1b2c3d4e5f6" }
+//! # Compile this build with a branch, tag, or commit of a remote git repository.
+//! upstream = { git = "https://github.com/owner/repo", rev = "v1.2.3" }
+//! # Compile this build with a version of the crate published on crates.io.
+//! released = { version = "1.2.3" }
+//! # Extra arguments to `cargo bench`, e.g. for cross-compilation.
+//! quux = { cargo-args = ["--target", "x86_64-unknown-linux-musl"] }
+//!
+//! # Optional. Generate a build for each combination of the features and environment variable values below,
+//! # in addition to the builds above. The four builds are named `a.THREADS=1`, `a.THREADS=8`, `b.THREADS=1`, and `b.THREADS=8`.
+//! [package.metadata.harness.profiles.default.matrix]
+//! features = [["a"], ["b"]]
+//! env = { THREADS = ["1", "8"] }
+//!
+//! # Optional. Benchmarks that run arbitrary commands, e.g. native or Python baselines, alongside the cargo benchmarks.
+//! # The walltime of the command is recorded, unless a regex extracts `time` (in milliseconds) from its output.
+//! [package.metadata.harness.profiles.default.external-benches]
+//! sort-c = { command = ["./baselines/sort"] }
+//! sort-py = { command = ["python3", "baselines/sort.py"], metrics = { time = "time: ([0-9.]+) ms" } }
+//!
+//! # Per-benchmark overrides, shared by all profiles.
+//! [package.metadata.harness.benches]
+//! # A long-running benchmark with fewer iterations and invocations
+//! slow = { warmup-iterations = 1, invocations = 5, timeout = "30m" }
+//! # A microbenchmark with more iterations, and extra environment variables
+//! micro = { warmup-iterations = 20, env = { "MICRO_SCALE" = "1000" } }
+//! # A client/server benchmark with a server started before each invocation, once it accepts connections on port 8080
+//! client = { services = { server = { command = "target/release/myserver --port 8080", port = 8080 } } }
+//! # A benchmark that reads its workload from the program arguments (`bencher.args()`) and its stdin
+//! parse = { args = ["--format", "json"], stdin = "inputs/large.json" }
+//!
+//! # Optional. Datasets shared by all profiles, downloaded into `target/harness/cache/datasets/<name>` before the run.
+//! # The benchmarks get the dataset directory with `harness::utils::dataset("enwik8")`.
+//! [package.metadata.harness.datasets]
+//! enwik8 = { url = "https://mattmahoney.net/dc/enwik8.zip", sha256 = "547994d9...", extract = true }
 //! ````
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
+use cargo_metadata::MetadataCommand;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use toml::Table;
 
 /// The information we care in a Cargo.toml
 #[derive(Deserialize)]
 pub(crate) struct CargoConfig {
-    /// The package section of the Cargo.toml
-    package: CargoConfigPackage,
+    /// The package section of the Cargo.toml. Not present in a virtual workspace manifest
+    #[serde(default)]
+    package: Option<CargoConfigPackage>,
     /// The bench list of the Cargo.toml
     #[serde(default)]
     bench: Vec<CargoBenchConfig>,
@@ -53,17 +152,18 @@ pub(crate) struct CargoConfig {
 }
 
 impl CargoConfig {
-    /// Load the Cargo.toml file
-    fn load_cargo_toml() -> anyhow::Result<CargoConfig> {
-        if !PathBuf::from("./Cargo.toml").is_file() {
-            anyhow::bail!("Failed to load ./Cargo.toml");
+    /// Load a Cargo.toml file
+    fn load_cargo_toml(manifest: &Path) -> anyhow::Result<CargoConfig> {
+        if !manifest.is_file() {
+            anyhow::bail!("Failed to load {}", manifest.display());
         }
-        let s = std::fs::read_to_string("./Cargo.toml")?;
+        let s = std::fs::read_to_string(manifest)?;
         Ok(toml::from_str::<CargoConfig>(&s)?)
     }
 
-    pub(crate) fn load_benches() -> anyhow::Result<Vec<String>> {
-        Ok(Self::load_cargo_toml()?
+    /// The benches with `harness = false` in a Cargo.toml file
+    pub(crate) fn load_benches(manifest: &Path) -> anyhow::Result<Vec<String>> {
+        Ok(Self::load_cargo_toml(manifest)?
             .bench
             .iter()
             .filter_map(|b| {
@@ -111,34 +211,109 @@ struct CargoConfigPackageMetadata {
 
 /// The harness configuration.
 ///
-/// This should be placed in the `[package.metadata.harness]` section of the `Cargo.toml` file.
+/// This should be placed in the `[package.metadata.harness]` section of the `Cargo.toml` file, or in `Harness.toml` at the workspace root.
 ///
 #[derive(Serialize, Deserialize, Debug)]
 pub struct HarnessConfig {
     /// Custom project name. Default to the crate name.
     pub project: Option<String>,
     /// Evaluation profiles
+    #[serde(default)]
     pub profiles: HashMap<String, Profile>,
+    /// Per-benchmark configurations shared by all profiles. The per-benchmark configurations of a profile take precedence
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub benches: HashMap<String, BenchConfig>,
+    /// Datasets shared by all profiles. The datasets of a profile take precedence
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub datasets: BTreeMap<String, DatasetConfig>,
 }
 
 impl HarnessConfig {
-    /// Load the harness configuration from the `Cargo.toml` file
-    /// If the `harness` section is not present, a default config with a default profile is returned.
+    /// The file names of the standalone config, in the order of lookup
+    const STANDALONE_FILE_NAMES: [&'static str; 2] = ["Harness.toml", "harness.toml"];
+
+    /// Load the standalone `Harness.toml` (or `harness.toml`) at the workspace root, if present
+    fn load_standalone() -> anyhow::Result<Option<HarnessConfig>> {
+        let Ok(meta) = MetadataCommand::new()
+            .manifest_path("./Cargo.toml")
+            .no_deps()
+            .exec()
+        else {
+            anyhow::bail!("Failed to get metadata from ./Cargo.toml");
+        };
+        let root = meta.workspace_root.as_std_path();
+        let Some(path) = Self::STANDALONE_FILE_NAMES
+            .iter()
+            .map(|name| root.join(name))
+            .find(|p| p.is_file())
+        else {
+            return Ok(None);
+        };
+        let s = std::fs::read_to_string(&path)?;
+        let config = toml::from_str::<HarnessConfig>(&s)
+            .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))?;
+        Ok(Some(config))
+    }
+
+    /// Merge `other` into this config. The profiles and per-benchmark configs of `other` replace the ones with the same name.
+    fn merge(mut self, other: HarnessConfig) -> HarnessConfig {
+        self.project = other.project.or(self.project);
+        self.profiles.extend(other.profiles);
+        self.benches.extend(other.benches);
+        self.datasets.extend(other.datasets);
+        self
+    }
+
+    /// Load the harness configuration from the `Cargo.toml` file, and the `Harness.toml` file at the workspace root.
+    /// The `[package.metadata.harness]` section of `Cargo.toml` takes precedence over `Harness.toml`.
+    /// If neither is present, a default config with a default profile is returned.
     pub fn load_from_cargo_toml() -> anyhow::Result<HarnessConfig> {
         if !PathBuf::from("./Cargo.toml").is_file() {
             anyhow::bail!("Failed to load ./Cargo.toml");
         }
         let s = std::fs::read_to_string("./Cargo.toml")?;
-        let mut harness = toml::from_str::<CargoConfig>(&s)?
+        let in_cargo_toml = toml::from_str::<CargoConfig>(&s)?
             .package
-            .metadata
-            .and_then(|m| m.harness)
-            .unwrap_or_default();
+            .and_then(|p| p.metadata)
+            .and_then(|m| m.harness);
+        let mut harness = match (Self::load_standalone()?, in_cargo_toml) {
+            (Some(standalone), Some(in_cargo_toml)) => standalone.merge(in_cargo_toml),
+            (standalone, in_cargo_toml) => in_cargo_toml.or(standalone).unwrap_or_default(),
+        };
         if harness.profiles.is_empty() {
             harness
                 .profiles
                 .insert("default".to_owned(), Default::default());
         }
+        // Merge the shared per-benchmark configurations into each profile, and expand the build matrices
+        for (name, profile) in harness.profiles.iter_mut() {
+            for (bench, config) in &harness.benches {
+                profile
+                    .benches
+                    .entry(bench.clone())
+                    .or_default()
+                    .merge_defaults(config);
+            }
+            for (dataset, config) in &harness.datasets {
+                profile
+                    .datasets
+                    .entry(dataset.clone())
+                    .or_insert_with(|| config.clone());
+            }
+            profile.resolve_legacy_iterations();
+            if let Some(matrix) = profile.matrix.take() {
+                for (build, config) in matrix.expand() {
+                    if profile.builds.contains_key(&build) {
+                        anyhow::bail!(
+                            "Build `{}` of the matrix conflicts with an existing build in profile `{}`",
+                            build,
+                            name
+                        );
+                    }
+                    profile.builds.insert(build, config);
+                }
+            }
+        }
         Ok(harness)
     }
 }
@@ -150,18 +325,28 @@ impl Default for HarnessConfig {
             profiles: [("default".to_owned(), Default::default())]
                 .into_iter()
                 .collect(),
+            benches: HashMap::new(),
+            datasets: BTreeMap::new(),
         }
     }
 }
 
-fn default_iterations() -> usize {
-    5
+fn default_warmup_iterations() -> usize {
+    4
 }
 
 fn default_invocations() -> usize {
     10
 }
 
+fn default_measured_iterations() -> usize {
+    1
+}
+
+fn default_parallelism() -> usize {
+    1
+}
+
 /// The benchmarking profile.
 ///
 /// A harness config can contain multiple profiles, each with a unique name.
@@ -170,7 +355,8 @@ fn default_invocations() -> usize {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Profile {
     /// Enabled probes and their configurations. The configuration must be a TOML table (e.g. `example_probe = { param = "42" }`).
-    #[serde(default)]
+    /// A list of probe names or presets (e.g. `probes = ["harness-memory"]`) is also accepted, with empty configurations.
+    #[serde(default, deserialize_with = "deserialize_probes")]
     pub probes: HashMap<String, Table>,
     /// Environment variables to set to all builds and benchmarks
     #[serde(default)]
@@ -178,12 +364,258 @@ pub struct Profile {
     /// Builds to evaluate
     #[serde(default)]
     pub builds: HashMap<String, BuildConfig>,
-    /// Number of iterations. Default is 5
-    #[serde(default = "default_iterations")]
-    pub iterations: usize,
+    /// Generate builds from the combinations of cargo features and environment variable values.
+    /// The generated builds are added to `builds` when the config is loaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matrix: Option<MatrixConfig>,
+    /// Sweep the number of threads, e.g. `[1, 2, 4, 8]`. Each build is multiplied into one variant per thread count, named `<build>.threads=<N>`.
+    /// The thread count is passed to the benchmarks in `HARNESS_BENCH_PARAM_THREADS` (see `Bencher::param`), and recorded in the `threads` column of `results.csv`.
+    /// The variants are added to `builds` when the run starts. Default is no sweep
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub threads: Vec<usize>,
+    /// Per-benchmark configurations
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub benches: HashMap<String, BenchConfig>,
+    /// Benchmarks that are arbitrary commands instead of cargo bench targets, e.g. native or Python baselines.
+    /// They are run with each build, and recorded in the same results as the cargo benchmarks
+    #[serde(
+        default,
+        rename = "external-benches",
+        alias = "external_benches",
+        skip_serializing_if = "HashMap::is_empty"
+    )]
+    pub external_benches: HashMap<String, ExternalBenchConfig>,
+    /// Number of warm-up iterations at the start of each invocation. Default is 4
+    #[serde(
+        default = "default_warmup_iterations",
+        rename = "warmup-iterations",
+        alias = "warmup_iterations"
+    )]
+    pub warmup_iterations: usize,
+    /// Number of measured iterations after the warm-up iterations.
+    /// Their mean is reported as the result of the invocation. Default is 1
+    #[serde(
+        default = "default_measured_iterations",
+        rename = "measured-iterations",
+        alias = "measured_iterations",
+        alias = "timing-iterations"
+    )]
+    pub measured_iterations: usize,
+    /// Deprecated. The total number of iterations, including the measured iterations.
+    /// Converted to `warmup-iterations` when the config is loaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iterations: Option<usize>,
     /// Number of invocations. Default is 10
     #[serde(default = "default_invocations")]
     pub invocations: usize,
+    /// The first `warmup-invocations` invocations of each benchmark and build are run to warm up the machine, e.g. to populate the file cache.
+    /// They are flagged in the `warmup` column of `results.csv`, and excluded from the reports. Default is 0
+    #[serde(default, rename = "warmup-invocations", alias = "warmup_invocations")]
+    pub warmup_invocations: usize,
+    /// Show the walltime of the latest iteration of the running invocation in the progress output, updated in place on a terminal,
+    /// and the timing result of each finished invocation. Default is `true`
+    #[serde(default = "default_true", rename = "live-preview")]
+    pub live_preview: bool,
+    /// Run the benchmarks under valgrind's cachegrind tool, and record the deterministic instruction and cache-miss counts.
+    /// Note that the counts cover the whole benchmark process, not only the timing iteration. Default is `false`
+    #[serde(default)]
+    pub cachegrind: bool,
+    /// Record the size of each bench binary after building it, in the `binary-size`, `binary-size-stripped`, and `text-size` columns (bytes) of `results.csv`.
+    /// The stripped size requires the system `strip` command. Default is `false`
+    #[serde(default, rename = "binary-size", alias = "binary_size")]
+    pub binary_size: bool,
+    /// Cache the bench binaries of each build in `target/harness/builds/<FINGERPRINT>/`, keyed by the commit, features, cargo args, environment variables (e.g. `RUSTFLAGS`), toolchain, and lockfile.
+    /// The invocations run the cached binaries directly, without checking out the commit of the build, and later runs reuse them instead of compiling again. Default is `false`
+    #[serde(default, rename = "build-cache", alias = "build_cache")]
+    pub build_cache: bool,
+    /// Sample the probes at this interval (in milliseconds, at least 1) during the timing phase, and record the time series of the timing iterations
+    /// of each invocation in `<bench>.<build>.<invocation>.samples.csv`. Default is no sampling
+    #[serde(
+        default,
+        rename = "sample-interval",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub sample_interval: Option<u64>,
+    /// Dump the histogram of the latencies recorded by `Bencher::record_latency` in the timing iterations of each invocation
+    /// to `<bench>.<build>.<invocation>.latency.hgrm`, in HdrHistogram's percentile distribution format. Default is `false`
+    #[serde(default, rename = "latency-histogram")]
+    pub latency_histogram: bool,
+    /// (*Linux only*) On hybrid CPUs (P/E cores, big.LITTLE), pin the benchmarks to the performance cores. Default is `false`
+    #[serde(default, rename = "pin-performance-cores")]
+    pub pin_performance_cores: bool,
+    /// Enable the probes for the warm-up iterations as well, not only the timing iteration.
+    /// The results of all iterations are recorded in `results.csv`, with an `iteration` column. Default is `false`
+    #[serde(default, rename = "measure-all-iterations")]
+    pub measure_all_iterations: bool,
+    /// Keep running warm-up iterations until the walltime reaches a steady state, instead of a fixed number of iterations.
+    /// `warmup-iterations` is ignored when this is enabled. Default is disabled
+    #[serde(
+        default,
+        rename = "steady-state",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub steady_state: Option<SteadyStateConfig>,
+    /// Kill an invocation if it does not finish within this duration (e.g. `"300s"`, `"10m"`), and continue with the remaining invocations.
+    /// Can be overridden per benchmark. Default is no timeout
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+    /// The time budget of the whole run (e.g. `"6h"`). Once it is used up, the running invocations are finished,
+    /// but no new invocations are started. The remaining invocations are recorded as `skipped` in `config.toml`. Default is no limit
+    #[serde(
+        default,
+        rename = "max-duration",
+        alias = "max_duration",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_duration: Option<String>,
+    /// Re-execute a failed invocation up to this many times before recording it as a failure.
+    /// The number of retries of each invocation is recorded in the `retries` column of `results.csv`. Default is 0
+    #[serde(default)]
+    pub retries: usize,
+    /// The order of running the invocations, benchmarks and builds. Default is `inv-bench-build`
+    #[serde(default, rename = "run-order", alias = "run_order")]
+    pub run_order: RunOrder,
+    /// (*Linux only*) Number of invocations to run concurrently. Each invocation is pinned to its own exclusive set of CPUs,
+    /// and bound to a NUMA node if possible. All builds must use the current commit. Default is 1
+    #[serde(default = "default_parallelism")]
+    pub parallelism: usize,
+    /// (*Linux only*) Pin all the benchmark processes to these CPUs. Default is no pinning
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpus: Option<Vec<usize>>,
+    /// (*Linux only*) Pin all the benchmark processes to the CPUs isolated from the scheduler by the kernel (the `isolcpus` boot parameter).
+    /// Default is `false`
+    #[serde(default, rename = "isolate-cores", alias = "isolate_cores")]
+    pub isolate_cores: bool,
+    /// (*Linux only*) Run each invocation in a transient cgroup with memory and CPU limits, via `systemd-run`. Default is no limits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cgroup: Option<CgroupConfig>,
+    /// (*Linux only*) Launch the benchmark processes with address space layout randomization disabled. Default is `false`
+    #[serde(default, rename = "disable-aslr", alias = "disable_aslr")]
+    pub disable_aslr: bool,
+    /// (*Linux only*) Run the benchmark processes on the CPUs of this NUMA node, and allocate their memory from it.
+    /// Can be overridden per build. Default is no binding
+    #[serde(
+        default,
+        rename = "numa-node",
+        alias = "numa_node",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub numa_node: Option<usize>,
+    /// (*Linux only*) The nice level of the benchmark processes, from -20 (highest priority) to 19. Default is no change
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nice: Option<i32>,
+    /// (*Linux only*) Run the benchmark processes in the `SCHED_FIFO` real-time scheduling class with this priority, from 1 to 99.
+    /// Default is the normal scheduling class
+    #[serde(
+        default,
+        rename = "realtime-priority",
+        alias = "realtime_priority",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub realtime_priority: Option<u32>,
+    /// (*Linux only*) Sample the CPU frequency and count the thermal throttling events during each invocation.
+    /// Recorded in the `cpu-freq-min`, `cpu-freq-mean`, `cpu-freq-max` (MHz), and `thermal-throttles` columns of `results.csv`. Default is `false`
+    #[serde(default, rename = "cpu-monitor", alias = "cpu_monitor")]
+    pub cpu_monitor: bool,
+    /// (*Linux only*) Allow the benchmark processes to write core dumps, and move the core dump of a crashed invocation
+    /// to `<bench>.<build>.<invocation>.core` in the log directory. Default is `false`
+    #[serde(default, rename = "core-dumps", alias = "core_dumps")]
+    pub core_dumps: bool,
+    /// Build and run the benchmarks with a minimal environment, instead of inheriting the full environment of the current shell.
+    /// Only the variables required by cargo and rustup, `env-allowlist`, and `env` are set.
+    /// The recorded system environment variables are also limited to these variables. Default is `false`
+    #[serde(default, rename = "clean-env", alias = "clean_env")]
+    pub clean_env: bool,
+    /// Extra environment variables to keep when `clean-env` is enabled. A trailing `*` matches any suffix, e.g. `"MY_APP_*"`
+    #[serde(
+        default,
+        rename = "env-allowlist",
+        alias = "env_allowlist",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub env_allowlist: Vec<String>,
+    /// Extra glob patterns of the environment variables to redact from the recorded system info, e.g. `"DATABASE_URL"`.
+    /// The values of the matching variables are recorded as `<redacted>`, in `config.toml` and in the uploaded results.
+    /// The variables matching `*TOKEN*`, `*KEY*`, `*SECRET*`, `*PASSWORD*`, or `*CREDENTIAL*` are always redacted. Default is none
+    #[serde(
+        default,
+        rename = "redact-env",
+        alias = "redact_env",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub redact_env: Vec<String>,
+    /// A shell command to run before the first invocation, e.g. to start a database server. The run is aborted if it fails.
+    /// Default is none
+    #[serde(
+        default,
+        rename = "pre-run",
+        alias = "pre_run",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub pre_run: Option<String>,
+    /// A shell command to run after all the invocations, even if the run has failed. Default is none
+    #[serde(
+        default,
+        rename = "post-run",
+        alias = "post_run",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub post_run: Option<String>,
+    /// A shell command to run before each invocation, e.g. to drop the page caches. The invocation fails if it fails.
+    /// `HARNESS_BENCH_RUNID`, `HARNESS_BENCH_NAME`, `HARNESS_BENCH_BUILD`, and `HARNESS_BENCH_INVOCATION` are set. Default is none
+    #[serde(
+        default,
+        rename = "pre-invocation",
+        alias = "pre_invocation",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub pre_invocation: Option<String>,
+    /// A shell command to run after each invocation, with the same environment variables as `pre-invocation`.
+    /// A failure is logged, but does not fail the invocation. Default is none
+    #[serde(
+        default,
+        rename = "post-invocation",
+        alias = "post_invocation",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub post_invocation: Option<String>,
+    /// Override the severities of the built-in pre-benchmarking checks by name, e.g. `{ dirty-worktree = "warn", logged-in-users = "off" }`.
+    /// Default is the built-in severity of each check
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub checks: HashMap<String, CheckSeverity>,
+    /// Extra pre-benchmarking checks. Each one is a shell command, and the check fails if the command exits with a non-zero status.
+    /// Default is none
+    #[serde(
+        default,
+        rename = "custom-checks",
+        alias = "custom_checks",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub custom_checks: Vec<CustomCheck>,
+    /// Datasets to download into the cache directory before the run, by name. Default is none
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub datasets: BTreeMap<String, DatasetConfig>,
+    /// The endpoints to push the summary metrics of each run to, with `cargo harness export --format prometheus|influxdb`. Default is none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub push: Option<PushConfig>,
+    /// The build to normalize each metric to in `cargo harness report`, e.g. `{ time = "HEAD~1", memory = "jemalloc" }`.
+    /// The other metrics are normalized to the baseline build. Default is empty
+    #[serde(
+        default,
+        rename = "norm-to",
+        alias = "norm_to",
+        skip_serializing_if = "HashMap::is_empty"
+    )]
+    pub norm_to: HashMap<String, String>,
+    /// The metrics to report in `cargo harness report`, e.g. `["time", "PERF_COUNT_HW_*"]`. A trailing `*` matches any suffix.
+    /// Default is all metrics
+    #[serde(
+        default,
+        rename = "report-metrics",
+        alias = "report_metrics",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub report_metrics: Vec<String>,
 }
 
 impl Default for Profile {
@@ -192,16 +624,662 @@ impl Default for Profile {
             probes: HashMap::new(),
             env: HashMap::new(),
             builds: HashMap::new(),
-            iterations: default_iterations(),
+            matrix: None,
+            threads: Vec::new(),
+            warmup_iterations: default_warmup_iterations(),
+            measured_iterations: default_measured_iterations(),
+            iterations: None,
             invocations: default_invocations(),
+            warmup_invocations: 0,
+            live_preview: true,
+            cachegrind: false,
+            binary_size: false,
+            build_cache: false,
+            sample_interval: None,
+            latency_histogram: false,
+            pin_performance_cores: false,
+            measure_all_iterations: false,
+            steady_state: None,
+            timeout: None,
+            max_duration: None,
+            retries: 0,
+            run_order: RunOrder::default(),
+            parallelism: default_parallelism(),
+            cpus: None,
+            isolate_cores: false,
+            cgroup: None,
+            disable_aslr: false,
+            numa_node: None,
+            nice: None,
+            realtime_priority: None,
+            cpu_monitor: false,
+            core_dumps: false,
+            clean_env: false,
+            env_allowlist: Vec::new(),
+            redact_env: Vec::new(),
+            pre_run: None,
+            post_run: None,
+            pre_invocation: None,
+            post_invocation: None,
+            checks: HashMap::new(),
+            custom_checks: Vec::new(),
+            datasets: BTreeMap::new(),
+            push: None,
+            norm_to: HashMap::new(),
+            report_metrics: Vec::new(),
+            benches: HashMap::new(),
+            external_benches: HashMap::new(),
         }
     }
 }
 
+impl Profile {
+    /// Environment variables that are always kept when `clean-env` is enabled
+    const CLEAN_ENV_DEFAULTS: &'static [&'static str] = &[
+        "PATH",
+        "HOME",
+        "USER",
+        "LANG",
+        "TERM",
+        "TMPDIR",
+        "CARGO_HOME",
+        "CARGO_TARGET_DIR",
+        "RUSTUP_HOME",
+        "RUSTUP_TOOLCHAIN",
+        "HARNESS_*",
+        // Required by Windows processes
+        "SYSTEMROOT",
+        "SYSTEMDRIVE",
+        "TEMP",
+        "TMP",
+        "USERPROFILE",
+        "APPDATA",
+        "LOCALAPPDATA",
+        "PATHEXT",
+    ];
+
+    /// Environment variables that are always redacted from the recorded system info
+    const REDACT_ENV_DEFAULTS: &'static [&'static str] =
+        &["*TOKEN*", "*KEY*", "*SECRET*", "*PASSWORD*", "*CREDENTIAL*"];
+
+    /// The recorded value of a redacted environment variable
+    pub const REDACTED_ENV_VALUE: &'static str = "<redacted>";
+
+    /// Whether the value of the environment variable is redacted from the recorded system info
+    pub fn redact_env_var(&self, name: &str) -> bool {
+        let options = glob::MatchOptions {
+            case_sensitive: false,
+            ..Default::default()
+        };
+        let matches = |pattern: &str| {
+            glob::Pattern::new(pattern).is_ok_and(|p| p.matches_with(name, options))
+        };
+        Self::REDACT_ENV_DEFAULTS.iter().any(|p| matches(p))
+            || self.redact_env.iter().any(|p| matches(p))
+    }
+
+    /// Whether the environment variable is passed to the benchmarks. Always true if `clean-env` is disabled.
+    pub fn keep_env_var(&self, name: &str) -> bool {
+        if !self.clean_env {
+            return true;
+        }
+        let matches = |pattern: &str| match pattern.strip_suffix('*') {
+            Some(prefix) => name
+                .get(..prefix.len())
+                .is_some_and(|p| p.eq_ignore_ascii_case(prefix)),
+            None => name.eq_ignore_ascii_case(pattern),
+        };
+        Self::CLEAN_ENV_DEFAULTS.iter().any(|p| matches(p))
+            || self.env_allowlist.iter().any(|p| matches(p))
+    }
+
+    /// The number of warm-up iterations of a benchmark. The per-benchmark warm-up iterations take precedence over the profile warm-up iterations.
+    pub fn get_warmup_iterations(&self, bench: &str) -> usize {
+        self.benches
+            .get(bench)
+            .and_then(|b| b.warmup_iterations)
+            .unwrap_or(self.warmup_iterations)
+    }
+
+    /// Convert the deprecated total `iterations` of the profile and the per-benchmark configs to warm-up iterations
+    pub(crate) fn resolve_legacy_iterations(&mut self) {
+        let measured = self.measured_iterations;
+        if let Some(iterations) = self.iterations.take() {
+            self.warmup_iterations = iterations.saturating_sub(measured);
+        }
+        for bench in self.benches.values_mut() {
+            if let Some(iterations) = bench.iterations.take() {
+                bench.warmup_iterations = Some(iterations.saturating_sub(measured));
+            }
+        }
+    }
+
+    /// Multiply each build into one variant per thread count of the `threads` sweep
+    pub(crate) fn expand_threads(&mut self) {
+        let threads = std::mem::take(&mut self.threads);
+        if threads.is_empty() {
+            return;
+        }
+        self.builds = std::mem::take(&mut self.builds)
+            .into_iter()
+            .flat_map(|(name, build)| {
+                threads.iter().map(move |n| {
+                    let mut build = build.clone();
+                    build
+                        .env
+                        .insert("HARNESS_BENCH_PARAM_THREADS".to_owned(), n.to_string());
+                    build.threads = Some(*n);
+                    (format!("{}.threads={}", name, n), build)
+                })
+            })
+            .collect();
+    }
+
+    /// The number of invocations of a benchmark. The per-benchmark invocations take precedence over the profile invocations.
+    pub fn get_invocations(&self, bench: &str) -> usize {
+        self.benches
+            .get(bench)
+            .and_then(|b| b.invocations)
+            .unwrap_or(self.invocations)
+    }
+
+    /// The timeout of each invocation of a benchmark. The per-benchmark timeout takes precedence over the profile timeout.
+    pub fn get_timeout(&self, bench: &str) -> anyhow::Result<Option<Duration>> {
+        let timeout = self
+            .benches
+            .get(bench)
+            .and_then(|b| b.timeout.as_ref())
+            .or(self.timeout.as_ref());
+        timeout.map(|t| parse_duration(t)).transpose()
+    }
+
+    /// The time budget of the whole run, if any
+    pub fn get_max_duration(&self) -> anyhow::Result<Option<Duration>> {
+        self.max_duration
+            .as_ref()
+            .map(|d| parse_duration(d))
+            .transpose()
+    }
+
+    /// The shell commands that a run of this profile executes, with the field of each one, e.g. `("pre-run", "make db")`
+    pub fn shell_commands(&self) -> Vec<(String, String)> {
+        let mut commands = vec![];
+        let hooks = [
+            ("pre-run", &self.pre_run),
+            ("post-run", &self.post_run),
+            ("pre-invocation", &self.pre_invocation),
+            ("post-invocation", &self.post_invocation),
+        ];
+        for (field, hook) in hooks {
+            if let Some(hook) = hook {
+                commands.push((field.to_owned(), hook.clone()));
+            }
+        }
+        for check in &self.custom_checks {
+            let field = format!("custom-checks.{}", check.name);
+            commands.push((field, check.command.clone()));
+        }
+        for (name, dataset) in &self.datasets {
+            for step in &dataset.post_extract {
+                commands.push((format!("datasets.{}.post-extract", name), step.clone()));
+            }
+        }
+        let mut benches = self.benches.iter().collect::<Vec<_>>();
+        benches.sort_by_key(|(name, _)| *name);
+        for (bench, config) in benches {
+            for (name, service) in &config.services {
+                let field = format!("benches.{}.services.{}", bench, name);
+                commands.push((field.clone(), service.command.clone()));
+                if let Some(health_check) = &service.health_check {
+                    commands.push((format!("{}.health-check", field), health_check.clone()));
+                }
+            }
+        }
+        let mut external_benches = self.external_benches.iter().collect::<Vec<_>>();
+        external_benches.sort_by_key(|(name, _)| *name);
+        for (name, bench) in external_benches {
+            let field = format!("external-benches.{}", name);
+            commands.push((field, bench.command.join(" ")));
+        }
+        commands
+    }
+
+    /// The settings of this profile that can run arbitrary code without a shell command, with the field of each one.
+    /// i.e. the sources of the builds other than the local repository, whose build scripts are compiled and run,
+    /// the extra cargo arguments (e.g. `--config target.<triple>.runner=...`), and the environment variables (e.g. `RUSTC_WRAPPER` or `LD_PRELOAD`)
+    pub fn code_settings(&self) -> Vec<(String, String)> {
+        // The benchmark parameters, e.g. of the `threads` sweep, are only read by `Bencher::param`
+        fn sorted_env(env: &HashMap<String, String>) -> Vec<(&String, &String)> {
+            let mut env = env
+                .iter()
+                .filter(|(name, _)| !name.starts_with("HARNESS_BENCH_PARAM_"))
+                .collect::<Vec<_>>();
+            env.sort();
+            env
+        }
+        let mut settings = vec![];
+        for (name, value) in sorted_env(&self.env) {
+            settings.push((format!("env.{}", name), value.clone()));
+        }
+        let mut benches = self.benches.iter().collect::<Vec<_>>();
+        benches.sort_by_key(|(name, _)| *name);
+        for (bench, config) in benches {
+            for (name, value) in sorted_env(&config.env) {
+                settings.push((format!("benches.{}.env.{}", bench, name), value.clone()));
+            }
+        }
+        let mut builds = self.builds.iter().collect::<Vec<_>>();
+        builds.sort_by_key(|(name, _)| *name);
+        for (build, config) in builds {
+            if let Some(git) = &config.git {
+                settings.push((format!("builds.{}.git", build), git.clone()));
+            }
+            if let Some(version) = &config.version {
+                settings.push((format!("builds.{}.version", build), version.clone()));
+            }
+            if !config.cargo_args.is_empty() {
+                let field = format!("builds.{}.cargo-args", build);
+                settings.push((field, config.cargo_args.join(" ")));
+            }
+            for (name, value) in sorted_env(&config.env) {
+                settings.push((format!("builds.{}.env.{}", build, name), value.clone()));
+            }
+        }
+        settings
+    }
+}
+
+/// Parse a duration string with a unit suffix (`ms`, `s`, `m`, `h` or `d`). e.g. `"300s"`, `"1.5h"`
+pub(crate) fn parse_duration(s: &str) -> anyhow::Result<Duration> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(s.len());
+    let (value, unit) = s.split_at(split);
+    let Ok(value) = value.parse::<f64>() else {
+        anyhow::bail!("Invalid duration: `{}`", s);
+    };
+    let secs = match unit.trim() {
+        "ms" => value / 1000.0,
+        "s" | "" => value,
+        "m" => value * 60.0,
+        "h" => value * 3600.0,
+        "d" => value * 86400.0,
+        _ => anyhow::bail!("Invalid duration: `{}`. Supported units: ms, s, m, h, d", s),
+    };
+    Ok(Duration::from_secs_f64(secs))
+}
+
+fn default_steady_state_window() -> usize {
+    3
+}
+
+fn default_max_warmup_iterations() -> usize {
+    50
+}
+
+/// The resource limits of the transient cgroup of each invocation.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct CgroupConfig {
+    /// The memory limit, e.g. `"512M"` or `"4G"`. Swapping is disabled when this is set. Default is no limit
+    #[serde(
+        default,
+        rename = "memory-max",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub memory_max: Option<String>,
+    /// The CPU quota in number of CPUs, e.g. `1.5`. Default is no limit
+    #[serde(default, rename = "cpu-quota", skip_serializing_if = "Option::is_none")]
+    pub cpu_quota: Option<f64>,
+}
+
+/// What to do when a pre-benchmarking check does not pass.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CheckSeverity {
+    /// Skip the check
+    Off,
+    /// Print a warning and continue
+    Warn,
+    /// Refuse to start the run
+    Fail,
+}
+
+fn default_check_severity() -> CheckSeverity {
+    CheckSeverity::Fail
+}
+
+/// A user-defined pre-benchmarking check, e.g. for a lab-specific machine policy.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CustomCheck {
+    /// The name of the check, shown in the error or warning message
+    pub name: String,
+    /// The shell command to run. The check fails if it exits with a non-zero status
+    pub command: String,
+    /// Whether a failed check blocks the run or only prints a warning. Default is `fail`
+    #[serde(default = "default_check_severity")]
+    pub severity: CheckSeverity,
+}
+
+fn default_push_job() -> String {
+    "harness".to_owned()
+}
+
+fn default_push_token_env() -> String {
+    "INFLUXDB_TOKEN".to_owned()
+}
+
+/// The monitoring endpoints to push the per-benchmark summary metrics to, for long-term dashboards (e.g. Grafana).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PushConfig {
+    /// The URL of a Prometheus pushgateway, e.g. `"http://localhost:9091"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prometheus: Option<String>,
+    /// The job name of the metrics pushed to the Prometheus pushgateway. Default is `harness`
+    #[serde(default = "default_push_job")]
+    pub job: String,
+    /// The write endpoint of an InfluxDB server, e.g. `"http://localhost:8086/api/v2/write?org=my-org&bucket=benchmarks"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub influxdb: Option<String>,
+    /// The environment variable that holds the InfluxDB API token. No token is sent if the variable is not set. Default is `INFLUXDB_TOKEN`
+    #[serde(
+        default = "default_push_token_env",
+        rename = "token-env",
+        alias = "token_env"
+    )]
+    pub token_env: String,
+}
+
+/// The steady-state detection for adaptive warm-up.
+///
+/// The warm-up phase ends when the coefficient of variation (stddev / mean) of the walltimes of the last `window` warm-up iterations
+/// drops below `cv`, or after `max-warmup-iterations` warm-up iterations. The number of warm-up iterations is recorded as the `warmup-iterations` stat.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SteadyStateConfig {
+    /// The coefficient of variation threshold. e.g. `0.02` for 2%
+    pub cv: f64,
+    /// Number of recent warm-up iterations to check. Default is 3
+    #[serde(default = "default_steady_state_window")]
+    pub window: usize,
+    /// Maximum number of warm-up iterations. Default is 50
+    #[serde(
+        default = "default_max_warmup_iterations",
+        rename = "max-warmup-iterations"
+    )]
+    pub max_warmup_iterations: usize,
+}
+
+/// Accept either a table of probe configurations, or a list of probe names.
+fn deserialize_probes<'de, D>(deserializer: D) -> Result<HashMap<String, Table>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Probes {
+        List(Vec<String>),
+        Table(HashMap<String, Table>),
+    }
+    Ok(match Probes::deserialize(deserializer)? {
+        Probes::List(names) => names.into_iter().map(|n| (n, Table::new())).collect(),
+        Probes::Table(probes) => probes,
+    })
+}
+
 fn default_true() -> bool {
     true
 }
 
+/// The order of running the invocations, benchmarks and builds, from the outermost loop to the innermost loop.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+#[clap(rename_all = "kebab-case")]
+pub enum RunOrder {
+    /// Interleave all benchmarks and builds within each invocation
+    #[default]
+    InvBenchBuild,
+    /// Finish all invocations of a benchmark before moving to the next benchmark, interleaving the builds
+    BenchInvBuild,
+    /// Finish all invocations of a benchmark and a build before moving to the next build
+    BenchBuildInv,
+}
+
+impl RunOrder {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RunOrder::InvBenchBuild => "inv-bench-build",
+            RunOrder::BenchInvBuild => "bench-inv-build",
+            RunOrder::BenchBuildInv => "bench-build-inv",
+        }
+    }
+}
+
+/// The per-benchmark configuration
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct BenchConfig {
+    /// Overrides the profile `timeout` for this benchmark
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+    /// Overrides the profile `warmup-iterations` for this benchmark. e.g. fewer iterations for long-running benchmarks
+    #[serde(
+        default,
+        rename = "warmup-iterations",
+        alias = "warmup_iterations",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub warmup_iterations: Option<usize>,
+    /// Deprecated. The total number of iterations of this benchmark, including the measured iterations of the profile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iterations: Option<usize>,
+    /// Overrides the profile `invocations` for this benchmark
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invocations: Option<usize>,
+    /// Extra environment variables to set for this benchmark, on top of the profile `env`. The build `env` takes precedence
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+    /// Companion processes started before each invocation of this benchmark and killed after it, by name.
+    /// e.g. `{ server = "target/release/myserver --port 8080" }`. Default is none
+    #[serde(
+        default,
+        deserialize_with = "deserialize_services",
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub services: BTreeMap<String, ServiceConfig>,
+    /// Extra arguments of the benchmark program, passed after `--` and read with `bencher.args()`.
+    /// They are appended to the command of an external benchmark. Default is none
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// A file to feed to the stdin of the benchmark, relative to the crate directory. Default is the stdin of `cargo harness`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stdin: Option<PathBuf>,
+}
+
+impl BenchConfig {
+    /// Fill in the unset fields from `defaults`
+    fn merge_defaults(&mut self, defaults: &BenchConfig) {
+        if self.timeout.is_none() {
+            self.timeout = defaults.timeout.clone();
+        }
+        if self.warmup_iterations.is_none() && self.iterations.is_none() {
+            self.warmup_iterations = defaults.warmup_iterations;
+            self.iterations = defaults.iterations;
+        }
+        if self.invocations.is_none() {
+            self.invocations = defaults.invocations;
+        }
+        for (k, v) in &defaults.env {
+            self.env.entry(k.clone()).or_insert_with(|| v.clone());
+        }
+        for (k, v) in &defaults.services {
+            self.services.entry(k.clone()).or_insert_with(|| v.clone());
+        }
+        if self.args.is_empty() {
+            self.args = defaults.args.clone();
+        }
+        if self.stdin.is_none() {
+            self.stdin = defaults.stdin.clone();
+        }
+    }
+}
+
+/// A dataset of the benchmarks. It is downloaded into `target/harness/cache/datasets/<name>` before the run,
+/// and reused by later runs until its config changes.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DatasetConfig {
+    /// The URL to download the dataset from
+    pub url: String,
+    /// The expected SHA-256 checksum of the downloaded file. The run fails on a mismatch. Default is no verification
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Extract the downloaded archive (`.tar`, `.tar.gz`, `.tgz`, `.tar.xz`, `.tar.zst`, or `.zip`) into the dataset directory. Default is false
+    #[serde(default)]
+    pub extract: bool,
+    /// Shell commands to run in the dataset directory after the download and the extraction, e.g. to preprocess the data.
+    /// Default is none
+    #[serde(
+        default,
+        rename = "post-extract",
+        alias = "post_extract",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub post_extract: Vec<String>,
+}
+
+fn default_service_ready_timeout() -> String {
+    "30s".to_owned()
+}
+
+/// A companion process of a benchmark, e.g. the server of a client/server benchmark.
+/// It runs in the crate directory, with the environment variables of the profile.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ServiceConfig {
+    /// The shell command to start the service, e.g. `"target/release/myserver --port 8080"`
+    pub command: String,
+    /// Wait until the service accepts TCP connections on this local port. Default is no port check
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// A shell command to check if the service is ready, retried until it exits with a zero status, e.g. `"curl -sf localhost:8080/health"`.
+    /// Default is no health check
+    #[serde(
+        default,
+        rename = "health-check",
+        alias = "health_check",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub health_check: Option<String>,
+    /// How long to wait for the service to be ready before failing the invocation. Default is `30s`
+    #[serde(
+        default = "default_service_ready_timeout",
+        rename = "ready-timeout",
+        alias = "ready_timeout"
+    )]
+    pub ready_timeout: String,
+}
+
+impl ServiceConfig {
+    /// The parsed `ready-timeout`
+    pub fn get_ready_timeout(&self) -> anyhow::Result<Duration> {
+        parse_duration(&self.ready_timeout)
+    }
+}
+
+/// Accept either a table of service configurations, or only the command of each service.
+fn deserialize_services<'de, D>(
+    deserializer: D,
+) -> Result<BTreeMap<String, ServiceConfig>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Service {
+        Command(String),
+        Config(ServiceConfig),
+    }
+    let services = BTreeMap::<String, Service>::deserialize(deserializer)?;
+    Ok(services
+        .into_iter()
+        .map(|(name, service)| {
+            let config = match service {
+                Service::Command(command) => ServiceConfig {
+                    command,
+                    port: None,
+                    health_check: None,
+                    ready_timeout: default_service_ready_timeout(),
+                },
+                Service::Config(config) => config,
+            };
+            (name, config)
+        })
+        .collect())
+}
+
+/// An external benchmark. Each invocation runs the command once, in the crate directory,
+/// with the environment variables of the profile, the benchmark, and the build.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExternalBenchConfig {
+    /// The program and its arguments, e.g. `["python3", "baselines/sort.py"]`
+    pub command: Vec<String>,
+    /// The regexes to extract the metrics from the output of the command, each with one capture group for the value,
+    /// e.g. `{ time = "time: ([0-9.]+) ms" }`. If `time` is not extracted, the walltime of the command in milliseconds is recorded instead.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metrics: HashMap<String, String>,
+}
+
+impl ExternalBenchConfig {
+    /// Check the command and the metric regexes
+    pub fn validate(&self, name: &str) -> anyhow::Result<()> {
+        if self.command.is_empty() {
+            anyhow::bail!("The command of external benchmark `{}` is empty", name);
+        }
+        for (metric, pattern) in &self.metrics {
+            let re = Regex::new(pattern).map_err(|e| {
+                anyhow::anyhow!(
+                    "Invalid regex of `{}` in external benchmark `{}`: {}",
+                    metric,
+                    name,
+                    e
+                )
+            })?;
+            if re.captures_len() != 2 {
+                anyhow::bail!(
+                    "The regex of `{}` in external benchmark `{}` must have exactly one capture group",
+                    metric,
+                    name
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Extract the metrics from the output of an invocation. `walltime` is recorded as `time` if there is no `time` regex.
+    pub fn parse_metrics(
+        &self,
+        output: &str,
+        walltime: Duration,
+    ) -> anyhow::Result<Vec<(String, String)>> {
+        let mut stats = vec![];
+        if !self.metrics.contains_key("time") {
+            stats.push((
+                "time".to_owned(),
+                format!("{:.3}", walltime.as_secs_f64() * 1000.0),
+            ));
+        }
+        for (metric, pattern) in &self.metrics {
+            let value = Regex::new(pattern)?
+                .captures_iter(output)
+                .last()
+                .and_then(|c| c.get(1))
+                .map(|v| v.as_str().trim().to_owned());
+            match value {
+                Some(v) if v.parse::<f64>().is_ok() => stats.push((metric.clone(), v)),
+                _ => anyhow::bail!("failed to extract `{}` from the output", metric),
+            }
+        }
+        Ok(stats)
+    }
+}
+
 /// The build configuration used for evaluation
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct BuildConfig {
@@ -217,6 +1295,36 @@ pub struct BuildConfig {
     /// The commit used to produce the build. Default to the current commit.
     #[serde(default)]
     pub commit: Option<String>,
+    /// A remote git repository to fetch the commit of the build from, e.g. `https://github.com/owner/repo`.
+    /// The resolved commit is recorded as `commit`. Default to the local repository.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git: Option<String>,
+    /// The branch, tag, or full commit hash to fetch from the remote `git` repository. Default to its `HEAD`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+    /// A version of the crate published on crates.io to produce the build from, instead of a commit, e.g. `1.2.3`. Default to the local source.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// (*Linux only*) The NUMA node to bind the benchmark processes to. Default to the `numa-node` of the profile.
+    #[serde(
+        default,
+        rename = "numa-node",
+        alias = "numa_node",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub numa_node: Option<usize>,
+    /// Extra arguments passed to `cargo bench` when building and running this build,
+    /// e.g. `["--target", "aarch64-unknown-linux-gnu"]` or `["--config", "profile.bench.lto=true"]`. Default to no extra arguments.
+    #[serde(
+        default,
+        rename = "cargo-args",
+        alias = "cargo_args",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub cargo_args: Vec<String>,
+    /// The thread count of a variant generated by the `threads` sweep of the profile. Not set for other builds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threads: Option<usize>,
 }
 
 impl Default for BuildConfig {
@@ -226,6 +1334,77 @@ impl Default for BuildConfig {
             default_features: true,
             env: HashMap::new(),
             commit: None,
+            git: None,
+            rev: None,
+            version: None,
+            numa_node: None,
+            cargo_args: Vec::new(),
+            threads: None,
+        }
+    }
+}
+
+/// A build matrix. One build is generated for each combination of a feature set and a value of each environment variable.
+///
+/// The build names are the feature set (joined by `+`, or `default` for an empty set) and the `NAME=VALUE` of each variable,
+/// separated by `.`, e.g. `a+b.THREADS=8`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MatrixConfig {
+    /// The sets of extra cargo features, e.g. `[["a"], ["b"]]`. Default to no feature dimension.
+    #[serde(default)]
+    pub features: Vec<Vec<String>>,
+    /// Whether to use default features in all the generated builds. Default to `true`
+    #[serde(default = "default_true", rename = "default-features")]
+    pub default_features: bool,
+    /// The values of each environment variable, e.g. `{ THREADS = ["1", "8"] }`. Default to no environment variable dimensions.
+    #[serde(default)]
+    pub env: BTreeMap<String, Vec<String>>,
+}
+
+impl MatrixConfig {
+    /// The names and configs of all the builds in the matrix
+    fn expand(&self) -> Vec<(String, BuildConfig)> {
+        let base = BuildConfig {
+            default_features: self.default_features,
+            ..Default::default()
+        };
+        let mut builds = vec![(vec![], base)];
+        if !self.features.is_empty() {
+            builds = builds
+                .into_iter()
+                .flat_map(|(name, build)| {
+                    self.features.iter().map(move |features| {
+                        let mut name = name.clone();
+                        name.push(if features.is_empty() {
+                            "default".to_owned()
+                        } else {
+                            features.join("+")
+                        });
+                        let mut build = build.clone();
+                        build.features = features.clone();
+                        (name, build)
+                    })
+                })
+                .collect();
+        }
+        for (var, values) in &self.env {
+            builds = builds
+                .into_iter()
+                .flat_map(|(name, build)| {
+                    values.iter().map(move |value| {
+                        let mut name = name.clone();
+                        name.push(format!("{}={}", var, value));
+                        let mut build = build.clone();
+                        build.env.insert(var.clone(), value.clone());
+                        (name, build)
+                    })
+                })
+                .collect();
         }
+        builds
+            .into_iter()
+            .filter(|(name, _)| !name.is_empty())
+            .map(|(name, build)| (name.join("."), build))
+            .collect()
     }
 }
@@ -5,3 +5,4 @@
 
 pub mod harness;
 pub mod run_info;
+pub mod user;
@@ -17,7 +17,11 @@
 //! cargo harness run --config /path/to/config.toml
 //! ```
 
-use std::{collections::HashMap, ops::Deref, path::PathBuf};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    ops::Deref,
+    path::{Path, PathBuf},
+};
 
 use cargo_metadata::MetadataCommand;
 use chrono::{DateTime, Local};
@@ -66,6 +70,36 @@ pub struct RunInfo {
     pub profile: ProfileWithName,
     /// Current system information
     pub system: SystemInfo,
+    /// (*Linux only*) The CPUs that all the benchmark processes are pinned to, resolved from `cpus` or `isolate-cores` in the profile
+    #[serde(
+        default,
+        rename = "cpu-affinity",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cpu_affinity: Option<Vec<usize>>,
+    /// The benchmark filter specified on the command line, if any
+    #[serde(
+        default,
+        rename = "bench-filter",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub bench_filter: Option<BenchFilter>,
+    /// The container that the benchmarks are built and run in, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<ContainerInfo>,
+    /// The datasets of the profile, resolved into the cache directory before the run
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub datasets: BTreeMap<String, DatasetInfo>,
+    /// The compile time of each build, measured when the builds are tested before the run
+    #[serde(
+        default,
+        rename = "build-times",
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub build_times: BTreeMap<String, BuildTime>,
+    /// The invocations that were not started because the run exceeded `max-duration`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<SkippedInvocation>,
     /// Cargo.lock files for each used git commit, for deterministic builds
     pub lockfiles: Lockfiles,
 }
@@ -81,11 +115,27 @@ impl RunInfo {
     ) -> anyhow::Result<Self> {
         let lockfiles = load_lockfiles(&crate_info, &profile)?;
         let project = project.unwrap_or_else(|| crate_info.name.clone());
+        let mut system = utils::sys::get_current_system_info();
+        system.env.retain(|k, _| profile.keep_env_var(k));
+        for (k, v) in system.env.iter_mut() {
+            if profile.redact_env_var(k) {
+                *v = Profile::REDACTED_ENV_VALUE.to_owned();
+            }
+        }
+        system.aslr_disabled = profile.disable_aslr || system.randomize_va_space == Some(0);
+        let cpu_affinity =
+            utils::sys::resolve_cpu_affinity(profile.cpus.as_deref(), profile.isolate_cores)?;
         Ok(Self {
             version: 0,
             crate_info,
             project,
-            system: utils::sys::get_current_system_info(),
+            system,
+            cpu_affinity,
+            bench_filter: None,
+            container: None,
+            datasets: BTreeMap::new(),
+            build_times: BTreeMap::new(),
+            skipped: Vec::new(),
             profile: ProfileWithName {
                 name: profile_name,
                 profile,
@@ -100,7 +150,52 @@ impl RunInfo {
 
     pub(crate) fn load(path: &PathBuf) -> anyhow::Result<Self> {
         let content = std::fs::read_to_string(path)?;
-        Ok(toml::from_str(&content)?)
+        let mut run: Self = toml::from_str(&content)?;
+        run.profile.profile.resolve_legacy_iterations();
+        Ok(run)
+    }
+}
+
+/// Select a subset of the benchmarks by names or glob patterns
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BenchFilter {
+    /// Only run the benchmarks matching any of these patterns. Empty means all benchmarks
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// Skip the benchmarks matching any of these patterns
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+}
+
+impl BenchFilter {
+    /// Returns the benchmarks selected by the filter, in the original order
+    pub fn apply(&self, benches: &[String]) -> anyhow::Result<Vec<String>> {
+        let compile = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|p| {
+                    glob::Pattern::new(p)
+                        .map_err(|e| anyhow::anyhow!("Invalid bench pattern `{}`: {}", p, e))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()
+        };
+        let include = compile(&self.include)?;
+        let exclude = compile(&self.exclude)?;
+        for (pattern, p) in self.include.iter().zip(&include) {
+            if !benches.iter().any(|b| p.matches(b)) {
+                anyhow::bail!("No benchmark matches `{}`", pattern);
+            }
+        }
+        let selected = benches
+            .iter()
+            .filter(|b| include.is_empty() || include.iter().any(|p| p.matches(b)))
+            .filter(|b| !exclude.iter().any(|p| p.matches(b)))
+            .cloned()
+            .collect::<Vec<_>>();
+        if selected.is_empty() {
+            anyhow::bail!("No benchmarks left to run after filtering");
+        }
+        Ok(selected)
     }
 }
 
@@ -115,6 +210,9 @@ pub struct CrateInfo {
     pub benches: Vec<String>,
     /// Workspace root
     pub workspace_root: PathBuf,
+    /// The package of each benchmark, in workspace mode. Empty if all the benchmarks are from the root package
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub packages: BTreeMap<String, String>,
 }
 
 impl CrateInfo {
@@ -126,22 +224,74 @@ impl CrateInfo {
         Ok(target_dir.to_owned())
     }
 
-    pub(crate) fn load() -> anyhow::Result<Self> {
+    /// Load the crate info of the root package.
+    ///
+    /// In workspace mode (`workspace`, a non-empty `packages` list, or a virtual workspace manifest),
+    /// the benchmarks of all the workspace members, or only the members in `packages`, are collected instead.
+    pub(crate) fn load(workspace: bool, packages: &[String]) -> anyhow::Result<Self> {
         let Ok(meta) = MetadataCommand::new().manifest_path("./Cargo.toml").exec() else {
             anyhow::bail!("Failed to get metadata from ./Cargo.toml");
         };
         let target_dir = meta.target_directory.as_std_path();
-        let Some(pkg) = meta.root_package() else {
-            anyhow::bail!("No root package found");
+        let workspace_root = meta.workspace_root.as_std_path().to_owned();
+        let root = meta.root_package();
+        if let Some(pkg) = root.filter(|_| !workspace && packages.is_empty()) {
+            let benches = CargoConfig::load_benches(Path::new("./Cargo.toml"))?;
+            return Ok(CrateInfo {
+                name: pkg.name.clone(),
+                target_dir: target_dir.to_owned(),
+                benches,
+                workspace_root,
+                packages: BTreeMap::new(),
+            });
+        }
+        let members = meta.workspace_packages();
+        for p in packages {
+            if !members.iter().any(|m| &m.name == p) {
+                anyhow::bail!("Package `{}` is not a member of the workspace", p);
+            }
+        }
+        let mut bench_packages = BTreeMap::new();
+        for pkg in members
+            .iter()
+            .filter(|m| packages.is_empty() || packages.contains(&m.name))
+        {
+            for bench in CargoConfig::load_benches(pkg.manifest_path.as_std_path())? {
+                if let Some(other) = bench_packages.insert(bench.clone(), pkg.name.clone()) {
+                    anyhow::bail!(
+                        "Benchmark `{}` is defined in both `{}` and `{}`. Benchmark names must be unique in the workspace",
+                        bench,
+                        other,
+                        pkg.name
+                    );
+                }
+            }
+        }
+        let name = match root {
+            Some(pkg) => pkg.name.clone(),
+            None => workspace_root
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "workspace".to_owned()),
         };
-        let benches = CargoConfig::load_benches()?;
         Ok(CrateInfo {
-            name: pkg.name.clone(),
+            name,
             target_dir: target_dir.to_owned(),
-            benches,
-            workspace_root: meta.workspace_root.as_std_path().to_owned(),
+            benches: bench_packages.keys().cloned().collect(),
+            workspace_root,
+            packages: bench_packages,
         })
     }
+
+    /// The package of a benchmark in workspace mode
+    pub fn package(&self, bench: &str) -> Option<&str> {
+        self.packages.get(bench).map(|p| p.as_str())
+    }
+
+    /// All the packages of the benchmarks in workspace mode
+    pub fn all_packages(&self) -> BTreeSet<&str> {
+        self.packages.values().map(|p| p.as_str()).collect()
+    }
 }
 
 /// The system information, including the hardware specs, the OS info, and the environment variables.
@@ -166,8 +316,8 @@ pub struct SystemInfo {
     pub memory_size: usize,
     /// Total swap size in bytes
     pub swap_size: usize,
-    /// (*Linux only*) All logged in users
-    #[cfg(target_os = "linux")]
+    /// (*Linux and Windows*) All logged in users
+    #[serde(default)]
     pub users: Vec<String>,
     /// Total number of running processes
     pub processes: usize,
@@ -177,10 +327,194 @@ pub struct SystemInfo {
     pub pid: usize,
     /// The rustc version
     pub rustc: String,
+    /// The exact Rust toolchain, used to reproduce the run with `--strict-toolchain`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub toolchain: Option<Toolchain>,
     /// (*Linux only*) The scaling governor of each CPU core
-    #[cfg(target_os = "linux")]
-    #[serde(rename = "scaling-governor")]
+    #[serde(default, rename = "scaling-governor")]
     pub scaling_governor: Vec<String>,
+    /// (*Windows only*) The active power plan
+    #[serde(
+        default,
+        rename = "power-plan",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub power_plan: Option<String>,
+    /// (*Linux only*) Logical CPUs of the performance core class, on hybrid (P/E, big.LITTLE) CPUs
+    #[serde(
+        default,
+        rename = "performance-cpus",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub performance_cpus: Vec<usize>,
+    /// (*Linux only*) Logical CPUs of the efficiency core class, on hybrid (P/E, big.LITTLE) CPUs
+    #[serde(
+        default,
+        rename = "efficiency-cpus",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub efficiency_cpus: Vec<usize>,
+    /// (*macOS only*) Number of performance cores
+    #[serde(
+        default,
+        rename = "performance-cores",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub performance_cores: Option<usize>,
+    /// (*macOS only*) Number of efficiency cores
+    #[serde(
+        default,
+        rename = "efficiency-cores",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub efficiency_cores: Option<usize>,
+    /// (*macOS only*) Whether the low power mode is enabled
+    #[serde(
+        default,
+        rename = "low-power-mode",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub low_power_mode: Option<bool>,
+    /// (*macOS only*) The CPU speed limit in percent. Less than 100 means the CPU is throttled due to thermal pressure
+    #[serde(
+        default,
+        rename = "cpu-speed-limit",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cpu_speed_limit: Option<usize>,
+    /// (*macOS only*) Whether Spotlight indexing is enabled for the root volume
+    #[serde(
+        default,
+        rename = "spotlight-indexing",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub spotlight_indexing: Option<bool>,
+    /// (*Linux and macOS only*) Whether the machine is running on battery
+    #[serde(
+        default,
+        rename = "on-battery",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub on_battery: Option<bool>,
+    /// (*Linux only*) The highest CPU temperature in °C, when the run started
+    #[serde(
+        default,
+        rename = "cpu-temperature",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cpu_temperature: Option<f64>,
+    /// (*Linux only*) The critical CPU temperature in °C, at which the CPU is throttled
+    #[serde(
+        default,
+        rename = "cpu-temperature-limit",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cpu_temperature_limit: Option<f64>,
+    /// (*Linux and macOS only*) Whether simultaneous multithreading (hyperthreading) is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smt: Option<bool>,
+    /// (*Linux only*) Whether turbo boost is enabled
+    #[serde(
+        default,
+        rename = "turbo-boost",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub turbo_boost: Option<bool>,
+    /// (*Linux only*) The system-wide ASLR setting (`/proc/sys/kernel/randomize_va_space`). `0` means disabled
+    #[serde(
+        default,
+        rename = "randomize-va-space",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub randomize_va_space: Option<usize>,
+    /// Whether the benchmark processes run with ASLR disabled, either by `disable-aslr` in the profile or system-wide
+    #[serde(default, rename = "aslr-disabled")]
+    pub aslr_disabled: bool,
+    /// (*Linux only*) The NUMA topology
+    #[serde(default, rename = "numa-nodes", skip_serializing_if = "Vec::is_empty")]
+    pub numa_nodes: Vec<NumaNode>,
+}
+
+/// A NUMA node, with its CPUs and memory
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct NumaNode {
+    /// Node id
+    pub id: usize,
+    /// Logical CPUs on this node
+    pub cpus: Vec<usize>,
+    /// Memory size of this node in bytes
+    #[serde(rename = "memory-size")]
+    pub memory_size: usize,
+}
+
+/// The container image of a container run
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ContainerInfo {
+    /// The container engine, `docker` or `podman`
+    pub engine: String,
+    /// The image specified on the command line, e.g. `rust:1.78`
+    pub image: String,
+    /// The image pinned by its digest, e.g. `rust@sha256:...`
+    pub digest: String,
+}
+
+/// A dataset downloaded for a run
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DatasetInfo {
+    /// The URL the dataset is downloaded from
+    pub url: String,
+    /// The SHA-256 checksum of the downloaded file
+    pub sha256: String,
+}
+
+/// The compile time of a build, in seconds
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BuildTime {
+    /// The wall-clock time of the whole build command
+    pub walltime: f64,
+    /// The time reported by cargo in its `Finished` line, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cargo: Option<f64>,
+}
+
+/// An invocation that was skipped because the run exceeded its time budget
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SkippedInvocation {
+    pub bench: String,
+    pub build: String,
+    pub invocation: usize,
+}
+
+/// The Rust toolchain that builds the benchmarks
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Toolchain {
+    /// The release channel, i.e. `stable`, `beta`, `nightly`, or `dev`
+    pub channel: String,
+    /// The rustc version, e.g. `1.78.0`
+    pub version: String,
+    /// The commit hash of rustc
+    #[serde(
+        default,
+        rename = "commit-hash",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub commit_hash: Option<String>,
+    /// The commit date of rustc, e.g. `2024-04-29`
+    #[serde(
+        default,
+        rename = "commit-date",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub commit_date: Option<String>,
+    /// The host triple of rustc, e.g. `x86_64-unknown-linux-gnu`
+    pub host: String,
+}
+
+impl SystemInfo {
+    /// Returns true if the CPU has more than one core class, e.g. P/E cores or big.LITTLE
+    pub fn is_hybrid_cpu(&self) -> bool {
+        !self.efficiency_cpus.is_empty() || self.efficiency_cores.is_some_and(|n| n > 0)
+    }
 }
 
 /// Cargo.lock files for each used git commit, for deterministic builds
@@ -0,0 +1,66 @@
+//! The user-level harness config
+//!
+//! This is placed at `$XDG_CONFIG_HOME/harness/config.toml` (`~/.config/harness/config.toml` by default),
+//! or `%APPDATA%\harness\config.toml` on Windows. It holds the settings that should not be committed to the crate, e.g. API tokens.
+//!
+//! # Example:
+//!
+//! ```toml
+//! [upload]
+//! # Optional. Upload to a self-hosted server. Default to https://r.harness.rs
+//! remote = "https://bench.example.com"
+//! # Optional. The API token. Can be overridden by the `HARNESS_UPLOAD_TOKEN` environment variable
+//! token = "..."
+//! # Optional. The project to upload the results to
+//! project = "my-team/my-crate"
+//! ```
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+/// The user-level harness config
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct UserConfig {
+    /// The settings of `cargo harness upload`
+    #[serde(default)]
+    pub upload: UploadConfig,
+}
+
+/// The settings of `cargo harness upload`
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct UploadConfig {
+    /// Host url of the harness server. Default to https://r.harness.rs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote: Option<String>,
+    /// The API token to authenticate the uploads. Anonymous uploads are claimed with a link instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// The slug of the project to upload the results to, e.g. `my-team/my-crate`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+}
+
+impl UserConfig {
+    /// The path of the user-level config file. `None` if the config directory is unknown.
+    pub fn path() -> Option<PathBuf> {
+        let config_dir = if cfg!(windows) {
+            std::env::var_os("APPDATA").map(PathBuf::from)
+        } else {
+            std::env::var_os("XDG_CONFIG_HOME")
+                .filter(|d| !d.is_empty())
+                .map(PathBuf::from)
+                .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+        };
+        Some(config_dir?.join("harness").join("config.toml"))
+    }
+
+    /// Load the user-level config. A default config is returned if the file does not exist.
+    pub fn load() -> anyhow::Result<Self> {
+        let Some(path) = Self::path().filter(|p| p.is_file()) else {
+            return Ok(Self::default());
+        };
+        let s = std::fs::read_to_string(&path)?;
+        toml::from_str(&s).map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))
+    }
+}
@@ -16,15 +16,26 @@ pub struct Cli {
 
 #[derive(Subcommand)]
 enum Commands {
-    Run(commands::run::RunArgs),
+    Run(Box<commands::run::RunArgs>),
+    Report(commands::report::ReportArgs),
     Upload(commands::upload::UploadResultsArgs),
     Viz(commands::viz::VizArgs),
+    DiffConfig(commands::diff_config::DiffConfigArgs),
+    CheckRegression(commands::check_regression::CheckRegressionArgs),
+    Flaky(commands::flaky::FlakyArgs),
+    History(commands::history::HistoryArgs),
+    Meta(commands::meta::MetaArgs),
+    Export(commands::export::ExportArgs),
+    Plot(commands::plot::PlotArgs),
+    Warmup(commands::warmup::WarmupArgs),
+    Archive(commands::archive::ArchiveArgs),
+    Unarchive(commands::archive::UnarchiveArgs),
+    Clean(commands::clean::CleanArgs),
+    Cache(commands::cache::CacheArgs),
+    Completions(commands::completions::CompletionsArgs),
+    Env(commands::env::EnvArgs),
 }
 
-/// Plot benchmark results
-#[derive(Parser)]
-struct PlotArgs {}
-
 static CMD_ARGS: Lazy<Cli> = Lazy::new(|| {
     let mut args = std::env::args().collect::<Vec<_>>();
     if args.len() > 1 && args[1] == "harness" {
@@ -56,8 +67,23 @@ pub fn entey(args: &Cli) -> anyhow::Result<()> {
     let git = git_info2::get();
     let run_result = match &args.command {
         Commands::Run(cmd) => cmd.run(),
+        Commands::Report(cmd) => cmd.run(),
         Commands::Upload(cmd) => cmd.run(),
         Commands::Viz(cmd) => cmd.run(),
+        Commands::DiffConfig(cmd) => cmd.run(),
+        Commands::CheckRegression(cmd) => cmd.run(),
+        Commands::Flaky(cmd) => cmd.run(),
+        Commands::History(cmd) => cmd.run(),
+        Commands::Meta(cmd) => cmd.run(),
+        Commands::Export(cmd) => cmd.run(),
+        Commands::Plot(cmd) => cmd.run(),
+        Commands::Warmup(cmd) => cmd.run(),
+        Commands::Archive(cmd) => cmd.run(),
+        Commands::Unarchive(cmd) => cmd.run(),
+        Commands::Clean(cmd) => cmd.run(),
+        Commands::Cache(cmd) => cmd.run(),
+        Commands::Completions(cmd) => cmd.run(),
+        Commands::Env(cmd) => cmd.run(),
     };
     if let Err(err) = run_result.as_ref() {
         eprintln!("❌ {}: {}", "ERROR".red().bold(), err.to_string().red());
@@ -68,5 +94,6 @@ pub fn entey(args: &Cli) -> anyhow::Result<()> {
         eprintln!("❌ {}: {}", "ERROR".red().bold(), err.to_string().red());
         dump_backtrace(err);
     }
-    Ok(())
+    // Exit with a non-zero status on failure, e.g. for `check-regression` in CI
+    run_result.and(restore_result)
 }
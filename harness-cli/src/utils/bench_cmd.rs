@@ -1,8 +1,70 @@
-use std::{path::Path, process::Command};
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::Command,
+};
 
-use crate::configs::{harness::Profile, run_info::RunInfo};
+use crate::configs::{
+    harness::Profile,
+    run_info::{CrateInfo, RunInfo},
+};
 
-fn generate_cargo_build_args_and_envs(profile: &Profile, build: &str, cmd: &mut Command) {
+use super::{build_cache, cachegrind, cgroup};
+
+/// Set the environment variables of a benchmark process: profile < bench < build
+fn generate_envs(profile: &Profile, build: &str, bench: Option<&str>, cmd: &mut Command) {
+    let build = &profile.builds[build];
+    // minimal environment
+    if profile.clean_env {
+        cmd.env_clear();
+        cmd.envs(std::env::vars().filter(|(k, _)| profile.keep_env_var(k)));
+    }
+    let mut envs = profile.env.clone();
+    if let Some(bench) = bench.and_then(|b| profile.benches.get(b)) {
+        for (k, v) in &bench.env {
+            envs.insert(k.clone(), v.clone());
+        }
+    }
+    for (k, v) in &build.env {
+        envs.insert(k.clone(), v.clone());
+    }
+    cmd.envs(envs);
+}
+
+/// The target directory of a build, `target/harness/targets/<build>`, so that switching between builds never invalidates the artifacts of each other.
+/// `None` if the profile or the build chooses its own target directory.
+pub fn get_build_target_dir(
+    crate_info: &CrateInfo,
+    profile: &Profile,
+    build: &str,
+) -> Option<PathBuf> {
+    let b = &profile.builds[build];
+    if profile.env.contains_key("CARGO_TARGET_DIR")
+        || b.env.contains_key("CARGO_TARGET_DIR")
+        || b.cargo_args.iter().any(|a| a.starts_with("--target-dir"))
+    {
+        return None;
+    }
+    Some(
+        crate_info
+            .target_dir
+            .join("harness")
+            .join("targets")
+            .join(build),
+    )
+}
+
+fn generate_cargo_build_args_and_envs(
+    crate_info: &CrateInfo,
+    profile: &Profile,
+    build: &str,
+    bench: Option<&str>,
+    cmd: &mut Command,
+) {
+    generate_envs(profile, build, bench, cmd);
+    if let Some(target_dir) = get_build_target_dir(crate_info, profile, build) {
+        cmd.env("CARGO_TARGET_DIR", target_dir);
+    }
     let build = &profile.builds[build];
     // features
     if !build.features.is_empty() {
@@ -12,51 +74,264 @@ fn generate_cargo_build_args_and_envs(profile: &Profile, build: &str, cmd: &mut
     if !build.default_features {
         cmd.arg("--no-default-features");
     }
-    // envs
-    let mut envs = profile.env.clone();
-    for (k, v) in &build.env {
-        envs.insert(k.clone(), v.clone());
-    }
-    cmd.envs(envs);
+    // extra cargo args
+    cmd.args(&build.cargo_args);
 }
 
-pub fn get_bench_build_command(profile: &Profile, build: &str) -> Command {
+pub fn get_bench_build_command(crate_info: &CrateInfo, profile: &Profile, build: &str) -> Command {
     let mut cmd = Command::new("cargo");
     cmd.arg("bench");
-    generate_cargo_build_args_and_envs(profile, build, &mut cmd);
+    // workspace mode: build the benches of all the involved packages
+    for package in crate_info.all_packages() {
+        cmd.args(["-p", package]);
+    }
+    generate_cargo_build_args_and_envs(crate_info, profile, build, None, &mut cmd);
     cmd.arg("--no-run");
     cmd
 }
 
+/// The cachegrind output file of a single invocation
+pub fn get_cachegrind_out_file(
+    log_dir: &Path,
+    bench: &str,
+    build_name: &str,
+    invocation: usize,
+) -> PathBuf {
+    log_dir.join(format!(
+        "{}.{}.{}.cachegrind.out",
+        bench, build_name, invocation
+    ))
+}
+
+/// The command to run a user-defined script of the profile with the system shell, e.g. a hook or a custom check
+pub fn get_shell_command(profile: &Profile, script: &str) -> Command {
+    let mut cmd = if cfg!(target_os = "windows") {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C");
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c");
+        cmd
+    };
+    cmd.arg(script).envs(profile.env.iter());
+    cmd
+}
+
+/// The command of an invocation of an external benchmark
+pub fn get_external_bench_run_command(
+    run: &RunInfo,
+    bench: &str,
+    build_name: &str,
+    invocation: usize,
+) -> Command {
+    let external = &run.profile.external_benches[bench];
+    let mut cmd = Command::new(&external.command[0]);
+    cmd.args(&external.command[1..]);
+    if let Some(config) = run.profile.benches.get(bench) {
+        cmd.args(&config.args);
+    }
+    generate_envs(&run.profile, build_name, Some(bench), &mut cmd);
+    cmd.env("HARNESS_BENCH_BUILD", build_name)
+        .env("HARNESS_BENCH_INVOCATION", invocation.to_string());
+    cmd
+}
+
+/// The wrapper command that runs the bench binary, e.g. in a cgroup and/or under cachegrind. Empty if none
+fn get_bench_runner(
+    run: &RunInfo,
+    bench: &str,
+    build_name: &str,
+    invocation: usize,
+    log_dir: Option<&Path>,
+) -> Vec<String> {
+    let mut runner = vec![];
+    // a container run applies the cgroup limits to the container instead
+    if let Some(cgroup) = run
+        .profile
+        .cgroup
+        .as_ref()
+        .filter(|_| run.container.is_none())
+    {
+        runner.extend(cgroup::runner_args(cgroup));
+    }
+    if run.profile.cachegrind {
+        if let Some(log_dir) = log_dir {
+            let out_file = get_cachegrind_out_file(log_dir, bench, build_name, invocation);
+            runner.extend(cachegrind::runner_args(&out_file));
+        }
+    }
+    runner
+}
+
+/// The arguments passed to the bench binary
+fn get_bench_args(
+    run: &RunInfo,
+    bench: &str,
+    build_name: &str,
+    invocation: usize,
+    log_dir: Option<&Path>,
+    output_csv: Option<&Path>,
+) -> Vec<OsString> {
+    let mut args: Vec<OsString> = vec![];
+    let package = run.crate_info.package(bench);
+    // `-n` is the total number of iterations, which is understood by all versions of the harness library
+    let measured_iterations = run.profile.measured_iterations;
+    let total_iterations = run.profile.get_warmup_iterations(bench) + measured_iterations;
+    args.extend(
+        [
+            "-n".to_owned(),
+            total_iterations.to_string(),
+            "--overwrite-crate-name".to_owned(),
+            package.unwrap_or(&run.crate_info.name).to_owned(),
+            "--overwrite-benchmark-name".to_owned(),
+            bench.to_owned(),
+            "--current-invocation".to_owned(),
+            format!("{invocation}"),
+            "--current-build".to_owned(),
+            build_name.to_owned(),
+        ]
+        .map(OsString::from),
+    );
+    if measured_iterations > 1 {
+        args.push("--measured-iterations".into());
+        args.push(measured_iterations.to_string().into());
+    }
+    if let Some(steady_state) = &run.profile.steady_state {
+        args.push("--steady-state-cv".into());
+        args.push(steady_state.cv.to_string().into());
+        args.push("--steady-state-window".into());
+        args.push(steady_state.window.to_string().into());
+        args.push("--max-warmup-iterations".into());
+        args.push(steady_state.max_warmup_iterations.to_string().into());
+    }
+    if run.profile.measure_all_iterations {
+        args.push("--measure-all-iterations".into());
+    }
+    if let Some(output_csv) = output_csv {
+        args.push("--output-csv".into());
+        args.push(output_csv.into());
+    }
+    if let Some(log_dir) = log_dir {
+        if let Some(interval) = run.profile.sample_interval {
+            let samples_file = format!("{}.{}.{}.samples.csv", bench, build_name, invocation);
+            args.push("--sample-interval".into());
+            args.push(interval.to_string().into());
+            args.push("--output-samples".into());
+            args.push(log_dir.join(samples_file).into());
+        }
+        if run.profile.latency_histogram {
+            let histogram_file = format!("{}.{}.{}.latency.hgrm", bench, build_name, invocation);
+            args.push("--output-latency-histogram".into());
+            args.push(log_dir.join(histogram_file).into());
+        }
+    }
+    if !run.profile.probes.is_empty() {
+        let probes_json_str = serde_json::to_string(&run.profile.probes).unwrap();
+        args.push("--probes".into());
+        args.push(probes_json_str.into());
+    }
+    // the arguments of the benchmark program
+    if let Some(config) = run
+        .profile
+        .benches
+        .get(bench)
+        .filter(|b| !b.args.is_empty())
+    {
+        args.push("--".into());
+        args.extend(config.args.iter().map(OsString::from));
+    }
+    args
+}
+
 pub fn get_bench_run_command(
     run: &RunInfo,
     bench: &str,
     build_name: &str,
     invocation: usize,
     log_dir: Option<&Path>,
+    output_csv: Option<&Path>,
 ) -> Command {
     let mut cmd = Command::new("cargo");
     cmd.arg("bench");
-    generate_cargo_build_args_and_envs(&run.profile, build_name, &mut cmd);
-    // pass bench name
+    generate_cargo_build_args_and_envs(
+        &run.crate_info,
+        &run.profile,
+        build_name,
+        Some(bench),
+        &mut cmd,
+    );
+    // let probes know which invocation they are running in
+    cmd.env("HARNESS_BENCH_BUILD", build_name)
+        .env("HARNESS_BENCH_INVOCATION", invocation.to_string());
+    // run the bench binary in a cgroup, and/or under cachegrind
+    let runner = get_bench_runner(run, bench, build_name, invocation, log_dir);
+    if !runner.is_empty() {
+        cmd.arg("--config").arg(format!(
+            "target.'cfg(all())'.runner = {}",
+            serde_json::to_string(&runner).unwrap()
+        ));
+    }
+    // pass bench name, and its package in workspace mode
+    if let Some(package) = run.crate_info.package(bench) {
+        cmd.args(["-p", package]);
+    }
     cmd.args(["--bench", bench]);
     // run args
-    cmd.args(["--", "-n"])
-        .arg(format!("{}", run.profile.iterations))
-        .arg("--overwrite-crate-name")
-        .arg(&run.crate_info.name)
-        .arg("--overwrite-benchmark-name")
-        .arg(bench)
-        .arg("--current-invocation")
-        .arg(format!("{invocation}"))
-        .arg("--current-build")
-        .arg(build_name);
-    if let Some(log_dir) = log_dir {
-        cmd.arg("--output-csv").arg(log_dir.join("results.csv"));
+    cmd.arg("--");
+    cmd.args(get_bench_args(
+        run, bench, build_name, invocation, log_dir, output_csv,
+    ));
+    cmd
+}
+
+/// The command to run a bench binary from the build cache directly, the same way as `cargo bench` runs it.
+///
+/// `package_dir` is the directory of the bench's package, which `cargo bench` runs the binary in.
+#[allow(clippy::too_many_arguments)]
+pub fn get_cached_bench_run_command(
+    run: &RunInfo,
+    bench: &str,
+    build_name: &str,
+    binary: &Path,
+    package_dir: Option<&Path>,
+    invocation: usize,
+    log_dir: Option<&Path>,
+    output_csv: Option<&Path>,
+) -> Command {
+    let runner = get_bench_runner(run, bench, build_name, invocation, log_dir);
+    let mut cmd = match runner.split_first() {
+        Some((program, args)) => {
+            let mut cmd = Command::new(program);
+            cmd.args(args).arg(binary);
+            cmd
+        }
+        None => Command::new(binary),
+    };
+    generate_envs(&run.profile, build_name, Some(bench), &mut cmd);
+    // let probes know which invocation they are running in
+    cmd.env("HARNESS_BENCH_BUILD", build_name)
+        .env("HARNESS_BENCH_INVOCATION", invocation.to_string());
+    // the dylibs of the build are copied next to the binary, instead of the target directory that `cargo bench` adds to the search path
+    if let Some(dir) = binary.parent() {
+        let var = build_cache::dylib_path_var();
+        let mut paths = vec![dir.to_owned()];
+        if !run.profile.clean_env || run.profile.keep_env_var(var) {
+            if let Some(value) = std::env::var_os(var) {
+                paths.extend(std::env::split_paths(&value));
+            }
+        }
+        if let Ok(value) = std::env::join_paths(paths) {
+            cmd.env(var, value);
+        }
     }
-    if !run.profile.probes.is_empty() {
-        let probes_json_str = serde_json::to_string(&run.profile.probes).unwrap();
-        cmd.args(["--probes".to_owned(), probes_json_str]);
+    if let Some(dir) = package_dir {
+        cmd.current_dir(dir);
     }
+    cmd.args(get_bench_args(
+        run, bench, build_name, invocation, log_dir, output_csv,
+    ));
+    // `cargo bench` passes `--bench` to the benchmarks
+    cmd.arg("--bench");
     cmd
 }
@@ -0,0 +1,55 @@
+//! Code size of the bench binaries.
+
+use std::{path::Path, process::Command};
+
+use object::{Object, ObjectSection, SectionKind};
+
+/// The total size of the code sections of a binary, e.g. `.text` on ELF or `__text` on Mach-O
+fn text_size(binary: &Path) -> anyhow::Result<u64> {
+    let data = std::fs::read(binary)?;
+    let file = object::File::parse(&*data)?;
+    Ok(file
+        .sections()
+        .filter(|s| s.kind() == SectionKind::Text)
+        .map(|s| s.size())
+        .sum())
+}
+
+/// The size of a binary after stripping its symbols and debug info with the system `strip`.
+/// The stripped copy is written to `scratch_dir`, and deleted afterwards.
+fn stripped_size(binary: &Path, scratch_dir: &Path) -> anyhow::Result<u64> {
+    let stripped = scratch_dir.join(format!(
+        "{}.stripped",
+        binary.file_name().unwrap_or_default().to_string_lossy()
+    ));
+    let status = Command::new("strip")
+        .arg("-o")
+        .arg(&stripped)
+        .arg(binary)
+        .output()?
+        .status;
+    let size = std::fs::metadata(&stripped).map(|m| m.len());
+    let _ = std::fs::remove_file(&stripped);
+    if !status.success() {
+        anyhow::bail!("Failed to strip {}: {}", binary.display(), status);
+    }
+    Ok(size?)
+}
+
+/// Measure the size of a bench binary, in bytes.
+///
+/// Returns the `binary-size`, `binary-size-stripped`, and `text-size` stats.
+/// A stat is left out if it cannot be measured, e.g. without a `strip` command.
+pub fn measure(binary: &Path, scratch_dir: &Path) -> Vec<(String, String)> {
+    let mut stats = vec![];
+    if let Ok(m) = std::fs::metadata(binary) {
+        stats.push(("binary-size".to_owned(), m.len().to_string()));
+    }
+    if let Ok(size) = stripped_size(binary, scratch_dir) {
+        stats.push(("binary-size-stripped".to_owned(), size.to_string()));
+    }
+    if let Ok(size) = text_size(binary) {
+        stats.push(("text-size".to_owned(), size.to_string()));
+    }
+    stats
+}
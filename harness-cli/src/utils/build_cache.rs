@@ -0,0 +1,197 @@
+//! The persistent cache of the bench binaries of each build.
+//!
+//! After a build is compiled, its bench binaries are copied to `target/harness/builds/<FINGERPRINT>/`.
+//! The fingerprint covers everything that changes the compiled binaries: the commit (and the uncommitted changes),
+//! the cargo features and arguments, the environment variables, the toolchain, and the lockfile.
+//! A later build with the same fingerprint, in the same run or in a later one, reuses the cached binaries without checking out the commit or compiling.
+//!
+//! The dylibs that `cargo bench` puts on the library search path, i.e. the dylib probes and the Rust standard library, are copied next to the binaries.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::configs::run_info::RunInfo;
+
+use super::git;
+
+/// The file in a cache entry that lists its bench binaries.
+/// It is written after all the binaries are copied, so an incomplete entry is never used.
+const MANIFEST_FILE: &str = "build.toml";
+
+/// The inputs of a build that determine its bench binaries
+#[derive(Serialize)]
+struct Fingerprint<'a> {
+    commit: String,
+    /// The SHA-256 checksum of `git diff HEAD`, for a dirty worktree
+    diff: Option<String>,
+    features: &'a [String],
+    default_features: bool,
+    cargo_args: &'a [String],
+    packages: Vec<&'a str>,
+    /// The variables of the profile and the build, and the `RUST*` and `CARGO_*` variables of the current environment
+    env: BTreeMap<String, String>,
+    rustc: &'a str,
+    lockfile: Option<&'a toml::Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    /// The file name of each bench binary in the cache entry
+    benches: BTreeMap<String, String>,
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// The environment variable of the dynamic library search path
+pub fn dylib_path_var() -> &'static str {
+    if cfg!(target_os = "windows") {
+        "PATH"
+    } else if cfg!(target_os = "macos") {
+        "DYLD_FALLBACK_LIBRARY_PATH"
+    } else {
+        "LD_LIBRARY_PATH"
+    }
+}
+
+fn is_dylib(path: &Path) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.is_file()
+        && name.starts_with(std::env::consts::DLL_PREFIX)
+        && name.ends_with(std::env::consts::DLL_SUFFIX)
+}
+
+/// The dylibs loaded by the bench binaries at runtime: the ones in the profile directory (e.g. `target/release`),
+/// and the Rust standard library if there are any, since they link to it dynamically
+fn dylibs(executables: &[(String, PathBuf)]) -> anyhow::Result<Vec<PathBuf>> {
+    // the bench binaries are in `<profile dir>/deps`
+    let Some(profile_dir) = executables
+        .first()
+        .and_then(|(_, path)| path.parent()?.parent())
+    else {
+        return Ok(vec![]);
+    };
+    let mut dylibs = std::fs::read_dir(profile_dir)?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| is_dylib(p))
+        .collect::<Vec<_>>();
+    if dylibs.is_empty() {
+        return Ok(dylibs);
+    }
+    let out = Command::new("rustc")
+        .args(["--print", "target-libdir"])
+        .output()?;
+    let libdir = PathBuf::from(String::from_utf8_lossy(&out.stdout).trim());
+    let std_prefix = format!("{}std-", std::env::consts::DLL_PREFIX);
+    for entry in std::fs::read_dir(libdir)?.flatten() {
+        if entry.file_name().to_string_lossy().starts_with(&std_prefix) && is_dylib(&entry.path()) {
+            dylibs.push(entry.path());
+        }
+    }
+    Ok(dylibs)
+}
+
+/// The directory of all the cached builds
+pub fn builds_dir(target_dir: &Path) -> PathBuf {
+    target_dir.join("harness").join("builds")
+}
+
+/// The fingerprint of a build, as a hex string
+pub fn fingerprint(run: &RunInfo, build_name: &str) -> anyhow::Result<String> {
+    let profile = &run.profile;
+    let build = &profile.builds[build_name];
+    let commit = build.commit.as_deref().unwrap_or(run.commit.as_str());
+    let dirty = commit.ends_with("-dirty") && build.version.is_none();
+    let rev = commit.trim_end_matches("-dirty");
+    let diff = if dirty {
+        let out = Command::new("git")
+            .args(["diff", "HEAD", "--binary"])
+            .output()?;
+        Some(sha256_hex(&out.stdout))
+    } else {
+        None
+    };
+    let mut env = std::env::vars()
+        .filter(|(k, _)| k.starts_with("RUST") || k.starts_with("CARGO_"))
+        .filter(|(k, _)| !profile.clean_env || profile.keep_env_var(k))
+        .collect::<BTreeMap<_, _>>();
+    env.extend(profile.env.clone());
+    env.extend(build.env.clone());
+    let fingerprint = Fingerprint {
+        commit: match &build.version {
+            Some(version) => format!("{}@{}", run.crate_info.name, version),
+            None => git::get_branch_last_git_hash(rev)?,
+        },
+        diff,
+        features: &build.features,
+        default_features: build.default_features,
+        cargo_args: &build.cargo_args,
+        packages: run.crate_info.all_packages().into_iter().collect(),
+        env,
+        rustc: &run.system.rustc,
+        lockfile: run
+            .lockfiles
+            .lockfiles
+            .get(rev)
+            .filter(|_| build.version.is_none()),
+    };
+    let hash = sha256_hex(serde_json::to_string(&fingerprint)?.as_bytes());
+    Ok(hash[..16].to_owned())
+}
+
+/// The cached bench binaries of a build, if all of `benches` are cached
+pub fn load(
+    builds_dir: &Path,
+    fingerprint: &str,
+    benches: &[String],
+) -> Option<Vec<(String, PathBuf)>> {
+    let dir = builds_dir.join(fingerprint);
+    let manifest = std::fs::read_to_string(dir.join(MANIFEST_FILE)).ok()?;
+    let manifest = toml::from_str::<Manifest>(&manifest).ok()?;
+    benches
+        .iter()
+        .map(|bench| {
+            let path = dir.join(manifest.benches.get(bench)?);
+            path.exists().then(|| (bench.clone(), path))
+        })
+        .collect()
+}
+
+/// Copy the bench binaries of a build to the cache, and return their cached paths
+pub fn store(
+    builds_dir: &Path,
+    fingerprint: &str,
+    executables: &[(String, PathBuf)],
+) -> anyhow::Result<Vec<(String, PathBuf)>> {
+    let dir = builds_dir.join(fingerprint);
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)?;
+    }
+    std::fs::create_dir_all(&dir)?;
+    let mut manifest = Manifest {
+        benches: BTreeMap::new(),
+    };
+    let mut cached = vec![];
+    for (bench, path) in executables {
+        let file_name = format!("{}{}", bench, std::env::consts::EXE_SUFFIX);
+        std::fs::copy(path, dir.join(&file_name))?;
+        cached.push((bench.clone(), dir.join(&file_name)));
+        manifest.benches.insert(bench.clone(), file_name);
+    }
+    for dylib in dylibs(executables)? {
+        std::fs::copy(&dylib, dir.join(dylib.file_name().unwrap()))?;
+    }
+    std::fs::write(dir.join(MANIFEST_FILE), toml::to_string(&manifest)?)?;
+    Ok(cached)
+}
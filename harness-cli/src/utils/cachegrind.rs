@@ -0,0 +1,44 @@
+use std::path::Path;
+
+/// Check if valgrind is installed
+pub fn valgrind_exists() -> bool {
+    std::process::Command::new("valgrind")
+        .arg("--version")
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+/// The command prefix to run the bench binary under cachegrind
+pub fn runner_args(out_file: &Path) -> Vec<String> {
+    vec![
+        "valgrind".to_owned(),
+        "--tool=cachegrind".to_owned(),
+        "--cache-sim=yes".to_owned(),
+        format!("--cachegrind-out-file={}", out_file.display()),
+    ]
+}
+
+/// Parse the `events:` and `summary:` lines of a cachegrind output file.
+///
+/// Returns the event counts, with the event names prefixed by `cachegrind-`. e.g. `cachegrind-Ir`.
+pub fn parse_summary(out_file: &Path) -> anyhow::Result<Vec<(String, String)>> {
+    let content = std::fs::read_to_string(out_file)?;
+    let find = |key: &str| {
+        content
+            .lines()
+            .find_map(|l| l.strip_prefix(key))
+            .map(|l| l.split_whitespace().collect::<Vec<_>>())
+    };
+    let Some(events) = find("events:") else {
+        anyhow::bail!("No events found in {}", out_file.display());
+    };
+    let Some(summary) = find("summary:") else {
+        anyhow::bail!("No summary found in {}", out_file.display());
+    };
+    Ok(events
+        .iter()
+        .zip(summary.iter())
+        .map(|(e, v)| (format!("cachegrind-{}", e), v.to_string()))
+        .collect())
+}
@@ -0,0 +1,45 @@
+use crate::configs::harness::CgroupConfig;
+
+/// Check if systemd-run is installed
+pub fn systemd_run_exists() -> bool {
+    std::process::Command::new("systemd-run")
+        .arg("--version")
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+/// Check that a memory size is accepted by systemd, e.g. `512M`, `4G`, or `infinity`
+pub fn validate_memory_size(size: &str) -> anyhow::Result<()> {
+    let digits = size.trim_end_matches(['K', 'M', 'G', 'T', 'k', 'm', 'g', 't']);
+    if size == "infinity" || (size.len() - digits.len() <= 1 && digits.parse::<u64>().is_ok()) {
+        Ok(())
+    } else {
+        anyhow::bail!("Invalid memory size: `{}`", size)
+    }
+}
+
+/// The command prefix to run the bench binary in a transient cgroup with the given limits.
+///
+/// Memory swapping is disabled, so that the memory limit is a hard budget.
+pub fn runner_args(config: &CgroupConfig) -> Vec<String> {
+    let mut args = vec!["systemd-run".to_owned()];
+    #[cfg(target_os = "linux")]
+    // SAFETY: `geteuid` is always successful.
+    if unsafe { libc::geteuid() } != 0 {
+        args.push("--user".to_owned());
+    }
+    args.extend(["--scope", "--quiet", "--collect"].map(|s| s.to_owned()));
+    if let Some(memory) = &config.memory_max {
+        args.push(format!("--property=MemoryMax={}", memory));
+        args.push("--property=MemorySwapMax=0".to_owned());
+    }
+    if let Some(cpus) = config.cpu_quota {
+        args.push(format!(
+            "--property=CPUQuota={}%",
+            (cpus * 100.0).round() as u64
+        ));
+    }
+    args.push("--".to_owned());
+    args
+}
@@ -0,0 +1,369 @@
+/// Colors of the builds in the charts
+const PALETTE: &[&str] = &[
+    "#4c72b0", "#dd8452", "#55a868", "#c44e52", "#8172b3", "#937860", "#da8bc3", "#8c8c8c",
+];
+
+/// Escape a string for HTML or SVG text
+pub fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A "nice" tick step for the axis range `[0, max]`
+fn tick_step(max: f64) -> f64 {
+    let raw = max / 5.0;
+    let magnitude = 10f64.powf(raw.log10().floor());
+    let normalized = raw / magnitude;
+    let nice = if normalized <= 1.0 {
+        1.0
+    } else if normalized <= 2.0 {
+        2.0
+    } else if normalized <= 5.0 {
+        5.0
+    } else {
+        10.0
+    };
+    nice * magnitude
+}
+
+/// Format an axis tick label without trailing zeros
+fn tick_label(tick: f64) -> String {
+    format!("{:.3}", tick)
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_owned()
+}
+
+/// A bar with an error bar
+#[derive(Debug, Clone, Copy)]
+pub struct Bar {
+    pub value: f64,
+    /// The `(low, high)` end of the error bar
+    pub error: Option<(f64, f64)>,
+}
+
+/// A grouped bar chart, with one group per benchmark and one bar per build in each group.
+#[derive(Debug, Clone)]
+pub struct BarChart {
+    pub title: String,
+    pub y_label: String,
+    /// The name of each bar series, e.g. builds
+    pub series: Vec<String>,
+    /// The name of each group and the bar of each series. `None` for a missing bar.
+    pub groups: Vec<(String, Vec<Option<Bar>>)>,
+    /// Draw a dashed horizontal reference line at this value, e.g. `1.0` for normalized values
+    pub reference: Option<f64>,
+}
+
+impl BarChart {
+    const HEIGHT: f64 = 360.0;
+    const MARGIN_LEFT: f64 = 70.0;
+    const MARGIN_TOP: f64 = 40.0;
+    const MARGIN_BOTTOM: f64 = 90.0;
+    const BAR_WIDTH: f64 = 18.0;
+    const GROUP_GAP: f64 = 24.0;
+    const LEGEND_WIDTH: f64 = 160.0;
+
+    /// Render the chart as a standalone SVG document
+    pub fn to_svg(&self) -> String {
+        let group_width = self.series.len().max(1) as f64 * Self::BAR_WIDTH + Self::GROUP_GAP;
+        let plot_width = (self.groups.len().max(1) as f64 * group_width).max(200.0);
+        let plot_height = Self::HEIGHT - Self::MARGIN_TOP - Self::MARGIN_BOTTOM;
+        let width = Self::MARGIN_LEFT + plot_width + Self::LEGEND_WIDTH;
+        let max = self
+            .groups
+            .iter()
+            .flat_map(|(_, bars)| bars.iter().flatten())
+            .map(|b| b.error.map_or(b.value, |(_, hi)| hi.max(b.value)))
+            .chain(self.reference)
+            .filter(|v| v.is_finite())
+            .fold(0.0, f64::max);
+        let step = if max > 0.0 { tick_step(max) } else { 1.0 };
+        let y_max = (max / step).ceil().max(1.0) * step;
+        let y = |v: f64| Self::MARGIN_TOP + plot_height * (1.0 - (v / y_max).clamp(0.0, 1.0));
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" viewBox=\"0 0 {:.0} {:.0}\" font-family=\"sans-serif\" font-size=\"12\">\n",
+            width,
+            Self::HEIGHT,
+            width,
+            Self::HEIGHT
+        );
+        svg += &format!(
+            "<rect width=\"{:.0}\" height=\"{:.0}\" fill=\"white\"/>\n",
+            width,
+            Self::HEIGHT
+        );
+        svg += &format!(
+            "<text x=\"{:.1}\" y=\"20\" font-size=\"14\" font-weight=\"bold\">{}</text>\n",
+            Self::MARGIN_LEFT,
+            escape(&self.title)
+        );
+        // Y axis, ticks, and grid lines
+        let mut tick = 0.0;
+        while tick <= y_max + step / 2.0 {
+            svg += &format!(
+                "<line x1=\"{:.1}\" x2=\"{:.1}\" y1=\"{:.1}\" y2=\"{:.1}\" stroke=\"#e0e0e0\"/>\n",
+                Self::MARGIN_LEFT,
+                Self::MARGIN_LEFT + plot_width,
+                y(tick),
+                y(tick)
+            );
+            svg += &format!(
+                "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>\n",
+                Self::MARGIN_LEFT - 6.0,
+                y(tick) + 4.0,
+                tick_label(tick)
+            );
+            tick += step;
+        }
+        svg += &format!(
+            "<text transform=\"translate(16,{:.1}) rotate(-90)\" text-anchor=\"middle\">{}</text>\n",
+            Self::MARGIN_TOP + plot_height / 2.0,
+            escape(&self.y_label)
+        );
+        // Bars
+        for (i, (group, bars)) in self.groups.iter().enumerate() {
+            let x0 = Self::MARGIN_LEFT + i as f64 * group_width + Self::GROUP_GAP / 2.0;
+            for (j, bar) in bars.iter().enumerate() {
+                let Some(bar) = bar.filter(|b| b.value.is_finite()) else {
+                    continue;
+                };
+                let x = x0 + j as f64 * Self::BAR_WIDTH;
+                let color = PALETTE[j % PALETTE.len()];
+                svg += &format!(
+                    "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"><title>{}: {:.3}</title></rect>\n",
+                    x,
+                    y(bar.value),
+                    Self::BAR_WIDTH - 2.0,
+                    y(0.0) - y(bar.value),
+                    color,
+                    escape(&self.series[j]),
+                    bar.value
+                );
+                if let Some((lo, hi)) = bar.error {
+                    let cx = x + (Self::BAR_WIDTH - 2.0) / 2.0;
+                    svg += &format!(
+                        "<path d=\"M{cx:.1} {:.1}V{:.1}M{:.1} {:.1}H{:.1}M{:.1} {:.1}H{:.1}\" stroke=\"black\"/>\n",
+                        y(lo),
+                        y(hi),
+                        cx - 4.0,
+                        y(lo),
+                        cx + 4.0,
+                        cx - 4.0,
+                        y(hi),
+                        cx + 4.0
+                    );
+                }
+            }
+            let label_x = x0 + bars.len() as f64 * Self::BAR_WIDTH / 2.0;
+            svg += &format!(
+                "<text transform=\"translate({:.1},{:.1}) rotate(-40)\" text-anchor=\"end\">{}</text>\n",
+                label_x,
+                y(0.0) + 14.0,
+                escape(group)
+            );
+        }
+        if let Some(r) = self.reference {
+            svg += &format!(
+                "<line x1=\"{:.1}\" x2=\"{:.1}\" y1=\"{:.1}\" y2=\"{:.1}\" stroke=\"#555\" stroke-dasharray=\"4 3\"/>\n",
+                Self::MARGIN_LEFT,
+                Self::MARGIN_LEFT + plot_width,
+                y(r),
+                y(r)
+            );
+        }
+        svg += &format!(
+            "<line x1=\"{:.1}\" x2=\"{:.1}\" y1=\"{:.1}\" y2=\"{:.1}\" stroke=\"black\"/>\n",
+            Self::MARGIN_LEFT,
+            Self::MARGIN_LEFT + plot_width,
+            y(0.0),
+            y(0.0)
+        );
+        // Legend
+        for (j, name) in self.series.iter().enumerate() {
+            let lx = Self::MARGIN_LEFT + plot_width + 16.0;
+            let ly = Self::MARGIN_TOP + j as f64 * 18.0;
+            svg += &format!(
+                "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"12\" height=\"12\" fill=\"{}\"/><text x=\"{:.1}\" y=\"{:.1}\">{}</text>\n",
+                lx,
+                ly,
+                PALETTE[j % PALETTE.len()],
+                lx + 18.0,
+                ly + 10.0,
+                escape(name)
+            );
+        }
+        svg += "</svg>\n";
+        svg
+    }
+}
+
+/// A line chart with categorical x values, e.g. the thread counts of a sweep, and one line per series.
+#[derive(Debug, Clone)]
+pub struct LineChart {
+    pub title: String,
+    pub x_label: String,
+    pub y_label: String,
+    /// The labels of the x values, evenly spaced
+    pub x: Vec<String>,
+    /// The name of each line and its value at each x. `None` for a missing point.
+    pub series: Vec<(String, Vec<Option<f64>>)>,
+    /// Draw a dashed horizontal reference line at this value, e.g. `1.0` for normalized values
+    pub reference: Option<f64>,
+}
+
+impl LineChart {
+    const HEIGHT: f64 = 360.0;
+    const MARGIN_LEFT: f64 = 70.0;
+    const MARGIN_TOP: f64 = 40.0;
+    const MARGIN_BOTTOM: f64 = 50.0;
+    const X_STEP: f64 = 80.0;
+    const LEGEND_WIDTH: f64 = 220.0;
+
+    /// Render the chart as a standalone SVG document
+    pub fn to_svg(&self) -> String {
+        let plot_width = (self.x.len().saturating_sub(1) as f64 * Self::X_STEP).max(200.0);
+        let plot_height = Self::HEIGHT - Self::MARGIN_TOP - Self::MARGIN_BOTTOM;
+        let width = Self::MARGIN_LEFT + plot_width + Self::LEGEND_WIDTH;
+        let max = self
+            .series
+            .iter()
+            .flat_map(|(_, values)| values.iter().flatten().copied())
+            .chain(self.reference)
+            .filter(|v| v.is_finite())
+            .fold(0.0, f64::max);
+        let step = if max > 0.0 { tick_step(max) } else { 1.0 };
+        let y_max = (max / step).ceil().max(1.0) * step;
+        let y = |v: f64| Self::MARGIN_TOP + plot_height * (1.0 - (v / y_max).clamp(0.0, 1.0));
+        let x = |i: usize| {
+            if self.x.len() > 1 {
+                Self::MARGIN_LEFT + plot_width * i as f64 / (self.x.len() - 1) as f64
+            } else {
+                Self::MARGIN_LEFT + plot_width / 2.0
+            }
+        };
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" viewBox=\"0 0 {:.0} {:.0}\" font-family=\"sans-serif\" font-size=\"12\">\n",
+            width,
+            Self::HEIGHT,
+            width,
+            Self::HEIGHT
+        );
+        svg += &format!(
+            "<rect width=\"{:.0}\" height=\"{:.0}\" fill=\"white\"/>\n",
+            width,
+            Self::HEIGHT
+        );
+        svg += &format!(
+            "<text x=\"{:.1}\" y=\"20\" font-size=\"14\" font-weight=\"bold\">{}</text>\n",
+            Self::MARGIN_LEFT,
+            escape(&self.title)
+        );
+        // Y axis, ticks, and grid lines
+        let mut tick = 0.0;
+        while tick <= y_max + step / 2.0 {
+            svg += &format!(
+                "<line x1=\"{:.1}\" x2=\"{:.1}\" y1=\"{:.1}\" y2=\"{:.1}\" stroke=\"#e0e0e0\"/>\n",
+                Self::MARGIN_LEFT,
+                Self::MARGIN_LEFT + plot_width,
+                y(tick),
+                y(tick)
+            );
+            svg += &format!(
+                "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>\n",
+                Self::MARGIN_LEFT - 6.0,
+                y(tick) + 4.0,
+                tick_label(tick)
+            );
+            tick += step;
+        }
+        svg += &format!(
+            "<text transform=\"translate(16,{:.1}) rotate(-90)\" text-anchor=\"middle\">{}</text>\n",
+            Self::MARGIN_TOP + plot_height / 2.0,
+            escape(&self.y_label)
+        );
+        // X ticks
+        for (i, label) in self.x.iter().enumerate() {
+            svg += &format!(
+                "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>\n",
+                x(i),
+                y(0.0) + 16.0,
+                escape(label)
+            );
+        }
+        svg += &format!(
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>\n",
+            Self::MARGIN_LEFT + plot_width / 2.0,
+            y(0.0) + 36.0,
+            escape(&self.x_label)
+        );
+        if let Some(r) = self.reference {
+            svg += &format!(
+                "<line x1=\"{:.1}\" x2=\"{:.1}\" y1=\"{:.1}\" y2=\"{:.1}\" stroke=\"#555\" stroke-dasharray=\"4 3\"/>\n",
+                Self::MARGIN_LEFT,
+                Self::MARGIN_LEFT + plot_width,
+                y(r),
+                y(r)
+            );
+        }
+        svg += &format!(
+            "<line x1=\"{:.1}\" x2=\"{:.1}\" y1=\"{:.1}\" y2=\"{:.1}\" stroke=\"black\"/>\n",
+            Self::MARGIN_LEFT,
+            Self::MARGIN_LEFT + plot_width,
+            y(0.0),
+            y(0.0)
+        );
+        // Lines, broken at the missing points
+        for (j, (name, values)) in self.series.iter().enumerate() {
+            let color = PALETTE[j % PALETTE.len()];
+            let mut path = String::new();
+            let mut pen_down = false;
+            for (i, v) in values.iter().enumerate() {
+                let Some(v) = v.filter(|v| v.is_finite()) else {
+                    pen_down = false;
+                    continue;
+                };
+                path += &format!(
+                    "{}{:.1} {:.1}",
+                    if pen_down { "L" } else { "M" },
+                    x(i),
+                    y(v)
+                );
+                pen_down = true;
+                svg += &format!(
+                    "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"3\" fill=\"{}\"><title>{} @ {}: {:.3}</title></circle>\n",
+                    x(i),
+                    y(v),
+                    color,
+                    escape(name),
+                    escape(&self.x[i]),
+                    v
+                );
+            }
+            if !path.is_empty() {
+                svg += &format!(
+                    "<path d=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\"/>\n",
+                    path, color
+                );
+            }
+        }
+        // Legend
+        for (j, (name, _)) in self.series.iter().enumerate() {
+            let lx = Self::MARGIN_LEFT + plot_width + 16.0;
+            let ly = Self::MARGIN_TOP + j as f64 * 18.0;
+            svg += &format!(
+                "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"12\" height=\"12\" fill=\"{}\"/><text x=\"{:.1}\" y=\"{:.1}\">{}</text>\n",
+                lx,
+                ly,
+                PALETTE[j % PALETTE.len()],
+                lx + 18.0,
+                ly + 10.0,
+                escape(name)
+            );
+        }
+        svg += "</svg>\n";
+        svg
+    }
+}
@@ -0,0 +1,78 @@
+//! Checksums of run artifacts.
+
+use std::{
+    collections::BTreeMap,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+
+/// Compute the SHA-256 checksum of a file, as a lowercase hex string.
+pub fn sha256_file(path: &Path) -> anyhow::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1 << 16];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// Find the bench executables in the stderr output of `cargo bench --no-run`.
+///
+/// Cargo reports each of them as `Executable benches/<bench>.rs (<path>)`.
+pub fn bench_executables(cargo_stderr: &str) -> Vec<(String, PathBuf)> {
+    cargo_stderr
+        .lines()
+        .filter_map(|l| {
+            let l = l.trim().strip_prefix("Executable ")?;
+            // Skip the unit test executables of the lib and bin targets
+            if l.starts_with("unittests ") {
+                return None;
+            }
+            let (src, path) = l.split_once(" (")?;
+            let bench = Path::new(src.trim()).file_stem()?.to_string_lossy();
+            Some((
+                bench.into_owned(),
+                PathBuf::from(path.trim_end_matches(')')),
+            ))
+        })
+        .collect()
+}
+
+/// The file recording the checksums of the bench binaries of each build
+pub fn binaries_file(log_dir: &Path) -> PathBuf {
+    log_dir.join("binaries.toml")
+}
+
+/// Record the checksums of the bench binaries of a build to `<log_dir>/binaries.toml`.
+pub fn record_binaries(
+    log_dir: &Path,
+    build: &str,
+    executables: &[(String, PathBuf)],
+) -> anyhow::Result<()> {
+    let file = binaries_file(log_dir);
+    let mut binaries: BTreeMap<String, BTreeMap<String, String>> = if file.exists() {
+        toml::from_str(&std::fs::read_to_string(&file)?)?
+    } else {
+        BTreeMap::new()
+    };
+    let hashes = binaries.entry(build.to_owned()).or_default();
+    for (bench, path) in executables {
+        if let Ok(hash) = sha256_file(path) {
+            hashes.insert(bench.clone(), hash);
+        }
+    }
+    std::fs::create_dir_all(log_dir)?;
+    std::fs::write(&file, toml::to_string(&binaries)?)?;
+    Ok(())
+}
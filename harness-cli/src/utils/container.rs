@@ -0,0 +1,188 @@
+use std::{
+    path::Path,
+    process::Command,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::configs::{
+    harness::CgroupConfig,
+    run_info::{ContainerInfo, RunInfo},
+};
+
+/// The supported container engines, in the order of preference
+const ENGINES: [&str; 2] = ["docker", "podman"];
+
+/// Find an installed container engine
+fn find_engine() -> Option<&'static str> {
+    ENGINES.into_iter().find(|engine| {
+        Command::new(engine)
+            .arg("--version")
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+    })
+}
+
+/// Get the digest of a local image, e.g. `rust@sha256:...`.
+/// Images that are built locally and never pushed are pinned by their image id instead.
+fn inspect_digest(engine: &str, image: &str) -> Option<String> {
+    let out = Command::new(engine)
+        .args([
+            "image",
+            "inspect",
+            "--format",
+            "{{if .RepoDigests}}{{index .RepoDigests 0}}{{else}}{{.Id}}{{end}}",
+            image,
+        ])
+        .output()
+        .ok()?;
+    let digest = String::from_utf8_lossy(&out.stdout).trim().to_owned();
+    (out.status.success() && !digest.is_empty()).then_some(digest)
+}
+
+fn pull(engine: &str, image: &str) -> anyhow::Result<()> {
+    let status = Command::new(engine)
+        .args(["pull", image])
+        .status()
+        .map_err(|e| anyhow::anyhow!("Failed to run {}: {}", engine, e))?;
+    if !status.success() {
+        anyhow::bail!("Failed to pull container image `{}`", image);
+    }
+    Ok(())
+}
+
+/// Pull the image if it's not available locally, and pin it by its digest
+pub fn resolve(image: &str) -> anyhow::Result<ContainerInfo> {
+    let Some(engine) = find_engine() else {
+        anyhow::bail!("Container runs require docker or podman, but neither is installed.");
+    };
+    if inspect_digest(engine, image).is_none() {
+        pull(engine, image)?;
+    }
+    let Some(digest) = inspect_digest(engine, image) else {
+        anyhow::bail!("Failed to get the digest of container image `{}`", image);
+    };
+    Ok(ContainerInfo {
+        engine: engine.to_owned(),
+        image: image.to_owned(),
+        digest,
+    })
+}
+
+/// Make the pinned image of a previous run available, pulling it by its digest if needed
+pub fn prepare(container: &ContainerInfo) -> anyhow::Result<()> {
+    if inspect_digest(&container.engine, &container.digest).is_none() {
+        pull(&container.engine, &container.digest)?;
+    }
+    Ok(())
+}
+
+/// Resource limits of a container
+#[derive(Default)]
+pub struct Limits<'a> {
+    /// The CPUs that the container can run on
+    pub cpus: Option<&'a [usize]>,
+    /// The NUMA node that the memory is allocated from
+    pub numa_node: Option<usize>,
+    /// The memory and CPU quota limits
+    pub cgroup: Option<&'a CgroupConfig>,
+}
+
+/// Wrap a command to run inside the container of the run.
+///
+/// The workspace and the target directory are mounted at the same paths, so that all the paths in the command remain valid.
+/// Only the environment variables set on the command and the `HARNESS_BENCH_*` variables are passed to the container.
+///
+/// With `interactive`, the stdin of the new command is forwarded to the command in the container.
+///
+/// Returns the new command, and the container name.
+pub fn wrap(cmd: &Command, run: &RunInfo, limits: Limits, interactive: bool) -> (Command, String) {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let container = run.container.as_ref().unwrap();
+    let name = format!(
+        "harness-{}-{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::SeqCst)
+    );
+    let mut c = Command::new(&container.engine);
+    c.args(["run", "--rm", "--init", "--name", &name]);
+    if interactive {
+        c.arg("--interactive");
+    }
+    // Create files in the mounted directories as the current user
+    #[cfg(unix)]
+    if container.engine == "podman" {
+        c.arg("--userns=keep-id");
+    } else {
+        // SAFETY: `getuid` and `getgid` are always successful.
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+        c.args(["--user", &format!("{}:{}", uid, gid)]);
+    }
+    let mut mount = |dir: &Path| {
+        let dir = dir.display().to_string();
+        c.args(["--volume", &format!("{}:{}", dir, dir)]);
+    };
+    let crate_info = &run.crate_info;
+    mount(&crate_info.workspace_root);
+    if !crate_info
+        .target_dir
+        .starts_with(&crate_info.workspace_root)
+    {
+        mount(&crate_info.target_dir);
+    }
+    let cwd = cmd
+        .get_current_dir()
+        .map(|d| d.to_owned())
+        .or_else(|| std::env::current_dir().ok());
+    if let Some(cwd) = cwd {
+        c.args(["--workdir", &cwd.display().to_string()]);
+    }
+    // Keep the downloaded crates across invocations
+    let cargo_home = crate_info
+        .target_dir
+        .join("harness")
+        .join("container")
+        .join("cargo-home");
+    c.args(["--env", &format!("CARGO_HOME={}", cargo_home.display())]);
+    let mut envs = std::env::vars()
+        .filter(|(k, _)| k.starts_with("HARNESS_BENCH_"))
+        .collect::<std::collections::BTreeMap<_, _>>();
+    for (k, v) in cmd.get_envs() {
+        let k = k.to_string_lossy().into_owned();
+        match v {
+            Some(v) => envs.insert(k, v.to_string_lossy().into_owned()),
+            None => envs.remove(&k),
+        };
+    }
+    for (k, v) in envs {
+        c.args(["--env", &format!("{}={}", k, v)]);
+    }
+    // Resource limits
+    if let Some(cpus) = limits.cpus {
+        let cpus = cpus.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        c.arg(format!("--cpuset-cpus={}", cpus.join(",")));
+    }
+    if let Some(node) = limits.numa_node {
+        c.arg(format!("--cpuset-mems={}", node));
+    }
+    if let Some(cgroup) = limits.cgroup {
+        if let Some(memory) = cgroup.memory_max.as_ref().filter(|m| *m != "infinity") {
+            c.arg(format!("--memory={}", memory));
+            c.arg(format!("--memory-swap={}", memory));
+        }
+        if let Some(cpus) = cgroup.cpu_quota {
+            c.arg(format!("--cpus={}", cpus));
+        }
+    }
+    c.arg(&container.digest);
+    c.arg(cmd.get_program());
+    c.args(cmd.get_args());
+    (c, name)
+}
+
+/// Kill a running container, e.g. after its invocation timed out
+pub fn kill(container: &ContainerInfo, name: &str) {
+    let _ = Command::new(&container.engine)
+        .args(["kill", name])
+        .output();
+}
@@ -0,0 +1,124 @@
+use std::{
+    sync::mpsc::{self, RecvTimeoutError, Sender},
+    thread::JoinHandle,
+    time::Duration,
+};
+
+/// The sampling interval of the CPU monitor
+const INTERVAL: Duration = Duration::from_millis(100);
+
+/// A background thread that samples the frequency of the given CPUs during an invocation,
+/// and counts the thermal throttling events that happened in the meantime.
+///
+/// Both are read from sysfs, and are only available on Linux.
+pub struct CpuMonitor {
+    cpus: Vec<usize>,
+    throttles_at_start: Option<u64>,
+    stop: Sender<()>,
+    handle: JoinHandle<Vec<f64>>,
+}
+
+impl CpuMonitor {
+    pub fn start(cpus: Vec<usize>) -> Self {
+        let (stop, rx) = mpsc::channel();
+        let throttles_at_start = get_throttle_count(&cpus);
+        let sampled_cpus = cpus.clone();
+        let handle = std::thread::spawn(move || {
+            let mut samples = vec![];
+            loop {
+                samples.extend(sampled_cpus.iter().filter_map(|c| get_cpu_frequency(*c)));
+                match rx.recv_timeout(INTERVAL) {
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    _ => break,
+                }
+            }
+            samples
+        });
+        Self {
+            cpus,
+            throttles_at_start,
+            stop,
+            handle,
+        }
+    }
+
+    /// Stop sampling, and return the `cpu-freq-min`, `cpu-freq-mean`, `cpu-freq-max` (in MHz), and `thermal-throttles` stats.
+    /// Stats that are not available on this machine are omitted.
+    pub fn stop(self) -> Vec<(String, String)> {
+        let _ = self.stop.send(());
+        let samples = self.handle.join().unwrap_or_default();
+        let mut stats = vec![];
+        if !samples.is_empty() {
+            let min = samples.iter().cloned().fold(f64::INFINITY, f64::min);
+            let max = samples.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+            stats.push(("cpu-freq-min".to_owned(), format!("{:.0}", min)));
+            stats.push(("cpu-freq-mean".to_owned(), format!("{:.0}", mean)));
+            stats.push(("cpu-freq-max".to_owned(), format!("{:.0}", max)));
+        }
+        if let (Some(start), Some(end)) = (self.throttles_at_start, get_throttle_count(&self.cpus))
+        {
+            stats.push((
+                "thermal-throttles".to_owned(),
+                end.saturating_sub(start).to_string(),
+            ));
+        }
+        stats
+    }
+}
+
+/// All online CPUs listed in sysfs
+pub fn get_all_cpus() -> Vec<usize> {
+    let Ok(entries) = std::fs::read_dir("/sys/devices/system/cpu/") else {
+        return vec![];
+    };
+    let mut cpus = entries
+        .flatten()
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().into_owned();
+            name.strip_prefix("cpu")?.parse::<usize>().ok()
+        })
+        .collect::<Vec<_>>();
+    cpus.sort();
+    cpus
+}
+
+/// Returns true if the CPU frequency can be sampled on this machine
+pub fn cpu_frequency_available() -> bool {
+    get_all_cpus()
+        .iter()
+        .any(|c| get_cpu_frequency(*c).is_some())
+}
+
+/// The current frequency of a CPU in MHz
+fn get_cpu_frequency(cpu: usize) -> Option<f64> {
+    let path = format!(
+        "/sys/devices/system/cpu/cpu{}/cpufreq/scaling_cur_freq",
+        cpu
+    );
+    let khz = std::fs::read_to_string(path)
+        .ok()?
+        .trim()
+        .parse::<f64>()
+        .ok()?;
+    Some(khz / 1000.0)
+}
+
+/// The total number of core and package thermal throttling events of the given CPUs
+fn get_throttle_count(cpus: &[usize]) -> Option<u64> {
+    let mut total = None;
+    for cpu in cpus {
+        for kind in ["core", "package"] {
+            let path = format!(
+                "/sys/devices/system/cpu/cpu{}/thermal_throttle/{}_throttle_count",
+                cpu, kind
+            );
+            if let Ok(count) = std::fs::read_to_string(path) {
+                if let Ok(count) = count.trim().parse::<u64>() {
+                    *total.get_or_insert(0) += count;
+                }
+            }
+        }
+    }
+    total
+}
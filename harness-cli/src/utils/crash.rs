@@ -0,0 +1,190 @@
+//! Diagnostics of crashed invocations.
+//!
+//! When an invocation is killed by a signal, the signal, the exit status, the last lines of its outputs,
+//! and its core dump (with `core-dumps` enabled) are saved to `target/harness/logs/<RUNID>/<bench>.<build>.<invocation>.crash.toml`.
+
+use std::{
+    path::{Path, PathBuf},
+    process::ExitStatus,
+};
+
+use serde::Serialize;
+
+/// The number of lines at the end of the invocation outputs to keep in the crash report
+const LOG_TAIL_LINES: usize = 50;
+
+/// The diagnostics of a crashed invocation
+#[derive(Debug, Serialize)]
+pub struct CrashReport {
+    pub bench: String,
+    pub build: String,
+    pub invocation: usize,
+    /// The signal that killed the benchmark process, e.g. `SIGSEGV (11)`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signal: Option<String>,
+    #[serde(rename = "exit-status")]
+    pub exit_status: String,
+    /// The collected core dump, if any
+    #[serde(rename = "core-dump", skip_serializing_if = "Option::is_none")]
+    pub core_dump: Option<PathBuf>,
+    /// Why the core dump was not collected, if core dumps are enabled
+    #[serde(rename = "core-dump-error", skip_serializing_if = "Option::is_none")]
+    pub core_dump_error: Option<String>,
+    /// The last lines of the outputs of the invocation
+    #[serde(rename = "log-tail")]
+    pub log_tail: String,
+}
+
+impl CrashReport {
+    pub fn new(
+        bench: &str,
+        build: &str,
+        invocation: usize,
+        status: Option<&ExitStatus>,
+        log: &str,
+    ) -> Self {
+        #[cfg(unix)]
+        let signal = status.and_then(std::os::unix::process::ExitStatusExt::signal);
+        #[cfg(not(unix))]
+        let signal = None;
+        let lines = log.lines().collect::<Vec<_>>();
+        Self {
+            bench: bench.to_owned(),
+            build: build.to_owned(),
+            invocation,
+            signal: signal_name(signal, log),
+            exit_status: status.map_or("none".to_owned(), |s| s.to_string()),
+            core_dump: None,
+            core_dump_error: None,
+            log_tail: lines[lines.len().saturating_sub(LOG_TAIL_LINES)..].join("\n"),
+        }
+    }
+
+    /// Write the report to `<bench>.<build>.<invocation>.crash.toml` in the log directory
+    pub fn save(&self, log_dir: &Path) -> anyhow::Result<PathBuf> {
+        let file = log_dir.join(format!(
+            "{}.{}.{}.crash.toml",
+            self.bench, self.build, self.invocation
+        ));
+        std::fs::write(&file, toml::to_string(self)?)?;
+        Ok(file)
+    }
+}
+
+/// The name and number of the signal that killed the benchmark, e.g. `SIGSEGV (11)`.
+///
+/// The benchmark is usually a child of `cargo bench`, which exits normally and reports the signal in its outputs instead.
+fn signal_name(signal: Option<i32>, log: &str) -> Option<String> {
+    let re = regex::Regex::new(r"\(signal: (\d+), (SIG[A-Z0-9]+)").unwrap();
+    if let Some(c) = re.captures_iter(log).last() {
+        return Some(format!("{} ({})", &c[2], &c[1]));
+    }
+    let signal = signal?;
+    #[cfg(target_os = "linux")]
+    let name = match signal {
+        libc::SIGHUP => Some("SIGHUP"),
+        libc::SIGINT => Some("SIGINT"),
+        libc::SIGQUIT => Some("SIGQUIT"),
+        libc::SIGILL => Some("SIGILL"),
+        libc::SIGTRAP => Some("SIGTRAP"),
+        libc::SIGABRT => Some("SIGABRT"),
+        libc::SIGBUS => Some("SIGBUS"),
+        libc::SIGFPE => Some("SIGFPE"),
+        libc::SIGKILL => Some("SIGKILL"),
+        libc::SIGSEGV => Some("SIGSEGV"),
+        libc::SIGPIPE => Some("SIGPIPE"),
+        libc::SIGTERM => Some("SIGTERM"),
+        _ => None,
+    };
+    #[cfg(not(target_os = "linux"))]
+    let name = None::<&str>;
+    Some(match name {
+        Some(name) => format!("{} ({})", name, signal),
+        None => format!("signal {}", signal),
+    })
+}
+
+/// Raise the core file size limit of the command to the hard limit, so a crash leaves a core dump.
+/// The limit is inherited by `cargo bench` and the bench binary it spawns.
+#[cfg(target_os = "linux")]
+pub fn enable_core_dumps(cmd: &mut std::process::Command) {
+    use std::os::unix::process::CommandExt;
+    // SAFETY: Only async-signal-safe syscalls are made between fork and exec.
+    unsafe {
+        cmd.pre_exec(|| {
+            let mut limit = std::mem::MaybeUninit::<libc::rlimit>::zeroed();
+            if libc::getrlimit(libc::RLIMIT_CORE, limit.as_mut_ptr()) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            let mut limit = limit.assume_init();
+            limit.rlim_cur = limit.rlim_max;
+            if libc::setrlimit(libc::RLIMIT_CORE, &limit) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+}
+
+/// Move the core dump of a crash after `since` to `dest`, following `/proc/sys/kernel/core_pattern`.
+///
+/// Core files written by a plain pattern are looked up in the pattern's directory, or the current directory for a relative pattern.
+/// Core dumps piped to `systemd-coredump` are exported with `coredumpctl`.
+#[cfg(target_os = "linux")]
+pub fn collect_core_dump(since: std::time::SystemTime, dest: &Path) -> anyhow::Result<PathBuf> {
+    let pattern = std::fs::read_to_string("/proc/sys/kernel/core_pattern")?;
+    let pattern = pattern.trim();
+    if let Some(handler) = pattern.strip_prefix('|') {
+        if !handler.contains("systemd-coredump") {
+            anyhow::bail!(
+                "Core dumps are piped to an unsupported handler: {}",
+                handler
+            );
+        }
+        let since = since.duration_since(std::time::UNIX_EPOCH)?.as_secs();
+        let status = std::process::Command::new("coredumpctl")
+            .arg("dump")
+            .arg("--quiet")
+            .arg(format!("--since=@{}", since))
+            .arg("--output")
+            .arg(dest)
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()?;
+        if !status.success() || !dest.exists() {
+            anyhow::bail!("No core dump found by coredumpctl");
+        }
+        return Ok(dest.to_owned());
+    }
+    let pattern = Path::new(pattern);
+    let dir = match pattern.parent() {
+        Some(p) if pattern.is_absolute() => p.to_owned(),
+        _ => PathBuf::from("."),
+    };
+    // The literal part of the file name, before the first `%` specifier
+    let file_name = pattern.file_name().unwrap_or_default().to_string_lossy();
+    let prefix = file_name.split('%').next().unwrap_or_default();
+    if prefix.is_empty() {
+        anyhow::bail!("Unsupported core pattern: {}", pattern.display());
+    }
+    let core = std::fs::read_dir(&dir)?
+        .flatten()
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_file()))
+        .filter(|e| e.file_name().to_string_lossy().starts_with(prefix))
+        .filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+        .filter(|(modified, _)| *modified >= since)
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path);
+    let Some(core) = core else {
+        anyhow::bail!(
+            "No core file matching `{}` found in {}",
+            file_name,
+            dir.display()
+        );
+    };
+    if std::fs::rename(&core, dest).is_err() {
+        std::fs::copy(&core, dest)?;
+        std::fs::remove_file(&core)?;
+    }
+    Ok(dest.to_owned())
+}
@@ -0,0 +1,176 @@
+//! Datasets of the benchmarks, downloaded into the cache directory before the run.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    path::{Component, Path, PathBuf},
+    process::Command,
+};
+
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+use crate::configs::{
+    harness::{DatasetConfig, Profile},
+    run_info::{DatasetInfo, RunInfo},
+};
+
+use super::{bench_cmd::get_shell_command, checksum::sha256_file};
+
+/// The file in a dataset directory that records how the dataset was resolved.
+/// It is written after all the steps have succeeded, so an incomplete dataset is always downloaded again.
+const MARKER_FILE: &str = ".harness-dataset.toml";
+
+/// The archive extensions that can be extracted with `tar`
+const TAR_EXTENSIONS: [&str; 7] = [
+    ".tar", ".tar.gz", ".tgz", ".tar.xz", ".txz", ".tar.bz2", ".tar.zst",
+];
+
+#[derive(Serialize, Deserialize)]
+struct Marker {
+    config: DatasetConfig,
+    sha256: String,
+}
+
+/// The directory of a dataset in the cache directory.
+/// The name must be a single plain path component, as the directory is deleted when the dataset is downloaded again.
+fn dataset_dir(cache_dir: &Path, name: &str) -> anyhow::Result<PathBuf> {
+    let mut components = Path::new(name).components();
+    let is_plain = matches!(components.next(), Some(Component::Normal(c)) if c == name)
+        && components.next().is_none();
+    if !is_plain {
+        anyhow::bail!("Invalid dataset name: `{}`", name);
+    }
+    Ok(cache_dir.join("datasets").join(name))
+}
+
+/// The file name of the downloaded dataset, i.e. the last segment of the URL path
+fn file_name(url: &str) -> anyhow::Result<String> {
+    let parsed = url::Url::parse(url)?;
+    parsed
+        .path_segments()
+        .and_then(|mut s| s.next_back())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_owned())
+        .ok_or_else(|| anyhow::anyhow!("No file name in the URL: {}", url))
+}
+
+pub fn download(url: &str, file: &Path) -> anyhow::Result<()> {
+    // Datasets can be large. Do not time out the download
+    let client = reqwest::blocking::Client::builder().timeout(None).build()?;
+    let mut response = client.get(url).send()?.error_for_status()?;
+    response.copy_to(&mut File::create(file)?)?;
+    Ok(())
+}
+
+/// Extract an archive into `dir`, with the system `tar` or `unzip`
+fn extract(file: &Path, dir: &Path) -> anyhow::Result<()> {
+    let name = file.to_string_lossy().to_lowercase();
+    let mut cmd = if name.ends_with(".zip") {
+        let mut cmd = Command::new("unzip");
+        cmd.arg("-q").arg("-o").arg(file).arg("-d").arg(dir);
+        cmd
+    } else if TAR_EXTENSIONS.iter().any(|e| name.ends_with(e)) {
+        let mut cmd = Command::new("tar");
+        cmd.arg("-xf").arg(file).arg("-C").arg(dir);
+        cmd
+    } else {
+        anyhow::bail!("Unsupported archive format: {}", file.display());
+    };
+    let status = cmd.status()?;
+    if !status.success() {
+        anyhow::bail!("Failed to extract {}: {}", file.display(), status);
+    }
+    Ok(())
+}
+
+/// Download, verify, and extract a dataset, unless the cached copy is resolved from the same config
+fn resolve(
+    profile: &Profile,
+    cache_dir: &Path,
+    name: &str,
+    config: &DatasetConfig,
+) -> anyhow::Result<DatasetInfo> {
+    let dir = dataset_dir(cache_dir, name)?;
+    let marker_file = dir.join(MARKER_FILE);
+    let marker = std::fs::read_to_string(&marker_file)
+        .ok()
+        .and_then(|s| toml::from_str::<Marker>(&s).ok());
+    if let Some(marker) = marker.filter(|m| m.config == *config) {
+        return Ok(DatasetInfo {
+            url: config.url.clone(),
+            sha256: marker.sha256,
+        });
+    }
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)?;
+    }
+    std::fs::create_dir_all(&dir)?;
+    println!(
+        "{}",
+        format!("Downloading dataset `{}` from {}", name, config.url).magenta()
+    );
+    let file = dir.join(file_name(&config.url)?);
+    download(&config.url, &file)?;
+    let sha256 = sha256_file(&file)?;
+    if let Some(expected) = &config.sha256 {
+        if !sha256.eq_ignore_ascii_case(expected.trim()) {
+            std::fs::remove_file(&file)?;
+            anyhow::bail!(
+                "SHA-256 checksum mismatch: expected {}, got {}",
+                expected.trim(),
+                sha256
+            );
+        }
+    }
+    if config.extract {
+        extract(&file, &dir)?;
+    }
+    for step in &config.post_extract {
+        let status = get_shell_command(profile, step)
+            .current_dir(&dir)
+            .status()?;
+        if !status.success() {
+            anyhow::bail!("The post-extract step `{}` failed: {}", step, status);
+        }
+    }
+    let marker = Marker {
+        config: config.clone(),
+        sha256: sha256.clone(),
+    };
+    std::fs::write(&marker_file, toml::to_string(&marker)?)?;
+    Ok(DatasetInfo {
+        url: config.url.clone(),
+        sha256,
+    })
+}
+
+/// Resolve all the datasets of the profile into `target/harness/cache/datasets`.
+/// The datasets are downloaded again if their configs have changed since the last download.
+pub fn resolve_all(run: &RunInfo) -> anyhow::Result<BTreeMap<String, DatasetInfo>> {
+    let cache_dir = run.crate_info.target_dir.join("harness").join("cache");
+    let mut datasets = BTreeMap::new();
+    for (name, config) in &run.profile.datasets {
+        let info = resolve(&run.profile, &cache_dir, name, config)
+            .map_err(|e| anyhow::anyhow!("Failed to resolve dataset `{}`: {}", name, e))?;
+        datasets.insert(name.clone(), info);
+    }
+    Ok(datasets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dataset_dir_rejects_non_plain_names() {
+        let cache_dir = Path::new("/cache");
+        assert_eq!(
+            dataset_dir(cache_dir, "enwik8").unwrap(),
+            Path::new("/cache/datasets/enwik8")
+        );
+        for name in ["", ".", "..", "../..", "a/b", "/etc", "a/../b"] {
+            assert!(dataset_dir(cache_dir, name).is_err(), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,116 @@
+//! The events log of a benchmark run.
+//!
+//! Notable events during a run (e.g. invocation failures) are appended to `target/harness/logs/<RUNID>/events.jsonl`,
+//! one JSON object per line.
+
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// The classified reason of an invocation failure
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum FailureKind {
+    /// The build failed to compile
+    CompileError,
+    /// The benchmark process was killed by a signal
+    Crash,
+    /// The benchmark process did not finish in time
+    Timeout,
+    /// The benchmark panicked, e.g. a failed assertion
+    Assertion,
+    /// A probe failed to load or panicked
+    ProbeFailure,
+    /// Any other failures
+    Unknown,
+}
+
+impl FailureKind {
+    /// Classify a failed invocation by its exit signal and the log output of the invocation.
+    pub fn classify(signal: Option<i32>, log: &str) -> Self {
+        if log.contains("error: could not compile") || log.contains("error[E") {
+            return FailureKind::CompileError;
+        }
+        if signal.is_some() || log.contains("(signal: ") {
+            return FailureKind::Crash;
+        }
+        if let Some(panic) = log.lines().find(|l| l.contains("panicked at")) {
+            if panic.contains("probe") {
+                return FailureKind::ProbeFailure;
+            }
+            return FailureKind::Assertion;
+        }
+        FailureKind::Unknown
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FailureKind::CompileError => "compile-error",
+            FailureKind::Crash => "crash",
+            FailureKind::Timeout => "timeout",
+            FailureKind::Assertion => "assertion",
+            FailureKind::ProbeFailure => "probe-failure",
+            FailureKind::Unknown => "unknown",
+        }
+    }
+}
+
+/// A run event
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum Event {
+    /// An invocation failed
+    InvocationFailed {
+        bench: String,
+        build: String,
+        invocation: usize,
+        kind: FailureKind,
+        message: String,
+        #[serde(rename = "time-utc")]
+        timestamp_utc: i64,
+    },
+    /// An attempt of an invocation failed, and the invocation is retried
+    InvocationRetried {
+        bench: String,
+        build: String,
+        invocation: usize,
+        /// The failed attempt, starting from 0
+        attempt: usize,
+        kind: FailureKind,
+        message: String,
+        #[serde(rename = "time-utc")]
+        timestamp_utc: i64,
+    },
+}
+
+pub fn events_file(log_dir: &Path) -> PathBuf {
+    log_dir.join("events.jsonl")
+}
+
+/// Append an event to the events log
+pub fn append(log_dir: &Path, event: &Event) -> anyhow::Result<()> {
+    let mut f = OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(events_file(log_dir))?;
+    writeln!(f, "{}", serde_json::to_string(event)?)?;
+    Ok(())
+}
+
+/// Load all events of a run. Returns an empty list if the run has no events log.
+pub fn load(log_dir: &Path) -> anyhow::Result<Vec<Event>> {
+    let file = events_file(log_dir);
+    if !file.exists() {
+        return Ok(vec![]);
+    }
+    let content = std::fs::read_to_string(file)?;
+    let mut events = vec![];
+    for line in content.lines().filter(|l| !l.trim().is_empty()) {
+        events.push(serde_json::from_str(line)?);
+    }
+    Ok(events)
+}
@@ -32,6 +32,31 @@ pub fn get_branch_last_git_hash(branch: &str) -> anyhow::Result<String> {
         .map(|s| s.trim().to_owned())
 }
 
+/// Whether a commit exists in the local repository
+pub fn has_commit(commit: &str) -> bool {
+    Command::new("git")
+        .args(["cat-file", "-e", &format!("{}^{{commit}}", commit)])
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+/// Fetch a branch, tag, or full commit hash of a remote repository into the local repository, and return the hash of the fetched commit
+pub fn fetch(url: &str, rev: &str) -> anyhow::Result<String> {
+    let output = Command::new("git")
+        .args(["fetch", "--quiet", "--no-tags", url, rev])
+        .output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Failed to fetch `{}` from {}: {}",
+            rev,
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    get_branch_last_git_hash("FETCH_HEAD^{commit}")
+}
+
 pub fn restore_git_state(prev: &GitInfo) -> anyhow::Result<()> {
     let curr = git_info2::get();
     if prev.head.last_commit_hash != curr.head.last_commit_hash {
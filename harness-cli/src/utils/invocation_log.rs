@@ -0,0 +1,72 @@
+//! The structured log of each invocation.
+//!
+//! Besides the plain text log of each benchmark and build, every invocation is logged to
+//! `target/harness/logs/<RUNID>/<bench>.<build>.<invocation>.log.jsonl`, one JSON object per line, tagged by `type`:
+//! a `metadata` record when an attempt starts, an `iteration` record for each iteration, and a `status` record when the attempt ends.
+
+use std::{
+    collections::BTreeMap,
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// A record of the structured invocation log
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum InvocationRecord {
+    /// An attempt of the invocation started
+    Metadata {
+        bench: String,
+        build: String,
+        invocation: usize,
+        /// The attempt, starting from 0
+        attempt: usize,
+        /// The program and its arguments
+        command: Vec<String>,
+        env: BTreeMap<String, String>,
+        features: Vec<String>,
+        #[serde(rename = "cargo-args")]
+        cargo_args: Vec<String>,
+        commit: String,
+        #[serde(rename = "time-utc")]
+        timestamp_utc: i64,
+    },
+    /// The stats of an iteration, the same as its record in `results.jsonl`
+    Iteration {
+        #[serde(flatten)]
+        stats: Map<String, Value>,
+    },
+    /// An attempt of the invocation finished
+    Status {
+        attempt: usize,
+        /// `ok`, or the kind of the failure, e.g. `timeout`
+        status: String,
+        /// The exit code of the benchmark process. Absent if it timed out or was killed by a signal
+        #[serde(default, rename = "exit-code", skip_serializing_if = "Option::is_none")]
+        exit_code: Option<i32>,
+        #[serde(rename = "walltime-ms")]
+        walltime_ms: f64,
+        /// Whether the failed attempt is retried
+        retried: bool,
+        #[serde(rename = "time-utc")]
+        timestamp_utc: i64,
+    },
+}
+
+/// The structured log file of an invocation
+pub fn log_file(log_dir: &Path, bench: &str, build: &str, invocation: usize) -> PathBuf {
+    log_dir.join(format!("{}.{}.{}.log.jsonl", bench, build, invocation))
+}
+
+/// Append records to the structured log of an invocation
+pub fn append(file: &Path, records: &[InvocationRecord]) -> anyhow::Result<()> {
+    let mut f = OpenOptions::new().append(true).create(true).open(file)?;
+    for record in records {
+        writeln!(f, "{}", serde_json::to_string(record)?)?;
+    }
+    Ok(())
+}
@@ -21,12 +21,16 @@ pub fn load_lockfiles(crate_info: &CrateInfo, profile: &Profile) -> anyhow::Resu
     let lockfile_path = crate_info.workspace_root.join("Cargo.lock");
     let profile_commit = super::git::get_git_hash()?;
     for (build_name, build) in &profile.builds {
+        // A crates.io build is not in the repository
+        if build.version.is_some() {
+            continue;
+        }
         // Switch to the build commit
         let commit = build.commit.as_deref().unwrap_or(profile_commit.as_str());
         let _git_guard = git::checkout(commit)?;
         // Run cargo build once to generate the lockfile
         if !lockfile_path.exists() {
-            let mut cmd = bench_cmd::get_bench_build_command(profile, build_name);
+            let mut cmd = bench_cmd::get_bench_build_command(crate_info, profile, build_name);
             let out = cmd
                 .output()
                 .map_err(|e| anyhow::anyhow!("Failed to build `{}`: {}", build_name, e))?;
@@ -61,6 +65,21 @@ impl Drop for TempLockfileGuard {
     }
 }
 
+/// Write the recorded lockfile of a commit to a workspace, e.g. the snapshot of the commit
+pub fn write_lockfile(run_info: &RunInfo, hash: &str, workspace_root: &Path) -> anyhow::Result<()> {
+    let hash = hash.trim_end_matches("-dirty");
+    let lockfile = run_info
+        .lockfiles
+        .lockfiles
+        .get(hash)
+        .ok_or_else(|| anyhow::anyhow!("Lockfile for commit `{}` not found", hash))?;
+    std::fs::write(
+        workspace_root.join("Cargo.lock"),
+        toml::to_string(lockfile)?,
+    )?;
+    Ok(())
+}
+
 pub fn replay_lockfile(run_info: &RunInfo, mut hash: &str) -> anyhow::Result<TempLockfileGuard> {
     if hash.ends_with("-dirty") {
         hash = hash.trim_end_matches("-dirty");
@@ -1,5 +1,7 @@
 use std::io::IsTerminal;
 
+use termimad::crossterm::style::Stylize;
+
 pub fn print_md(s: impl AsRef<str>) {
     let mut printer = MarkdownPrinter::new();
     printer.add(s);
@@ -31,12 +33,20 @@ impl MarkdownPrinter {
             }
             skin.headers[0].set_bg(termimad::crossterm::style::Color::Blue);
             skin.headers[0].add_attr(termimad::crossterm::style::Attribute::NoUnderline);
-            skin.print_text(&self.content);
+            let text = skin.term_text(&self.content).to_string();
+            print!("{}", Self::colorize_markers(&text));
         } else {
             println!("{}", self.content);
         }
     }
 
+    /// Color the `▲` (higher) and `▼` (lower) markers of the differences in the report tables.
+    /// This is done after the layout, so that the escape codes do not affect the column widths.
+    fn colorize_markers(text: &str) -> String {
+        text.replace('▲', &"▲".red().to_string())
+            .replace('▼', &"▼".green().to_string())
+    }
+
     pub fn add(&mut self, s: impl AsRef<str>) {
         self.content.push_str(s.as_ref());
     }
@@ -1,5 +1,23 @@
 pub mod bench_cmd;
+pub mod binary_size;
+pub mod build_cache;
+pub mod cachegrind;
+pub mod cgroup;
+pub mod chart;
+pub mod checksum;
+pub mod container;
+pub mod cpu_monitor;
+pub mod crash;
+pub mod dataset;
+pub mod events;
 pub mod git;
+pub mod invocation_log;
 pub mod lockfile;
 pub mod md;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+pub mod results;
+pub mod service;
 pub mod sys;
+pub mod toolchain;
+pub mod worktree;
@@ -0,0 +1,193 @@
+use std::{fs::File, path::Path, sync::Arc};
+
+use harness::schema::KEY_COLUMNS;
+use parquet::{
+    basic::{LogicalType, Repetition, Type as PhysicalType},
+    data_type::{BoolType, ByteArray, ByteArrayType, DoubleType, Int64Type},
+    file::{properties::WriterProperties, writer::SerializedFileWriter},
+    schema::types::Type,
+};
+use serde_json::{Map, Value};
+
+/// The type of a column, inferred from all its values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColumnType {
+    Boolean,
+    Int64,
+    Double,
+    String,
+}
+
+impl ColumnType {
+    fn of(values: &[Option<&Value>]) -> Self {
+        let values = values.iter().flatten();
+        if values.clone().all(|v| v.is_boolean()) {
+            Self::Boolean
+        } else if values.clone().all(|v| v.is_i64()) {
+            Self::Int64
+        } else if values.clone().all(|v| v.is_number()) {
+            Self::Double
+        } else {
+            Self::String
+        }
+    }
+
+    fn field(&self, name: &str) -> parquet::errors::Result<Type> {
+        let (physical, logical) = match self {
+            Self::Boolean => (PhysicalType::BOOLEAN, None),
+            Self::Int64 => (PhysicalType::INT64, None),
+            Self::Double => (PhysicalType::DOUBLE, None),
+            Self::String => (PhysicalType::BYTE_ARRAY, Some(LogicalType::String)),
+        };
+        Type::primitive_type_builder(name, physical)
+            .with_repetition(Repetition::OPTIONAL)
+            .with_logical_type(logical)
+            .build()
+    }
+}
+
+/// Write the structured records to a Parquet file, with one row per record.
+///
+/// The columns are the same as in results.csv: `bench,build,invocation,iteration`, then all the other stats sorted by name.
+/// All columns are nullable, and the type of each column is inferred from its values:
+/// booleans, integers, numbers (as doubles), or strings for anything else.
+pub fn write_records(path: &Path, records: &[Map<String, Value>]) -> anyhow::Result<()> {
+    let mut names = KEY_COLUMNS.map(|s| s.to_owned()).to_vec();
+    let mut others = records
+        .iter()
+        .flat_map(|r| r.keys())
+        .filter(|k| !KEY_COLUMNS.contains(&k.as_str()))
+        .cloned()
+        .collect::<Vec<_>>();
+    others.sort();
+    others.dedup();
+    names.extend(others);
+    let columns = names
+        .iter()
+        .map(|name| {
+            let values = records.iter().map(|r| r.get(name)).collect::<Vec<_>>();
+            (ColumnType::of(&values), values)
+        })
+        .collect::<Vec<_>>();
+    let fields = names
+        .iter()
+        .zip(&columns)
+        .map(|(name, (ty, _))| Ok(Arc::new(ty.field(name)?)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let schema = Type::group_type_builder("results")
+        .with_fields(fields)
+        .build()?;
+    let props = WriterProperties::builder().build();
+    let mut writer =
+        SerializedFileWriter::new(File::create(path)?, Arc::new(schema), Arc::new(props))?;
+    let mut row_group = writer.next_row_group()?;
+    for (ty, values) in &columns {
+        // The schema has one column per name
+        let mut column = row_group.next_column()?.unwrap();
+        // Nulls are encoded as the definition level 0, and have no value
+        let levels = values
+            .iter()
+            .map(|v| v.is_some() as i16)
+            .collect::<Vec<_>>();
+        let values = values.iter().flatten();
+        match ty {
+            ColumnType::Boolean => {
+                let values = values.map(|v| v.as_bool().unwrap()).collect::<Vec<_>>();
+                column
+                    .typed::<BoolType>()
+                    .write_batch(&values, Some(&levels), None)?;
+            }
+            ColumnType::Int64 => {
+                let values = values.map(|v| v.as_i64().unwrap()).collect::<Vec<_>>();
+                column
+                    .typed::<Int64Type>()
+                    .write_batch(&values, Some(&levels), None)?;
+            }
+            ColumnType::Double => {
+                let values = values.map(|v| v.as_f64().unwrap()).collect::<Vec<_>>();
+                column
+                    .typed::<DoubleType>()
+                    .write_batch(&values, Some(&levels), None)?;
+            }
+            ColumnType::String => {
+                let values = values
+                    .map(|v| match v {
+                        Value::String(s) => ByteArray::from(s.as_str()),
+                        v => ByteArray::from(v.to_string().as_str()),
+                    })
+                    .collect::<Vec<_>>();
+                column
+                    .typed::<ByteArrayType>()
+                    .write_batch(&values, Some(&levels), None)?;
+            }
+        }
+        column.close()?;
+    }
+    row_group.close()?;
+    writer.close()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use parquet::{
+        file::reader::{FileReader, SerializedFileReader},
+        record::Field,
+    };
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn columns_keep_the_value_types() {
+        let dir = tempdir::TempDir::new("harness-parquet").unwrap();
+        let path = dir.path().join("results.parquet");
+        let records = [
+            json!({ "bench": "foo", "build": "a", "invocation": 0, "iteration": 0, "time": 1.5, "warmup": true, "cycles": 100 }),
+            json!({ "bench": "foo", "build": "a", "invocation": 0, "iteration": 1, "time": 2, "warmup": false, "status": "ok" }),
+        ]
+        .map(|r| r.as_object().unwrap().clone());
+        write_records(&path, &records).unwrap();
+        let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+        let schema = reader.metadata().file_metadata().schema_descr_ptr();
+        let types = schema
+            .columns()
+            .iter()
+            .map(|c| (c.name().to_owned(), c.physical_type()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            [
+                ("bench", PhysicalType::BYTE_ARRAY),
+                ("build", PhysicalType::BYTE_ARRAY),
+                ("invocation", PhysicalType::INT64),
+                ("iteration", PhysicalType::INT64),
+                ("cycles", PhysicalType::INT64),
+                ("status", PhysicalType::BYTE_ARRAY),
+                ("time", PhysicalType::DOUBLE),
+                ("warmup", PhysicalType::BOOLEAN),
+            ]
+            .map(|(n, t)| (n.to_owned(), t))
+        );
+        let rows = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(rows.len(), 2);
+        let row = |i: usize, name: &str| {
+            rows[i]
+                .get_column_iter()
+                .find(|(n, _)| *n == name)
+                .unwrap()
+                .1
+                .clone()
+        };
+        assert_eq!(row(0, "time"), Field::Double(1.5));
+        assert_eq!(row(1, "time"), Field::Double(2.0));
+        assert_eq!(row(0, "cycles"), Field::Long(100));
+        assert_eq!(row(1, "cycles"), Field::Null);
+        assert_eq!(row(0, "warmup"), Field::Bool(true));
+        assert_eq!(row(1, "status"), Field::Str("ok".to_owned()));
+    }
+}
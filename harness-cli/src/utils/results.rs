@@ -0,0 +1,551 @@
+use std::{
+    collections::BTreeMap,
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use harness::schema::{Table, BOOKKEEPING_COLUMNS};
+use serde_json::{Map, Value};
+
+/// A results file that is exclusively locked until it's dropped.
+///
+/// The locks are advisory, and also taken by the benchmark processes when they append their records.
+/// So a results file is never corrupted by concurrent updates, e.g. from another harness process.
+struct LockedFile(File);
+
+impl LockedFile {
+    /// Open or create the file, and wait for the lock
+    fn open(path: &Path) -> anyhow::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        file.lock()?;
+        Ok(Self(file))
+    }
+
+    fn read(&mut self) -> anyhow::Result<String> {
+        let mut content = String::new();
+        self.0.seek(SeekFrom::Start(0))?;
+        self.0.read_to_string(&mut content)?;
+        Ok(content)
+    }
+
+    fn write(&mut self, content: &str) -> anyhow::Result<()> {
+        self.0.set_len(0)?;
+        self.0.seek(SeekFrom::Start(0))?;
+        self.0.write_all(content.as_bytes())?;
+        Ok(())
+    }
+
+    fn read_table(&mut self) -> anyhow::Result<Table> {
+        Ok(Table::parse(&self.read()?))
+    }
+
+    /// Save the table with the current schema
+    fn write_table(&mut self, table: &mut Table) -> anyhow::Result<()> {
+        self.write(&table.to_csv())
+    }
+}
+
+/// Read a results file with a shared lock, so that it's not read in the middle of an update
+fn read_shared(path: &Path) -> anyhow::Result<String> {
+    let mut file = File::open(path)?;
+    file.lock_shared()?;
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+/// The structured results file next to a results.csv file, with one JSON object per iteration and the types of the stats preserved.
+/// e.g. `results.jsonl` for `results.csv`.
+pub fn json_path(csv: &Path) -> PathBuf {
+    csv.with_extension("jsonl")
+}
+
+/// The Parquet results file next to a results.csv file, e.g. `results.parquet` for `results.csv`.
+#[cfg(feature = "parquet")]
+pub fn parquet_path(csv: &Path) -> PathBuf {
+    csv.with_extension("parquet")
+}
+
+/// Convert the structured results file next to a results.csv file to Parquet.
+/// Does nothing if there is no structured results file.
+#[cfg(feature = "parquet")]
+pub fn write_parquet(csv: &Path) -> anyhow::Result<()> {
+    let json = json_path(csv);
+    if !json.exists() {
+        return Ok(());
+    }
+    let records = parse_json_records(&read_shared(&json)?)?;
+    super::parquet::write_records(&parquet_path(csv), &records)
+}
+
+fn parse_json_records(content: &str) -> anyhow::Result<Vec<Map<String, Value>>> {
+    content
+        .lines()
+        .filter(|l| !l.is_empty())
+        .map(|l| Ok(serde_json::from_str(l)?))
+        .collect()
+}
+
+fn save_json_records(file: &mut LockedFile, records: &[Map<String, Value>]) -> anyhow::Result<()> {
+    let mut out = String::new();
+    for r in records {
+        out += &serde_json::to_string(r)?;
+        out.push('\n');
+    }
+    file.write(&out)
+}
+
+fn is_invocation(r: &Map<String, Value>, bench: &str, build: &str, invocation: usize) -> bool {
+    r.get("bench").and_then(|v| v.as_str()) == Some(bench)
+        && r.get("build").and_then(|v| v.as_str()) == Some(build)
+        && r.get("invocation").and_then(|v| v.as_u64()) == Some(invocation as u64)
+}
+
+/// Load the structured records of an invocation from the structured results file next to a results.csv file, in file order.
+/// Returns an empty list if there is no structured results file.
+pub fn load_invocation_json_records(
+    csv: &Path,
+    bench: &str,
+    build: &str,
+    invocation: usize,
+) -> anyhow::Result<Vec<Map<String, Value>>> {
+    let json = json_path(csv);
+    if !json.exists() {
+        return Ok(vec![]);
+    }
+    let mut records = parse_json_records(&read_shared(&json)?)?;
+    records.retain(|r| is_invocation(r, bench, build, invocation));
+    Ok(records)
+}
+
+/// Convert a stat collected by the runner to a typed JSON value
+fn parse_json_value(value: &str) -> Value {
+    if let Ok(v) = value.parse::<i64>() {
+        Value::from(v)
+    } else if let Ok(v) = value.parse::<f64>() {
+        Value::from(v)
+    } else if let Ok(v) = value.parse::<bool>() {
+        Value::from(v)
+    } else {
+        Value::from(value)
+    }
+}
+
+/// The same as [`append_stats`], for the structured results file.
+fn append_json_stats(
+    json: &Path,
+    bench: &str,
+    build: &str,
+    invocation: usize,
+    stats: &[(String, String)],
+) -> anyhow::Result<()> {
+    let mut file = LockedFile::open(json)?;
+    let mut records = parse_json_records(&file.read()?)?;
+    let index = match records
+        .iter()
+        .rposition(|r| is_invocation(r, bench, build, invocation))
+    {
+        Some(i) => i,
+        None => {
+            let mut r = Map::new();
+            r.insert("bench".to_owned(), Value::from(bench));
+            r.insert("build".to_owned(), Value::from(build));
+            r.insert("invocation".to_owned(), Value::from(invocation));
+            records.push(r);
+            records.len() - 1
+        }
+    };
+    for (name, value) in stats {
+        records[index].insert(name.clone(), parse_json_value(value));
+    }
+    save_json_records(&mut file, &records)
+}
+
+/// Add extra stats to the last timing iteration of an invocation in a results.csv file.
+///
+/// This is used for stats that are collected by the runner, outside of the benchmark process.
+/// New columns are inserted by the schema, and the other rows are padded with empty values.
+/// If the invocation has no records (e.g. it failed before finishing the first iteration), a new record is added.
+pub fn append_stats(
+    csv: &Path,
+    bench: &str,
+    build: &str,
+    invocation: usize,
+    stats: &[(String, String)],
+) -> anyhow::Result<()> {
+    if stats.is_empty() {
+        return Ok(());
+    }
+    let mut file = LockedFile::open(csv)?;
+    let mut table = file.read_table()?;
+    table.add_columns(stats.iter().map(|(name, _)| name.as_str()));
+    // After sorting the columns, the key columns come first
+    let (bench_col, build_col, inv_col) = (0, 1, 2);
+    // The last record of the invocation is the timing iteration
+    let inv = invocation.to_string();
+    let index = match table
+        .rows
+        .iter()
+        .rposition(|r| r[bench_col] == bench && r[build_col] == build && r[inv_col] == inv)
+    {
+        Some(i) => i,
+        None => {
+            let mut row = vec![String::new(); table.headers.len()];
+            row[bench_col] = bench.to_owned();
+            row[build_col] = build.to_owned();
+            row[inv_col] = inv;
+            table.rows.push(row);
+            table.rows.len() - 1
+        }
+    };
+    for (name, value) in stats {
+        let i = table.col(name).unwrap();
+        table.rows[index][i] = value.clone();
+    }
+    file.write_table(&mut table)?;
+    drop(file);
+    append_json_stats(&json_path(csv), bench, build, invocation, stats)
+}
+
+/// Append all the records of a results.csv file to another one, matching the columns by name.
+///
+/// New columns are inserted by the schema, and the other rows are padded with empty values.
+/// The structured results files are merged as well, and `from`'s is deleted.
+pub fn merge(from: &Path, into: &Path) -> anyhow::Result<()> {
+    let from_json = json_path(from);
+    if from_json.exists() {
+        let mut file = LockedFile::open(&json_path(into))?;
+        let mut records = parse_json_records(&file.read()?)?;
+        records.extend(parse_json_records(&read_shared(&from_json)?)?);
+        save_json_records(&mut file, &records)?;
+        std::fs::remove_file(&from_json)?;
+    }
+    if !from.exists() {
+        return Ok(());
+    }
+    let new = Table::parse(&read_shared(from)?);
+    let mut file = LockedFile::open(into)?;
+    let mut table = file.read_table()?;
+    table.add_columns(new.headers.iter().map(|h| h.as_str()));
+    for new_row in new.rows {
+        let mut row = vec![String::new(); table.headers.len()];
+        for (name, value) in new.headers.iter().zip(new_row) {
+            let i = table.col(name).unwrap();
+            row[i] = value;
+        }
+        table.rows.push(row);
+    }
+    file.write_table(&mut table)
+}
+
+/// Remove all the records of an invocation from a results.csv file, e.g. the partial results of a failed attempt.
+pub fn remove_invocation(
+    csv: &Path,
+    bench: &str,
+    build: &str,
+    invocation: usize,
+) -> anyhow::Result<()> {
+    let json = json_path(csv);
+    if json.exists() {
+        let mut file = LockedFile::open(&json)?;
+        let mut records = parse_json_records(&file.read()?)?;
+        records.retain(|r| !is_invocation(r, bench, build, invocation));
+        save_json_records(&mut file, &records)?;
+    }
+    if !csv.exists() {
+        return Ok(());
+    }
+    let mut file = LockedFile::open(csv)?;
+    let mut table = file.read_table()?;
+    let (Some(bench_col), Some(build_col), Some(inv_col)) = (
+        table.col("bench"),
+        table.col("build"),
+        table.col("invocation"),
+    ) else {
+        anyhow::bail!("Malformed results file: {}", csv.display());
+    };
+    let invocation = invocation.to_string();
+    table
+        .rows
+        .retain(|r| !(r[bench_col] == bench && r[build_col] == build && r[inv_col] == invocation));
+    file.write_table(&mut table)
+}
+
+/// The timing iterations of an invocation, loaded from a results.csv file.
+#[derive(Debug, Clone)]
+pub struct TimingRecord {
+    pub bench: String,
+    pub build: String,
+    pub invocation: usize,
+    /// The mean of all numeric stats over the timing iterations. e.g. `time`
+    pub values: BTreeMap<String, f64>,
+    /// The sample variance of each stat over the timing iterations. Empty if there is only one timing iteration.
+    pub variances: BTreeMap<String, f64>,
+}
+
+/// A single iteration of an invocation, loaded from a results.csv file.
+#[derive(Debug, Clone)]
+pub struct IterationRecord {
+    pub bench: String,
+    pub build: String,
+    pub invocation: usize,
+    pub iteration: usize,
+    /// The status of the invocation, e.g. `ok` or `timeout`. Empty if not recorded.
+    pub status: String,
+    /// Whether the invocation only warms up the machine, with `warmup-invocations`
+    pub warmup: bool,
+    /// All numeric stats of the iteration. e.g. `time`
+    pub values: BTreeMap<String, f64>,
+}
+
+/// Load all the iterations of each invocation from a results.csv file, in file order.
+///
+/// Empty or non-numeric values, and the bookkeeping columns, are skipped. Files without an `iteration` column are treated as one iteration per record.
+pub fn load_iteration_records(csv: &Path) -> anyhow::Result<Vec<IterationRecord>> {
+    let table = Table::parse(&read_shared(csv)?);
+    let col = |name: &str| table.col(name);
+    let (Some(bench_col), Some(build_col), Some(inv_col)) =
+        (col("bench"), col("build"), col("invocation"))
+    else {
+        anyhow::bail!("Malformed results file: {}", csv.display());
+    };
+    let iter_col = col("iteration");
+    let status_col = col("status");
+    let warmup_col = col("warmup");
+    let mut records = vec![];
+    for row in &table.rows {
+        let Ok(invocation) = row[inv_col].parse::<usize>() else {
+            anyhow::bail!("Malformed results file: {}", csv.display());
+        };
+        let iteration = iter_col
+            .and_then(|i| row[i].parse::<usize>().ok())
+            .unwrap_or(0);
+        let values = table
+            .headers
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| ![bench_col, build_col, inv_col].contains(i) && Some(*i) != iter_col)
+            .filter(|(_, h)| !BOOKKEEPING_COLUMNS.contains(&h.as_str()))
+            .filter_map(|(i, h)| Some((h.clone(), row[i].parse::<f64>().ok()?)))
+            .collect();
+        records.push(IterationRecord {
+            bench: row[bench_col].clone(),
+            build: row[build_col].clone(),
+            invocation,
+            iteration,
+            status: status_col.map(|i| row[i].clone()).unwrap_or_default(),
+            warmup: warmup_col.is_some_and(|i| row[i] == "true"),
+            values,
+        });
+    }
+    Ok(records)
+}
+
+/// Load the timing iterations of each invocation from a results.csv file.
+///
+/// The last `timing_iterations` records of each `(bench, build, invocation)` are the timing iterations, and are aggregated into one record.
+/// Empty or non-numeric values are skipped. Failed invocations (with a `status` other than `ok`) and warm-up invocations are skipped.
+pub fn load_timing_records(
+    csv: &Path,
+    timing_iterations: usize,
+) -> anyhow::Result<Vec<TimingRecord>> {
+    let mut invocations = BTreeMap::<(String, String, usize), Vec<IterationRecord>>::new();
+    for r in load_iteration_records(csv)? {
+        invocations
+            .entry((r.bench.clone(), r.build.clone(), r.invocation))
+            .or_default()
+            .push(r);
+    }
+    let mut records = vec![];
+    for ((bench, build, invocation), iterations) in invocations {
+        if iterations
+            .iter()
+            .any(|r| r.warmup || (!r.status.is_empty() && r.status != "ok"))
+        {
+            continue;
+        }
+        let timing = &iterations[iterations.len().saturating_sub(timing_iterations.max(1))..];
+        let mut samples = BTreeMap::<&str, Vec<f64>>::new();
+        for r in timing {
+            for (k, v) in &r.values {
+                samples.entry(k).or_default().push(*v);
+            }
+        }
+        let mut values = BTreeMap::new();
+        let mut variances = BTreeMap::new();
+        for (k, v) in samples {
+            let n = v.len() as f64;
+            let mean = v.iter().sum::<f64>() / n;
+            if v.len() > 1 {
+                let var = v.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+                variances.insert(k.to_owned(), var);
+            }
+            values.insert(k.to_owned(), mean);
+        }
+        records.push(TimingRecord {
+            bench,
+            build,
+            invocation,
+            values,
+            variances,
+        });
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEGACY: &str = "bench,build,invocation,time\nfoo,a,0,1.5\nfoo,a,1,2.5\n";
+
+    fn read(path: &Path) -> String {
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn append_stats_inserts_new_metrics() {
+        let dir = tempdir::TempDir::new("harness-results").unwrap();
+        let csv = dir.path().join("results.csv");
+        std::fs::write(&csv, "# harness-results-schema: 1\nbench,build,invocation,iteration,time\nfoo,\"x,y\",0,0,1.5\nfoo,\"x,y\",0,1,2.5\n").unwrap();
+        let stats = [("status".to_owned(), "ok".to_owned())];
+        append_stats(&csv, "foo", "x,y", 0, &stats).unwrap();
+        assert_eq!(
+            read(&csv),
+            "# harness-results-schema: 1\nbench,build,invocation,iteration,status,time\nfoo,\"x,y\",0,0,,1.5\nfoo,\"x,y\",0,1,ok,2.5\n"
+        );
+        // An invocation without records gets a new one
+        append_stats(&csv, "bar", "x,y", 0, &stats).unwrap();
+        let records = load_iteration_records(&csv).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            (records[2].bench.as_str(), records[2].status.as_str()),
+            ("bar", "ok")
+        );
+        assert_eq!(records[2].build, "x,y");
+    }
+
+    #[test]
+    fn legacy_files_are_upgraded() {
+        let dir = tempdir::TempDir::new("harness-results").unwrap();
+        let csv = dir.path().join("results.csv");
+        std::fs::write(&csv, LEGACY).unwrap();
+        // Files without an `iteration` column are read as one iteration per record
+        let records = load_timing_records(&csv, 1).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].values["time"], 2.5);
+        append_stats(
+            &csv,
+            "foo",
+            "a",
+            1,
+            &[("retries".to_owned(), "1".to_owned())],
+        )
+        .unwrap();
+        assert_eq!(
+            read(&csv),
+            "# harness-results-schema: 1\nbench,build,invocation,iteration,retries,time\nfoo,a,0,,,1.5\nfoo,a,1,,1,2.5\n"
+        );
+        // The bookkeeping columns are not loaded as metrics
+        let records = load_timing_records(&csv, 1).unwrap();
+        assert_eq!(records[1].values.keys().collect::<Vec<_>>(), ["time"]);
+    }
+
+    #[test]
+    fn merge_and_remove_invocation() {
+        let dir = tempdir::TempDir::new("harness-results").unwrap();
+        let (from, into) = (dir.path().join("from.csv"), dir.path().join("into.csv"));
+        std::fs::write(&into, LEGACY).unwrap();
+        std::fs::write(
+            &from,
+            "# harness-results-schema: 1\nbench,build,invocation,iteration,faults,time\n\"b,c\",a,0,0,7,3.5\n",
+        )
+        .unwrap();
+        std::fs::write(
+            json_path(&from),
+            "{\"bench\":\"b,c\",\"build\":\"a\",\"invocation\":0,\"time\":3.5}\n",
+        )
+        .unwrap();
+        merge(&from, &into).unwrap();
+        assert!(!json_path(&from).exists());
+        assert_eq!(
+            read(&into),
+            "# harness-results-schema: 1\nbench,build,invocation,iteration,faults,time\nfoo,a,0,,,1.5\nfoo,a,1,,,2.5\n\"b,c\",a,0,0,7,3.5\n"
+        );
+        assert_eq!(
+            load_invocation_json_records(&into, "b,c", "a", 0)
+                .unwrap()
+                .len(),
+            1
+        );
+        remove_invocation(&into, "b,c", "a", 0).unwrap();
+        remove_invocation(&into, "foo", "a", 0).unwrap();
+        assert_eq!(
+            read(&into),
+            "# harness-results-schema: 1\nbench,build,invocation,iteration,faults,time\nfoo,a,1,,,2.5\n"
+        );
+        assert!(load_invocation_json_records(&into, "b,c", "a", 0)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn concurrent_writers_keep_all_rows() {
+        let dir = tempdir::TempDir::new("harness-results").unwrap();
+        let csv = dir.path().join("results.csv");
+        const WRITERS: usize = 8;
+        const INVOCATIONS: usize = 10;
+        std::thread::scope(|s| {
+            for w in 0..WRITERS {
+                let (dir, csv) = (dir.path(), &csv);
+                s.spawn(move || {
+                    for i in 0..INVOCATIONS {
+                        let bench = format!("bench-{}", w);
+                        // Each writer merges the records of its invocation, and then appends a new metric to them
+                        let part = dir.join(format!("{}.{}.results.csv", bench, i));
+                        let row = format!(
+                            "bench,build,invocation,iteration,time\n{},a,{},0,{}\n",
+                            bench, i, i
+                        );
+                        std::fs::write(&part, row).unwrap();
+                        merge(&part, csv).unwrap();
+                        let stats = [(format!("stat-{}", w), i.to_string())];
+                        append_stats(csv, &bench, "a", i, &stats).unwrap();
+                    }
+                });
+            }
+        });
+        let table = Table::parse(&read(&csv));
+        assert_eq!(table.headers.len(), 5 + WRITERS);
+        assert_eq!(table.rows.len(), WRITERS * INVOCATIONS);
+        for w in 0..WRITERS {
+            let bench = format!("bench-{}", w);
+            let stat = table.col(&format!("stat-{}", w)).unwrap();
+            for i in 0..INVOCATIONS {
+                let rows = table
+                    .rows
+                    .iter()
+                    .filter(|r| r[0] == bench && r[2] == i.to_string())
+                    .collect::<Vec<_>>();
+                assert_eq!(rows.len(), 1, "{} #{}", bench, i);
+                assert_eq!(rows[0][table.col("time").unwrap()], i.to_string());
+                assert_eq!(rows[0][stat], i.to_string());
+            }
+        }
+        // The structured records are not lost either
+        for w in 0..WRITERS {
+            for i in 0..INVOCATIONS {
+                let bench = format!("bench-{}", w);
+                let records = load_invocation_json_records(&csv, &bench, "a", i).unwrap();
+                assert_eq!(records.len(), 1);
+                assert_eq!(records[0][&format!("stat-{}", w)], Value::from(i));
+            }
+        }
+    }
+}
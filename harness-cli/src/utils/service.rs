@@ -0,0 +1,114 @@
+use std::{
+    fs::OpenOptions,
+    net::{Ipv4Addr, SocketAddr, TcpStream},
+    path::Path,
+    process::{Child, Stdio},
+    time::{Duration, Instant},
+};
+
+use crate::configs::harness::{Profile, ServiceConfig};
+
+use super::{bench_cmd::get_shell_command, sys::kill_process_tree};
+
+/// The interval between two readiness checks of a starting service
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A running companion process of a benchmark. It is killed, together with its child processes, when dropped.
+pub struct Service {
+    name: String,
+    child: Child,
+}
+
+impl Service {
+    /// Start a service and wait until it is ready.
+    /// Its outputs are appended to `log_file`, or inherited if there is no log file.
+    pub fn start(
+        profile: &Profile,
+        name: &str,
+        config: &ServiceConfig,
+        log_file: Option<&Path>,
+        envs: &[(&str, String)],
+    ) -> anyhow::Result<Self> {
+        let mut cmd = get_shell_command(profile, &config.command);
+        cmd.envs(envs.iter().map(|(k, v)| (k, v)))
+            .stdin(Stdio::null());
+        if let Some(log_file) = log_file {
+            let outputs = OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(log_file)?;
+            cmd.stdout(outputs.try_clone()?).stderr(outputs);
+        }
+        // Put the service in a new process group, so the processes started by the shell are killed together
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+        let child = cmd
+            .spawn()
+            .map_err(|e| anyhow::anyhow!("Failed to start service `{}`: {}", name, e))?;
+        let mut service = Service {
+            name: name.to_owned(),
+            child,
+        };
+        service.wait_until_ready(profile, config, envs)?;
+        Ok(service)
+    }
+
+    fn wait_until_ready(
+        &mut self,
+        profile: &Profile,
+        config: &ServiceConfig,
+        envs: &[(&str, String)],
+    ) -> anyhow::Result<()> {
+        let timeout = config.get_ready_timeout()?;
+        let start = Instant::now();
+        loop {
+            if let Some(status) = self.child.try_wait()? {
+                anyhow::bail!(
+                    "Service `{}` exited before it was ready: {}",
+                    self.name,
+                    status
+                );
+            }
+            if Self::is_ready(profile, config, envs) {
+                return Ok(());
+            }
+            if start.elapsed() >= timeout {
+                anyhow::bail!(
+                    "Service `{}` is not ready after {}",
+                    self.name,
+                    config.ready_timeout
+                );
+            }
+            std::thread::sleep(READY_POLL_INTERVAL);
+        }
+    }
+
+    /// Check if the service accepts connections on its port, and if its health check passes
+    fn is_ready(profile: &Profile, config: &ServiceConfig, envs: &[(&str, String)]) -> bool {
+        if let Some(port) = config.port {
+            let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+            if TcpStream::connect_timeout(&addr, Duration::from_secs(1)).is_err() {
+                return false;
+            }
+        }
+        if let Some(health_check) = &config.health_check {
+            return get_shell_command(profile, health_check)
+                .envs(envs.iter().map(|(k, v)| (k, v)))
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .is_ok_and(|s| s.success());
+        }
+        true
+    }
+}
+
+impl Drop for Service {
+    fn drop(&mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            let _ = kill_process_tree(self.child.id());
+        }
+        let _ = self.child.wait();
+    }
+}
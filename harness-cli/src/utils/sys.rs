@@ -1,6 +1,6 @@
 use sysinfo::{CpuExt, System, SystemExt};
 
-use crate::configs::run_info::SystemInfo;
+use crate::configs::run_info::{NumaNode, SystemInfo};
 
 #[cfg(target_os = "linux")]
 fn get_logged_in_users() -> anyhow::Result<Vec<String>> {
@@ -19,6 +19,49 @@ fn get_logged_in_users() -> anyhow::Result<Vec<String>> {
         .map_err(|e| e.into())
 }
 
+#[cfg(target_os = "windows")]
+fn get_logged_in_users() -> anyhow::Result<Vec<String>> {
+    use std::process::Command;
+
+    // `query user` prints a header line, followed by one session per line.
+    // The current session is prefixed with `>`.
+    let out = Command::new("query").arg("user").output()?;
+    let mut users = String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .skip(1)
+        .filter_map(|l| l.split_whitespace().next())
+        .map(|u| u.trim_start_matches('>').to_owned())
+        .collect::<Vec<_>>();
+    users.sort();
+    users.dedup();
+    Ok(users)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn get_logged_in_users() -> anyhow::Result<Vec<String>> {
+    Ok(vec![])
+}
+
+/// The name of the active power plan. e.g. `High performance`
+#[cfg(target_os = "windows")]
+fn get_power_plan() -> Option<String> {
+    use std::process::Command;
+
+    // Power Scheme GUID: 8c5e7fda-e8bf-4a96-9a85-a6e23a8c635c  (High performance)
+    let out = Command::new("powercfg")
+        .arg("/getactivescheme")
+        .output()
+        .ok()?;
+    let out = String::from_utf8_lossy(&out.stdout);
+    let name = out.split_once('(')?.1.rsplit_once(')')?.0;
+    Some(name.trim().to_owned())
+}
+
+#[cfg(not(target_os = "windows"))]
+fn get_power_plan() -> Option<String> {
+    None
+}
+
 #[cfg(target_os = "linux")]
 fn get_scaling_governor() -> anyhow::Result<Vec<String>> {
     let mut governors = Vec::new();
@@ -38,6 +81,135 @@ fn get_scaling_governor() -> anyhow::Result<Vec<String>> {
     Ok(governors)
 }
 
+#[cfg(not(target_os = "linux"))]
+fn get_scaling_governor() -> anyhow::Result<Vec<String>> {
+    Ok(vec![])
+}
+
+/// Apple-specific system info, collected with `sysctl`, `pmset`, and `mdutil`.
+#[derive(Default)]
+struct MacInfo {
+    performance_cores: Option<usize>,
+    efficiency_cores: Option<usize>,
+    low_power_mode: Option<bool>,
+    cpu_speed_limit: Option<usize>,
+    spotlight_indexing: Option<bool>,
+    on_battery: Option<bool>,
+    smt: Option<bool>,
+}
+
+#[cfg(target_os = "macos")]
+fn get_mac_info() -> MacInfo {
+    use std::process::Command;
+
+    let run = |cmd: &str, args: &[&str]| -> Option<String> {
+        let out = Command::new(cmd).args(args).output().ok()?;
+        Some(String::from_utf8_lossy(&out.stdout).into_owned())
+    };
+    let sysctl = |key: &str| -> Option<usize> { run("sysctl", &["-n", key])?.trim().parse().ok() };
+    // Find `<key> <value>` or `<key> = <value>` in the output of pmset
+    let pmset_value = |out: &str, key: &str| -> Option<usize> {
+        out.lines()
+            .find_map(|l| l.trim().strip_prefix(key))
+            .and_then(|v| v.trim().trim_start_matches('=').trim().parse().ok())
+    };
+    // On Apple Silicon, perflevel0 is the performance cores, and perflevel1 is the efficiency cores
+    let (performance_cores, efficiency_cores) = match sysctl("hw.nperflevels") {
+        Some(n) if n > 1 => (
+            sysctl("hw.perflevel0.physicalcpu"),
+            sysctl("hw.perflevel1.physicalcpu"),
+        ),
+        _ => (sysctl("hw.physicalcpu"), Some(0)),
+    };
+    let pmset = run("pmset", &["-g"]).unwrap_or_default();
+    let therm = run("pmset", &["-g", "therm"]).unwrap_or_default();
+    let spotlight = run("mdutil", &["-s", "/"]);
+    let batt = run("pmset", &["-g", "batt"]);
+    MacInfo {
+        performance_cores,
+        efficiency_cores,
+        low_power_mode: pmset_value(&pmset, "lowpowermode").map(|v| v != 0),
+        cpu_speed_limit: pmset_value(&therm, "CPU_Speed_Limit"),
+        spotlight_indexing: spotlight.map(|s| s.contains("Indexing enabled")),
+        on_battery: batt.map(|s| s.contains("'Battery Power'")),
+        // Apple Silicon has no SMT. Intel Macs have two logical CPUs per core with hyperthreading.
+        smt: sysctl("hw.logicalcpu")
+            .zip(sysctl("hw.physicalcpu"))
+            .map(|(l, p)| l > p),
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn get_mac_info() -> MacInfo {
+    MacInfo::default()
+}
+
+/// Parse a linux cpu list. e.g. `0-3,8,10-11`
+#[cfg(target_os = "linux")]
+pub(crate) fn parse_cpu_list(list: &str) -> Vec<usize> {
+    let mut cpus = vec![];
+    for part in list.trim().split(',').filter(|s| !s.is_empty()) {
+        match part.split_once('-') {
+            Some((a, b)) => {
+                if let (Ok(a), Ok(b)) = (a.parse::<usize>(), b.parse::<usize>()) {
+                    cpus.extend(a..=b);
+                }
+            }
+            None => cpus.extend(part.parse::<usize>()),
+        }
+    }
+    cpus
+}
+
+/// Detect the performance and efficiency CPUs of a hybrid CPU.
+/// Returns two empty lists if all CPUs are of the same class.
+#[cfg(target_os = "linux")]
+fn get_cpu_classes() -> (Vec<usize>, Vec<usize>) {
+    // Intel hybrid CPUs expose the two core types as separate PMUs
+    let core = std::fs::read_to_string("/sys/devices/cpu_core/cpus");
+    let atom = std::fs::read_to_string("/sys/devices/cpu_atom/cpus");
+    if let (Ok(core), Ok(atom)) = (core, atom) {
+        return (parse_cpu_list(&core), parse_cpu_list(&atom));
+    }
+    // ARM big.LITTLE CPUs report a different capacity for each core class
+    let mut capacities = vec![];
+    let Ok(entries) = std::fs::read_dir("/sys/devices/system/cpu/") else {
+        return (vec![], vec![]);
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Some(id) = name
+            .strip_prefix("cpu")
+            .and_then(|s| s.parse::<usize>().ok())
+        else {
+            continue;
+        };
+        let capacity = std::fs::read_to_string(entry.path().join("cpu_capacity"))
+            .ok()
+            .and_then(|s| s.trim().parse::<usize>().ok());
+        if let Some(capacity) = capacity {
+            capacities.push((id, capacity));
+        }
+    }
+    capacities.sort();
+    let Some(max) = capacities.iter().map(|(_, c)| *c).max() else {
+        return (vec![], vec![]);
+    };
+    if capacities.iter().all(|(_, c)| *c == max) {
+        return (vec![], vec![]);
+    }
+    let (p, e): (Vec<_>, Vec<_>) = capacities.into_iter().partition(|(_, c)| *c == max);
+    (
+        p.into_iter().map(|(id, _)| id).collect(),
+        e.into_iter().map(|(id, _)| id).collect(),
+    )
+}
+
+#[cfg(not(target_os = "linux"))]
+fn get_cpu_classes() -> (Vec<usize>, Vec<usize>) {
+    (vec![], vec![])
+}
+
 fn get_rustc_version() -> Option<String> {
     let v = rustc_version::version_meta().ok()?;
     Some(format!(
@@ -57,6 +229,9 @@ pub fn get_current_system_info() -> SystemInfo {
     let mut sys = System::new_all();
     sys.refresh_all();
     const UNKNOWN: &str = "<unknown>";
+    let mac = get_mac_info();
+    let (performance_cpus, efficiency_cpus) = get_cpu_classes();
+    let (cpu_temperature, cpu_temperature_limit) = get_cpu_temperature();
     SystemInfo {
         host: sys.host_name().unwrap_or(UNKNOWN.to_string()),
         os: sys.long_os_version().unwrap_or(UNKNOWN.to_string()),
@@ -70,9 +245,536 @@ pub fn get_current_system_info() -> SystemInfo {
         env: std::env::vars().collect(),
         pid: std::process::id() as usize,
         rustc: get_rustc_version().unwrap_or_else(|| UNKNOWN.to_string()),
-        #[cfg(target_os = "linux")]
+        toolchain: super::toolchain::current(),
         users: get_logged_in_users().unwrap_or_default(),
-        #[cfg(target_os = "linux")]
         scaling_governor: get_scaling_governor().unwrap_or_default(),
+        power_plan: get_power_plan(),
+        performance_cpus,
+        efficiency_cpus,
+        performance_cores: mac.performance_cores,
+        efficiency_cores: mac.efficiency_cores,
+        low_power_mode: mac.low_power_mode,
+        cpu_speed_limit: mac.cpu_speed_limit,
+        spotlight_indexing: mac.spotlight_indexing,
+        on_battery: get_on_battery().or(mac.on_battery),
+        cpu_temperature,
+        cpu_temperature_limit,
+        smt: get_smt().or(mac.smt),
+        turbo_boost: get_turbo_boost(),
+        randomize_va_space: get_randomize_va_space(),
+        numa_nodes: get_numa_nodes(),
+        aslr_disabled: false,
+    }
+}
+
+/// Returns the CPUs that the current process is allowed to run on.
+#[cfg(target_os = "linux")]
+pub fn get_cpu_affinity() -> anyhow::Result<Vec<usize>> {
+    // SAFETY: `cpu_set_t` is a plain bitmask, and is fully initialized by `sched_getaffinity`.
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        if libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set) != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok((0..libc::CPU_SETSIZE as usize)
+            .filter(|cpu| libc::CPU_ISSET(*cpu, &set))
+            .collect())
+    }
+}
+
+/// Resolve the CPUs that the benchmark processes should be pinned to.
+/// Returns `None` if no pinning is requested.
+#[cfg(target_os = "linux")]
+pub fn resolve_cpu_affinity(
+    cpus: Option<&[usize]>,
+    isolate_cores: bool,
+) -> anyhow::Result<Option<Vec<usize>>> {
+    let mut cpus = if isolate_cores {
+        let isolated = std::fs::read_to_string("/sys/devices/system/cpu/isolated")
+            .map(|s| parse_cpu_list(&s))
+            .unwrap_or_default();
+        if isolated.is_empty() {
+            anyhow::bail!("No isolated CPUs found. Use the `isolcpus` kernel parameter to isolate CPUs from the scheduler.");
+        }
+        isolated
+    } else if let Some(cpus) = cpus {
+        if cpus.is_empty() {
+            anyhow::bail!("The list of CPUs to pin the benchmarks to is empty");
+        }
+        cpus.to_vec()
+    } else {
+        return Ok(None);
+    };
+    cpus.sort();
+    cpus.dedup();
+    // Isolated CPUs are not in the default affinity mask, so only check that they exist
+    let available = if isolate_cores {
+        std::fs::read_to_string("/sys/devices/system/cpu/possible")
+            .map(|s| parse_cpu_list(&s))
+            .unwrap_or_default()
+    } else {
+        get_cpu_affinity()?
+    };
+    if let Some(cpu) = cpus.iter().find(|c| !available.contains(c)) {
+        anyhow::bail!("CPU {} is not available for benchmarking", cpu);
+    }
+    Ok(Some(cpus))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn resolve_cpu_affinity(
+    cpus: Option<&[usize]>,
+    isolate_cores: bool,
+) -> anyhow::Result<Option<Vec<usize>>> {
+    if cpus.is_some() || isolate_cores {
+        anyhow::bail!("Pinning the benchmarks to CPUs is only supported on Linux");
+    }
+    Ok(None)
+}
+
+/// Returns the CPUs and the memory size of each NUMA node, sorted by the node id.
+/// Returns an empty list if the NUMA topology is not available.
+#[cfg(target_os = "linux")]
+fn get_numa_nodes() -> Vec<NumaNode> {
+    let mut nodes = vec![];
+    let Ok(entries) = std::fs::read_dir("/sys/devices/system/node/") else {
+        return nodes;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Some(id) = name
+            .strip_prefix("node")
+            .and_then(|s| s.parse::<usize>().ok())
+        else {
+            continue;
+        };
+        let Ok(cpus) = std::fs::read_to_string(entry.path().join("cpulist")) else {
+            continue;
+        };
+        // e.g. `Node 0 MemTotal:       65536000 kB`
+        let memory_size = std::fs::read_to_string(entry.path().join("meminfo"))
+            .ok()
+            .and_then(|s| {
+                s.lines()
+                    .find(|l| l.contains("MemTotal:"))
+                    .and_then(|l| l.split_whitespace().nth(3)?.parse::<usize>().ok())
+            })
+            .map(|kb| kb * 1024)
+            .unwrap_or(0);
+        nodes.push(NumaNode {
+            id,
+            cpus: parse_cpu_list(&cpus),
+            memory_size,
+        });
     }
+    nodes.sort_by_key(|n| n.id);
+    nodes
+}
+
+#[cfg(not(target_os = "linux"))]
+fn get_numa_nodes() -> Vec<NumaNode> {
+    vec![]
+}
+
+/// Launch the command with a fixed CPU affinity, and optionally bind its memory allocations to a NUMA node.
+#[cfg(target_os = "linux")]
+pub fn bind_command(cmd: &mut std::process::Command, cpus: &[usize], numa_node: Option<usize>) {
+    use std::os::unix::process::CommandExt;
+    // SAFETY: `cpu_set_t` is a plain bitmask, and is fully initialized by `CPU_ZERO` before use.
+    let set = unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_ZERO(&mut set);
+        for cpu in cpus {
+            libc::CPU_SET(*cpu, &mut set);
+        }
+        set
+    };
+    // SAFETY: Only async-signal-safe syscalls are made between fork and exec.
+    unsafe {
+        cmd.pre_exec(move || {
+            if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            if let Some(node) = numa_node.filter(|n| *n < libc::c_ulong::BITS as usize) {
+                const MPOL_BIND: libc::c_int = 2;
+                let mask: libc::c_ulong = 1 << node;
+                let max_node = libc::c_ulong::BITS as libc::c_ulong;
+                if libc::syscall(libc::SYS_set_mempolicy, MPOL_BIND, &mask, max_node) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        });
+    }
+}
+
+/// Launch the command with address space layout randomization disabled.
+/// The personality is inherited by `cargo bench` and the bench binary it spawns.
+#[cfg(target_os = "linux")]
+pub fn disable_aslr(cmd: &mut std::process::Command) {
+    use std::os::unix::process::CommandExt;
+    // SAFETY: Only async-signal-safe syscalls are made between fork and exec.
+    unsafe {
+        cmd.pre_exec(|| {
+            let persona = libc::personality(0xffffffff);
+            if persona == -1
+                || libc::personality((persona | libc::ADDR_NO_RANDOMIZE) as libc::c_ulong) == -1
+            {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+}
+
+/// Launch the command with the given nice level, and/or in the `SCHED_FIFO` real-time class with the given priority.
+#[cfg(target_os = "linux")]
+pub fn set_priority(
+    cmd: &mut std::process::Command,
+    nice: Option<i32>,
+    realtime_priority: Option<u32>,
+) {
+    use std::os::unix::process::CommandExt;
+    // SAFETY: Only async-signal-safe syscalls are made between fork and exec.
+    unsafe {
+        cmd.pre_exec(move || {
+            if let Some(nice) = nice {
+                if libc::setpriority(libc::PRIO_PROCESS, 0, nice) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+            }
+            if let Some(priority) = realtime_priority {
+                let param = libc::sched_param {
+                    sched_priority: priority as libc::c_int,
+                };
+                if libc::sched_setscheduler(0, libc::SCHED_FIFO, &param) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        });
+    }
+}
+
+/// Check if the current process is allowed to lower the nice level, or to use the real-time scheduling class,
+/// either with `CAP_SYS_NICE` or within the `RLIMIT_NICE` / `RLIMIT_RTPRIO` resource limits.
+#[cfg(target_os = "linux")]
+pub fn check_priority_privileges(
+    nice: Option<i32>,
+    realtime_priority: Option<u32>,
+) -> anyhow::Result<()> {
+    const CAP_SYS_NICE: u32 = 23;
+    let cap_sys_nice = std::fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|s| {
+            let caps = s.lines().find_map(|l| l.strip_prefix("CapEff:"))?;
+            u64::from_str_radix(caps.trim(), 16).ok()
+        })
+        .is_some_and(|caps| caps & (1 << CAP_SYS_NICE) != 0);
+    if cap_sys_nice {
+        return Ok(());
+    }
+    let get_rlimit = |resource| {
+        // SAFETY: `rlimit` is fully initialized by `getrlimit`.
+        unsafe {
+            let mut limit: libc::rlimit = std::mem::zeroed();
+            if libc::getrlimit(resource, &mut limit) != 0 {
+                return 0;
+            }
+            limit.rlim_cur
+        }
+    };
+    if let Some(nice) = nice.filter(|n| *n < 0) {
+        // The nice ceiling is `20 - rlim_cur`
+        if 20 - (get_rlimit(libc::RLIMIT_NICE).min(40) as i32) > nice {
+            anyhow::bail!(
+                "Not allowed to set the nice level to {}. Run as root, grant CAP_SYS_NICE, or raise RLIMIT_NICE.",
+                nice
+            );
+        }
+    }
+    if let Some(priority) = realtime_priority {
+        if get_rlimit(libc::RLIMIT_RTPRIO) < priority as u64 {
+            anyhow::bail!(
+                "Not allowed to use the real-time priority {}. Run as root, grant CAP_SYS_NICE, or raise RLIMIT_RTPRIO.",
+                priority
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Whether the machine has a battery, but no online AC adapter
+#[cfg(target_os = "linux")]
+fn get_on_battery() -> Option<bool> {
+    let read = |path: std::path::PathBuf| std::fs::read_to_string(path).unwrap_or_default();
+    let mut has_battery = false;
+    let mut on_ac = false;
+    for supply in std::fs::read_dir("/sys/class/power_supply/")
+        .ok()?
+        .flatten()
+    {
+        match read(supply.path().join("type")).trim() {
+            "Battery" => has_battery = true,
+            "Mains" | "USB" => on_ac |= read(supply.path().join("online")).trim() == "1",
+            _ => {}
+        }
+    }
+    Some(has_battery && !on_ac)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn get_on_battery() -> Option<bool> {
+    None
+}
+
+/// The hwmon drivers of the CPU temperature sensors
+#[cfg(target_os = "linux")]
+const CPU_HWMON_DRIVERS: &[&str] = &["coretemp", "k10temp", "zenpower", "cpu_thermal"];
+
+/// The highest temperature of the CPU sensors, and the lowest critical temperature of them, in °C
+#[cfg(target_os = "linux")]
+fn get_cpu_temperature() -> (Option<f64>, Option<f64>) {
+    // hwmon reports temperatures in millidegrees Celsius
+    let read = |path: std::path::PathBuf| -> Option<f64> {
+        let v = std::fs::read_to_string(path)
+            .ok()?
+            .trim()
+            .parse::<f64>()
+            .ok()?;
+        Some(v / 1000.0)
+    };
+    let (mut temperature, mut limit) = (None::<f64>, None::<f64>);
+    let Ok(entries) = std::fs::read_dir("/sys/class/hwmon/") else {
+        return (None, None);
+    };
+    for hwmon in entries.flatten().map(|e| e.path()) {
+        let name = std::fs::read_to_string(hwmon.join("name")).unwrap_or_default();
+        if !CPU_HWMON_DRIVERS.contains(&name.trim()) {
+            continue;
+        }
+        let Ok(files) = std::fs::read_dir(&hwmon) else {
+            continue;
+        };
+        for file in files.flatten() {
+            let file = file.file_name().to_string_lossy().into_owned();
+            let Some(sensor) = file
+                .strip_suffix("_input")
+                .filter(|f| f.starts_with("temp"))
+            else {
+                continue;
+            };
+            if let Some(t) = read(hwmon.join(&file)) {
+                temperature = Some(temperature.map_or(t, |x| x.max(t)));
+            }
+            let crit = read(hwmon.join(format!("{}_crit", sensor)))
+                .or_else(|| read(hwmon.join(format!("{}_max", sensor))));
+            if let Some(c) = crit.filter(|c| *c > 0.0) {
+                limit = Some(limit.map_or(c, |x| x.min(c)));
+            }
+        }
+    }
+    (temperature, limit)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn get_cpu_temperature() -> (Option<f64>, Option<f64>) {
+    (None, None)
+}
+
+/// Whether SMT is active. `None` if the kernel does not support SMT control.
+#[cfg(target_os = "linux")]
+fn get_smt() -> Option<bool> {
+    let active = std::fs::read_to_string("/sys/devices/system/cpu/smt/active").ok()?;
+    Some(active.trim() == "1")
+}
+
+#[cfg(not(target_os = "linux"))]
+fn get_smt() -> Option<bool> {
+    None
+}
+
+/// Whether turbo boost is enabled, from the `intel_pstate` driver or the generic `cpufreq` boost switch
+#[cfg(target_os = "linux")]
+fn get_turbo_boost() -> Option<bool> {
+    let read = |path: &str| std::fs::read_to_string(path).ok().map(|s| s.trim() == "1");
+    read("/sys/devices/system/cpu/intel_pstate/no_turbo")
+        .map(|no_turbo| !no_turbo)
+        .or_else(|| read("/sys/devices/system/cpu/cpufreq/boost"))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn get_turbo_boost() -> Option<bool> {
+    None
+}
+
+/// (*Linux only*) The system-wide ASLR setting in `/proc/sys/kernel/randomize_va_space`. `0` means disabled
+#[cfg(target_os = "linux")]
+fn get_randomize_va_space() -> Option<usize> {
+    std::fs::read_to_string("/proc/sys/kernel/randomize_va_space")
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn get_randomize_va_space() -> Option<usize> {
+    None
+}
+
+/// Kill a process and all its descendants. The caller is responsible for reaping the process.
+///
+/// On Unix, the process must be the leader of its own process group (see `CommandExt::process_group`).
+pub fn kill_process_tree(pid: u32) -> anyhow::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        // SAFETY: Sends a signal to the process group of the child. No memory is accessed.
+        if unsafe { libc::kill(-(pid as i32), libc::SIGKILL) } != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+    }
+    #[cfg(all(unix, not(target_os = "linux")))]
+    {
+        std::process::Command::new("kill")
+            .args(["-KILL", &format!("-{}", pid)])
+            .status()?;
+    }
+    #[cfg(target_os = "windows")]
+    {
+        std::process::Command::new("taskkill")
+            .args(["/T", "/F", "/PID", &pid.to_string()])
+            .status()?;
+    }
+    Ok(())
+}
+
+/// The process group of the running command, killed if harness is interrupted. 0 if there is none.
+#[cfg(target_os = "linux")]
+static INTERRUPTIBLE_GROUP: std::sync::atomic::AtomicI32 = std::sync::atomic::AtomicI32::new(0);
+
+#[cfg(target_os = "linux")]
+extern "C" fn kill_interruptible_group(signal: libc::c_int) {
+    let pgid = INTERRUPTIBLE_GROUP.load(std::sync::atomic::Ordering::SeqCst);
+    // SAFETY: `kill`, `signal` and `raise` are async-signal-safe.
+    unsafe {
+        if pgid > 0 {
+            libc::kill(-pgid, libc::SIGKILL);
+        }
+        libc::signal(signal, libc::SIG_DFL);
+        libc::raise(signal);
+    }
+}
+
+/// Kills the process group of a command if harness receives SIGINT or SIGTERM, until it is dropped.
+/// Created by `forward_interrupts`.
+pub struct InterruptGuard(());
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        #[cfg(target_os = "linux")]
+        INTERRUPTIBLE_GROUP.store(0, std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+/// Kill the process group of `pid` if harness is interrupted by SIGINT or SIGTERM while the guard is alive, and then exit with the signal.
+///
+/// A process in its own process group does not receive the Ctrl-C of the terminal, and would be orphaned otherwise.
+/// The process must be the leader of its own process group (see `CommandExt::process_group`).
+pub fn forward_interrupts(pid: u32) -> InterruptGuard {
+    #[cfg(target_os = "linux")]
+    {
+        static INSTALL: std::sync::Once = std::sync::Once::new();
+        INTERRUPTIBLE_GROUP.store(pid as i32, std::sync::atomic::Ordering::SeqCst);
+        INSTALL.call_once(|| {
+            for signal in [libc::SIGINT, libc::SIGTERM] {
+                // SAFETY: The handler only calls async-signal-safe functions.
+                unsafe {
+                    libc::signal(
+                        signal,
+                        kill_interruptible_group as *const () as libc::sighandler_t,
+                    );
+                }
+            }
+        });
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = pid;
+    InterruptGuard(())
+}
+
+/// The resource usage of a finished process, including all its descendants that it has waited for
+#[derive(Debug, Clone, Copy)]
+pub struct ResourceUsage {
+    /// The largest resident set size of the process or any of its descendants, in bytes
+    pub max_rss: u64,
+    /// CPU time spent in user mode
+    pub user_time: std::time::Duration,
+    /// CPU time spent in the kernel
+    pub sys_time: std::time::Duration,
+}
+
+impl ResourceUsage {
+    /// The `process-max-rss` (bytes), `process-user-time`, and `process-sys-time` (ms) stats
+    pub fn stats(&self) -> Vec<(String, String)> {
+        vec![
+            ("process-max-rss".to_owned(), self.max_rss.to_string()),
+            (
+                "process-user-time".to_owned(),
+                format!("{:.3}", self.user_time.as_secs_f64() * 1000.0),
+            ),
+            (
+                "process-sys-time".to_owned(),
+                format!("{:.3}", self.sys_time.as_secs_f64() * 1000.0),
+            ),
+        ]
+    }
+}
+
+/// Wait for a child process to exit, and collect its resource usage with `wait4(2)`.
+#[cfg(target_os = "linux")]
+pub fn wait_with_rusage(
+    child: &mut std::process::Child,
+) -> std::io::Result<(std::process::ExitStatus, Option<ResourceUsage>)> {
+    use std::os::unix::process::ExitStatusExt;
+    let mut status = 0;
+    let mut usage = std::mem::MaybeUninit::<libc::rusage>::zeroed();
+    loop {
+        // SAFETY: `status` and `usage` are valid for writes. The child is reaped here instead of by `Child::wait`.
+        if unsafe {
+            libc::wait4(
+                child.id() as libc::pid_t,
+                &mut status,
+                0,
+                usage.as_mut_ptr(),
+            )
+        } != -1
+        {
+            break;
+        }
+        let e = std::io::Error::last_os_error();
+        if e.kind() != std::io::ErrorKind::Interrupted {
+            return Err(e);
+        }
+    }
+    // SAFETY: `wait4` has succeeded and filled in `usage`
+    let usage = unsafe { usage.assume_init() };
+    let time = |t: libc::timeval| {
+        std::time::Duration::from_secs(t.tv_sec as u64)
+            + std::time::Duration::from_micros(t.tv_usec as u64)
+    };
+    let usage = ResourceUsage {
+        // `ru_maxrss` is in kilobytes on Linux
+        max_rss: usage.ru_maxrss as u64 * 1024,
+        user_time: time(usage.ru_utime),
+        sys_time: time(usage.ru_stime),
+    };
+    Ok((std::process::ExitStatus::from_raw(status), Some(usage)))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn wait_with_rusage(
+    child: &mut std::process::Child,
+) -> std::io::Result<(std::process::ExitStatus, Option<ResourceUsage>)> {
+    Ok((child.wait()?, None))
 }
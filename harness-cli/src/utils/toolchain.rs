@@ -0,0 +1,79 @@
+use std::process::Command;
+
+use chrono::{Days, NaiveDate};
+
+use crate::configs::run_info::Toolchain;
+
+/// The toolchain of the `rustc` that cargo uses in the current directory
+pub fn current() -> Option<Toolchain> {
+    let v = rustc_version::version_meta().ok()?;
+    Some(Toolchain {
+        channel: format!("{:?}", v.channel).to_lowercase(),
+        version: format!("{}.{}.{}", v.semver.major, v.semver.minor, v.semver.patch),
+        commit_hash: v.commit_hash,
+        commit_date: v.commit_date,
+        host: v.host,
+    })
+}
+
+/// The rustup name of a toolchain, e.g. `1.78.0-x86_64-unknown-linux-gnu` or `nightly-2024-05-01-x86_64-unknown-linux-gnu`.
+///
+/// Nightly and beta toolchains are published the day after their commit date.
+fn rustup_name(toolchain: &Toolchain) -> anyhow::Result<String> {
+    let name = match toolchain.channel.as_str() {
+        "stable" => toolchain.version.clone(),
+        "beta" | "nightly" => {
+            let Some(date) = toolchain
+                .commit_date
+                .as_deref()
+                .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+            else {
+                anyhow::bail!("Unknown commit date of the {} toolchain", toolchain.channel);
+            };
+            let date = date.checked_add_days(Days::new(1)).unwrap();
+            format!("{}-{}", toolchain.channel, date.format("%Y-%m-%d"))
+        }
+        c => anyhow::bail!("Cannot install a `{}` toolchain with rustup", c),
+    };
+    Ok(format!("{}-{}", name, toolchain.host))
+}
+
+/// Whether two toolchains are the same build of rustc
+pub fn is_same(a: &Toolchain, b: &Toolchain) -> bool {
+    match (&a.commit_hash, &b.commit_hash) {
+        (Some(x), Some(y)) => x == y && a.host == b.host,
+        _ => a == b,
+    }
+}
+
+/// Install the toolchain with rustup if needed, and use it for all the subsequent cargo and rustc commands.
+pub fn install_and_use(toolchain: &Toolchain) -> anyhow::Result<()> {
+    let name = rustup_name(toolchain)?;
+    let status = Command::new("rustup")
+        .args([
+            "toolchain",
+            "install",
+            &name,
+            "--profile",
+            "minimal",
+            "--no-self-update",
+        ])
+        .status()
+        .map_err(|e| anyhow::anyhow!("Failed to run rustup: {}", e))?;
+    if !status.success() {
+        anyhow::bail!("Failed to install toolchain `{}`", name);
+    }
+    std::env::set_var("RUSTUP_TOOLCHAIN", &name);
+    match current() {
+        Some(t) if is_same(&t, toolchain) => Ok(()),
+        Some(t) => anyhow::bail!(
+            "Toolchain `{}` is rustc {} ({}), not the recorded rustc {} ({})",
+            name,
+            t.version,
+            t.commit_hash.unwrap_or_default(),
+            toolchain.version,
+            toolchain.commit_hash.clone().unwrap_or_default()
+        ),
+        None => anyhow::bail!("Failed to get the version of toolchain `{}`", name),
+    }
+}
@@ -0,0 +1,152 @@
+//! Snapshots of the commits of the builds.
+//!
+//! Instead of checking out the commit of a build in the current worktree, each commit is checked out once into its own git worktree,
+//! `target/harness/worktrees/<COMMIT>/`, where the build is compiled and run. The current worktree is left untouched for the whole run.
+//! A dirty commit, i.e. the current commit with uncommitted changes, cannot be snapshotted, so its builds still use the current worktree.
+//!
+//! A build of a crates.io version of the crate is compiled and run in the extracted package, `target/harness/worktrees/<crate>-<version>/`.
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use super::dataset;
+
+/// The file in an extracted crates.io package that marks it as complete.
+/// It is written after the package is extracted, so an incomplete package is always downloaded again.
+const EXTRACTED_MARKER: &str = ".harness-extracted";
+
+fn git(args: &[&str], dir: Option<&Path>) -> anyhow::Result<String> {
+    let mut cmd = Command::new("git");
+    if let Some(dir) = dir {
+        cmd.current_dir(dir);
+    }
+    let out = cmd.args(args).output()?;
+    if !out.status.success() {
+        anyhow::bail!(
+            "`git {}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&out.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).trim().to_owned())
+}
+
+/// The directory of all the worktrees
+pub fn worktrees_dir(target_dir: &Path) -> PathBuf {
+    target_dir.join("harness").join("worktrees")
+}
+
+/// Drop the records of the deleted worktrees from the repository
+pub fn prune() -> anyhow::Result<()> {
+    git(&["worktree", "prune"], None)?;
+    Ok(())
+}
+
+/// A commit checked out in its own worktree, or an extracted crates.io package
+#[derive(Debug)]
+pub struct Snapshot {
+    /// The root of the worktree
+    pub root: PathBuf,
+    /// The full hash of the commit, or `<crate>@<version>` for a crates.io package
+    pub commit: String,
+    /// The root of the current worktree, or the current package for a crates.io package
+    toplevel: PathBuf,
+}
+
+impl Snapshot {
+    /// Check out a commit into its worktree.
+    /// An existing worktree of the commit, e.g. from a previous run, is reused after discarding all its changes and untracked files.
+    pub fn create(target_dir: &Path, commit: &str) -> anyhow::Result<Self> {
+        let toplevel = PathBuf::from(git(&["rev-parse", "--show-toplevel"], None)?);
+        let hash = git(
+            &["rev-parse", "--verify", &format!("{}^{{commit}}", commit)],
+            None,
+        )?;
+        let root = worktrees_dir(target_dir).join(&hash);
+        if root.join(".git").exists() {
+            git(&["reset", "--hard", "--quiet", &hash], Some(&root))?;
+            git(&["clean", "-f", "-d", "-x", "--quiet"], Some(&root))?;
+        } else {
+            if root.exists() {
+                std::fs::remove_dir_all(&root)?;
+            }
+            prune()?;
+            git(
+                &[
+                    "worktree",
+                    "add",
+                    "--detach",
+                    "--force",
+                    &root.to_string_lossy(),
+                    &hash,
+                ],
+                None,
+            )?;
+        }
+        if root.join(".gitmodules").exists() {
+            git(
+                &["submodule", "update", "--init", "--recursive", "--quiet"],
+                Some(&root),
+            )?;
+        }
+        Ok(Self {
+            root,
+            commit: hash,
+            toplevel,
+        })
+    }
+
+    /// Download a version of a crate from crates.io, and extract it into `target/harness/worktrees/<crate>-<version>/`.
+    /// The package is extracted once, and reused by later runs.
+    pub fn download(target_dir: &Path, name: &str, version: &str) -> anyhow::Result<Self> {
+        let dir = worktrees_dir(target_dir);
+        let package = format!("{}-{}", name, version);
+        let root = dir.join(&package);
+        if !root.join(EXTRACTED_MARKER).exists() {
+            if root.exists() {
+                std::fs::remove_dir_all(&root)?;
+            }
+            std::fs::create_dir_all(&dir)?;
+            let file = dir.join(format!("{}.crate", package));
+            let url = format!("https://static.crates.io/crates/{}/{}.crate", name, package);
+            dataset::download(&url, &file)
+                .map_err(|e| anyhow::anyhow!("Failed to download {}: {}", url, e))?;
+            let status = Command::new("tar")
+                .arg("-xzf")
+                .arg(&file)
+                .arg("-C")
+                .arg(&dir)
+                .status()?;
+            std::fs::remove_file(&file)?;
+            if !status.success() {
+                anyhow::bail!("Failed to extract {}: {}", file.display(), status);
+            }
+            // Make the package its own workspace, instead of a stray member of the workspace that contains the target directory
+            let manifest = root.join("Cargo.toml");
+            let mut toml = std::fs::read_to_string(&manifest)?;
+            if !toml.lines().any(|l| l.trim() == "[workspace]") {
+                toml += "\n[workspace]\n";
+                std::fs::write(&manifest, toml)?;
+            }
+            std::fs::write(root.join(EXTRACTED_MARKER), "")?;
+        }
+        let toplevel = std::env::current_dir()?;
+        Ok(Self {
+            root,
+            commit: format!("{}@{}", name, version),
+            toplevel: toplevel.canonicalize().unwrap_or(toplevel),
+        })
+    }
+
+    /// The path in the snapshot that corresponds to `path` in the current worktree.
+    /// Paths outside the current worktree are returned as is.
+    pub fn path(&self, path: &Path) -> PathBuf {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_owned());
+        match path.strip_prefix(&self.toplevel) {
+            Ok(relative) => self.root.join(relative),
+            Err(_) => path,
+        }
+    }
+}
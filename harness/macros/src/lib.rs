@@ -11,13 +11,15 @@ struct BenchMacroArgs {
     startup: Option<syn::Path>,
     #[darling(default)]
     teardown: Option<syn::Path>,
+    #[darling(default)]
+    fixture: Option<syn::Path>,
 }
 
 /// Annotation for the benchmark function.
 ///
 /// The annotated function will be invoked for **N** iterations at a time in a loop.
 ///
-/// Iterations **0** ~ **N-2** will be used for warm-up, and the last iteration (**N-1**) will be used for measurement.
+/// The first iterations are used for warm-up, and the last iteration(s) are used for measurement.
 ///
 /// Each iteration has three phases:
 /// 1. **Prepare**: Prepare any data or resources needed for this iteration.
@@ -79,6 +81,27 @@ struct BenchMacroArgs {
 ///     assert_eq!(result, LEN * (LEN - 1) / 2)
 /// }
 /// ````
+///
+/// # Fixtures
+///
+/// To share an input across all the iterations of an invocation, use the `fixture` attribute.
+///
+/// The fixture function is called once per invocation, after `startup` and outside of the timing phase.
+/// Its result is passed to each iteration by reference, as the second argument of the benchmark function.
+///
+/// ```rust
+/// use harness::{bench, Bencher};
+///
+/// fn make_input() -> Vec<usize> {
+///     (0..10000000).collect()
+/// }
+///
+/// #[bench(fixture = make_input)]
+/// fn example(bencher: &Bencher, input: &Vec<usize>) {
+///     let result = bencher.time(|| input.iter().sum::<usize>());
+///     assert_eq!(result, input.len() * (input.len() - 1) / 2)
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn bench(attr: TokenStream, item: TokenStream) -> TokenStream {
     let input = syn::parse_macro_input!(item as syn::ItemFn);
@@ -97,13 +120,15 @@ pub fn bench(attr: TokenStream, item: TokenStream) -> TokenStream {
     };
     let startup = &args.startup;
     let teardown = &args.teardown;
-    let result = if args.oneshot {
+    let oneshot = args.oneshot;
+    let result = if let Some(fixture) = &args.fixture {
         quote! {
             #input
 
             fn main() {
                 #startup();
-                ::harness::run(file!(), #name, true);
+                let fixture = #fixture();
+                ::harness::run(file!(), move |bencher: &::harness::Bencher| #name(bencher, &fixture), #oneshot);
                 #teardown();
             }
         }
@@ -113,7 +138,7 @@ pub fn bench(attr: TokenStream, item: TokenStream) -> TokenStream {
 
             fn main() {
                 #startup();
-                ::harness::run(file!(), #name, false);
+                ::harness::run(file!(), #name, #oneshot);
                 #teardown();
             }
         }
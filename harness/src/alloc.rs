@@ -0,0 +1,207 @@
+//! Allocation counting support.
+//!
+//! Install [`CountingAllocator`] as the global allocator of a benchmark to report the
+//! allocation count, the total allocated bytes, and the peak live bytes for the timing iteration:
+//!
+//! ```rust
+//! use harness::alloc::CountingAllocator;
+//!
+//! #[global_allocator]
+//! static ALLOC: CountingAllocator = CountingAllocator::new();
+//! ```
+//!
+//! The companion probe is registered automatically once the allocator is installed,
+//! and adds the `alloc-count`, `alloc-bytes`, and `alloc-peak-live-bytes` columns to the results.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use crate::probe::Probe;
+use crate::Value;
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+static PEAK_LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// A global allocator wrapper that counts all allocations.
+///
+/// By default it forwards all requests to the system allocator.
+/// Use [`CountingAllocator::with_allocator`] to wrap a different allocator.
+pub struct CountingAllocator<A: GlobalAlloc = System> {
+    inner: A,
+}
+
+impl CountingAllocator<System> {
+    pub const fn new() -> Self {
+        Self { inner: System }
+    }
+}
+
+impl Default for CountingAllocator<System> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: GlobalAlloc> CountingAllocator<A> {
+    /// Count all allocations served by `inner`.
+    pub const fn with_allocator(inner: A) -> Self {
+        Self { inner }
+    }
+
+    fn on_alloc(size: usize) {
+        INSTALLED.store(true, Ordering::Relaxed);
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(size, Ordering::Relaxed);
+        let live = LIVE_BYTES.fetch_add(size, Ordering::Relaxed) + size;
+        PEAK_LIVE_BYTES.fetch_max(live, Ordering::Relaxed);
+    }
+
+    fn on_dealloc(size: usize) {
+        LIVE_BYTES.fetch_sub(size, Ordering::Relaxed);
+    }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc(layout);
+        if !ptr.is_null() {
+            Self::on_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            Self::on_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout);
+        Self::on_dealloc(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = self.inner.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            Self::on_dealloc(layout.size());
+            Self::on_alloc(new_size);
+        }
+        new_ptr
+    }
+}
+
+/// Returns true if a `CountingAllocator` is installed as the global allocator.
+pub fn is_installed() -> bool {
+    INSTALLED.load(Ordering::Relaxed)
+}
+
+/// A snapshot of the allocation counters.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AllocStats {
+    /// Number of allocations
+    pub allocations: usize,
+    /// Total number of allocated bytes
+    pub allocated_bytes: usize,
+    /// Current number of live bytes
+    pub live_bytes: usize,
+    /// Peak number of live bytes since the last reset
+    pub peak_live_bytes: usize,
+}
+
+impl AllocStats {
+    /// Read the current allocation counters.
+    pub fn now() -> Self {
+        Self {
+            allocations: ALLOCATIONS.load(Ordering::Relaxed),
+            allocated_bytes: ALLOCATED_BYTES.load(Ordering::Relaxed),
+            live_bytes: LIVE_BYTES.load(Ordering::Relaxed),
+            peak_live_bytes: PEAK_LIVE_BYTES.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Reset the peak live bytes watermark to the current live bytes.
+fn reset_peak() {
+    PEAK_LIVE_BYTES.store(LIVE_BYTES.load(Ordering::Relaxed), Ordering::Relaxed);
+}
+
+/// The companion probe of `CountingAllocator`.
+#[derive(Default)]
+pub(crate) struct AllocProbe {
+    start: AllocStats,
+    end: AllocStats,
+}
+
+impl Probe for AllocProbe {
+    fn begin(&mut self, _benchmark: &str, _iteration: usize, _warmup: bool) {
+        reset_peak();
+        self.start = AllocStats::now();
+    }
+
+    fn end(&mut self, _benchmark: &str, _iteration: usize, _warmup: bool) {
+        self.end = AllocStats::now();
+    }
+
+    fn report(&mut self) -> HashMap<String, Value> {
+        let mut values = HashMap::new();
+        values.insert(
+            "alloc-count".to_owned(),
+            (self.end.allocations - self.start.allocations).into(),
+        );
+        values.insert(
+            "alloc-bytes".to_owned(),
+            (self.end.allocated_bytes - self.start.allocated_bytes).into(),
+        );
+        values.insert(
+            "alloc-peak-live-bytes".to_owned(),
+            self.end.peak_live_bytes.into(),
+        );
+        values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_alloc_realloc_and_dealloc() {
+        let alloc = CountingAllocator::new();
+        let small = Layout::from_size_align(100, 8).unwrap();
+        let large = Layout::from_size_align(300, 8).unwrap();
+        let mut probe = AllocProbe::default();
+        probe.begin("foo", 0, false);
+        let before = AllocStats::now();
+        unsafe {
+            let p = alloc.alloc(small);
+            let q = alloc.alloc_zeroed(small);
+            // A realloc counts as a new allocation of the new size, and frees the old one
+            let p = alloc.realloc(p, small, large.size());
+            let after = AllocStats::now();
+            assert_eq!(after.allocations - before.allocations, 3);
+            assert_eq!(after.allocated_bytes - before.allocated_bytes, 500);
+            assert_eq!(after.live_bytes - before.live_bytes, 400);
+            alloc.dealloc(p, large);
+            alloc.dealloc(q, small);
+        }
+        probe.end("foo", 0, false);
+        assert!(is_installed());
+        assert_eq!(AllocStats::now().live_bytes, before.live_bytes);
+        let values = probe.report();
+        let get = |name: &str| match values[name] {
+            Value::Usize(v) => v,
+            v => panic!("unexpected value {:?}", v),
+        };
+        assert_eq!(get("alloc-count"), 3);
+        assert_eq!(get("alloc-bytes"), 500);
+        // The peak is reached after the realloc: 300 + 100 bytes
+        assert_eq!(get("alloc-peak-live-bytes"), before.live_bytes + 400);
+    }
+}
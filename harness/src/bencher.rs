@@ -3,13 +3,14 @@ use std::fmt;
 use std::{
     cell::RefCell,
     path::PathBuf,
-    sync::Mutex,
+    sync::{Barrier, Mutex, OnceLock},
     time::{Duration, Instant},
 };
 
 use clap::Parser;
 
 use crate::{
+    latency::{LatencyHistogram, LatencySnapshot},
     probe::ProbeManager,
     record::{Record, StatPrintFormat},
 };
@@ -18,9 +19,25 @@ use crate::{
 pub struct BenchArgs {
     #[arg(long, default_value = "false")]
     pub bench: bool,
-    #[arg(short = 'n', long, default_value = "1")]
-    /// Number of iterations to run
-    pub iterations: usize,
+    #[arg(long, default_value = "0")]
+    /// Number of warm-up iterations to run before the measured iterations
+    pub warmup_iterations: usize,
+    #[arg(
+        long,
+        alias = "timing-iterations",
+        default_value = "1",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    /// Number of measured iterations to run at the end of the invocation
+    pub measured_iterations: usize,
+    #[arg(
+        short = 'n',
+        long,
+        conflicts_with = "warmup_iterations",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    /// Deprecated. Total number of iterations to run, including the measured iterations
+    pub iterations: Option<usize>,
     /// Enabled probes and their configurations, as a json string.
     #[arg(long, default_value = "{}")]
     pub probes: String,
@@ -38,12 +55,62 @@ pub struct BenchArgs {
     pub current_invocation: Option<usize>,
     #[arg(long)]
     #[doc(hidden)]
-    /// Append counter values to csv
+    /// Append counter values to csv, and to a `.jsonl` file next to it with the value types preserved
     pub output_csv: Option<PathBuf>,
     #[arg(long)]
     #[doc(hidden)]
     /// Specify current build name
     pub current_build: Option<String>,
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    #[doc(hidden)]
+    /// Sample the probes at this interval (in milliseconds) during the timing phase
+    pub sample_interval: Option<u64>,
+    #[arg(long)]
+    #[doc(hidden)]
+    /// Write the probe samples to this csv file
+    pub output_samples: Option<PathBuf>,
+    #[arg(long)]
+    #[doc(hidden)]
+    /// Write the histogram of the latencies recorded in the timing iterations to this file, in HdrHistogram's `.hgrm` format
+    pub output_latency_histogram: Option<PathBuf>,
+    #[arg(long, default_value = "false")]
+    #[doc(hidden)]
+    /// Enable the probes for the warm-up iterations as well
+    pub measure_all_iterations: bool,
+    #[arg(long)]
+    #[doc(hidden)]
+    /// Keep running warm-up iterations until the coefficient of variation of the recent walltimes drops below this threshold
+    pub steady_state_cv: Option<f64>,
+    #[arg(long, default_value = "3")]
+    #[doc(hidden)]
+    /// Number of recent warm-up iterations used to detect the steady state
+    pub steady_state_window: usize,
+    #[arg(long, default_value = "50")]
+    #[doc(hidden)]
+    /// Maximum number of warm-up iterations before the steady state is reached
+    pub max_warmup_iterations: usize,
+    /// The arguments of the benchmark program, after `--`
+    #[arg(last = true)]
+    pub program_args: Vec<String>,
+}
+
+impl BenchArgs {
+    /// Parse the command line arguments.
+    /// `cargo bench` appends `--bench` to the arguments, which ends up after the program arguments if there are any.
+    fn parse_command_line() -> Self {
+        let mut args = Self::parse();
+        if args.program_args.last().is_some_and(|a| a == "--bench") {
+            args.program_args.pop();
+            args.bench = true;
+        }
+        args
+    }
+
+    /// The total number of iterations of an invocation
+    fn total_iterations(&self) -> usize {
+        self.iterations
+            .unwrap_or(self.warmup_iterations + self.measured_iterations)
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -81,6 +148,25 @@ impl Value {
             Value::Bool(v) => v.to_string(),
         }
     }
+
+    pub(crate) fn into_json(self) -> serde_json::Value {
+        match self {
+            Value::F64(v) => v.into(),
+            // Go through the shortest decimal representation to avoid e.g. `0.1f32` becoming `0.10000000149011612`
+            Value::F32(v) => v.to_string().parse::<f64>().unwrap().into(),
+            Value::Usize(v) => v.into(),
+            Value::Isize(v) => v.into(),
+            Value::U64(v) => v.into(),
+            Value::I64(v) => v.into(),
+            Value::U32(v) => v.into(),
+            Value::I32(v) => v.into(),
+            Value::U16(v) => v.into(),
+            Value::I16(v) => v.into(),
+            Value::U8(v) => v.into(),
+            Value::I8(v) => v.into(),
+            Value::Bool(v) => v.into(),
+        }
+    }
 }
 
 macro_rules! impl_helper_traits {
@@ -156,28 +242,46 @@ pub struct Bencher {
     bench: String,
     current_iteration: usize,
     max_iterations: usize,
+    timing_iterations: usize,
+    measure_all_iterations: bool,
     elapsed: Mutex<Option<Duration>>,
     probes: RefCell<ProbeManager>,
     extra_stats: Mutex<Vec<(String, Value)>>,
+    /// Allocated on the first `record_latency` call, and reset before each iteration
+    latencies: OnceLock<LatencyHistogram>,
     state: Mutex<BencherState>,
+    args: Vec<String>,
 }
 
 impl Bencher {
-    fn new(bench: String, max_iterations: usize) -> Self {
+    fn new(
+        bench: String,
+        max_iterations: usize,
+        timing_iterations: usize,
+        measure_all_iterations: bool,
+        args: Vec<String>,
+    ) -> Self {
         Self {
             bench,
             current_iteration: 0,
             max_iterations,
+            timing_iterations: timing_iterations.clamp(1, max_iterations),
+            measure_all_iterations,
             elapsed: Mutex::new(None),
             probes: RefCell::new(ProbeManager::new()),
             extra_stats: Mutex::new(Vec::new()),
+            latencies: OnceLock::new(),
             state: Mutex::new(BencherState::BeforeTiming),
+            args,
         }
     }
 
     fn iter_start(&mut self, iteration: usize) {
         self.current_iteration = iteration;
         self.extra_stats.lock().unwrap().clear();
+        if let Some(latencies) = self.latencies.get() {
+            latencies.reset();
+        }
         *self.state.lock().unwrap() = BencherState::BeforeTiming;
         // Erase scratch directory
         let scratch_dir = &*crate::utils::HARNESS_BENCH_SCRATCH_DIR;
@@ -196,7 +300,7 @@ impl Bencher {
         probes.begin(
             &self.bench,
             self.current_iteration,
-            !self.is_timing_iteration(),
+            !self.is_measured_iteration(),
         )
     }
 
@@ -205,14 +309,20 @@ impl Bencher {
         probes.end(
             &self.bench,
             self.current_iteration,
-            !self.is_timing_iteration(),
+            !self.is_measured_iteration(),
             walltime,
         )
     }
 
-    /// Returns true if this is the last iteration
+    /// Returns true if this is one of the last `measured-iterations` iterations. By default, only the last iteration is a timing iteration.
     pub fn is_timing_iteration(&self) -> bool {
-        self.current_iteration == self.max_iterations - 1
+        self.current_iteration + self.timing_iterations >= self.max_iterations
+    }
+
+    /// Returns true if the probes are enabled for this iteration.
+    /// This is the timing iteration, or any iteration if `measure-all-iterations` is enabled.
+    fn is_measured_iteration(&self) -> bool {
+        self.measure_all_iterations || self.is_timing_iteration()
     }
 
     /// Indicates the start of the timing phase. Should not be called more than once, or used the same time as `time`.
@@ -240,7 +350,7 @@ impl Bencher {
     ///     assert_eq!(result, LEN * (LEN - 1) / 2)
     /// }
     /// ```
-    pub fn start_timing(&self) -> BenchTimer {
+    pub fn start_timing(&self) -> BenchTimer<'_> {
         {
             let mut state = self.state.lock().unwrap();
             if *state != BencherState::BeforeTiming {
@@ -284,6 +394,134 @@ impl Bencher {
         f()
     }
 
+    /// Runs `f(tid)` on `threads` worker threads, and marks the whole timing phase. Should not be called more than once, or used the same time as `time` or `start_timing`.
+    ///
+    /// All threads are spawned before the timing phase, and are released together by a barrier after the timer starts.
+    /// The timer stops after all threads have finished. Besides the overall `time`, the walltime of each thread is added to the results
+    /// as `thread-time-<tid>`, and summarized as `thread-time-min`, `thread-time-mean`, and `thread-time-max` (in milliseconds).
+    ///
+    /// Returns the results of all threads, in the order of their `tid`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use harness::{bench, Bencher, black_box};
+    ///
+    /// const LEN: usize = 10000000;
+    ///
+    /// #[bench]
+    /// fn example(bencher: &Bencher) {
+    ///     const THREADS: usize = 4;
+    ///     // Prepare the inputs
+    ///     let list = black_box((0..LEN).collect::<Vec<_>>());
+    ///     // Actual work. Each thread sums its own chunk of the list.
+    ///     let sums = bencher.time_threads(THREADS, |tid| {
+    ///         list.chunks(LEN / THREADS).nth(tid).unwrap().iter().sum::<usize>()
+    ///     });
+    ///     // Check the result
+    ///     assert_eq!(sums.iter().sum::<usize>(), LEN * (LEN - 1) / 2)
+    /// }
+    /// ```
+    pub fn time_threads<R: Send, F: Fn(usize) -> R + Sync>(&self, threads: usize, f: F) -> Vec<R> {
+        assert!(threads > 0, "time_threads requires at least one thread");
+        // Check before spawning the threads, as they would wait forever if `start_timing` panics
+        if *self.state.lock().unwrap() != BencherState::BeforeTiming {
+            panic!("More than one benchmark timing phase detected");
+        }
+        let start = Barrier::new(threads + 1);
+        let end = Barrier::new(threads + 1);
+        let results = std::thread::scope(|s| {
+            let handles = (0..threads)
+                .map(|tid| {
+                    let (f, start, end) = (&f, &start, &end);
+                    s.spawn(move || {
+                        start.wait();
+                        let t = Instant::now();
+                        // A panicking thread must still reach the end barrier, or the other threads would wait forever
+                        let result =
+                            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(tid)));
+                        let elapsed = t.elapsed();
+                        end.wait();
+                        (result, elapsed)
+                    })
+                })
+                .collect::<Vec<_>>();
+            let timer = self.start_timing();
+            start.wait();
+            end.wait();
+            drop(timer);
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        let ms = |d: Duration| d.as_micros() as f64 / 1000.0;
+        let times = results.iter().map(|(_, t)| ms(*t)).collect::<Vec<_>>();
+        for (tid, t) in times.iter().enumerate() {
+            self.add_stat(format!("thread-time-{tid}"), *t);
+        }
+        let min = times.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = times.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        self.add_stat("thread-time-min", min);
+        self.add_stat(
+            "thread-time-mean",
+            times.iter().sum::<f64>() / threads as f64,
+        );
+        self.add_stat("thread-time-max", max);
+        results
+            .into_iter()
+            .map(|(r, _)| r.unwrap_or_else(|e| std::panic::resume_unwind(e)))
+            .collect()
+    }
+
+    /// Returns the value of a benchmark parameter, e.g. the thread count of a `threads` sweep.
+    ///
+    /// The parameter `name` is read from the `HARNESS_BENCH_PARAM_<NAME>` environment variable, with `name` in upper case and `-` replaced by `_`.
+    /// Returns `None` if the parameter is not set.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use harness::{bench, Bencher, black_box};
+    ///
+    /// #[bench]
+    /// fn example(bencher: &Bencher) {
+    ///     let threads = bencher
+    ///         .param("threads")
+    ///         .and_then(|t| t.parse().ok())
+    ///         .unwrap_or(1);
+    ///     bencher.time_threads(threads, |_| black_box((0..1000u64).sum::<u64>()));
+    /// }
+    /// ```
+    pub fn param(&self, name: impl AsRef<str>) -> Option<String> {
+        let name = name.as_ref().to_uppercase().replace('-', "_");
+        std::env::var(format!("HARNESS_BENCH_PARAM_{}", name)).ok()
+    }
+
+    /// Returns the arguments of the benchmark program, e.g. the workload of the invocation.
+    ///
+    /// The arguments are given after `--` on the command line, after the arguments of harness.
+    /// `cargo harness run` passes the `args` of the benchmark config in the profile. Empty if not specified.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use harness::{bench, Bencher, black_box};
+    ///
+    /// #[bench]
+    /// fn example(bencher: &Bencher) {
+    ///     let n = bencher
+    ///         .args()
+    ///         .first()
+    ///         .and_then(|n| n.parse().ok())
+    ///         .unwrap_or(1000u64);
+    ///     bencher.time(|| black_box((0..n).sum::<u64>()));
+    /// }
+    /// ```
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
     /// Adds a custom statistic to the benchmark results
     ///
     /// Please ensure you are collecting the statistics in a cheap way during the timing phase,
@@ -295,6 +533,36 @@ impl Bencher {
             .push((name.as_ref().to_owned(), value.into()));
     }
 
+    /// Records the latency of a single operation, e.g. a request or a transaction, during the timing phase.
+    ///
+    /// The latencies are counted in a lock-free histogram with 3 significant digits,
+    /// and the `latency-p50`, `latency-p90`, `latency-p99`, and `latency-p99.9` stats (in milliseconds) of each iteration are added to the results.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use harness::{bench, Bencher, black_box};
+    /// use std::time::Instant;
+    ///
+    /// #[bench]
+    /// fn example(bencher: &Bencher) {
+    ///     let requests = black_box((0..1000).collect::<Vec<u64>>());
+    ///     bencher.time(|| {
+    ///         for r in &requests {
+    ///             let start = Instant::now();
+    ///             // Serve the request here
+    ///             black_box(r * 2);
+    ///             bencher.record_latency(start.elapsed());
+    ///         }
+    ///     });
+    /// }
+    /// ```
+    pub fn record_latency(&self, latency: Duration) {
+        self.latencies
+            .get_or_init(LatencyHistogram::new)
+            .record(latency);
+    }
+
     /// Returns the wall-clock time of the last timing phase.
     /// Returns `None` if the timing phase has not finished yet.
     pub fn get_walltime(&self) -> Option<Duration> {
@@ -312,14 +580,16 @@ pub struct SingleBenchmarkRunner {
     bench_name: String,
     crate_name: String,
     bencher: Bencher,
-    benchmark: fn(&Bencher),
+    benchmark: Box<dyn Fn(&Bencher)>,
     is_single_shot: bool,
+    /// The latencies recorded in all the timing iterations
+    latencies: LatencySnapshot,
 }
 
 impl SingleBenchmarkRunner {
     #[doc(hidden)]
-    pub fn new(fname: &str, benchmark: fn(&Bencher), is_single_shot: bool) -> Self {
-        let args = BenchArgs::parse();
+    pub fn new(fname: &str, benchmark: Box<dyn Fn(&Bencher)>, is_single_shot: bool) -> Self {
+        let args = BenchArgs::parse_command_line();
         let fname = std::path::PathBuf::from(fname);
         let name = fname.file_stem().unwrap().to_str().unwrap().to_owned();
         let bench_name = if let Some(n) = args.overwrite_benchmark_name.as_ref() {
@@ -333,12 +603,23 @@ impl SingleBenchmarkRunner {
             "harness".to_owned()
         };
         Self {
-            args: BenchArgs::parse(),
+            args: BenchArgs::parse_command_line(),
             bench_name: bench_name.clone(),
             crate_name,
-            bencher: Bencher::new(bench_name, if is_single_shot { 1 } else { args.iterations }),
+            bencher: Bencher::new(
+                bench_name,
+                if is_single_shot {
+                    1
+                } else {
+                    args.total_iterations()
+                },
+                args.measured_iterations,
+                args.measure_all_iterations,
+                args.program_args.clone(),
+            ),
             benchmark,
             is_single_shot,
+            latencies: LatencySnapshot::default(),
         }
     }
 
@@ -374,27 +655,99 @@ impl SingleBenchmarkRunner {
         elapsed.as_micros() as f32 / 1000.0
     }
 
+    /// Run one iteration, and return its walltime in milliseconds
+    fn run_iteration(&mut self, i: usize, extra_stats: &[(String, Value)]) -> f32 {
+        let first_timing_iteration = self.bencher.max_iterations - self.bencher.timing_iterations;
+        let is_timing_iteration = i == self.bencher.max_iterations - 1;
+        let (start_label, end_label) = if i < first_timing_iteration {
+            (
+                format!("warmup {} ", i + 1),
+                format!("completed warmup {}", i + 1),
+            )
+        } else if !is_timing_iteration {
+            (
+                format!("timing {} ", i - first_timing_iteration + 1),
+                format!("completed timing {}", i - first_timing_iteration + 1),
+            )
+        } else {
+            ("".to_owned(), "PASSED".to_owned())
+        };
+        eprintln!(
+            "===== {} {} starting {}=====",
+            self.crate_name, self.bench_name, start_label
+        );
+        let elapsed = self.run_once_impl(i);
+        eprintln!(
+            "===== {} {} {} in {:.1} msec =====",
+            self.crate_name, self.bench_name, end_label, elapsed
+        );
+        for (name, value) in extra_stats {
+            self.bencher.add_stat(name, *value);
+        }
+        if let Some(latencies) = self.bencher.latencies.get() {
+            let snapshot = latencies.snapshot();
+            if snapshot.total() > 0 {
+                for (name, value) in snapshot.stats() {
+                    self.bencher.add_stat(name, value);
+                }
+                if self.bencher.is_timing_iteration() {
+                    self.latencies.merge(&snapshot);
+                }
+            }
+        }
+        self.dump_counters(i, is_timing_iteration);
+        elapsed
+    }
+
     fn run_iterative(&mut self, iterations: usize) {
         for i in 0..iterations {
-            let is_timing_iteration = i == iterations - 1;
-            let (start_label, end_label) = if !is_timing_iteration {
-                (
-                    format!("warmup {} ", i + 1),
-                    format!("completed warmup {}", i + 1),
-                )
-            } else {
-                ("".to_owned(), "PASSED".to_owned())
-            };
+            self.run_iteration(i, &[]);
+        }
+    }
+
+    /// Returns true if the coefficient of variation of the last `window` walltimes is below `cv`.
+    fn is_steady_state(walltimes: &[f32], window: usize, cv: f64) -> bool {
+        if window < 2 || walltimes.len() < window {
+            return false;
+        }
+        let recent = walltimes[walltimes.len() - window..]
+            .iter()
+            .map(|t| *t as f64)
+            .collect::<Vec<_>>();
+        let n = recent.len() as f64;
+        let mean = recent.iter().sum::<f64>() / n;
+        let var = recent.iter().map(|t| (t - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        mean > 0.0 && var.sqrt() / mean < cv
+    }
+
+    /// Keep running warm-up iterations until the walltime is stable, or the maximum number of warm-up iterations is reached.
+    /// Then run the timing iterations.
+    ///
+    /// The number of warm-up iterations is recorded as the `warmup-iterations` stat of the timing iterations.
+    fn run_adaptive(&mut self, cv: f64) {
+        let window = self.args.steady_state_window;
+        let max_warmup = self.args.max_warmup_iterations;
+        let timing_iterations = self.args.measured_iterations.max(1);
+        self.bencher.timing_iterations = timing_iterations;
+        // The total number of iterations is unknown until the steady state is reached
+        self.bencher.max_iterations = usize::MAX;
+        let mut walltimes = vec![];
+        while walltimes.len() < max_warmup && !Self::is_steady_state(&walltimes, window, cv) {
+            walltimes.push(self.run_iteration(walltimes.len(), &[]));
+        }
+        let warmup_iterations = walltimes.len();
+        if !Self::is_steady_state(&walltimes, window, cv) {
             eprintln!(
-                "===== {} {} starting {}=====",
-                self.crate_name, self.bench_name, start_label
+                "Harness: Steady state not reached after {} warm-up iterations.",
+                warmup_iterations
             );
-            let elapsed = self.run_once_impl(i);
-            eprintln!(
-                "===== {} {} {} in {:.1} msec =====",
-                self.crate_name, self.bench_name, end_label, elapsed
+        }
+        self.bencher.max_iterations = warmup_iterations + timing_iterations;
+        for i in warmup_iterations..self.bencher.max_iterations {
+            self.run_iteration(
+                i,
+                &[("warmup-iterations".to_owned(), warmup_iterations.into())],
             );
-            self.dump_counters(i, is_timing_iteration);
         }
     }
 
@@ -402,16 +755,30 @@ impl SingleBenchmarkRunner {
     pub fn run(&mut self) -> anyhow::Result<()> {
         // Initialize probes
         self.bencher.probes.borrow_mut().init(&self.args.probes);
+        if let (Some(interval), Some(out)) = (self.args.sample_interval, &self.args.output_samples)
+        {
+            self.bencher
+                .probes
+                .borrow_mut()
+                .enable_sampling(Duration::from_millis(interval), out.clone());
+        }
         // Run the benchmark
-        let iterations = if self.is_single_shot {
+        if self.is_single_shot {
             eprintln!("Harness: Single-shot run.");
-            1
+            self.run_iterative(1);
+        } else if let Some(cv) = self.args.steady_state_cv {
+            self.run_adaptive(cv);
         } else {
-            self.args.iterations
-        };
-        self.run_iterative(iterations);
+            self.run_iterative(self.args.total_iterations());
+        }
         // Destroy probes
         self.bencher.probes.borrow_mut().deinit();
+        if let Some(out) = &self.args.output_latency_histogram {
+            if self.latencies.total() > 0 {
+                let mut file = std::fs::File::create(out)?;
+                self.latencies.write_hgrm(&mut file)?;
+            }
+        }
         Ok(())
     }
 }
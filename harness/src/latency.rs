@@ -0,0 +1,180 @@
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use crate::Value;
+
+/// Values are recorded with 11 significant bits, i.e. at least 3 significant decimal digits.
+const SUB_BUCKET_BITS: u32 = 11;
+const SUB_BUCKET_COUNT: usize = 1 << SUB_BUCKET_BITS;
+const SUB_BUCKET_HALF_COUNT: usize = SUB_BUCKET_COUNT / 2;
+const SUB_BUCKET_MASK: u64 = SUB_BUCKET_COUNT as u64 - 1;
+/// The number of buckets to cover all `u64` values
+const BUCKET_COUNT: usize = 64 - SUB_BUCKET_BITS as usize + 1;
+const COUNTS_LEN: usize = (BUCKET_COUNT + 1) * SUB_BUCKET_HALF_COUNT;
+
+/// The percentiles reported as the `latency-p*` stats
+const PERCENTILES: [(&str, f64); 4] = [
+    ("latency-p50", 50.0),
+    ("latency-p90", 90.0),
+    ("latency-p99", 99.0),
+    ("latency-p99.9", 99.9),
+];
+
+/// A histogram of latencies in nanoseconds, with the counter layout of HdrHistogram.
+///
+/// Recording a latency is a single relaxed atomic increment, without any locks or allocations.
+pub(crate) struct LatencyHistogram {
+    counts: Box<[AtomicU64]>,
+}
+
+impl LatencyHistogram {
+    pub fn new() -> Self {
+        Self {
+            counts: (0..COUNTS_LEN).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+
+    fn index_of(value: u64) -> usize {
+        let bucket = (64 - SUB_BUCKET_BITS - (value | SUB_BUCKET_MASK).leading_zeros()) as usize;
+        let sub_bucket = (value >> bucket) as usize;
+        ((bucket + 1) << (SUB_BUCKET_BITS - 1)) + sub_bucket - SUB_BUCKET_HALF_COUNT
+    }
+
+    /// The highest value that is counted by the same counter as the values at `index`
+    fn highest_equivalent_value(index: usize) -> u64 {
+        let (bucket, sub_bucket) = match index >> (SUB_BUCKET_BITS - 1) {
+            0 => (0, index),
+            b => (
+                b - 1,
+                (index & (SUB_BUCKET_HALF_COUNT - 1)) + SUB_BUCKET_HALF_COUNT,
+            ),
+        };
+        ((sub_bucket as u64) << bucket) + ((1u64 << bucket) - 1)
+    }
+
+    pub fn record(&self, latency: Duration) {
+        let nanos = u64::try_from(latency.as_nanos()).unwrap_or(u64::MAX);
+        self.counts[Self::index_of(nanos)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn reset(&self) {
+        for c in self.counts.iter() {
+            c.store(0, Ordering::Relaxed);
+        }
+    }
+
+    pub fn snapshot(&self) -> LatencySnapshot {
+        let mut counts = BTreeMap::new();
+        for (i, c) in self.counts.iter().enumerate() {
+            let c = c.load(Ordering::Relaxed);
+            if c > 0 {
+                counts.insert(Self::highest_equivalent_value(i), c);
+            }
+        }
+        LatencySnapshot(counts)
+    }
+}
+
+/// The recorded latencies, as the number of values of each non-empty counter, keyed by its highest equivalent value in nanoseconds
+#[derive(Default)]
+pub(crate) struct LatencySnapshot(BTreeMap<u64, u64>);
+
+impl LatencySnapshot {
+    pub fn total(&self) -> u64 {
+        self.0.values().sum()
+    }
+
+    pub fn merge(&mut self, other: &Self) {
+        for (v, c) in &other.0 {
+            *self.0.entry(*v).or_default() += c;
+        }
+    }
+
+    /// The smallest recorded value that `percentile` percent of the values are less than or equal to.
+    /// Returns the value, and the number of values that are less than or equal to it.
+    fn value_at_percentile(&self, percentile: f64) -> (u64, u64) {
+        let total = self.total();
+        let target = ((percentile / 100.0 * total as f64).ceil() as u64).clamp(1, total);
+        let mut count = 0;
+        for (v, c) in &self.0 {
+            count += c;
+            if count >= target {
+                return (*v, count);
+            }
+        }
+        (0, 0)
+    }
+
+    /// The `latency-p*` stats, in milliseconds
+    pub fn stats(&self) -> Vec<(String, Value)> {
+        PERCENTILES
+            .iter()
+            .map(|(name, p)| {
+                let (v, _) = self.value_at_percentile(*p);
+                (name.to_string(), Value::F64(v as f64 / 1e6))
+            })
+            .collect()
+    }
+
+    /// Write the percentile distribution in the `.hgrm` format of HdrHistogram, with the values in milliseconds
+    pub fn write_hgrm(&self, out: &mut impl Write) -> std::io::Result<()> {
+        const TICKS_PER_HALF_DISTANCE: f64 = 5.0;
+        let total = self.total();
+        let ms = |v: u64| v as f64 / 1e6;
+        writeln!(
+            out,
+            "{:>12} {:>14} {:>10} {:>14}\n",
+            "Value", "Percentile", "TotalCount", "1/(1-Percentile)"
+        )?;
+        let mut percentile = 0.0f64;
+        loop {
+            let (value, count) = self.value_at_percentile(percentile);
+            if count == total {
+                writeln!(out, "{:12.6} {:2.12} {:10}", ms(value), 1.0, count)?;
+                break;
+            }
+            let fraction = percentile / 100.0;
+            writeln!(
+                out,
+                "{:12.6} {:2.12} {:10} {:14.2}",
+                ms(value),
+                fraction,
+                count,
+                1.0 / (1.0 - fraction)
+            )?;
+            // Halve the percentile step every time the distance to 100% is halved
+            let half_distance = (100.0 / (100.0 - percentile)).log2().floor() + 1.0;
+            percentile += 100.0 / (TICKS_PER_HALF_DISTANCE * 2f64.powf(half_distance));
+        }
+        let mean = self.0.iter().map(|(v, c)| ms(*v) * *c as f64).sum::<f64>() / total as f64;
+        let variance = self
+            .0
+            .iter()
+            .map(|(v, c)| (ms(*v) - mean).powi(2) * *c as f64)
+            .sum::<f64>()
+            / total as f64;
+        let max = self.0.keys().next_back().copied().unwrap_or(0);
+        writeln!(
+            out,
+            "#[Mean    = {:12.6}, StdDeviation   = {:12.6}]",
+            mean,
+            variance.sqrt()
+        )?;
+        writeln!(
+            out,
+            "#[Max     = {:12.6}, Total count    = {:12}]",
+            ms(max),
+            total
+        )?;
+        writeln!(
+            out,
+            "#[Buckets = {:12}, SubBuckets     = {:12}]",
+            BUCKET_COUNT, SUB_BUCKET_COUNT
+        )?;
+        Ok(())
+    }
+}
@@ -1,6 +1,10 @@
+pub mod alloc;
 mod bencher;
+mod latency;
 pub mod probe;
 mod record;
+#[doc(hidden)]
+pub mod schema;
 pub mod utils;
 
 pub use bencher::{BenchTimer, Bencher, Value};
@@ -8,8 +12,9 @@ pub use harness_macros::{bench, probe};
 pub use std::hint::black_box;
 
 #[doc(hidden)]
-pub fn run(file_name: &str, bench_fn: fn(&Bencher), single_shot: bool) {
-    let mut bencher = bencher::SingleBenchmarkRunner::new(file_name, bench_fn, single_shot);
+pub fn run(file_name: &str, bench_fn: impl Fn(&Bencher) + 'static, single_shot: bool) {
+    let mut bencher =
+        bencher::SingleBenchmarkRunner::new(file_name, Box::new(bench_fn), single_shot);
     if let Err(e) = bencher.run() {
         panic!("{}", e.to_string())
     }
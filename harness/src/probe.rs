@@ -1,3 +1,8 @@
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
 use std::time::Duration;
 use std::{collections::HashMap, time::Instant};
 
@@ -43,6 +48,28 @@ pub struct ProbeArgs {
 }
 
 impl ProbeArgs {
+    /// Set a key if it is not already specified by the user.
+    fn set_default(&mut self, key: &str, value: serde_json::Value) {
+        self.raw.entry(key.to_owned()).or_insert(value);
+    }
+
+    /// Add the args of another config of the same probe, e.g. from a preset.
+    /// Fails if both configs set a key to different values.
+    fn merge(&mut self, other: ProbeArgs) -> anyhow::Result<()> {
+        for (key, value) in other.raw {
+            match self.raw.get(&key) {
+                Some(v) if *v != value => {
+                    anyhow::bail!("conflicting values of `{}`: {} and {}", key, v, value)
+                }
+                Some(_) => {}
+                None => {
+                    self.raw.insert(key, value);
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub fn get<T: DeserializeOwned>(&self, key: &str) -> anyhow::Result<T> {
         let value = self
             .raw
@@ -54,6 +81,12 @@ impl ProbeArgs {
 
 #[allow(unused)]
 pub trait Probe {
+    /// Check the args before `init`, e.g. for unknown option values.
+    /// If this fails, the probe is reported as failed to load, and is not initialized.
+    fn check_args(&self, args: &ProbeArgs) -> anyhow::Result<()> {
+        Ok(())
+    }
+
     fn init(&mut self, args: ProbeArgs) {}
 
     fn begin(&mut self, benchmark: &str, iteration: usize, warmup: bool) {}
@@ -64,9 +97,109 @@ pub trait Probe {
         HashMap::new()
     }
 
+    /// Create a sampler that takes samples during the timing phase of the timing iterations, e.g. the current RSS or CPU frequency.
+    ///
+    /// This is only called when sampling is enabled (`sample-interval` in the profile), at the start of each timing phase.
+    /// The sampler is moved to a background thread that calls it at a fixed interval, and dropped at the end of the timing phase.
+    /// The samples are written to `<bench>.<build>.<invocation>.samples.csv` in the run's log directory.
+    fn sampler(&mut self) -> Option<Box<dyn SamplingProbe>> {
+        None
+    }
+
     fn deinit(&mut self) {}
 }
 
+/// The sampler of a probe, created by [`Probe::sampler`].
+///
+/// Unlike the probe itself, it is called from the sampling thread, so it must be [`Send`].
+pub trait SamplingProbe: Send {
+    /// Take a sample
+    fn sample(&mut self) -> HashMap<String, Value>;
+}
+
+/// The sampler of a probe, with the index of the probe
+struct SamplerEntry {
+    probe: usize,
+    sampler: Box<dyn SamplingProbe>,
+}
+
+/// A time series of probe samples, with the timing iteration of each sample
+type Samples = Vec<(usize, Duration, HashMap<String, Value>)>;
+
+/// The background thread that periodically calls the samplers of the probes
+struct Sampler {
+    stop: Arc<AtomicBool>,
+    /// The time series of the timing phase, and the indices of the probes whose sampler panicked
+    handle: JoinHandle<(Samples, Vec<usize>)>,
+}
+
+impl Sampler {
+    fn start(mut samplers: Vec<SamplerEntry>, interval: Duration, iteration: usize) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                let mut samples = vec![];
+                let mut failed = vec![];
+                let start = Instant::now();
+                loop {
+                    std::thread::park_timeout(interval);
+                    if stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let mut values = HashMap::new();
+                    samplers.retain_mut(|s| {
+                        let sample = AssertUnwindSafe(|| s.sampler.sample());
+                        match std::panic::catch_unwind(sample) {
+                            Ok(sample) => {
+                                values.extend(sample);
+                                true
+                            }
+                            Err(_) => {
+                                failed.push(s.probe);
+                                false
+                            }
+                        }
+                    });
+                    if !values.is_empty() {
+                        samples.push((iteration, start.elapsed(), values));
+                    }
+                }
+                (samples, failed)
+            })
+        };
+        Self { stop, handle }
+    }
+
+    fn stop(self) -> (Samples, Vec<usize>) {
+        self.stop.store(true, Ordering::SeqCst);
+        self.handle.thread().unpark();
+        self.handle.join().unwrap_or_default()
+    }
+}
+
+/// Write the samples as a csv file, with the timing iteration, and one column per sampled value.
+fn dump_samples(path: &Path, samples: &Samples) -> std::io::Result<()> {
+    let mut keys = samples
+        .iter()
+        .flat_map(|(_, _, values)| values.keys().cloned())
+        .collect::<Vec<_>>();
+    keys.sort();
+    keys.dedup();
+    let mut out = format!("iteration,time,{}\n", keys.join(","));
+    for (iteration, time, values) in samples {
+        out += &format!("{},{:.3}", iteration, time.as_micros() as f64 / 1000.0);
+        for k in &keys {
+            out += ",";
+            if let Some(v) = values.get(k) {
+                out += &v.to_string();
+            }
+        }
+        out += "\n";
+    }
+    std::fs::write(path, out)
+}
+
 #[derive(Default)]
 struct BaseProbe {
     start: Option<std::time::Instant>,
@@ -92,10 +225,119 @@ impl Probe for BaseProbe {
     }
 }
 
+/// Probe presets: curated probe configurations that are selectable by name.
+///
+/// * `harness-memory`: dTLB/iTLB misses, LLC misses, and page faults, collected by `harness-probe-perf`
+///   with the correct event names for the current CPU vendor.
+///
+/// Returns the real probe name. The preset args are merged into `args`, without overwriting the user-specified ones.
+fn resolve_preset(probe: &str, args: &mut ProbeArgs) -> String {
+    match probe {
+        "harness-memory" => {
+            args.set_default("preset", "memory".into());
+            "harness-probe-perf".to_owned()
+        }
+        _ => probe.to_owned(),
+    }
+}
+
+/// A probe registered at compile time by [`register_probe!`](crate::register_probe).
+#[doc(hidden)]
+pub struct StaticProbe {
+    pub name: &'static str,
+    pub create: fn() -> Box<dyn Probe>,
+}
+
+inventory::collect!(StaticProbe);
+
+#[doc(hidden)]
+pub use inventory as __inventory;
+
+/// Register a probe at compile time, without loading it from a dylib.
+///
+/// The probe type must implement [`Probe`] and [`Default`]. Statically registered probes are always enabled.
+/// The probe configuration is looked up by the given name, which defaults to the type name.
+/// If a dylib probe with the same name is enabled in the profile, the static one is used instead.
+///
+/// ```rust
+/// use harness::probe::Probe;
+///
+/// #[derive(Default)]
+/// struct MyProbe;
+///
+/// impl Probe for MyProbe {}
+///
+/// harness::register_probe!(MyProbe);
+/// // Or with an explicit name:
+/// // harness::register_probe!("my-probe" => MyProbe);
+/// ```
+#[macro_export]
+macro_rules! register_probe {
+    ($name: expr => $probe: ty) => {
+        const _: () = {
+            fn __harness_create_probe() -> Box<dyn $crate::probe::Probe> {
+                Box::new(<$probe as ::std::default::Default>::default())
+            }
+            $crate::probe::__inventory::submit! {
+                $crate::probe::StaticProbe {
+                    name: $name,
+                    create: __harness_create_probe,
+                }
+            }
+        };
+    };
+    ($probe: ty) => {
+        $crate::register_probe!(stringify!($probe) => $probe);
+    };
+}
+
+/// A registered probe
+struct ProbeEntry {
+    /// The probe name in the profile. e.g. `harness-probe-perf`
+    name: String,
+    probe: Box<dyn Probe>,
+    /// The probe panicked in one of its hooks, and is disabled for the rest of the run
+    failed: bool,
+}
+
+impl ProbeEntry {
+    /// Run a probe hook. If the hook panics, the probe is disabled and `None` is returned,
+    /// so one broken probe does not take down the whole benchmark process.
+    fn call<R>(&mut self, hook: &str, f: impl FnOnce(&mut dyn Probe) -> R) -> Option<R> {
+        if self.failed {
+            return None;
+        }
+        let probe = self.probe.as_mut();
+        match std::panic::catch_unwind(AssertUnwindSafe(|| f(probe))) {
+            Ok(result) => Some(result),
+            Err(e) => {
+                self.failed = true;
+                let msg = e
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| e.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "unknown error".to_owned());
+                eprintln!(
+                    "WARNING: Probe `{}` failed in `{}`: {}. The probe is disabled for the rest of the run.",
+                    self.name, hook, msg
+                );
+                None
+            }
+        }
+    }
+}
+
 pub struct ProbeManager {
-    probes: Vec<Box<dyn Probe>>,
+    probes: Vec<ProbeEntry>,
     counters: Counters,
     libraries: Vec<Library>,
+    /// The sampling interval and the output file, if sampling is enabled
+    sampling: Option<(Duration, PathBuf)>,
+    sampler: Option<Sampler>,
+    /// The samples of all the timing iterations so far
+    samples: Samples,
+    /// Number of probes that failed to load
+    failed_to_load: usize,
 }
 
 impl ProbeManager {
@@ -104,11 +346,54 @@ impl ProbeManager {
             probes: vec![],
             counters: Counters::new(Duration::ZERO),
             libraries: vec![],
+            sampling: None,
+            sampler: None,
+            samples: vec![],
+            failed_to_load: 0,
         }
     }
 
+    /// Periodically sample all probes during the timing phase of the timing iterations, and write the samples to `out`.
+    pub(crate) fn enable_sampling(&mut self, interval: Duration, out: PathBuf) {
+        self.sampling = Some((interval, out));
+    }
+
     pub fn register(&mut self, probe: Box<dyn Probe>) {
-        self.probes.push(probe);
+        self.probes.push(ProbeEntry {
+            name: String::new(),
+            probe,
+            failed: false,
+        });
+    }
+
+    /// Set the name of the probes registered since `start`
+    fn set_names(&mut self, start: usize, name: &str) {
+        for p in &mut self.probes[start..] {
+            p.name = name.to_owned();
+        }
+    }
+
+    /// Load a probe from its dylib, and register it.
+    fn load_dylib(&mut self, probe: &str) -> anyhow::Result<()> {
+        let dylib_name = probe.replace('-', "_");
+        let dylib_filename = if cfg!(target_os = "macos") {
+            format!("lib{dylib_name}.dylib")
+        } else if cfg!(target_os = "linux") {
+            format!("lib{dylib_name}.so")
+        } else if cfg!(target_os = "windows") {
+            format!("{dylib_name}.dll")
+        } else {
+            unimplemented!()
+        };
+        unsafe {
+            let lib = Library::new(dylib_filename)?;
+            // This will call `ProbeManager::register` to add the probe to the list of probes
+            let register_probe_fn: Symbol<extern "C" fn(probes: &mut ProbeManager)> =
+                lib.get(b"harness_register_probe")?;
+            register_probe_fn(self);
+            self.libraries.push(lib);
+        }
+        Ok(())
     }
 
     pub(crate) fn init(&mut self, probes: &str) {
@@ -117,41 +402,81 @@ impl ProbeManager {
         } else {
             serde_json::from_str::<HashMap<String, ProbeArgs>>(probes).unwrap()
         };
+        // Merge the args of a preset and its probe, e.g. `harness-memory` and `harness-probe-perf`
+        let mut probes = probes.into_iter().collect::<Vec<_>>();
+        probes.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut resolved = HashMap::<String, ProbeArgs>::new();
+        for (name, mut args) in probes {
+            let probe = resolve_preset(&name, &mut args);
+            let Some(existing) = resolved.get_mut(&probe) else {
+                resolved.insert(probe, args);
+                continue;
+            };
+            if let Err(e) = existing.merge(args) {
+                eprintln!(
+                    "WARNING: Failed to load probe `{}`: it conflicts with `{}`: {}",
+                    name, probe, e
+                );
+                self.failed_to_load += 1;
+            }
+        }
+        let mut probes = resolved;
         let mut probe_args = vec![];
+        // Probes registered at compile time. These are always enabled, and take precedence over the dylib ones.
+        for p in inventory::iter::<StaticProbe> {
+            let start = self.probes.len();
+            self.register((p.create)());
+            self.set_names(start, p.name);
+            probe_args.push(Some(probes.remove(p.name).unwrap_or_default()));
+        }
         for (probe, args) in probes {
-            let dylib_name = probe.replace('-', "_");
-            let dylib_filename = if cfg!(target_os = "macos") {
-                format!("lib{dylib_name}.dylib")
-            } else if cfg!(target_os = "linux") {
-                format!("lib{dylib_name}.so")
-            } else {
-                unimplemented!()
-            };
-            unsafe {
-                let lib = Library::new(dylib_filename).unwrap();
-                // This will call `ProbeManager::register` to add the probe to the list of probes
-                let register_probe_fn: Symbol<extern "C" fn(probes: &mut ProbeManager)> =
-                    lib.get(b"harness_register_probe").unwrap();
-                register_probe_fn(self);
-                self.libraries.push(lib);
+            let start = self.probes.len();
+            if let Err(e) = self.load_dylib(&probe) {
+                eprintln!("WARNING: Failed to load probe `{}`: {}", probe, e);
+                self.failed_to_load += 1;
+                continue;
+            }
+            self.set_names(start, &probe);
+            for _ in start..self.probes.len() {
+                probe_args.push(Some(args.clone()));
             }
-            probe_args.push(Some(args));
+        }
+        // Built-in probes
+        if crate::alloc::is_installed() {
+            let start = self.probes.len();
+            self.register(Box::<crate::alloc::AllocProbe>::default());
+            self.set_names(start, "alloc");
+            probe_args.push(Some(ProbeArgs::default()));
         }
         for (i, probe) in self.probes.iter_mut().enumerate() {
             let args = probe_args[i].take().unwrap();
-            probe.init(args);
+            if let Some(Err(e)) = probe.call("check_args", |p| p.check_args(&args)) {
+                eprintln!("WARNING: Failed to load probe `{}`: {}", probe.name, e);
+                probe.failed = true;
+                continue;
+            }
+            probe.call("init", |p| p.init(args));
         }
     }
 
     pub(crate) fn deinit(&mut self) {
         for probe in self.probes.iter_mut() {
-            probe.deinit();
+            probe.call("deinit", |p| p.deinit());
         }
     }
 
     pub(crate) fn begin(&mut self, benchmark: &str, iteration: usize, warmup: bool) {
         for probe in self.probes.iter_mut() {
-            probe.begin(benchmark, iteration, warmup)
+            probe.call("begin", |p| p.begin(benchmark, iteration, warmup));
+        }
+        if let (Some((interval, _)), false) = (&self.sampling, warmup) {
+            let mut samplers = vec![];
+            for (i, probe) in self.probes.iter_mut().enumerate() {
+                if let Some(Some(sampler)) = probe.call("sampler", |p| p.sampler()) {
+                    samplers.push(SamplerEntry { probe: i, sampler });
+                }
+            }
+            self.sampler = Some(Sampler::start(samplers, *interval, iteration));
         }
     }
 
@@ -162,14 +487,45 @@ impl ProbeManager {
         warmup: bool,
         walltime: Duration,
     ) {
+        if let Some(sampler) = self.sampler.take() {
+            let (samples, failed) = sampler.stop();
+            for i in failed {
+                let probe = &mut self.probes[i];
+                probe.failed = true;
+                eprintln!(
+                    "WARNING: Probe `{}` failed in `sample`. The probe is disabled for the rest of the run.",
+                    probe.name
+                );
+            }
+            // Keep the series of the previous timing iterations, which are in the same file
+            self.samples.extend(samples);
+            let out = &self.sampling.as_ref().unwrap().1;
+            if let Err(e) = dump_samples(out, &self.samples) {
+                eprintln!(
+                    "WARNING: Failed to write samples to {}: {}",
+                    out.display(),
+                    e
+                );
+            }
+        }
         // harness_end
         for probe in self.probes.iter_mut() {
-            probe.end(benchmark, iteration, warmup)
+            probe.call("end", |p| p.end(benchmark, iteration, warmup));
         }
         // report values
         let mut counters = Counters::new(walltime);
         for probe in self.probes.iter_mut() {
-            counters.merge(probe.report());
+            if let Some(values) = probe.call("report", |p| p.report()) {
+                counters.merge(values);
+            }
+        }
+        // Record the number of broken probes, so the affected results can be identified
+        let failures = self.failed_to_load + self.probes.iter().filter(|p| p.failed).count();
+        if failures > 0 {
+            counters.merge(HashMap::from([(
+                "probe-failures".to_owned(),
+                failures.into(),
+            )]));
         }
         self.counters = counters;
     }
@@ -190,3 +546,33 @@ impl ProbeManager {
         stats_map
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(json: &str) -> ProbeArgs {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn presets_are_merged_with_their_probe() {
+        let mut preset = ProbeArgs::default();
+        assert_eq!(
+            resolve_preset("harness-memory", &mut preset),
+            "harness-probe-perf"
+        );
+        let mut perf = args(r#"{"events": "PERF_COUNT_HW_CPU_CYCLES"}"#);
+        perf.merge(preset.clone()).unwrap();
+        assert_eq!(perf.get::<String>("preset").unwrap(), "memory");
+        assert_eq!(
+            perf.get::<String>("events").unwrap(),
+            "PERF_COUNT_HW_CPU_CYCLES"
+        );
+        // The same value is not a conflict
+        args(r#"{"preset": "memory"}"#)
+            .merge(preset.clone())
+            .unwrap();
+        assert!(args(r#"{"preset": "cache"}"#).merge(preset).is_err());
+    }
+}
@@ -1,8 +1,13 @@
-use std::io::Write;
-use std::{collections::HashMap, fs::OpenOptions, path::PathBuf};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    path::{Path, PathBuf},
+};
 
 use clap::ValueEnum;
 
+use crate::schema::{format_record, Table, KEY_COLUMNS};
 use crate::Value;
 
 #[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
@@ -12,6 +17,28 @@ pub(crate) enum StatPrintFormat {
     Yaml,
 }
 
+/// Open a results file, and wait for an exclusive advisory lock on it.
+/// The lock is released when the file is closed.
+///
+/// If the file can't be locked, e.g. on a file system without locking support, it's still returned unlocked, with a warning.
+fn lock(path: &Path) -> File {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .unwrap();
+    if let Err(e) = file.lock() {
+        eprintln!(
+            "WARNING: Failed to lock {}, concurrent updates may interleave: {}",
+            path.display(),
+            e
+        );
+    }
+    file
+}
+
 pub(crate) struct Record<'a> {
     pub name: &'a str,
     pub csv: Option<&'a PathBuf>,
@@ -54,27 +81,48 @@ impl<'a> Record<'a> {
 
     fn dump_counters_csv(&self, stats: &[(String, Value)]) {
         if let Some(csv) = self.csv {
-            if !csv.exists() {
-                let mut headers = "bench,build,invocation,iteration".to_owned();
-                for (name, _value) in stats {
-                    headers += ",";
-                    headers += name;
-                }
-                headers += "\n";
-                std::fs::write(csv, headers).unwrap();
+            // Hold an advisory lock while updating the file, so the records of concurrent writers never interleave
+            let mut file = lock(csv);
+            let mut content = String::new();
+            file.read_to_string(&mut content).unwrap();
+            let mut table = Table::parse(&content);
+            let new_columns = table.add_columns(stats.iter().map(|(name, _)| name.as_str()));
+            if !table.versioned || new_columns {
+                // Rewrite the file with the new header, and the existing values moved to their columns by name
+                let out = table.to_csv();
+                file.set_len(0).unwrap();
+                file.seek(SeekFrom::Start(0)).unwrap();
+                file.write_all(out.as_bytes()).unwrap();
             }
-            let mut record = format!(
-                "{},{},{},{}",
-                self.name,
-                self.build.unwrap(),
-                self.invocation.unwrap_or(0),
-                self.iteration
-            );
-            for (_, value) in stats {
-                record += &format!(",{}", value.into_string());
+            let mut record = vec![
+                self.name.to_owned(),
+                self.build.unwrap().clone(),
+                self.invocation.unwrap_or(0).to_string(),
+                self.iteration.to_string(),
+            ];
+            for name in &table.headers[KEY_COLUMNS.len()..] {
+                let value = stats.iter().find(|(n, _)| n == name);
+                record.push(value.map(|(_, v)| v.into_string()).unwrap_or_default());
             }
-            let mut csv = OpenOptions::new().append(true).open(csv).unwrap();
-            writeln!(csv, "{record}").unwrap();
+            file.seek(SeekFrom::End(0)).unwrap();
+            writeln!(file, "{}", format_record(&record)).unwrap();
+        }
+    }
+
+    /// Append the stats as a JSON object to the `.jsonl` file next to the csv file
+    fn dump_counters_json(&self, stats: &[(String, Value)]) {
+        if let Some(csv) = self.csv {
+            let mut record = serde_json::Map::new();
+            record.insert("bench".to_owned(), self.name.into());
+            record.insert("build".to_owned(), self.build.unwrap().as_str().into());
+            record.insert("invocation".to_owned(), self.invocation.unwrap_or(0).into());
+            record.insert("iteration".to_owned(), self.iteration.into());
+            for (name, value) in stats {
+                record.insert(name.clone(), value.into_json());
+            }
+            let mut json = lock(&csv.with_extension("jsonl"));
+            json.seek(SeekFrom::End(0)).unwrap();
+            writeln!(json, "{}", serde_json::Value::Object(record)).unwrap();
         }
     }
 
@@ -101,7 +149,103 @@ impl<'a> Record<'a> {
             });
             eprintln!("{banner_end}");
         }
-        // Print to the CSV file
+        // Print to the CSV and JSON files
         self.dump_counters_csv(&stats);
+        self.dump_counters_json(&stats);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dump(csv: &PathBuf, build: &String, iteration: usize, stats: &[(&str, Value)]) {
+        Record {
+            name: "foo",
+            csv: Some(csv),
+            invocation: Some(0),
+            build: Some(build),
+            format: StatPrintFormat::Yaml,
+            iteration,
+            is_timing_iteration: false,
+            stats: stats.iter().map(|(k, v)| (k.to_string(), *v)).collect(),
+        }
+        .dump_values();
+    }
+
+    #[test]
+    fn csv_records_round_trip() {
+        let dir = std::env::temp_dir().join(format!("harness-record-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let csv = dir.join("results.csv");
+        // A matrix build name with a comma
+        let build = "opt.features=a,b".to_owned();
+        dump(&csv, &build, 0, &[("time", 1.5f32.into())]);
+        // A new metric is inserted by name, and the earlier records are padded
+        dump(
+            &csv,
+            &build,
+            1,
+            &[("time", 2.5f32.into()), ("faults", 3usize.into())],
+        );
+        let table = Table::parse(&std::fs::read_to_string(&csv).unwrap());
+        assert!(table.versioned);
+        assert_eq!(
+            table.headers,
+            [
+                "bench",
+                "build",
+                "invocation",
+                "iteration",
+                "faults",
+                "time"
+            ]
+        );
+        assert_eq!(
+            table.rows,
+            vec![
+                vec!["foo", "opt.features=a,b", "0", "0", "", "1.5"],
+                vec!["foo", "opt.features=a,b", "0", "1", "3", "2.5"],
+            ]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn json_records_keep_value_types() {
+        let dir = std::env::temp_dir().join(format!("harness-record-json-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let csv = dir.join("results.csv");
+        dump(
+            &csv,
+            &"a".to_owned(),
+            3,
+            &[
+                ("time", 0.1f32.into()),
+                ("faults", 42usize.into()),
+                ("delta", (-7i64).into()),
+                ("verified", true.into()),
+            ],
+        );
+        let json = std::fs::read_to_string(csv.with_extension("jsonl")).unwrap();
+        let record = serde_json::from_str::<serde_json::Value>(json.trim()).unwrap();
+        assert_eq!(
+            record,
+            serde_json::json!({
+                "bench": "foo",
+                "build": "a",
+                "invocation": 0,
+                "iteration": 3,
+                "time": 0.1,
+                "faults": 42,
+                "delta": -7,
+                "verified": true,
+            })
+        );
+        // Integers stay integers, and f32 values keep their shortest decimal representation
+        assert!(record["faults"].is_u64());
+        assert!(record["delta"].is_i64());
+        assert_eq!(record["time"].as_f64(), Some(0.1));
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -0,0 +1,226 @@
+//! The schema of the results.csv files, shared by the benchmark processes that append their records and the CLI that updates and loads them.
+//!
+//! Fields are quoted as in RFC 4180 when they contain a comma, a quote, or a line break, e.g. a build name with a comma.
+
+/// The version of the results.csv schema written by this version of harness.
+///
+/// Version 1: the key columns (`bench,build,invocation,iteration`), followed by all the metrics sorted by name.
+/// Records without a metric leave it empty.
+/// Files written by older versions have no version line, and their columns are in the order the stats were first reported.
+pub const SCHEMA_VERSION: usize = 1;
+
+/// The first line of a results.csv file, marking the version of its schema
+pub const SCHEMA_VERSION_PREFIX: &str = "# harness-results-schema: ";
+
+/// The columns that identify a record in results.csv
+pub const KEY_COLUMNS: [&str; 4] = ["bench", "build", "invocation", "iteration"];
+
+/// The columns that the runner and the probes record about a record, e.g. its status or the number of retries.
+/// They are not measured metrics, and are not reported.
+pub const BOOKKEEPING_COLUMNS: [&str; 7] = [
+    "status",
+    "warmup",
+    "package",
+    "retries",
+    "threads",
+    "warmup-iterations",
+    "probe-failures",
+];
+
+/// Quote a field if it contains a comma, a quote, or a line break, or if it starts with `#` and would be read as a comment
+pub fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) || field.starts_with('#') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// Format a record as a csv line, without the line break
+pub fn format_record<S: AsRef<str>>(fields: &[S]) -> String {
+    fields
+        .iter()
+        .map(|f| escape(f.as_ref()))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Parse the records of a csv file. Empty lines, and lines starting with `#`, are skipped.
+fn parse_records(content: &str) -> Vec<Vec<String>> {
+    let mut records = vec![];
+    let mut chars = content.chars().peekable();
+    while chars.peek().is_some() {
+        if chars.peek() == Some(&'#') {
+            chars.by_ref().find(|c| *c == '\n');
+            continue;
+        }
+        let mut record = vec![];
+        let mut field = String::new();
+        let mut quoted = false;
+        loop {
+            match chars.next() {
+                Some('"') if quoted => {
+                    if chars.peek() == Some(&'"') {
+                        chars.next();
+                        field.push('"');
+                    } else {
+                        quoted = false;
+                    }
+                }
+                Some('"') if field.is_empty() => quoted = true,
+                Some(c) if quoted => field.push(c),
+                Some(',') => record.push(std::mem::take(&mut field)),
+                Some('\r') if chars.peek() == Some(&'\n') => {}
+                Some('\n') | None => break,
+                Some(c) => field.push(c),
+            }
+        }
+        if !record.is_empty() || !field.is_empty() {
+            record.push(field);
+            records.push(record);
+        }
+    }
+    records
+}
+
+/// The header and rows of a results.csv file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    pub headers: Vec<String>,
+    /// All rows, padded to the width of the header
+    pub rows: Vec<Vec<String>>,
+    /// Whether the file starts with the version line of the current schema
+    pub versioned: bool,
+}
+
+impl Table {
+    pub fn parse(content: &str) -> Self {
+        let versioned =
+            content.lines().next() == Some(&format!("{SCHEMA_VERSION_PREFIX}{SCHEMA_VERSION}"));
+        let mut records = parse_records(content).into_iter();
+        let headers = records
+            .next()
+            .unwrap_or_else(|| KEY_COLUMNS.map(|s| s.to_owned()).to_vec());
+        let rows = records
+            .map(|mut row| {
+                row.resize(headers.len(), String::new());
+                row
+            })
+            .collect();
+        Self {
+            headers,
+            rows,
+            versioned,
+        }
+    }
+
+    pub fn col(&self, name: &str) -> Option<usize> {
+        self.headers.iter().position(|h| h == name)
+    }
+
+    /// Add the missing columns, and sort all the columns by the schema.
+    /// Existing values are moved to their new columns by name. Returns true if the columns have changed.
+    pub fn add_columns<'a>(&mut self, names: impl IntoIterator<Item = &'a str>) -> bool {
+        let mut metrics = names
+            .into_iter()
+            .map(|h| h.to_owned())
+            .collect::<std::collections::BTreeSet<_>>();
+        metrics.extend(self.headers.iter().cloned());
+        metrics.retain(|h| !KEY_COLUMNS.contains(&h.as_str()));
+        let headers = KEY_COLUMNS
+            .iter()
+            .map(|h| h.to_string())
+            .chain(metrics)
+            .collect::<Vec<_>>();
+        if headers == self.headers {
+            return false;
+        }
+        let old_headers = std::mem::replace(&mut self.headers, headers);
+        for row in self.rows.iter_mut() {
+            let mut new_row = vec![String::new(); self.headers.len()];
+            for (name, value) in old_headers.iter().zip(row.drain(..)) {
+                let i = self.headers.iter().position(|h| h == name).unwrap();
+                new_row[i] = value;
+            }
+            *row = new_row;
+        }
+        true
+    }
+
+    /// Format the table with the current schema
+    pub fn to_csv(&mut self) -> String {
+        self.add_columns([]);
+        self.versioned = true;
+        let mut out = format!("{SCHEMA_VERSION_PREFIX}{SCHEMA_VERSION}\n");
+        for row in std::iter::once(&self.headers).chain(&self.rows) {
+            out += &format_record(row);
+            out.push('\n');
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_round_trip() {
+        let fields = [
+            "plain",
+            "a,b",
+            "say \"hi\"",
+            "two\nlines",
+            "#not-a-comment",
+            "",
+        ];
+        let line = format_record(&fields);
+        assert_eq!(
+            line,
+            "plain,\"a,b\",\"say \"\"hi\"\"\",\"two\nlines\",\"#not-a-comment\","
+        );
+        let table = Table::parse(&format!("{}\n{}\n", format_record(&fields), line));
+        assert_eq!(table.headers, fields);
+        assert_eq!(table.rows, vec![fields.map(|f| f.to_owned()).to_vec()]);
+    }
+
+    #[test]
+    fn add_columns_moves_values_by_name() {
+        let mut table = Table::parse(
+            "# harness-results-schema: 1\nbench,build,invocation,iteration,time\nfoo,a,0,4,1.5\n",
+        );
+        assert!(table.versioned);
+        assert!(!table.add_columns(["time"]));
+        assert!(table.add_columns(["alloc-bytes"]));
+        assert_eq!(
+            table.headers,
+            [
+                "bench",
+                "build",
+                "invocation",
+                "iteration",
+                "alloc-bytes",
+                "time"
+            ]
+        );
+        assert_eq!(table.rows, vec![vec!["foo", "a", "0", "4", "", "1.5"]]);
+    }
+
+    #[test]
+    fn legacy_files_are_sorted_by_the_schema() {
+        let mut table =
+            Table::parse("time,bench,build,invocation,iteration\n1.5,foo,a,0,4\n2.5,bar,a,0,4");
+        assert!(!table.versioned);
+        assert_eq!(
+            table.to_csv(),
+            "# harness-results-schema: 1\nbench,build,invocation,iteration,time\nfoo,a,0,4,1.5\nbar,a,0,4,2.5\n"
+        );
+        assert!(table.versioned);
+    }
+
+    #[test]
+    fn short_rows_are_padded() {
+        let table = Table::parse("bench,build,invocation,iteration,time\r\nfoo,a,0\r\n");
+        assert_eq!(table.rows, vec![vec!["foo", "a", "0", "", ""]]);
+    }
+}
@@ -17,6 +17,105 @@ pub fn download_file(key: impl AsRef<str>, url: impl AsRef<str>) -> anyhow::Resu
     Ok(path)
 }
 
+/// Get the directory of a dataset declared in the `[package.metadata.harness.datasets]` section of `Cargo.toml`.
+/// The dataset is downloaded, verified, and extracted into the cache dir by `cargo harness run` before the benchmarks start.
+pub fn dataset(name: impl AsRef<str>) -> anyhow::Result<PathBuf> {
+    let dir = HARNESS_BENCH_CACHE_DIR.join("datasets").join(name.as_ref());
+    if !dir.is_dir() {
+        anyhow::bail!(
+            "Dataset `{}` is not found. Declare it in `[package.metadata.harness.datasets]`",
+            name.as_ref()
+        );
+    }
+    Ok(dir)
+}
+
+/// A dataset declared by a benchmark, downloaded into the same cache dir as the datasets declared in `Cargo.toml`.
+///
+/// ```no_run
+/// let file = harness::utils::Dataset::new("enwik8", "https://mattmahoney.net/dc/enwik8.zip")
+///     .sha256("547994d9980ebed1288380d652999f38a14fe291a6247c157c3d33d4932534bc")
+///     .fetch()?;
+/// # anyhow::Ok(())
+/// ```
+#[cfg(feature = "net")]
+pub struct Dataset {
+    name: String,
+    url: String,
+    sha256: Option<String>,
+}
+
+#[cfg(feature = "net")]
+impl Dataset {
+    pub fn new(name: impl AsRef<str>, url: impl AsRef<str>) -> Self {
+        Self {
+            name: name.as_ref().to_owned(),
+            url: url.as_ref().to_owned(),
+            sha256: None,
+        }
+    }
+
+    /// The expected SHA-256 checksum of the downloaded file
+    pub fn sha256(mut self, sha256: impl AsRef<str>) -> Self {
+        self.sha256 = Some(sha256.as_ref().trim().to_lowercase());
+        self
+    }
+
+    fn sha256_file(path: &std::path::Path) -> anyhow::Result<String> {
+        use sha2::{Digest, Sha256};
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut File::open(path)?, &mut hasher)?;
+        Ok(hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect())
+    }
+
+    /// Download the dataset, unless it is already in the cache dir, and verify its checksum.
+    /// Returns the path of the downloaded file.
+    pub fn fetch(&self) -> anyhow::Result<PathBuf> {
+        let dir = HARNESS_BENCH_CACHE_DIR.join("datasets").join(&self.name);
+        let file_name = self
+            .url
+            .split(['?', '#'])
+            .next()
+            .and_then(|u| u.rsplit('/').next())
+            .filter(|f| !f.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("No file name in the URL: {}", self.url))?;
+        let path = dir.join(file_name);
+        let verified = |path: &std::path::Path| -> anyhow::Result<bool> {
+            match &self.sha256 {
+                Some(expected) => Ok(Self::sha256_file(path)? == *expected),
+                None => Ok(true),
+            }
+        };
+        if path.exists() && verified(&path)? {
+            return Ok(path);
+        }
+        std::fs::create_dir_all(&dir)?;
+        // Download to a temporary file first, so an interrupted download is never used
+        let tmp = dir.join(format!("{}.download", file_name));
+        let mut response = reqwest::blocking::Client::builder()
+            .timeout(None)
+            .build()?
+            .get(&self.url)
+            .send()?
+            .error_for_status()?;
+        response.copy_to(&mut File::create(&tmp)?)?;
+        if !verified(&tmp)? {
+            std::fs::remove_file(&tmp)?;
+            anyhow::bail!(
+                "SHA-256 checksum mismatch of dataset `{}` downloaded from {}",
+                self.name,
+                self.url
+            );
+        }
+        std::fs::rename(&tmp, &path)?;
+        Ok(path)
+    }
+}
+
 /// Get a cached file from the cache dir.
 pub fn get_cached_file(key: impl AsRef<str>) -> Option<PathBuf> {
     let cache_dir = PathBuf::from(env::var("HARNESS_BENCH_CACHE_DIR").unwrap());
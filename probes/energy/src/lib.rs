@@ -0,0 +1,139 @@
+#[cfg(target_os = "linux")]
+use std::{collections::HashMap, path::PathBuf};
+
+use harness::probe::Probe;
+#[cfg(target_os = "linux")]
+use harness::probe::ProbeArgs;
+#[cfg(target_os = "linux")]
+use harness::Value;
+
+#[cfg(target_os = "linux")]
+const POWERCAP_DIR: &str = "/sys/class/powercap";
+
+/// A RAPL power domain exposed by the linux powercap framework.
+/// This covers both the `intel-rapl` driver and the `amd-rapl` driver on recent kernels.
+#[cfg(target_os = "linux")]
+struct Domain {
+    /// Domain name without the socket suffix. e.g. `package`, `dram`, `core`.
+    name: String,
+    /// Path to the `energy_uj` file
+    energy_file: PathBuf,
+    /// The counter wraps around at this value
+    max_energy_uj: u64,
+    start_uj: u64,
+    end_uj: u64,
+}
+
+#[cfg(target_os = "linux")]
+impl Domain {
+    fn read_u64(path: PathBuf) -> Option<u64> {
+        std::fs::read_to_string(path).ok()?.trim().parse().ok()
+    }
+
+    fn load(zone: PathBuf) -> Option<Self> {
+        let name = std::fs::read_to_string(zone.join("name")).ok()?;
+        // `package-0` and `package-1` are both reported as `package`
+        let name = name.trim();
+        let name = match name.rsplit_once('-') {
+            Some((prefix, id)) if id.parse::<usize>().is_ok() => prefix,
+            _ => name,
+        };
+        let energy_file = zone.join("energy_uj");
+        // Check that the counter is readable
+        Self::read_u64(energy_file.clone())?;
+        Some(Self {
+            name: name.to_owned(),
+            energy_file,
+            max_energy_uj: Self::read_u64(zone.join("max_energy_range_uj")).unwrap_or(u64::MAX),
+            start_uj: 0,
+            end_uj: 0,
+        })
+    }
+
+    fn read(&self) -> u64 {
+        Self::read_u64(self.energy_file.clone()).unwrap_or(0)
+    }
+
+    fn energy_joules(&self) -> f64 {
+        let uj = if self.end_uj >= self.start_uj {
+            self.end_uj - self.start_uj
+        } else {
+            // The counter wrapped around
+            self.max_energy_uj - self.start_uj + self.end_uj
+        };
+        uj as f64 / 1_000_000.0
+    }
+}
+
+#[harness::probe]
+#[derive(Default)]
+pub struct EnergyProbe {
+    #[cfg(target_os = "linux")]
+    domains: Vec<Domain>,
+}
+
+#[cfg(not(target_os = "linux"))]
+impl Probe for EnergyProbe {
+    fn init(&mut self, _args: harness::probe::ProbeArgs) {
+        eprintln!("WARNING: harness-probe-energy is only supported on linux. No energy data will be reported.");
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Probe for EnergyProbe {
+    /// Discover all readable RAPL domains.
+    fn init(&mut self, args: ProbeArgs) {
+        // Optional comma-separated list of domains to report. Default to all domains.
+        let filter = args
+            .get::<String>("domains")
+            .map(|s| {
+                s.split(',')
+                    .map(|s| s.trim().to_owned())
+                    .filter(|s| !s.is_empty())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let mut zones = std::fs::read_dir(POWERCAP_DIR)
+            .map(|d| d.flatten().map(|e| e.path()).collect::<Vec<_>>())
+            .unwrap_or_default();
+        zones.sort();
+        for zone in zones {
+            let zone_name = zone.file_name().unwrap().to_string_lossy().to_string();
+            // Skip the `*-mmio` zones as they duplicate the msr zones
+            if !zone_name.contains("rapl:") {
+                continue;
+            }
+            if let Some(domain) = Domain::load(zone) {
+                if filter.is_empty() || filter.contains(&domain.name) {
+                    self.domains.push(domain);
+                }
+            }
+        }
+        if self.domains.is_empty() {
+            eprintln!("WARNING: No readable RAPL energy counters found under {POWERCAP_DIR}. No energy data will be reported.");
+        }
+    }
+
+    /// Record the energy counters at the start of the timing iteration.
+    fn begin(&mut self, _benchmark: &str, _iteration: usize, _warmup: bool) {
+        for d in &mut self.domains {
+            d.start_uj = d.read();
+        }
+    }
+
+    /// Record the energy counters at the end of the timing iteration.
+    fn end(&mut self, _benchmark: &str, _iteration: usize, _warmup: bool) {
+        for d in &mut self.domains {
+            d.end_uj = d.read();
+        }
+    }
+
+    /// Report the energy consumption of each domain, in joules. Domains with the same name are summed up.
+    fn report(&mut self) -> HashMap<String, Value> {
+        let mut energy = HashMap::<String, f64>::new();
+        for d in &self.domains {
+            *energy.entry(format!("energy-{}", d.name)).or_default() += d.energy_joules();
+        }
+        energy.into_iter().map(|(k, v)| (k, v.into())).collect()
+    }
+}
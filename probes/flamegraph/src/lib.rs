@@ -0,0 +1,191 @@
+#[cfg(target_os = "linux")]
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+};
+
+use harness::probe::Probe;
+#[cfg(target_os = "linux")]
+use harness::probe::ProbeArgs;
+
+/// Fold the stack samples of a `perf script` output into the collapsed-stack format.
+///
+/// Each sample in the `perf script` output is a header line followed by one frame per line,
+/// innermost frame first. The collapsed format has one line per unique stack, outermost frame first:
+/// `main;foo;bar 42`.
+#[cfg(target_os = "linux")]
+fn fold_stacks(script: &str) -> Vec<(String, usize)> {
+    let mut stacks = HashMap::<String, usize>::new();
+    let mut frames: Vec<&str> = vec![];
+    let mut flush = |frames: &mut Vec<&str>| {
+        if !frames.is_empty() {
+            frames.reverse();
+            *stacks.entry(frames.join(";")).or_default() += 1;
+            frames.clear();
+        }
+    };
+    for line in script.lines() {
+        if line.trim().is_empty() {
+            flush(&mut frames);
+        } else if line.starts_with(char::is_whitespace) {
+            // A frame line: `  <addr> <symbol>+<offset> (<dso>)`
+            let mut parts = line.split_whitespace();
+            parts.next();
+            let symbol = parts.next().unwrap_or("[unknown]");
+            let symbol = match symbol.rsplit_once("+0x") {
+                Some((s, _)) => s,
+                None => symbol,
+            };
+            frames.push(symbol);
+        }
+    }
+    flush(&mut frames);
+    let mut stacks = stacks.into_iter().collect::<Vec<_>>();
+    stacks.sort();
+    stacks
+}
+
+#[harness::probe]
+#[derive(Default)]
+pub struct FlamegraphProbe {
+    /// Sampling frequency, in Hz
+    #[cfg(target_os = "linux")]
+    frequency: usize,
+    /// The `--call-graph` mode of perf record. e.g. `dwarf`, `fp`, `lbr`.
+    #[cfg(target_os = "linux")]
+    call_graph: String,
+    /// Generate a collapsed-stack file after recording
+    #[cfg(target_os = "linux")]
+    collapse: bool,
+    /// The log directory of the current run. Samples are not recorded if this is not set.
+    #[cfg(target_os = "linux")]
+    log_dir: Option<PathBuf>,
+    #[cfg(target_os = "linux")]
+    build: String,
+    #[cfg(target_os = "linux")]
+    invocation: String,
+    #[cfg(target_os = "linux")]
+    perf: Option<Child>,
+}
+
+#[cfg(not(target_os = "linux"))]
+impl Probe for FlamegraphProbe {
+    fn init(&mut self, _args: harness::probe::ProbeArgs) {
+        eprintln!("WARNING: harness-probe-flamegraph is only supported on linux. No samples will be recorded.");
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl FlamegraphProbe {
+    /// The output file next to the invocation log: `<log-dir>/<bench>.<build>.<invocation>.<ext>`
+    fn output_file(&self, benchmark: &str, ext: &str) -> Option<PathBuf> {
+        let log_dir = self.log_dir.as_ref()?;
+        Some(log_dir.join(format!(
+            "{}.{}.{}.{}",
+            benchmark, self.build, self.invocation, ext
+        )))
+    }
+
+    fn collapse(&self, perf_data: &Path, out_file: &Path) -> Result<(), String> {
+        let output = Command::new("perf")
+            .arg("script")
+            .arg("-i")
+            .arg(perf_data)
+            .stderr(Stdio::null())
+            .output()
+            .map_err(|e| e.to_string())?;
+        if !output.status.success() {
+            return Err(format!("perf script exited with {}", output.status));
+        }
+        let script = String::from_utf8_lossy(&output.stdout);
+        let folded = fold_stacks(&script)
+            .into_iter()
+            .map(|(stack, count)| format!("{} {}\n", stack, count))
+            .collect::<String>();
+        std::fs::write(out_file, folded).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Probe for FlamegraphProbe {
+    /// Initialize the probe before benchmarking.
+    fn init(&mut self, args: ProbeArgs) {
+        self.frequency = args.get::<usize>("frequency").unwrap_or(999);
+        self.call_graph = args
+            .get::<String>("call-graph")
+            .unwrap_or_else(|_| "dwarf".to_owned());
+        self.collapse = args.get::<bool>("collapse").unwrap_or(true);
+        let Ok(log_dir) = std::env::var("HARNESS_BENCH_LOG_DIR") else {
+            eprintln!("WARNING: harness-probe-flamegraph requires a log directory. No samples will be recorded.");
+            return;
+        };
+        self.log_dir = Some(PathBuf::from(log_dir));
+        self.build = std::env::var("HARNESS_BENCH_BUILD").unwrap_or_else(|_| "unknown".to_owned());
+        self.invocation =
+            std::env::var("HARNESS_BENCH_INVOCATION").unwrap_or_else(|_| "0".to_owned());
+    }
+
+    /// Attach `perf record` to the current process before the timing iteration.
+    fn begin(&mut self, benchmark: &str, _iteration: usize, warmup: bool) {
+        if warmup {
+            return;
+        }
+        let Some(out_file) = self.output_file(benchmark, "perf.data") else {
+            return;
+        };
+        let child = Command::new("perf")
+            .arg("record")
+            .arg("-F")
+            .arg(self.frequency.to_string())
+            .arg("--call-graph")
+            .arg(&self.call_graph)
+            .arg("-p")
+            .arg(std::process::id().to_string())
+            .arg("-o")
+            .arg(&out_file)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        match child {
+            Ok(child) => {
+                self.perf = Some(child);
+                // Give perf some time to attach before the timer starts
+                std::thread::sleep(std::time::Duration::from_millis(200));
+            }
+            Err(e) => eprintln!("WARNING: Failed to run perf record: {}", e),
+        }
+    }
+
+    /// Stop `perf record` after the timing iteration, and fold the recorded stacks.
+    fn end(&mut self, benchmark: &str, _iteration: usize, warmup: bool) {
+        if warmup {
+            return;
+        }
+        let Some(mut perf) = self.perf.take() else {
+            return;
+        };
+        // perf record flushes the samples on SIGINT
+        unsafe {
+            libc::kill(perf.id() as libc::pid_t, libc::SIGINT);
+        }
+        match perf.wait() {
+            Ok(status) if status.success() => {}
+            Ok(status) => {
+                eprintln!("WARNING: perf record exited with {}", status);
+                return;
+            }
+            Err(e) => {
+                eprintln!("WARNING: Failed to wait for perf record: {}", e);
+                return;
+            }
+        }
+        if self.collapse {
+            let perf_data = self.output_file(benchmark, "perf.data").unwrap();
+            let folded = self.output_file(benchmark, "folded").unwrap();
+            if let Err(e) = self.collapse(&perf_data, &folded) {
+                eprintln!("WARNING: Failed to collapse perf samples: {}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use harness::probe::Probe;
+#[cfg(unix)]
+use harness::probe::ProbeArgs;
+#[cfg(target_os = "linux")]
+use harness::probe::SamplingProbe;
+use harness::Value;
+
+/// Resource usage counters of the current process.
+#[cfg(unix)]
+#[derive(Default, Clone, Copy)]
+struct Usage {
+    /// Max resident set size, in bytes
+    max_rss: u64,
+    minor_faults: u64,
+    major_faults: u64,
+    voluntary_switches: u64,
+    involuntary_switches: u64,
+}
+
+#[cfg(unix)]
+impl Usage {
+    fn now() -> Self {
+        let mut usage = std::mem::MaybeUninit::<libc::rusage>::zeroed();
+        let usage = unsafe {
+            if libc::getrusage(libc::RUSAGE_SELF, usage.as_mut_ptr()) != 0 {
+                return Self::default();
+            }
+            usage.assume_init()
+        };
+        // `ru_maxrss` is in kilobytes on Linux, but in bytes on macOS.
+        let max_rss = if cfg!(target_os = "macos") {
+            usage.ru_maxrss as u64
+        } else {
+            usage.ru_maxrss as u64 * 1024
+        };
+        Self {
+            max_rss,
+            minor_faults: usage.ru_minflt as u64,
+            major_faults: usage.ru_majflt as u64,
+            voluntary_switches: usage.ru_nvcsw as u64,
+            involuntary_switches: usage.ru_nivcsw as u64,
+        }
+    }
+}
+
+/// Reset the peak RSS watermark of the current process.
+/// Returns false if this is not supported by the kernel.
+#[cfg(target_os = "linux")]
+fn reset_peak_rss() -> bool {
+    std::fs::write("/proc/self/clear_refs", "5").is_ok()
+}
+
+/// Read a `/proc/self/status` field that is reported in kilobytes, and convert it to bytes.
+#[cfg(target_os = "linux")]
+fn read_proc_status_bytes(key: &str) -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with(key))?;
+    let kb = line[key.len()..]
+        .trim_start_matches(':')
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kb * 1024)
+}
+
+#[harness::probe]
+#[derive(Default)]
+pub struct MemoryProbe {
+    #[cfg(unix)]
+    start: Usage,
+    #[cfg(unix)]
+    end: Usage,
+    /// Peak RSS during the timing phase, if the platform allows resetting the watermark
+    #[cfg(unix)]
+    peak_rss: Option<u64>,
+    /// Also report the RSS at the end of the timing phase
+    #[cfg(target_os = "linux")]
+    report_rss: bool,
+}
+
+#[cfg(not(unix))]
+impl Probe for MemoryProbe {}
+
+#[cfg(unix)]
+impl Probe for MemoryProbe {
+    /// Initialize the probe before benchmarking.
+    fn init(&mut self, #[allow(unused)] args: ProbeArgs) {
+        #[cfg(target_os = "linux")]
+        {
+            self.report_rss = args.get::<bool>("rss").unwrap_or_default();
+        }
+    }
+
+    /// Reset the peak RSS watermark and snapshot the counters at the start of the timing phase.
+    fn begin(&mut self, _benchmark: &str, _iteration: usize, _warmup: bool) {
+        #[cfg(target_os = "linux")]
+        let reset = reset_peak_rss();
+        #[cfg(not(target_os = "linux"))]
+        let reset = false;
+        self.peak_rss = None;
+        self.start = Usage::now();
+        if reset {
+            self.peak_rss = Some(0);
+        }
+    }
+
+    /// Snapshot the counters at the end of the timing phase.
+    fn end(&mut self, _benchmark: &str, _iteration: usize, _warmup: bool) {
+        self.end = Usage::now();
+        #[cfg(target_os = "linux")]
+        if self.peak_rss.is_some() {
+            self.peak_rss = read_proc_status_bytes("VmHWM");
+        }
+    }
+
+    /// Report data after the timing iteration.
+    fn report(&mut self) -> HashMap<String, Value> {
+        let mut values = HashMap::new();
+        // Fallback to the whole-process high-water mark if the watermark could not be reset
+        let peak_rss = self.peak_rss.unwrap_or(self.end.max_rss);
+        values.insert("peak-rss".to_owned(), peak_rss.into());
+        values.insert(
+            "minor-page-faults".to_owned(),
+            (self.end.minor_faults - self.start.minor_faults).into(),
+        );
+        values.insert(
+            "major-page-faults".to_owned(),
+            (self.end.major_faults - self.start.major_faults).into(),
+        );
+        values.insert(
+            "voluntary-context-switches".to_owned(),
+            (self.end.voluntary_switches - self.start.voluntary_switches).into(),
+        );
+        values.insert(
+            "involuntary-context-switches".to_owned(),
+            (self.end.involuntary_switches - self.start.involuntary_switches).into(),
+        );
+        #[cfg(target_os = "linux")]
+        if self.report_rss {
+            if let Some(rss) = read_proc_status_bytes("VmRSS") {
+                values.insert("rss".to_owned(), rss.into());
+            }
+        }
+        values
+    }
+
+    /// Record the RSS over time, when sampling is enabled.
+    #[cfg(target_os = "linux")]
+    fn sampler(&mut self) -> Option<Box<dyn SamplingProbe>> {
+        Some(Box::new(RssSampler))
+    }
+}
+
+/// Samples the RSS of the current process
+#[cfg(target_os = "linux")]
+struct RssSampler;
+
+#[cfg(target_os = "linux")]
+impl SamplingProbe for RssSampler {
+    fn sample(&mut self) -> HashMap<String, Value> {
+        let mut values = HashMap::new();
+        if let Some(rss) = read_proc_status_bytes("VmRSS") {
+            values.insert("rss".to_owned(), rss.into());
+        }
+        values
+    }
+}
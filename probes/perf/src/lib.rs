@@ -1,71 +1,499 @@
-#[cfg(target_os = "linux")]
+#[cfg(any(target_os = "linux", target_os = "macos"))]
 use std::collections::HashMap;
 
 use harness::probe::Probe;
-#[cfg(target_os = "linux")]
+#[cfg(any(target_os = "linux", target_os = "macos"))]
 use harness::probe::ProbeArgs;
-#[cfg(target_os = "linux")]
+#[cfg(any(target_os = "linux", target_os = "macos"))]
 use harness::Value;
 
+/// The events of the `memory` preset, as `(column name, libpfm event name)` pairs.
+///
+/// Intel CPUs use the precise page-walk events. Other vendors use the generic perf cache events,
+/// which the kernel maps to the correct raw events for the current CPU.
+#[cfg(target_os = "linux")]
+fn memory_preset_events() -> Vec<(&'static str, &'static str)> {
+    let vendor = std::fs::read_to_string("/proc/cpuinfo")
+        .ok()
+        .and_then(|s| {
+            s.lines()
+                .find(|l| l.starts_with("vendor_id"))
+                .and_then(|l| l.split(':').nth(1))
+                .map(|v| v.trim().to_owned())
+        })
+        .unwrap_or_default();
+    let mut events = if vendor == "GenuineIntel" {
+        vec![
+            ("dtlb-load-misses", "DTLB_LOAD_MISSES:WALK_COMPLETED"),
+            ("dtlb-store-misses", "DTLB_STORE_MISSES:WALK_COMPLETED"),
+            ("itlb-misses", "ITLB_MISSES:WALK_COMPLETED"),
+            ("llc-misses", "LONGEST_LAT_CACHE:MISS"),
+        ]
+    } else {
+        vec![
+            ("dtlb-load-misses", "PERF_COUNT_HW_CACHE_DTLB:READ:MISS"),
+            ("dtlb-store-misses", "PERF_COUNT_HW_CACHE_DTLB:WRITE:MISS"),
+            ("itlb-misses", "PERF_COUNT_HW_CACHE_ITLB:READ:MISS"),
+            ("llc-misses", "PERF_COUNT_HW_CACHE_LL:READ:MISS"),
+        ]
+    };
+    events.push(("minor-page-faults", "PERF_COUNT_SW_PAGE_FAULTS_MIN"));
+    events.push(("major-page-faults", "PERF_COUNT_SW_PAGE_FAULTS_MAJ"));
+    events
+}
+
 #[harness::probe]
 #[derive(Default)]
 pub struct PerfEventProbe {
     #[cfg(target_os = "linux")]
     perfmon: pfm::Perfmon,
     #[cfg(target_os = "linux")]
-    events: Vec<pfm::PerfEvent>,
-    #[cfg(target_os = "linux")]
+    groups: Vec<linux::Group>,
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
     event_names: Vec<String>,
+    #[cfg(target_os = "macos")]
+    mac_events: Vec<macos::Counter>,
+    #[cfg(target_os = "macos")]
+    start: Vec<u64>,
+    #[cfg(target_os = "macos")]
+    end: Vec<u64>,
 }
 
-#[cfg(not(target_os = "linux"))]
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
 impl Probe for PerfEventProbe {}
 
+/// macOS support, based on the per-process instruction and cycle counters reported by `proc_pid_rusage`.
+///
+/// This does not require root permission, but only the retired instructions and cycles are available.
+#[cfg(target_os = "macos")]
+mod macos {
+    #[derive(Clone, Copy)]
+    pub enum Counter {
+        Instructions,
+        Cycles,
+    }
+
+    impl Counter {
+        /// Accept both the short names and the linux perf event names, so the same config works on both platforms.
+        pub fn from_name(name: &str) -> Option<Self> {
+            match name {
+                "instructions" | "PERF_COUNT_HW_INSTRUCTIONS" | "INST_RETIRED" => {
+                    Some(Counter::Instructions)
+                }
+                "cycles" | "PERF_COUNT_HW_CPU_CYCLES" | "CPU_CLK_UNHALTED" => Some(Counter::Cycles),
+                _ => None,
+            }
+        }
+    }
+
+    /// Read the counters of the current process. Returns `None` if they are not supported.
+    pub fn read(counters: &[Counter]) -> Option<Vec<u64>> {
+        let mut info = std::mem::MaybeUninit::<libc::rusage_info_v4>::zeroed();
+        let info = unsafe {
+            let ret = libc::proc_pid_rusage(
+                std::process::id() as libc::c_int,
+                libc::RUSAGE_INFO_V4,
+                info.as_mut_ptr() as *mut libc::rusage_info_t,
+            );
+            if ret != 0 {
+                return None;
+            }
+            info.assume_init()
+        };
+        Some(
+            counters
+                .iter()
+                .map(|c| match c {
+                    Counter::Instructions => info.ri_instructions,
+                    Counter::Cycles => info.ri_cycles,
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Linux support, based on `perf_event_open`.
+///
+/// The events are encoded by libpfm, and opened in groups. The events of a group are always scheduled on the PMU
+/// together. When there are more events than hardware counters, the kernel multiplexes the groups, and the counts
+/// are scaled up by `time_enabled / time_running`.
 #[cfg(target_os = "linux")]
+mod linux {
+    use std::ffi::{c_int, c_uint, c_void, CStr, CString};
+    use std::io;
+
+    use perf_event_open_sys::bindings::{
+        perf_event_attr, PERF_FORMAT_TOTAL_TIME_ENABLED, PERF_FORMAT_TOTAL_TIME_RUNNING,
+        PERF_IOC_FLAG_GROUP, PERF_TYPE_RAW,
+    };
+    use perf_event_open_sys::{ioctls, perf_event_open};
+
+    /// The privilege levels an event counts in, selected by the `:u`, `:k`, or `:uk` modifier.
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub enum Mode {
+        User,
+        Kernel,
+        UserKernel,
+    }
+
+    impl Mode {
+        /// Split the privilege modifier off an event name, e.g. `LONGEST_LAT_CACHE:MISS:k`.
+        pub fn split(name: &str) -> (&str, Option<Mode>) {
+            let Some((base, modifier)) = name.rsplit_once(':') else {
+                return (name, None);
+            };
+            let mode = match modifier {
+                "u" => Mode::User,
+                "k" => Mode::Kernel,
+                "uk" | "ku" => Mode::UserKernel,
+                _ => return (name, None),
+            };
+            (base, Some(mode))
+        }
+
+        fn plm(self) -> i32 {
+            match self {
+                Mode::User => pfm_sys::PFM_PLM3,
+                Mode::Kernel => pfm_sys::PFM_PLM0,
+                Mode::UserKernel => pfm_sys::PFM_PLM0 | pfm_sys::PFM_PLM3,
+            }
+        }
+    }
+
+    /// Parse a raw PMU event descriptor, i.e. `r` followed by the hex encoding of the event, e.g. `r01c2` for
+    /// umask `0x01` and event `0xc2`.
+    fn parse_raw(name: &str) -> Option<u64> {
+        let hex = name.strip_prefix('r')?;
+        if hex.is_empty() || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        u64::from_str_radix(hex, 16).ok()
+    }
+
+    /// Encode an event, e.g. `PERF_COUNT_HW_CPU_CYCLES`, `LONGEST_LAT_CACHE:MISS`, or the raw event `r01c2`.
+    /// Events count in user mode only, unless a `:k` or `:uk` modifier is given.
+    pub fn encode(name: &str, inherit: bool) -> Result<perf_event_attr, String> {
+        let (base, mode) = Mode::split(name);
+        let mode = mode.unwrap_or(Mode::User);
+        let mut attr: perf_event_attr = unsafe { std::mem::zeroed() };
+        // libpfm only writes the first `attr.size` bytes of the struct
+        attr.size = std::mem::size_of::<perf_event_attr>() as u32;
+        if let Some(config) = parse_raw(base) {
+            attr.type_ = PERF_TYPE_RAW;
+            attr.config = config;
+            attr.set_exclude_user((mode == Mode::Kernel) as u64);
+            attr.set_exclude_kernel((mode == Mode::User) as u64);
+            attr.set_exclude_hv(1);
+        } else {
+            let cname = CString::new(base).map_err(|e| e.to_string())?;
+            let mut arg: pfm_sys::pfm_perf_encode_arg_t = unsafe { std::mem::zeroed() };
+            arg.size = std::mem::size_of::<pfm_sys::pfm_perf_encode_arg_t>();
+            arg.attr = &mut attr as *mut perf_event_attr as *mut pfm_sys::perf_event_attr;
+            let err = unsafe {
+                pfm_sys::pfm_get_os_event_encoding(
+                    cname.as_ptr(),
+                    mode.plm(),
+                    pfm_sys::pfm_os_t::PFM_OS_PERF_EVENT_EXT,
+                    &mut arg as *mut pfm_sys::pfm_perf_encode_arg_t as *mut c_void,
+                )
+            };
+            if err != pfm_sys::PFM_SUCCESS {
+                let msg = unsafe { CStr::from_ptr(pfm_sys::pfm_strerror(err)) };
+                return Err(format!("{}: {}", name, msg.to_string_lossy()));
+            }
+        }
+        attr.read_format = (PERF_FORMAT_TOTAL_TIME_ENABLED | PERF_FORMAT_TOTAL_TIME_RUNNING) as u64;
+        attr.set_inherit(inherit as u64);
+        Ok(attr)
+    }
+
+    /// A counter value, with the time the counter was enabled and the time it was actually counting on the PMU.
+    #[derive(Clone, Copy)]
+    pub struct Count {
+        pub value: u64,
+        pub time_enabled: u64,
+        pub time_running: u64,
+    }
+
+    impl Count {
+        /// The fraction of the enabled time the counter was running. 1.0 means the counter was not multiplexed.
+        pub fn running_ratio(&self) -> f64 {
+            if self.time_enabled == 0 {
+                return 1.0;
+            }
+            self.time_running as f64 / self.time_enabled as f64
+        }
+
+        /// The count scaled up to the whole enabled time.
+        /// Returns `None` if the counter was enabled but never scheduled on the PMU.
+        pub fn scaled(&self) -> Option<f64> {
+            if self.time_running == self.time_enabled {
+                return Some(self.value as f64);
+            }
+            if self.time_running == 0 {
+                return None;
+            }
+            Some(self.value as f64 * self.time_enabled as f64 / self.time_running as f64)
+        }
+    }
+
+    /// A perf event group. The first event is the group leader.
+    pub struct Group {
+        fds: Vec<c_int>,
+    }
+
+    impl Group {
+        /// Open a group of events for the current process.
+        pub fn open(attrs: Vec<perf_event_attr>) -> io::Result<Self> {
+            let mut group = Group { fds: vec![] };
+            for mut attr in attrs {
+                // Only the leader is disabled. The members are enabled and disabled with it.
+                let leader = group.fds.first().copied().unwrap_or(-1);
+                attr.set_disabled((leader == -1) as u64);
+                let fd = unsafe { perf_event_open(&mut attr, 0, -1, leader, 0) };
+                if fd == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                group.fds.push(fd);
+            }
+            Ok(group)
+        }
+
+        /// Apply an ioctl to the whole group.
+        fn ioctl(&self, f: unsafe fn(c_int, c_uint) -> c_int) -> io::Result<()> {
+            let Some(leader) = self.fds.first() else {
+                return Ok(());
+            };
+            if unsafe { f(*leader, PERF_IOC_FLAG_GROUP) } == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        }
+
+        pub fn reset(&self) -> io::Result<()> {
+            self.ioctl(ioctls::RESET)
+        }
+
+        pub fn enable(&self) -> io::Result<()> {
+            self.ioctl(ioctls::ENABLE)
+        }
+
+        pub fn disable(&self) -> io::Result<()> {
+            self.ioctl(ioctls::DISABLE)
+        }
+
+        /// Read the counts of all the events in the group, in the order they were opened.
+        pub fn read(&self) -> io::Result<Vec<Count>> {
+            let mut counts = vec![];
+            for fd in &self.fds {
+                let mut buf = [0u64; 3];
+                let size = std::mem::size_of_val(&buf);
+                let n = unsafe { libc::read(*fd, buf.as_mut_ptr() as *mut c_void, size) };
+                if n != size as isize {
+                    return Err(io::Error::last_os_error());
+                }
+                counts.push(Count {
+                    value: buf[0],
+                    time_enabled: buf[1],
+                    time_running: buf[2],
+                });
+            }
+            Ok(counts)
+        }
+    }
+
+    impl Drop for Group {
+        fn drop(&mut self) {
+            for fd in &self.fds {
+                unsafe { libc::close(*fd) };
+            }
+        }
+    }
+}
+
+/// Split the `events` option into event groups.
+///
+/// Events in braces are opened as one group, e.g. `{cycles,instructions},LLC_MISSES` has two groups.
+/// Every other event is a group on its own.
+#[cfg(target_os = "linux")]
+fn parse_event_groups(events: &str) -> Vec<Vec<String>> {
+    let mut groups = vec![];
+    let mut group: Option<Vec<String>> = None;
+    for token in events.split(',') {
+        let mut name = token.trim();
+        if let Some(rest) = name.strip_prefix('{') {
+            assert!(group.is_none(), "Nested perf event groups: {}", events);
+            group = Some(vec![]);
+            name = rest.trim();
+        }
+        let closed = name.ends_with('}');
+        name = name.trim_end_matches('}').trim();
+        if !name.is_empty() {
+            match &mut group {
+                Some(g) => g.push(name.to_owned()),
+                None => groups.push(vec![name.to_owned()]),
+            }
+        }
+        if closed {
+            let g = group.take().expect("Unmatched `}` in perf events");
+            if !g.is_empty() {
+                groups.push(g);
+            }
+        }
+    }
+    assert!(group.is_none(), "Unclosed perf event group: {}", events);
+    groups
+}
+
+#[cfg(target_os = "macos")]
 impl Probe for PerfEventProbe {
     /// Initialize the probe before benchmarking.
     fn init(&mut self, args: ProbeArgs) {
-        self.perfmon.initialize().expect("libpfm init failed.");
         let events = args.get::<String>("events").unwrap_or_default();
-        let inherit = args.get::<bool>("inherit").unwrap_or_default();
-        self.event_names = events
+        if args.get::<String>("preset").is_ok() {
+            eprintln!("WARNING: harness-probe-perf presets are not supported on macOS.");
+        }
+        for name in events
             .split(',')
             .map(|s| s.trim())
             .filter(|s| !s.is_empty())
-            .map(|s| s.to_owned())
-            .collect();
-        self.events = self
-            .event_names
-            .iter()
-            .map(|s| pfm::PerfEvent::new(s, inherit).unwrap())
-            .collect();
-        for e in &mut self.events {
-            e.open(0, -1).unwrap();
+        {
+            match macos::Counter::from_name(name) {
+                Some(c) => {
+                    self.mac_events.push(c);
+                    self.event_names.push(name.to_owned());
+                }
+                None => eprintln!(
+                    "WARNING: perf event `{}` is not supported on macOS. Only `instructions` and `cycles` are available.",
+                    name
+                ),
+            }
+        }
+        if !self.mac_events.is_empty() && macos::read(&self.mac_events).is_none() {
+            eprintln!(
+                "WARNING: Failed to read the hardware counters. No perf events will be reported."
+            );
+            self.mac_events.clear();
+            self.event_names.clear();
+        }
+    }
+
+    /// Snapshot the counters at the start of the timing iteration.
+    fn begin(&mut self, _benchmark: &str, _iteration: usize, _warmup: bool) {
+        self.start = macos::read(&self.mac_events).unwrap_or_default();
+    }
+
+    /// Snapshot the counters at the end of the timing iteration.
+    fn end(&mut self, _benchmark: &str, _iteration: usize, _warmup: bool) {
+        self.end = macos::read(&self.mac_events).unwrap_or_default();
+    }
+
+    /// Report data after the timing iteration.
+    fn report(&mut self) -> HashMap<String, Value> {
+        let mut values = HashMap::new();
+        for (i, name) in self.event_names.iter().enumerate() {
+            let (Some(start), Some(end)) = (self.start.get(i), self.end.get(i)) else {
+                continue;
+            };
+            values.insert(name.clone(), ((end - start) as f32).into());
+        }
+        values
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Probe for PerfEventProbe {
+    /// Reject unknown presets, before opening any events.
+    fn check_args(&self, args: &ProbeArgs) -> anyhow::Result<()> {
+        match args.get::<String>("preset").unwrap_or_default().as_str() {
+            "" | "memory" => Ok(()),
+            preset => anyhow::bail!("Unknown perf probe preset: {}", preset),
+        }
+    }
+
+    /// Initialize the probe before benchmarking.
+    fn init(&mut self, args: ProbeArgs) {
+        self.perfmon.initialize().expect("libpfm init failed.");
+        let events = args.get::<String>("events").unwrap_or_default();
+        let inherit = args.get::<bool>("inherit").unwrap_or_default();
+        let preset = args.get::<String>("preset").unwrap_or_default();
+        // Groups of (column name, event name) pairs
+        let mut groups = parse_event_groups(&events)
+            .into_iter()
+            .map(|g| g.into_iter().map(|e| (e.clone(), e)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        match preset.as_str() {
+            "" => {}
+            "memory" => groups.extend(
+                memory_preset_events()
+                    .into_iter()
+                    .map(|(c, e)| vec![(c.to_owned(), e.to_owned())]),
+            ),
+            _ => unreachable!("Unknown presets are rejected by `check_args`"),
+        }
+        // Count the events without an explicit `:u`/`:k` modifier in user and kernel mode separately
+        if args.get::<bool>("split-user-kernel").unwrap_or_default() {
+            for group in &mut groups {
+                *group = std::mem::take(group)
+                    .into_iter()
+                    .flat_map(|(c, e)| match linux::Mode::split(&e).1 {
+                        Some(_) => vec![(c, e)],
+                        None => vec![
+                            (format!("{}.user", c), format!("{}:u", e)),
+                            (format!("{}.kernel", c), format!("{}:k", e)),
+                        ],
+                    })
+                    .collect();
+            }
+        }
+        for group in groups {
+            let attrs = group
+                .iter()
+                .map(|(_, e)| linux::encode(e, inherit).unwrap())
+                .collect();
+            self.groups
+                .push(linux::Group::open(attrs).expect("Failed to open perf events"));
+            self.event_names.extend(group.into_iter().map(|(c, _)| c));
         }
     }
 
     /// Prepare recording at the start of the timing iteration.
     fn begin(&mut self, _benchmark: &str, _iteration: usize, _warmup: bool) {
-        for e in &mut self.events {
-            e.reset().expect("Failed to reset perf event");
-            e.enable().expect("Failed to enable perf event");
+        for g in &self.groups {
+            g.reset().expect("Failed to reset perf events");
+            g.enable().expect("Failed to enable perf events");
         }
     }
 
     /// Finish timing iteration. Disable recording.
     fn end(&mut self, _benchmark: &str, _iteration: usize, _warmup: bool) {
-        for e in &mut self.events {
-            e.disable().expect("Failed to disable perf event");
+        for g in &self.groups {
+            g.disable().expect("Failed to disable perf events");
         }
     }
 
     /// Report data after the timing iteration.
+    ///
+    /// The counts are scaled up to the whole timing iteration if the events were multiplexed.
+    /// `multiplexing-ratio` is the lowest fraction of time any event group was counting.
     fn report(&mut self) -> HashMap<String, Value> {
         let mut values = HashMap::new();
-        for (i, e) in self.events.iter().enumerate() {
-            let v = e.read().unwrap().value as f32;
-            values.insert(self.event_names[i].clone(), v.into());
+        if self.groups.is_empty() {
+            return values;
+        }
+        let counts = self
+            .groups
+            .iter()
+            .flat_map(|g| g.read().expect("Failed to read perf events"));
+        let mut ratio = 1f64;
+        for (name, count) in self.event_names.iter().zip(counts) {
+            ratio = ratio.min(count.running_ratio());
+            // Skip the events that were never scheduled, rather than reporting a bogus zero
+            if let Some(v) = count.scaled() {
+                values.insert(name.clone(), (v as f32).into());
+            }
         }
+        values.insert("multiplexing-ratio".to_owned(), (ratio as f32).into());
         values
     }
 }
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use harness::probe::Probe;
+#[cfg(unix)]
+use harness::probe::ProbeArgs;
+use harness::Value;
+
+/// Scheduler counters of the current process.
+#[cfg(unix)]
+#[derive(Default, Clone, Copy)]
+struct SchedStat {
+    voluntary_switches: u64,
+    involuntary_switches: u64,
+    /// Total time the threads spent waiting on a runqueue, in nanoseconds.
+    /// `None` if schedstats are not available.
+    run_delay_ns: Option<u64>,
+}
+
+#[cfg(unix)]
+impl SchedStat {
+    fn now() -> Self {
+        let mut usage = std::mem::MaybeUninit::<libc::rusage>::zeroed();
+        let usage = unsafe {
+            if libc::getrusage(libc::RUSAGE_SELF, usage.as_mut_ptr()) != 0 {
+                return Self::default();
+            }
+            usage.assume_init()
+        };
+        Self {
+            voluntary_switches: usage.ru_nvcsw as u64,
+            involuntary_switches: usage.ru_nivcsw as u64,
+            run_delay_ns: run_delay_ns(),
+        }
+    }
+}
+
+/// Sum up the runqueue wait time of all the live threads of the current process.
+///
+/// Each `/proc/self/task/<tid>/schedstat` has three fields: the time spent on the cpu, the time spent waiting on a
+/// runqueue, and the number of timeslices run on the cpu. The file is missing if the kernel is built without
+/// `CONFIG_SCHEDSTATS` or `CONFIG_SCHED_INFO`.
+#[cfg(target_os = "linux")]
+fn run_delay_ns() -> Option<u64> {
+    let mut total = 0;
+    for task in std::fs::read_dir("/proc/self/task").ok()?.flatten() {
+        // The thread may have exited
+        let Ok(stat) = std::fs::read_to_string(task.path().join("schedstat")) else {
+            continue;
+        };
+        total += stat.split_whitespace().nth(1)?.parse::<u64>().ok()?;
+    }
+    Some(total)
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn run_delay_ns() -> Option<u64> {
+    None
+}
+
+#[harness::probe]
+#[derive(Default)]
+pub struct SchedProbe {
+    #[cfg(unix)]
+    start: SchedStat,
+    #[cfg(unix)]
+    end: SchedStat,
+}
+
+#[cfg(not(unix))]
+impl Probe for SchedProbe {}
+
+#[cfg(unix)]
+impl Probe for SchedProbe {
+    /// Initialize the probe before benchmarking.
+    fn init(&mut self, _args: ProbeArgs) {
+        if cfg!(target_os = "linux") && run_delay_ns().is_none() {
+            eprintln!("WARNING: /proc/self/task/*/schedstat is not available. The runqueue delay will not be reported.");
+        }
+    }
+
+    /// Snapshot the counters at the start of the timing iteration.
+    fn begin(&mut self, _benchmark: &str, _iteration: usize, _warmup: bool) {
+        self.start = SchedStat::now();
+    }
+
+    /// Snapshot the counters at the end of the timing iteration.
+    fn end(&mut self, _benchmark: &str, _iteration: usize, _warmup: bool) {
+        self.end = SchedStat::now();
+    }
+
+    /// Report data after the timing iteration. The runqueue delay is in milliseconds.
+    fn report(&mut self) -> HashMap<String, Value> {
+        let mut values = HashMap::new();
+        values.insert(
+            "voluntary-context-switches".to_owned(),
+            (self.end.voluntary_switches - self.start.voluntary_switches).into(),
+        );
+        values.insert(
+            "involuntary-context-switches".to_owned(),
+            (self.end.involuntary_switches - self.start.involuntary_switches).into(),
+        );
+        if let (Some(start), Some(end)) = (self.start.run_delay_ns, self.end.run_delay_ns) {
+            // Threads that exited during the iteration take their wait time with them
+            let delay = end.saturating_sub(start);
+            values.insert("run-delay".to_owned(), (delay as f64 / 1_000_000.0).into());
+        }
+        values
+    }
+}
@@ -0,0 +1,91 @@
+#[cfg(windows)]
+use std::collections::HashMap;
+
+use harness::probe::Probe;
+#[cfg(windows)]
+use harness::Value;
+
+/// Process counters of the current process.
+#[cfg(windows)]
+#[derive(Default, Clone, Copy)]
+struct Counters {
+    /// CPU cycles consumed by all threads of the process
+    cycles: u64,
+    page_faults: u64,
+    /// Peak working set size, in bytes
+    peak_working_set: u64,
+}
+
+#[cfg(windows)]
+impl Counters {
+    fn now() -> Self {
+        use windows_sys::Win32::System::{
+            ProcessStatus::{GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS},
+            Threading::GetCurrentProcess,
+            WindowsProgramming::QueryProcessCycleTime,
+        };
+        let mut counters = Self::default();
+        unsafe {
+            let process = GetCurrentProcess();
+            let mut cycles = 0u64;
+            if QueryProcessCycleTime(process, &mut cycles) != 0 {
+                counters.cycles = cycles;
+            }
+            let mut mem = std::mem::zeroed::<PROCESS_MEMORY_COUNTERS>();
+            let size = std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32;
+            if GetProcessMemoryInfo(process, &mut mem, size) != 0 {
+                counters.page_faults = mem.PageFaultCount as u64;
+                counters.peak_working_set = mem.PeakWorkingSetSize as u64;
+            }
+        }
+        counters
+    }
+}
+
+#[harness::probe]
+#[derive(Default)]
+pub struct WindowsProbe {
+    #[cfg(windows)]
+    start: Counters,
+    #[cfg(windows)]
+    end: Counters,
+}
+
+#[cfg(not(windows))]
+impl Probe for WindowsProbe {
+    fn init(&mut self, _args: harness::probe::ProbeArgs) {
+        eprintln!("WARNING: harness-probe-windows is only supported on windows. No data will be reported.");
+    }
+}
+
+#[cfg(windows)]
+impl Probe for WindowsProbe {
+    /// Snapshot the counters at the start of the timing iteration.
+    fn begin(&mut self, _benchmark: &str, _iteration: usize, _warmup: bool) {
+        self.start = Counters::now();
+    }
+
+    /// Snapshot the counters at the end of the timing iteration.
+    fn end(&mut self, _benchmark: &str, _iteration: usize, _warmup: bool) {
+        self.end = Counters::now();
+    }
+
+    /// Report data after the timing iteration.
+    fn report(&mut self) -> HashMap<String, Value> {
+        let mut values = HashMap::new();
+        values.insert(
+            "cycles".to_owned(),
+            (self.end.cycles - self.start.cycles).into(),
+        );
+        values.insert(
+            "page-faults".to_owned(),
+            (self.end.page_faults - self.start.page_faults).into(),
+        );
+        // Windows cannot reset the peak working set, so this is the peak of the whole process so far
+        values.insert(
+            "peak-working-set".to_owned(),
+            self.end.peak_working_set.into(),
+        );
+        values
+    }
+}